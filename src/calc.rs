@@ -0,0 +1,1277 @@
+//! CSS Math Functions
+//!
+//! This module parses the math functions of CSS Values and Units Level 4 into
+//! `CalcNode` expression trees: `calc()`, `min()`, `max()`, `clamp()`,
+//! `round()`, `mod()`, `rem()`, the trigonometric functions, `pow()`,
+//! `sqrt()`, `hypot()`, `log()`, `exp()`, `abs()` and `sign()`, with the
+//! constants `e`, `pi`, `infinity` and `NaN`; `e` and `pi` keep their name
+//! until the expression is simplified. A `var()`, `env()` or `attr()`
+//! inside an expression is kept as a placeholder, since its type and value are
+//! only known once it is substituted.
+//!
+//! Parsing checks the types of the expression (`1px + 2` is invalid) and
+//! `simplify()` follows the simplification rules of the specification:
+//! absolute units are converted to their canonical unit, terms with the same
+//! unit are added up, numbers are multiplied in, and functions whose
+//! arguments are all known are computed. Mixed units such as `1em + 10px`
+//! stay symbolic until `evaluate()` resolves them against a
+//! `ResolutionContext`.
+//!
+//! ## Main API
+//!
+//! - `CalcNode::from_string()` - Parse a math function
+//! - `CalcNode::from_component()` - Interpret a parsed `ComponentValue` as a math function
+//! - `CSSDeclaration::math_functions()` - The math functions in a declaration's value
+//! - `CalcNode::simplify()` - Simplify an expression
+//! - `CalcNode::evaluate()` - Compute the value given a context and a percentage basis
+//! - `Display` trait implementation for converting back to CSS string
+//!
+//! ## Examples
+//!
+//! ```rust
+//! use css_structs::{CalcNode, Dimension, Numeric, ResolutionContext, Unit};
+//!
+//! let calc = CalcNode::from_string("calc(10px + 2 * 5px)").unwrap();
+//! assert_eq!(calc.to_string(), "calc(10px + 2 * 5px)");
+//! assert_eq!(calc.simplify().to_string(), "20px");
+//!
+//! // Mixed units stay symbolic
+//! let mixed = CalcNode::from_string("calc(1em + 2in - 50%)").unwrap().simplify();
+//! assert_eq!(mixed.to_string(), "calc(-50% + 1em + 192px)");
+//!
+//! // ...until they are evaluated
+//! let context = ResolutionContext::new(1280.0, 720.0);
+//! let basis = Some(Dimension::new(400.0, Unit::Px));
+//! assert_eq!(mixed.evaluate(&context, basis), Some(Numeric::Dimension(Dimension::new(8.0, Unit::Px))));
+//!
+//! let clamp = CalcNode::from_string("clamp(1rem, 2.5vw, 3rem)").unwrap();
+//! assert_eq!(clamp.evaluate(&context, None).unwrap().to_string(), "32px");
+//!
+//! assert_eq!(CalcNode::from_string("round(up, 7.2px, 2px)").unwrap().simplify().to_string(), "8px");
+//! assert_eq!(CalcNode::from_string("calc(var(--gap) * 2)").unwrap().to_string(), "calc(var(--gap) * 2)");
+//!
+//! // Invalid expressions are reported at the offending token
+//! let error = CalcNode::from_string("calc(1px + 2)").unwrap_err();
+//! assert_eq!(error.to_string(), "1:12: invalid value: expected operands of the same type, found `2`");
+//! ```
+
+
+use std::cmp::Ordering;
+use std::f64::consts::{E, PI};
+use std::fmt;
+use crate::dimension::{Dimension, ResolutionContext, Unit, UnitKind};
+use crate::parse_error::{ParseError, ParseErrorKind};
+use crate::value::{format_number, replace_components, ComponentValue, Value};
+
+
+/// A leaf of a math expression.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Numeric {
+  Number(f64),
+  Percentage(f64),
+  Dimension(Dimension),
+}
+
+/// The rounding strategy of `round()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RoundingStrategy {
+  #[default]
+  Nearest,
+  Up,
+  Down,
+  ToZero,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MathFunction {
+  Min,
+  Max,
+  Clamp,
+  Round(RoundingStrategy),
+  Mod,
+  Rem,
+  Sin,
+  Cos,
+  Tan,
+  Asin,
+  Acos,
+  Atan,
+  Atan2,
+  Pow,
+  Sqrt,
+  Hypot,
+  Log,
+  Exp,
+  Abs,
+  Sign,
+}
+
+/// The constants `e` and `pi`, kept by name until `CalcNode::simplify()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MathConstant {
+  E,
+  Pi,
+}
+
+/// A node of a math expression. `calc()` itself is not a node: its
+/// expression is the root.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CalcNode {
+  Value(Numeric),
+  Constant(MathConstant),
+  /// A `var()`, `env()` or `attr()` function, kept as written.
+  Placeholder(ComponentValue),
+  Sum(Vec<CalcNode>),
+  Product(Vec<CalcNode>),
+  /// The negation of a node, as in the `- b` of `a - b`.
+  Negate(Box<CalcNode>),
+  /// The inverse of a node, as in the `/ b` of `a / b`.
+  Invert(Box<CalcNode>),
+  Function(MathFunction, Vec<CalcNode>),
+}
+
+/// The type of an expression: the power of each kind of unit (length, angle,
+/// time, frequency, resolution, flex and percentage), or unknown when it
+/// depends on a placeholder.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CalcType {
+  Known([i32; 7]),
+  Unknown,
+}
+
+const PERCENT: usize = 6;
+const NUMBER: CalcType = CalcType::Known([0; 7]);
+
+fn kind_index(kind: UnitKind) -> usize {
+  match kind {
+    UnitKind::Length => 0,
+    UnitKind::Angle => 1,
+    UnitKind::Time => 2,
+    UnitKind::Frequency => 3,
+    UnitKind::Resolution => 4,
+    UnitKind::Flex => 5,
+  }
+}
+
+fn single(index: usize) -> CalcType {
+  let mut powers = [0; 7];
+  powers[index] = 1;
+  CalcType::Known(powers)
+}
+
+impl CalcType {
+  /// The type of the sum of two expressions. A percentage can be added to
+  /// any single unit, since it resolves against a basis of that unit.
+  fn add(self, other: CalcType) -> Option<CalcType> {
+    match (self, other) {
+      (CalcType::Unknown, other) | (other, CalcType::Unknown) => Some(other),
+      (a, b) if a == b => Some(a),
+      (a, b) if a == single(PERCENT) && b.is_single_unit() => Some(b),
+      (a, b) if b == single(PERCENT) && a.is_single_unit() => Some(a),
+      _ => None,
+    }
+  }
+
+  fn multiply(self, other: CalcType) -> CalcType {
+    match (self, other) {
+      (CalcType::Known(a), CalcType::Known(b)) => CalcType::Known(std::array::from_fn(|i| a[i] + b[i])),
+      _ => CalcType::Unknown,
+    }
+  }
+
+  fn invert(self) -> CalcType {
+    match self {
+      CalcType::Known(powers) => CalcType::Known(powers.map(|power| -power)),
+      CalcType::Unknown => CalcType::Unknown,
+    }
+  }
+
+  fn is_single_unit(self) -> bool {
+    (0..PERCENT).any(|index| self == single(index))
+  }
+
+  /// Whether the type can be the result of a math function: a number, or a
+  /// single unit or percentage.
+  fn is_valid_result(self) -> bool {
+    self == CalcType::Unknown || self == NUMBER || self.is_single_unit() || self == single(PERCENT)
+  }
+
+  fn is_number_or_unknown(self) -> bool {
+    self == NUMBER || self == CalcType::Unknown
+  }
+}
+
+impl MathConstant {
+  pub fn name(self) -> &'static str {
+    match self {
+      MathConstant::E => "e",
+      MathConstant::Pi => "pi",
+    }
+  }
+
+  pub fn value(self) -> f64 {
+    match self {
+      MathConstant::E => E,
+      MathConstant::Pi => PI,
+    }
+  }
+}
+
+impl Numeric {
+  /// The name of an infinite or NaN value, which has no numeric form.
+  fn constant(self) -> Option<&'static str> {
+    match self.value() {
+      value if value.is_nan() => Some("NaN"),
+      f64::INFINITY => Some("infinity"),
+      f64::NEG_INFINITY => Some("-infinity"),
+      _ => None,
+    }
+  }
+
+  /// Writes the value as an operand of an expression, where `infinity` and
+  /// `NaN` need no `calc()` around them.
+  fn write_operand(self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match (self.constant(), self) {
+      (None, _) => write!(f, "{}", self),
+      (Some(constant), Numeric::Number(_)) => write!(f, "{}", constant),
+      (Some(constant), unit) => write!(f, "{} * {}", constant, unit.with_value(1.0)),
+    }
+  }
+
+  /// Converts an absolute dimension to the canonical unit of its kind.
+  fn canonical(self) -> Numeric {
+    match self {
+      Numeric::Dimension(dimension) => {
+        Numeric::Dimension(dimension.to(dimension.unit.canonical()).unwrap_or(dimension))
+      }
+      numeric => numeric,
+    }
+  }
+
+  pub fn value(self) -> f64 {
+    match self {
+      Numeric::Number(value) | Numeric::Percentage(value) => value,
+      Numeric::Dimension(dimension) => dimension.value,
+    }
+  }
+
+  fn with_value(self, value: f64) -> Numeric {
+    match self {
+      Numeric::Number(_) => Numeric::Number(value),
+      Numeric::Percentage(_) => Numeric::Percentage(value),
+      Numeric::Dimension(dimension) => Numeric::Dimension(Dimension::new(value, dimension.unit)),
+    }
+  }
+
+  /// Whether two values have the same unit, so that they can be added or compared.
+  fn same_unit(self, other: Numeric) -> bool {
+    match (self, other) {
+      (Numeric::Number(_), Numeric::Number(_)) | (Numeric::Percentage(_), Numeric::Percentage(_)) => true,
+      (Numeric::Dimension(a), Numeric::Dimension(b)) => a.unit == b.unit,
+      _ => false,
+    }
+  }
+
+  /// The serialization order of terms in a sum: numbers, percentages, then
+  /// dimensions by unit.
+  fn sort_key(self) -> (u8, &'static str) {
+    match self {
+      Numeric::Number(_) => (0, ""),
+      Numeric::Percentage(_) => (1, ""),
+      Numeric::Dimension(dimension) => (2, dimension.unit.name()),
+    }
+  }
+
+  fn calc_type(self) -> CalcType {
+    match self {
+      Numeric::Number(_) => NUMBER,
+      Numeric::Percentage(_) => single(PERCENT),
+      Numeric::Dimension(dimension) => single(kind_index(dimension.unit.kind())),
+    }
+  }
+}
+
+impl MathFunction {
+  fn from_name(name: &str) -> Option<MathFunction> {
+    let function = match name.to_ascii_lowercase().as_str() {
+      "min" => MathFunction::Min,
+      "max" => MathFunction::Max,
+      "clamp" => MathFunction::Clamp,
+      "round" => MathFunction::Round(RoundingStrategy::Nearest),
+      "mod" => MathFunction::Mod,
+      "rem" => MathFunction::Rem,
+      "sin" => MathFunction::Sin,
+      "cos" => MathFunction::Cos,
+      "tan" => MathFunction::Tan,
+      "asin" => MathFunction::Asin,
+      "acos" => MathFunction::Acos,
+      "atan" => MathFunction::Atan,
+      "atan2" => MathFunction::Atan2,
+      "pow" => MathFunction::Pow,
+      "sqrt" => MathFunction::Sqrt,
+      "hypot" => MathFunction::Hypot,
+      "log" => MathFunction::Log,
+      "exp" => MathFunction::Exp,
+      "abs" => MathFunction::Abs,
+      "sign" => MathFunction::Sign,
+      _ => return None,
+    };
+    Some(function)
+  }
+
+  pub fn name(self) -> &'static str {
+    match self {
+      MathFunction::Min => "min",
+      MathFunction::Max => "max",
+      MathFunction::Clamp => "clamp",
+      MathFunction::Round(_) => "round",
+      MathFunction::Mod => "mod",
+      MathFunction::Rem => "rem",
+      MathFunction::Sin => "sin",
+      MathFunction::Cos => "cos",
+      MathFunction::Tan => "tan",
+      MathFunction::Asin => "asin",
+      MathFunction::Acos => "acos",
+      MathFunction::Atan => "atan",
+      MathFunction::Atan2 => "atan2",
+      MathFunction::Pow => "pow",
+      MathFunction::Sqrt => "sqrt",
+      MathFunction::Hypot => "hypot",
+      MathFunction::Log => "log",
+      MathFunction::Exp => "exp",
+      MathFunction::Abs => "abs",
+      MathFunction::Sign => "sign",
+    }
+  }
+
+  /// Whether `count` arguments are allowed.
+  fn accepts(self, count: usize) -> bool {
+    match self {
+      MathFunction::Min | MathFunction::Max | MathFunction::Hypot => count >= 1,
+      MathFunction::Clamp => count == 3,
+      MathFunction::Round(_) => (1..=2).contains(&count),
+      MathFunction::Mod | MathFunction::Rem | MathFunction::Atan2 | MathFunction::Pow => count == 2,
+      MathFunction::Log => (1..=2).contains(&count),
+      _ => count == 1,
+    }
+  }
+
+  /// The number of arguments allowed, for errors.
+  fn arity(self) -> &'static str {
+    match self {
+      MathFunction::Min | MathFunction::Max | MathFunction::Hypot => "at least one argument",
+      MathFunction::Clamp => "three arguments",
+      MathFunction::Mod | MathFunction::Rem | MathFunction::Atan2 | MathFunction::Pow => "two arguments",
+      MathFunction::Round(_) | MathFunction::Log => "one or two arguments",
+      _ => "one argument",
+    }
+  }
+
+  /// Whether `count` arguments all need the same type, rather than each a
+  /// type the function takes.
+  fn takes_same_types(self, count: usize) -> bool {
+    match self {
+      MathFunction::Round(_) => count > 1,
+      MathFunction::Sin | MathFunction::Cos | MathFunction::Tan => false,
+      MathFunction::Asin | MathFunction::Acos | MathFunction::Atan => false,
+      MathFunction::Pow | MathFunction::Sqrt | MathFunction::Log | MathFunction::Exp => false,
+      _ => true,
+    }
+  }
+
+  /// The type of the result given the types of the arguments, or `None`
+  /// when the arguments do not fit.
+  fn result_type(self, types: &[CalcType]) -> Option<CalcType> {
+    let same = || types.iter().try_fold(types[0], |result, other| result.add(*other));
+    let angle = single(kind_index(UnitKind::Angle));
+    match self {
+      MathFunction::Min | MathFunction::Max | MathFunction::Clamp | MathFunction::Hypot => same(),
+      MathFunction::Mod | MathFunction::Rem | MathFunction::Abs => same(),
+      MathFunction::Round(_) => match types.len() {
+        1 => types[0].is_number_or_unknown().then_some(types[0]),
+        _ => same(),
+      },
+      MathFunction::Sign => same().map(|_| NUMBER),
+      MathFunction::Sin | MathFunction::Cos | MathFunction::Tan => {
+        (types[0].is_number_or_unknown() || types[0] == angle).then_some(NUMBER)
+      }
+      MathFunction::Asin | MathFunction::Acos | MathFunction::Atan => types[0].is_number_or_unknown().then_some(angle),
+      MathFunction::Atan2 => same().map(|_| angle),
+      MathFunction::Pow | MathFunction::Sqrt | MathFunction::Log | MathFunction::Exp => {
+        types.iter().all(|t| t.is_number_or_unknown()).then_some(NUMBER)
+      }
+    }
+  }
+
+  /// Computes the function on known values with the same unit. Returns
+  /// `None` when the units differ, so the function has to stay symbolic.
+  fn compute(self, values: &[Numeric]) -> Option<Numeric> {
+    let first = values[0];
+    if !values.iter().all(|value| value.same_unit(first)) {
+      // Trigonometric functions take numbers and angles alike
+      if !matches!(self, MathFunction::Sin | MathFunction::Cos | MathFunction::Tan) {
+        return None;
+      }
+    }
+    let numbers: Vec<f64> = values.iter().map(|value| value.value()).collect();
+    let radians = |value: Numeric| match value {
+      Numeric::Dimension(angle) => angle.to(Unit::Rad).map(|angle| angle.value),
+      value => Some(value.value()),
+    };
+    let degrees = |radians: f64| Numeric::Dimension(Dimension::new(radians * 180.0 / PI, Unit::Deg));
+
+    let result = match self {
+      MathFunction::Min => first.with_value(numbers.iter().copied().fold(f64::INFINITY, f64::min)),
+      MathFunction::Max => first.with_value(numbers.iter().copied().fold(f64::NEG_INFINITY, f64::max)),
+      MathFunction::Clamp => first.with_value(numbers[0].max(numbers[1].min(numbers[2]))),
+      MathFunction::Round(strategy) => {
+        let step = numbers.get(1).copied().unwrap_or(1.0);
+        let quotient = numbers[0] / step;
+        let rounded = match strategy {
+          RoundingStrategy::Nearest => (quotient + 0.5).floor(),
+          RoundingStrategy::Up => quotient.ceil(),
+          RoundingStrategy::Down => quotient.floor(),
+          RoundingStrategy::ToZero => quotient.trunc(),
+        };
+        first.with_value(if step == 0.0 { f64::NAN } else { rounded * step })
+      }
+      MathFunction::Mod => first.with_value(numbers[0] - numbers[1] * (numbers[0] / numbers[1]).floor()),
+      MathFunction::Rem => first.with_value(numbers[0] % numbers[1]),
+      MathFunction::Sin => Numeric::Number(radians(first)?.sin()),
+      MathFunction::Cos => Numeric::Number(radians(first)?.cos()),
+      MathFunction::Tan => {
+        // The asymptotes are infinite, where floating point only gets close
+        let radians = radians(first)?;
+        let asymptote = (radians.to_degrees() - 90.0).rem_euclid(360.0);
+        Numeric::Number(if asymptote == 0.0 {
+          f64::INFINITY
+        } else if asymptote == 180.0 {
+          f64::NEG_INFINITY
+        } else {
+          radians.tan()
+        })
+      }
+      MathFunction::Asin => degrees(numbers[0].asin()),
+      MathFunction::Acos => degrees(numbers[0].acos()),
+      MathFunction::Atan => degrees(numbers[0].atan()),
+      MathFunction::Atan2 => degrees(numbers[0].atan2(numbers[1])),
+      MathFunction::Pow => Numeric::Number(numbers[0].powf(numbers[1])),
+      MathFunction::Sqrt => Numeric::Number(numbers[0].sqrt()),
+      MathFunction::Hypot => first.with_value(numbers.iter().map(|n| n * n).sum::<f64>().sqrt()),
+      MathFunction::Log => Numeric::Number(match numbers.get(1) {
+        Some(base) => numbers[0].ln() / base.ln(),
+        None => numbers[0].ln(),
+      }),
+      MathFunction::Exp => Numeric::Number(numbers[0].exp()),
+      MathFunction::Abs => first.with_value(numbers[0].abs()),
+      MathFunction::Sign => Numeric::Number(if numbers[0] == 0.0 { 0.0 } else { numbers[0].signum() }),
+    };
+    Some(result.canonical())
+  }
+}
+
+/// Where an invalid expression went wrong, relative to one of its tokens.
+#[derive(Debug, Clone, Copy)]
+enum At<'a> {
+  Start(&'a ComponentValue),
+  End(&'a ComponentValue),
+  /// Right after the opening bracket of a function or block.
+  Inside(&'a ComponentValue),
+}
+
+/// Why an expression is invalid: what was expected, and where.
+#[derive(Debug)]
+struct CalcError<'a> {
+  at: At<'a>,
+  expected: String,
+}
+
+type CalcResult<'a> = Result<CalcNode, CalcError<'a>>;
+
+fn invalid<'a>(at: At<'a>, expected: impl Into<String>) -> CalcError<'a> {
+  CalcError { at, expected: expected.into() }
+}
+
+/// Splits function arguments at top-level commas, each with where it starts:
+/// inside `function` for the first, and after its comma for the others.
+fn split_arguments<'a>(function: &'a ComponentValue, arguments: &'a [ComponentValue]) -> Vec<(At<'a>, Vec<&'a ComponentValue>)> {
+  let mut split = vec![(At::Inside(function), Vec::new())];
+  for value in arguments {
+    match value {
+      ComponentValue::Comma => split.push((At::End(value), Vec::new())),
+      value => split.last_mut().unwrap().1.push(value),
+    }
+  }
+  split
+}
+
+/// Returns the token of an argument made of a single token.
+fn only_token<'a>(argument: &[&'a ComponentValue]) -> Option<&'a ComponentValue> {
+  match argument.iter().filter(|value| ***value != ComponentValue::Whitespace).collect::<Vec<_>>().as_slice() {
+    [token] => Some(**token),
+    _ => None,
+  }
+}
+
+/// Parses a `<calc-sum>`: products joined by `+` and `-`, which need
+/// whitespace on both sides. `start` is where the sum begins.
+fn parse_sum<'a>(tokens: &[&'a ComponentValue], start: At<'a>) -> CalcResult<'a> {
+  let mut terms = Vec::new();
+  let mut sum_type: Option<CalcType> = None;
+  let mut rest = tokens;
+  let (mut start, mut expected) = (start, "a value".to_string());
+  let mut negate = false;
+
+  loop {
+    let end = rest.iter().position(|token| matches!(token, ComponentValue::Delim('+' | '-'))).unwrap_or(rest.len());
+    let factors: Vec<&ComponentValue> = rest[..end].iter().copied().filter(|token| **token != ComponentValue::Whitespace).collect();
+    if factors.is_empty() {
+      return Err(invalid(start, expected));
+    }
+    let product = parse_product(&factors)?;
+
+    let product_type = product.calc_type();
+    sum_type = match (sum_type, product_type) {
+      (Some(sum), Some(product)) => Some(sum.add(product).ok_or_else(|| invalid(At::Start(factors[0]), "operands of the same type"))?),
+      (sum, product) => sum.or(product),
+    };
+    terms.push(if negate { CalcNode::Negate(Box::new(product)) } else { product });
+
+    let Some(operator) = rest.get(end) else {
+      break;
+    };
+    let spaced = |token: Option<&&ComponentValue>| token.is_none_or(|token| **token == ComponentValue::Whitespace);
+    if !spaced(rest.get(end - 1)) || !spaced(rest.get(end + 1)) {
+      return Err(invalid(At::Start(operator), format!("whitespace around `{}`", operator)));
+    }
+    negate = **operator == ComponentValue::Delim('-');
+    (start, expected) = (At::End(operator), format!("a value after `{}`", operator));
+    rest = &rest[end + 1..];
+  }
+
+  Ok(if terms.len() == 1 { terms.remove(0) } else { CalcNode::Sum(terms) })
+}
+
+/// Parses a `<calc-product>`: values joined by `*` and `/`.
+fn parse_product<'a>(tokens: &[&'a ComponentValue]) -> CalcResult<'a> {
+  let mut factors = Vec::new();
+  let mut invert = false;
+
+  for (index, token) in tokens.iter().enumerate() {
+    match (index % 2, token) {
+      (0, value) => {
+        let value = parse_value(value)?;
+        factors.push(if invert { CalcNode::Invert(Box::new(value)) } else { value });
+      }
+      (_, ComponentValue::Delim('*')) => invert = false,
+      (_, ComponentValue::Slash) => invert = true,
+      // `1px -2px` and `1px+2px` are two values, the second one signed
+      (_, ComponentValue::Number(_) | ComponentValue::Percentage(_) | ComponentValue::Dimension { .. }) => {
+        return Err(invalid(At::Start(token), "an operator, with whitespace around `+` and `-`"));
+      }
+      _ => return Err(invalid(At::Start(token), "an operator")),
+    }
+  }
+  if let Some(operator) = tokens.last().filter(|_| tokens.len().is_multiple_of(2)) {
+    return Err(invalid(At::End(operator), format!("a value after `{}`", operator)));
+  }
+
+  Ok(if factors.len() == 1 { factors.remove(0) } else { CalcNode::Product(factors) })
+}
+
+/// Parses a `<calc-value>`: a number, dimension, percentage, constant,
+/// parenthesized sum or nested math function.
+fn parse_value(token: &ComponentValue) -> CalcResult<'_> {
+  let unexpected = || invalid(At::Start(token), "a number, dimension, percentage or math function");
+  let value = match token {
+    ComponentValue::Number(number) => Numeric::Number(*number),
+    ComponentValue::Percentage(percentage) => Numeric::Percentage(*percentage),
+    ComponentValue::Dimension { .. } => Numeric::Dimension(Dimension::from_component(token).ok_or_else(unexpected)?),
+    ComponentValue::Ident(name) => Numeric::Number(match name.to_ascii_lowercase().as_str() {
+      "e" => return Ok(CalcNode::Constant(MathConstant::E)),
+      "pi" => return Ok(CalcNode::Constant(MathConstant::Pi)),
+      "infinity" => f64::INFINITY,
+      "-infinity" => f64::NEG_INFINITY,
+      "nan" => f64::NAN,
+      _ => return Err(unexpected()),
+    }),
+    ComponentValue::Block { bracket: '(', contents } => {
+      let tokens: Vec<&ComponentValue> = contents.iter().collect();
+      return parse_sum(&tokens, At::Inside(token));
+    }
+    ComponentValue::Function { name, .. } if is_placeholder(name) => return Ok(CalcNode::Placeholder(token.clone())),
+    ComponentValue::Function { name, arguments } => return parse_function(token, name, arguments),
+    _ => return Err(unexpected()),
+  };
+  Ok(CalcNode::Value(value))
+}
+
+fn is_placeholder(name: &str) -> bool {
+  ["var", "env", "attr"].iter().any(|placeholder| name.eq_ignore_ascii_case(placeholder))
+}
+
+fn parse_function<'a>(token: &'a ComponentValue, name: &str, arguments: &'a [ComponentValue]) -> CalcResult<'a> {
+  let mut arguments = split_arguments(token, arguments);
+  if name.eq_ignore_ascii_case("calc") {
+    return match arguments.as_slice() {
+      [(start, argument)] => parse_sum(argument, *start),
+      _ => Err(invalid(At::Start(token), "one argument")),
+    };
+  }
+
+  let mut function = MathFunction::from_name(name).ok_or_else(|| invalid(At::Start(token), "a math function"))?;
+  if function == MathFunction::Round(RoundingStrategy::Nearest) {
+    let strategy = match arguments.first().and_then(|(_, argument)| only_token(argument)) {
+      Some(keyword) => match keyword.as_ident().map(str::to_ascii_lowercase).as_deref() {
+        Some("nearest") => Some(RoundingStrategy::Nearest),
+        Some("up") => Some(RoundingStrategy::Up),
+        Some("down") => Some(RoundingStrategy::Down),
+        Some("to-zero") => Some(RoundingStrategy::ToZero),
+        _ => None,
+      },
+      _ => None,
+    };
+    if let Some(strategy) = strategy {
+      function = MathFunction::Round(strategy);
+      arguments.remove(0);
+    }
+  }
+
+  // `none` bounds of `clamp()` turn it into `min()` or `max()`
+  if function == MathFunction::Clamp && arguments.len() == 3 {
+    let is_none = |(_, argument): &(At, Vec<&ComponentValue>)| only_token(argument).is_some_and(|keyword| keyword.is_ident("none"));
+    match (is_none(&arguments[0]), is_none(&arguments[2])) {
+      (true, true) => return parse_sum(&arguments[1].1, arguments[1].0),
+      (true, false) => {
+        function = MathFunction::Min;
+        arguments.remove(0);
+      }
+      (false, true) => {
+        function = MathFunction::Max;
+        arguments.pop();
+      }
+      (false, false) => {}
+    }
+  }
+
+  if !function.accepts(arguments.len()) {
+    return Err(invalid(At::Start(token), function.arity()));
+  }
+  let nodes = arguments.iter().map(|(start, argument)| parse_sum(argument, *start)).collect::<Result<Vec<_>, _>>()?;
+
+  let types: Vec<CalcType> = nodes.iter().filter_map(CalcNode::calc_type).collect();
+  if types.len() == nodes.len() && function.result_type(&types).is_none() {
+    let same_types = function.takes_same_types(types.len());
+    let mismatch = (0..types.len())
+      .find(|&i| if same_types { types[0].add(types[i]).is_none() } else { function.result_type(&[types[i]]).is_none() })
+      .unwrap_or(0);
+    let start = arguments[mismatch].1.iter().find(|token| ***token != ComponentValue::Whitespace).map_or(At::Start(token), |first| At::Start(first));
+    let expected = match function {
+      _ if same_types => "arguments of the same type",
+      MathFunction::Sin | MathFunction::Cos | MathFunction::Tan => "a number or an angle",
+      _ => "a number",
+    };
+    return Err(invalid(start, expected));
+  }
+  Ok(CalcNode::Function(function, nodes))
+}
+
+/// Finds where the outermost math function `token` gets a type that is not a
+/// valid result, such as the second length in `calc(1px * 2px)`: the factor
+/// of a top-level product after which its type last became invalid.
+fn invalid_result<'a>(token: &'a ComponentValue, arguments: &'a [ComponentValue]) -> CalcError<'a> {
+  for (_, argument) in split_arguments(token, arguments) {
+    for product in argument.split(|token| matches!(token, ComponentValue::Delim('+' | '-'))) {
+      let product: Vec<&ComponentValue> = product.iter().copied().filter(|token| **token != ComponentValue::Whitespace).collect();
+      let mut product_type = NUMBER;
+      let mut culprit = None;
+      for (index, factor) in product.iter().enumerate().step_by(2) {
+        let Some(factor_type) = parse_value(factor).ok().and_then(|value| value.calc_type()) else {
+          continue;
+        };
+        let inverted = index > 0 && *product[index - 1] == ComponentValue::Slash;
+        let was_valid = product_type.is_valid_result();
+        product_type = product_type.multiply(if inverted { factor_type.invert() } else { factor_type });
+        if was_valid && !product_type.is_valid_result() {
+          culprit = Some((*factor, inverted));
+        }
+      }
+      if let Some((factor, inverted)) = culprit.filter(|_| !product_type.is_valid_result()) {
+        let expected = if inverted { "a number after `/`" } else { "a number on one side of `*`" };
+        return invalid(At::Start(factor), expected);
+      }
+    }
+  }
+  invalid(At::Start(token), "a number, dimension or percentage as the result")
+}
+
+/// Returns `input` from the position `at` points to, given the components
+/// `input` was parsed into.
+fn locate<'a>(input: &'a str, components: &[ComponentValue], at: At<'_>) -> &'a str {
+  fn index_of(components: &[ComponentValue], target: &ComponentValue, index: &mut usize) -> bool {
+    for component in components.iter().filter(|component| **component != ComponentValue::Whitespace) {
+      if std::ptr::eq(component, target) {
+        return true;
+      }
+      *index += 1;
+      let children = match component {
+        ComponentValue::Function { arguments, .. } => arguments,
+        ComponentValue::Block { contents, .. } => contents,
+        _ => continue,
+      };
+      if index_of(children, target, index) {
+        return true;
+      }
+    }
+    false
+  }
+
+  let (At::Start(target) | At::End(target) | At::Inside(target)) = at;
+  let mut index = 0;
+  if !index_of(components, target, &mut index) {
+    return input.trim_start();
+  }
+
+  // The components are visited in the same order, each with its own text
+  let mut visited = 0;
+  let mut found = None;
+  replace_components(input, &mut |_, text| {
+    if visited == index {
+      found = Some((text.as_ptr() as usize - input.as_ptr() as usize, text.len(), text.find('(')));
+    }
+    visited += 1;
+    None
+  });
+  let Some((offset, len, open)) = found else {
+    return input.trim_start();
+  };
+
+  let offset = match at {
+    At::Start(_) => offset,
+    At::End(_) => offset + len,
+    At::Inside(_) => offset + open.map_or(1, |open| open + 1),
+  };
+  &input[offset..]
+}
+
+/// Adds `node` to the terms of a sum, merging it with a term of the same unit.
+fn add_term(terms: &mut Vec<CalcNode>, node: CalcNode) {
+  match node {
+    CalcNode::Sum(children) => children.into_iter().for_each(|child| add_term(terms, child)),
+    CalcNode::Value(value) => {
+      let same = terms.iter_mut().find_map(|term| match term {
+        CalcNode::Value(other) if other.same_unit(value) => Some(other),
+        _ => None,
+      });
+      match same {
+        Some(other) => *other = other.with_value(other.value() + value.value()),
+        None => terms.push(CalcNode::Value(value)),
+      }
+    }
+    node => terms.push(node),
+  }
+}
+
+impl CalcNode {
+  /// Interprets a parsed math function, such as `calc(1px + 2em)` or
+  /// `min(10px, 5vw)`. Returns `None` for other values and for invalid
+  /// expressions.
+  pub fn from_component(value: &ComponentValue) -> Option<CalcNode> {
+    Self::parse_component(value).ok()
+  }
+
+  fn parse_component(value: &ComponentValue) -> CalcResult<'_> {
+    let ComponentValue::Function { name, arguments } = value else {
+      return Err(invalid(At::Start(value), "a math function"));
+    };
+    let node = parse_function(value, name, arguments)?;
+    if !node.calc_type().is_some_and(CalcType::is_valid_result) {
+      return Err(invalid_result(value, arguments));
+    }
+    Ok(node)
+  }
+
+  /// Parses a math function. An invalid expression is reported at the
+  /// offending token, with what was expected there.
+  pub fn from_string(input: &str) -> Result<CalcNode, ParseError> {
+    let value = Value::from_string(input)?;
+    let error = |at: &str, expected: &str| ParseError::new(input, at, ParseErrorKind::InvalidValue, expected);
+    match value.components.as_slice() {
+      [] => Err(error(input.trim_start(), "a math function")),
+      [component] => Self::parse_component(component).map_err(|invalid| error(locate(input, &value.components, invalid.at), &invalid.expected)),
+      [_, rest @ ..] => {
+        let extra = rest.iter().find(|component| **component != ComponentValue::Whitespace).unwrap_or(&rest[0]);
+        Err(error(locate(input, &value.components, At::Start(extra)), "the end of the value"))
+      }
+    }
+  }
+
+  /// Whether `name` is the name of a math function, such as `calc` or `clamp`.
+  pub fn is_math_function(name: &str) -> bool {
+    name.eq_ignore_ascii_case("calc") || MathFunction::from_name(name).is_some()
+  }
+
+  fn calc_type(&self) -> Option<CalcType> {
+    match self {
+      CalcNode::Value(value) => Some(value.calc_type()),
+      CalcNode::Constant(_) => Some(NUMBER),
+      CalcNode::Placeholder(_) => Some(CalcType::Unknown),
+      CalcNode::Sum(children) => {
+        let mut types = children.iter().map(CalcNode::calc_type);
+        let first = types.next()??;
+        types.try_fold(first, |result, other| result.add(other?))
+      }
+      CalcNode::Product(children) => {
+        children.iter().try_fold(NUMBER, |result, child| Some(result.multiply(child.calc_type()?)))
+      }
+      CalcNode::Negate(child) => child.calc_type(),
+      CalcNode::Invert(child) => child.calc_type().map(CalcType::invert),
+      CalcNode::Function(function, arguments) => {
+        let types = arguments.iter().map(CalcNode::calc_type).collect::<Option<Vec<_>>>()?;
+        function.result_type(&types)
+      }
+    }
+  }
+
+  /// Simplifies the expression following CSS Values 4: absolute units become
+  /// canonical, terms with the same unit are added, numbers are multiplied
+  /// in, and functions of known values are computed. The result is a single
+  /// `Value` when everything could be computed.
+  pub fn simplify(&self) -> CalcNode {
+    match self {
+      CalcNode::Value(value) => CalcNode::Value(value.canonical()),
+      CalcNode::Constant(constant) => CalcNode::Value(Numeric::Number(constant.value())),
+      CalcNode::Placeholder(_) => self.clone(),
+      CalcNode::Negate(child) => match child.simplify() {
+        CalcNode::Value(value) => CalcNode::Value(value.with_value(-value.value())),
+        CalcNode::Negate(inner) => *inner,
+        CalcNode::Sum(terms) => {
+          CalcNode::Sum(terms.into_iter().map(|term| CalcNode::Negate(Box::new(term))).collect()).simplify()
+        }
+        CalcNode::Product(mut factors) if matches!(factors[0], CalcNode::Value(Numeric::Number(_))) => {
+          factors[0] = CalcNode::Negate(Box::new(factors[0].clone()));
+          CalcNode::Product(factors).simplify()
+        }
+        child => CalcNode::Negate(Box::new(child)),
+      },
+      CalcNode::Invert(child) => match child.simplify() {
+        CalcNode::Value(Numeric::Number(number)) => CalcNode::Value(Numeric::Number(1.0 / number)),
+        CalcNode::Invert(inner) => *inner,
+        child => CalcNode::Invert(Box::new(child)),
+      },
+      CalcNode::Sum(children) => {
+        let mut terms = Vec::new();
+        for child in children {
+          add_term(&mut terms, child.simplify());
+        }
+        // Known values first, by unit, then the rest in their order
+        terms.sort_by(|a, b| match (a, b) {
+          (CalcNode::Value(a), CalcNode::Value(b)) => a.sort_key().cmp(&b.sort_key()),
+          (CalcNode::Value(_), _) => Ordering::Less,
+          (_, CalcNode::Value(_)) => Ordering::Greater,
+          _ => Ordering::Equal,
+        });
+        if terms.len() == 1 { terms.remove(0) } else { CalcNode::Sum(terms) }
+      }
+      CalcNode::Product(children) => {
+        let mut factors = Vec::new();
+        for child in children {
+          match child.simplify() {
+            CalcNode::Product(inner) => factors.extend(inner),
+            child => factors.push(child),
+          }
+        }
+        let mut scale = 1.0;
+        factors.retain(|factor| match factor {
+          CalcNode::Value(Numeric::Number(number)) => {
+            scale *= number;
+            false
+          }
+          _ => true,
+        });
+
+        // A value divided by a value of the same unit is a number
+        let mut index = 0;
+        while index < factors.len() {
+          let divisor = match &factors[index] {
+            CalcNode::Invert(inner) => match **inner {
+              CalcNode::Value(value) => Some(value),
+              _ => None,
+            },
+            _ => None,
+          };
+          let dividend = divisor.and_then(|divisor| factors.iter().position(|factor| {
+            matches!(factor, CalcNode::Value(value) if value.same_unit(divisor))
+          }));
+          match (divisor, dividend) {
+            (Some(divisor), Some(dividend)) => {
+              let CalcNode::Value(value) = factors[dividend] else { unreachable!() };
+              scale *= value.value() / divisor.value();
+              factors.remove(index.max(dividend));
+              factors.remove(index.min(dividend));
+              index = 0;
+            }
+            _ => index += 1,
+          }
+        }
+
+        match factors.as_slice() {
+          [] => CalcNode::Value(Numeric::Number(scale)),
+          [CalcNode::Value(value)] => CalcNode::Value(value.with_value(value.value() * scale)),
+          [CalcNode::Sum(terms)] if scale != 1.0 => {
+            let terms = terms.iter().map(|term| CalcNode::Product(vec![CalcNode::Value(Numeric::Number(scale)), term.clone()]));
+            CalcNode::Sum(terms.collect()).simplify()
+          }
+          [factor] if scale == 1.0 => factor.clone(),
+          _ => {
+            if scale != 1.0 {
+              factors.insert(0, CalcNode::Value(Numeric::Number(scale)));
+            }
+            CalcNode::Product(factors)
+          }
+        }
+      }
+      CalcNode::Function(function, arguments) => {
+        let arguments: Vec<CalcNode> = arguments.iter().map(CalcNode::simplify).collect();
+        let values: Option<Vec<Numeric>> = arguments.iter().map(|argument| match argument {
+          CalcNode::Value(value) => Some(*value),
+          _ => None,
+        }).collect();
+        match values.and_then(|values| function.compute(&values)) {
+          Some(value) => CalcNode::Value(value),
+          None => CalcNode::Function(*function, arguments),
+        }
+      }
+    }
+  }
+
+  /// Returns a copy of the expression with `map` applied to every value.
+  fn map_values(&self, map: &impl Fn(Numeric) -> Option<Numeric>) -> Option<CalcNode> {
+    let node = match self {
+      CalcNode::Value(value) => CalcNode::Value(map(*value)?),
+      CalcNode::Constant(_) => self.clone(),
+      CalcNode::Placeholder(_) => return None,
+      CalcNode::Sum(children) => CalcNode::Sum(children.iter().map(|child| child.map_values(map)).collect::<Option<_>>()?),
+      CalcNode::Product(children) => CalcNode::Product(children.iter().map(|child| child.map_values(map)).collect::<Option<_>>()?),
+      CalcNode::Negate(child) => CalcNode::Negate(Box::new(child.map_values(map)?)),
+      CalcNode::Invert(child) => CalcNode::Invert(Box::new(child.map_values(map)?)),
+      CalcNode::Function(function, arguments) => {
+        CalcNode::Function(*function, arguments.iter().map(|argument| argument.map_values(map)).collect::<Option<_>>()?)
+      }
+    };
+    Some(node)
+  }
+
+  /// Computes the value of the expression: relative units are resolved
+  /// against `context`, and percentages against `percentage_basis`. The
+  /// result is in a canonical unit. Returns `None` when a placeholder is
+  /// left, or a percentage has no basis.
+  pub fn evaluate(&self, context: &ResolutionContext, percentage_basis: Option<Dimension>) -> Option<Numeric> {
+    let resolved = self.map_values(&|value| match value {
+      Numeric::Dimension(dimension) => dimension.resolve(context).map(Numeric::Dimension),
+      Numeric::Percentage(percentage) => match percentage_basis {
+        Some(basis) => basis.resolve(context).map(|basis| Numeric::Dimension(Dimension::new(basis.value * percentage / 100.0, basis.unit))),
+        None => Some(value),
+      },
+      number => Some(number),
+    })?;
+
+    match resolved.simplify() {
+      CalcNode::Value(value) => Some(value),
+      _ => None,
+    }
+  }
+
+  /// Writes the expression without the `calc()` around operators.
+  fn write_expression(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      CalcNode::Sum(children) => {
+        for (index, child) in children.iter().enumerate() {
+          match child {
+            _ if index == 0 => child.write_operand(f, true)?,
+            CalcNode::Negate(inner) => {
+              write!(f, " - ")?;
+              inner.write_operand(f, true)?;
+            }
+            CalcNode::Value(value) if value.value() < 0.0 => {
+              write!(f, " - ")?;
+              value.with_value(-value.value()).write_operand(f)?;
+            }
+            child => {
+              write!(f, " + ")?;
+              child.write_operand(f, true)?;
+            }
+          }
+        }
+        Ok(())
+      }
+      CalcNode::Product(children) => {
+        for (index, child) in children.iter().enumerate() {
+          match child {
+            CalcNode::Invert(inner) => {
+              write!(f, "{}", if index == 0 { "1 / " } else { " / " })?;
+              inner.write_operand(f, false)?;
+            }
+            child => {
+              if index > 0 {
+                write!(f, " * ")?;
+              }
+              child.write_operand(f, false)?;
+            }
+          }
+        }
+        Ok(())
+      }
+      CalcNode::Negate(inner) => {
+        write!(f, "-1 * ")?;
+        inner.write_operand(f, false)
+      }
+      CalcNode::Invert(inner) => {
+        write!(f, "1 / ")?;
+        inner.write_operand(f, false)
+      }
+      CalcNode::Value(value) => value.write_operand(f),
+      CalcNode::Constant(constant) => write!(f, "{}", constant.name()),
+      node => write!(f, "{}", node),
+    }
+  }
+
+  /// Writes a child of an operator, in parentheses unless it is a product
+  /// within a sum.
+  fn write_operand(&self, f: &mut fmt::Formatter<'_>, in_sum: bool) -> fmt::Result {
+    match self {
+      CalcNode::Product(_) if in_sum => self.write_expression(f),
+      // `infinity * 1px` is a product too
+      CalcNode::Value(value @ (Numeric::Percentage(_) | Numeric::Dimension(_))) if !in_sum && value.constant().is_some() => {
+        write!(f, "(")?;
+        self.write_expression(f)?;
+        write!(f, ")")
+      }
+      CalcNode::Sum(_) | CalcNode::Product(_) | CalcNode::Negate(_) | CalcNode::Invert(_) => {
+        write!(f, "(")?;
+        self.write_expression(f)?;
+        write!(f, ")")
+      }
+      CalcNode::Value(_) | CalcNode::Constant(_) => self.write_expression(f),
+      node => write!(f, "{}", node),
+    }
+  }
+}
+
+impl fmt::Display for Numeric {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    if self.constant().is_some() {
+      // `infinity` and `NaN` are only valid in math functions
+      write!(f, "calc(")?;
+      self.write_operand(f)?;
+      return write!(f, ")");
+    }
+    match self {
+      Numeric::Number(number) => write!(f, "{}", format_number(*number)),
+      Numeric::Percentage(percentage) => write!(f, "{}%", format_number(*percentage)),
+      Numeric::Dimension(dimension) => write!(f, "{}", dimension),
+    }
+  }
+}
+
+impl fmt::Display for CalcNode {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      CalcNode::Value(value) => write!(f, "{}", value),
+      CalcNode::Constant(constant) => write!(f, "calc({})", constant.name()),
+      CalcNode::Placeholder(placeholder) => write!(f, "{}", placeholder),
+      CalcNode::Function(function, arguments) => {
+        write!(f, "{}(", function.name())?;
+        let strategy = match function {
+          MathFunction::Round(RoundingStrategy::Up) => Some("up"),
+          MathFunction::Round(RoundingStrategy::Down) => Some("down"),
+          MathFunction::Round(RoundingStrategy::ToZero) => Some("to-zero"),
+          _ => None,
+        };
+        if let Some(strategy) = strategy {
+          write!(f, "{}, ", strategy)?;
+        }
+        for (index, argument) in arguments.iter().enumerate() {
+          if index > 0 {
+            write!(f, ", ")?;
+          }
+          argument.write_expression(f)?;
+        }
+        write!(f, ")")
+      }
+      node => {
+        write!(f, "calc(")?;
+        node.write_expression(f)?;
+        write!(f, ")")
+      }
+    }
+  }
+}
+
+/// Collects the outermost valid math functions in `components`.
+pub(crate) fn collect_math_functions(components: &[ComponentValue], functions: &mut Vec<CalcNode>) {
+  for component in components {
+    match component {
+      ComponentValue::Function { name, .. } if CalcNode::is_math_function(name) => {
+        functions.extend(CalcNode::from_component(component));
+      }
+      ComponentValue::Function { arguments, .. } | ComponentValue::Block { contents: arguments, .. } => {
+        collect_math_functions(arguments, functions);
+      }
+      _ => {}
+    }
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::css_declaration::CSSDeclaration;
+
+  fn simplified(input: &str) -> String {
+    CalcNode::from_string(input).unwrap().simplify().to_string()
+  }
+
+  #[test]
+  fn test_parse_and_display() {
+    let calc = CalcNode::from_string("calc( (1px + 2px) * 3 / 4 - -5px)").unwrap();
+    assert_eq!(calc.to_string(), "calc((1px + 2px) * 3 / 4 - -5px)");
+    assert_eq!(CalcNode::from_string("CALC(1px)").unwrap(), CalcNode::Value(Numeric::Dimension(Dimension::new(1.0, Unit::Px))));
+    assert_eq!(CalcNode::from_string("min(1px, 2em)").unwrap().to_string(), "min(1px, 2em)");
+    assert_eq!(CalcNode::from_string("round(to-zero, 1.5, 1)").unwrap().to_string(), "round(to-zero, 1.5, 1)");
+    assert_eq!(CalcNode::from_string("calc(pi * 1deg)").unwrap().to_string(), "calc(pi * 1deg)");
+    assert_eq!(CalcNode::from_string("cos(pi)").unwrap().to_string(), "cos(pi)");
+    assert_eq!(CalcNode::from_string("calc(E)").unwrap().to_string(), "calc(e)");
+    assert_eq!(CalcNode::from_string("calc(-infinity)").unwrap().to_string(), "calc(-infinity)");
+    assert_eq!(CalcNode::from_string("calc(1px / infinity)").unwrap().to_string(), "calc(1px / infinity)");
+  }
+
+  #[test]
+  fn test_invalid_expressions() {
+    for input in [
+      "calc(1px + 2)", "calc(1px+2px)", "calc(1px -2px)", "calc(1px *)", "calc()", "calc(1foo)",
+      "min()", "clamp(1px, 2px)", "sin(1px)", "pow(2px, 2)", "calc(1px * 1px)", "calc(red)", "rgb(1 2 3)",
+    ] {
+      assert!(CalcNode::from_string(input).is_err(), "{}", input);
+    }
+    assert_eq!(CalcNode::from_string("calc(1px + 2)").unwrap_err().kind, ParseErrorKind::InvalidValue);
+    assert!(CalcNode::from_string("calc(1px * 1px / 2px)").is_ok());
+    assert!(CalcNode::from_string("calc(50% + 2em)").is_ok());
+  }
+
+  #[test]
+  fn test_error_positions() {
+    let error = |input: &str| {
+      let error = CalcNode::from_string(input).unwrap_err();
+      (error.offset, error.expected, error.found)
+    };
+    assert_eq!(error("calc(1px + 1)"), (11, "operands of the same type".to_string(), "`1`".to_string()));
+    assert_eq!(error("calc(1px * 2px)"), (11, "a number on one side of `*`".to_string(), "`2px`".to_string()));
+    assert_eq!(error("calc(1 / 2px)"), (9, "a number after `/`".to_string(), "`2px`".to_string()));
+    assert_eq!(error("calc(1px +)"), (10, "a value after `+`".to_string(), "`)`".to_string()));
+    assert_eq!(error("calc(1px *)"), (10, "a value after `*`".to_string(), "`)`".to_string()));
+    assert_eq!(error("calc(1px+ 2px)"), (8, "whitespace around `+`".to_string(), "`+`".to_string()));
+    assert_eq!(error("calc(1px+2px)"), (8, "an operator, with whitespace around `+` and `-`".to_string(), "`+`".to_string()));
+    assert_eq!(error("calc()"), (5, "a value".to_string(), "`)`".to_string()));
+    assert_eq!(error("min(1px, (2px + 1))"), (16, "operands of the same type".to_string(), "`1`".to_string()));
+    assert_eq!(error("max(1px, 2s)"), (9, "arguments of the same type".to_string(), "`2s`".to_string()));
+    assert_eq!(error("calc(2 * sin(1px))"), (13, "a number or an angle".to_string(), "`1px`".to_string()));
+    assert_eq!(error("clamp(1px, 2px)"), (0, "three arguments".to_string(), "`clamp`".to_string()));
+    assert_eq!(error("calc(red)"), (5, "a number, dimension, percentage or math function".to_string(), "`red`".to_string()));
+    assert_eq!(error("rgb(1 2 3)"), (0, "a math function".to_string(), "`rgb`".to_string()));
+    assert_eq!(error("calc(1px) 2px"), (10, "the end of the value".to_string(), "`2px`".to_string()));
+    assert_eq!(
+      CalcNode::from_string("calc(1px +\n 1)").unwrap_err().to_string(),
+      "2:2: invalid value: expected operands of the same type, found `1`"
+    );
+  }
+
+  #[test]
+  fn test_simplify() {
+    assert_eq!(simplified("calc(10px + 2 * 5px)"), "20px");
+    assert_eq!(simplified("calc(1in + 10px)"), "106px");
+    assert_eq!(simplified("calc(200ms)"), "0.2s");
+    assert_eq!(simplified("calc(1em + 10px + 1em)"), "calc(2em + 10px)");
+    assert_eq!(simplified("calc(2 * (1em + 10px))"), "calc(2em + 20px)");
+    assert_eq!(simplified("calc(10px / 4px * 1em)"), "2.5em");
+    assert_eq!(simplified("calc(1em - 1em)"), "0em");
+    assert_eq!(simplified("calc(100% - (20px + 1em))"), "calc(100% - 1em - 20px)");
+    assert_eq!(simplified("calc(1px / 0)"), "calc(infinity * 1px)");
+    assert_eq!(simplified("calc(-1 / 0)"), "calc(-infinity)");
+    assert_eq!(simplified("calc(NaN)"), "calc(NaN)");
+    assert_eq!(simplified("calc(NaN * 1%)"), "calc(NaN * 1%)");
+    assert_eq!(simplified("calc(1em - 1px / 0)"), "calc(1em - infinity * 1px)");
+    assert_eq!(simplified("min(1em, 1px / 0)"), "min(1em, infinity * 1px)");
+    assert_eq!(simplified("calc(2 * pi)"), "6.283185");
+    let px = |value: f64| CalcNode::Value(Numeric::Dimension(Dimension::new(value, Unit::Px)));
+    let quotient = CalcNode::Product(vec![px(1.0), CalcNode::Invert(Box::new(px(f64::INFINITY)))]);
+    assert_eq!(quotient.to_string(), "calc(1px / (infinity * 1px))");
+    assert_eq!(simplified("calc(var(--x) + 1px + 1px)"), "calc(2px + var(--x))");
+    assert_eq!(simplified("calc(1px - 2 * var(--x) * 3)"), "calc(1px + -6 * var(--x))");
+  }
+
+  #[test]
+  fn test_functions() {
+    assert_eq!(simplified("min(10px, 1in, 5px)"), "5px");
+    assert_eq!(simplified("max(1em, 2em)"), "2em");
+    assert_eq!(simplified("min(1em, 10px)"), "min(1em, 10px)");
+    assert_eq!(simplified("clamp(10px, 50px, 20px)"), "20px");
+    assert_eq!(simplified("clamp(none, 50px, 20px)"), "20px");
+    assert_eq!(simplified("clamp(30px, 10px, none)"), "30px");
+    assert_eq!(simplified("round(7.5)"), "8");
+    assert_eq!(simplified("round(-7.5)"), "-7");
+    assert_eq!(simplified("round(down, 7.9px, 2px)"), "6px");
+    assert_eq!(simplified("round(to-zero, -7.9px, 2px)"), "-6px");
+    assert_eq!(simplified("mod(-7, 3)"), "2");
+    assert_eq!(simplified("rem(-7, 3)"), "-1");
+    assert_eq!(simplified("mod(1in, 5px)"), "1px");
+    assert_eq!(simplified("sin(90deg)"), "1");
+    assert_eq!(simplified("cos(pi)"), "-1");
+    assert_eq!(simplified("tan(0.125turn)"), "1");
+    assert_eq!(simplified("asin(1)"), "90deg");
+    assert_eq!(simplified("atan2(1px, 1px)"), "45deg");
+    assert_eq!(simplified("pow(2, 10)"), "1024");
+    assert_eq!(simplified("sqrt(16)"), "4");
+    assert_eq!(simplified("hypot(3px, 4px)"), "5px");
+    assert_eq!(simplified("log(8, 2)"), "3");
+    assert_eq!(simplified("exp(0)"), "1");
+    assert_eq!(simplified("abs(-2em)"), "2em");
+    assert_eq!(simplified("sign(-2em)"), "-1");
+    assert_eq!(simplified("calc(sin(var(--a)) * 2)"), "calc(2 * sin(var(--a)))");
+  }
+
+  #[test]
+  fn test_tan_asymptotes() {
+    assert_eq!(simplified("tan(90deg)"), "calc(infinity)");
+    assert_eq!(simplified("tan(450deg)"), "calc(infinity)");
+    assert_eq!(simplified("tan(0.25turn)"), "calc(infinity)");
+    assert_eq!(simplified("tan(-90deg)"), "calc(-infinity)");
+    assert_eq!(simplified("tan(270deg)"), "calc(-infinity)");
+    assert_eq!(simplified("tan(-270deg)"), "calc(infinity)");
+    assert_eq!(simplified("tan(45deg)"), "1");
+  }
+
+  #[test]
+  fn test_evaluate() {
+    let mut context = ResolutionContext::new(1000.0, 800.0);
+    context.font.font_size = 20.0;
+    let evaluate = |input: &str, basis: Option<Dimension>| CalcNode::from_string(input).unwrap().evaluate(&context, basis);
+    let px = |value: f64| Some(Numeric::Dimension(Dimension::new(value, Unit::Px)));
+
+    assert_eq!(evaluate("calc(1em + 10px)", None), px(30.0));
+    assert_eq!(evaluate("calc(100% - 2em)", Some(Dimension::new(10.0, Unit::Em))), px(160.0));
+    assert_eq!(evaluate("max(10vw, 5em)", None), px(100.0));
+    assert_eq!(evaluate("calc(50%)", None), Some(Numeric::Percentage(50.0)));
+    assert_eq!(evaluate("calc(50% + 1px)", None), None);
+    assert_eq!(evaluate("calc(var(--x) + 1px)", None), None);
+    assert_eq!(evaluate("calc(2 * 3)", None), Some(Numeric::Number(6.0)));
+  }
+
+  #[test]
+  fn test_declaration_math_functions() {
+    let declaration = CSSDeclaration::from_string("margin: calc(1px + 1px) max(1em, 2px) var(--x, min(1px, 2px)) calc(1px + 1)").unwrap();
+    let functions: Vec<String> = declaration.math_functions().iter().map(ToString::to_string).collect();
+    assert_eq!(functions, ["calc(1px + 1px)", "max(1em, 2px)", "min(1px, 2px)"]);
+  }
+}
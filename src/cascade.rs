@@ -0,0 +1,567 @@
+//! CSS Cascade
+//!
+//! This module resolves which declarations apply to an element (CSS Cascading
+//! and Inheritance Level 5). Stylesheets are added to a `Cascade` with their
+//! `Origin`, and for a given `Element` the declarations of every matching style
+//! rule are sorted by:
+//!
+//! 1. Origin and importance: user-agent, user and author normal declarations,
+//!    then author, user and user-agent `!important` declarations.
+//! 2. Context: shadow trees are not modeled, so every declaration has the same.
+//! 3. The element's `style` attribute, which wins over style rules of the
+//!    same origin and importance, whatever their layer.
+//! 4. Cascade layer: for normal declarations, layers in the order they were
+//!    first declared and unlayered styles last; the reverse for `!important`.
+//! 5. Specificity of the most specific matching selector.
+//! 6. Source order, stylesheets counting in the order they were added.
+//!
+//! Nested rules are flattened first (see `nesting`). Rules inside `@media`,
+//! `@supports` and `@container` only apply when the condition callback given
+//! to `Cascade::with_conditions` accepts their condition; by default they do
+//! not. `@scope`, `@starting-style` and `@import` rules are not applied.
+//!
+//! ## Main API
+//!
+//! - `Cascade::new()` / `Cascade::add_stylesheet()` - Collect stylesheets by origin
+//! - `Cascade::resolve()` - The winning declaration for each property of an element
+//! - `Cascade::matching_declarations()` - All declarations that apply, in cascade order
+//! - `Cascade::computed_style()` - The complete style, with inheritance and initial values (see `computed_style`)
+//!
+//! ## Examples
+//!
+//! ```rust
+//! use css_structs::{Cascade, Element, Origin, Stylesheet};
+//!
+//! #[derive(Clone, PartialEq)]
+//! struct Button;
+//!
+//! impl Element for Button {
+//!   fn parent_element(&self) -> Option<Self> { None }
+//!   fn previous_sibling_element(&self) -> Option<Self> { None }
+//!   fn next_sibling_element(&self) -> Option<Self> { None }
+//!   fn first_child_element(&self) -> Option<Self> { None }
+//!   fn local_name(&self) -> &str { "button" }
+//!   fn id(&self) -> Option<&str> { Some("send") }
+//!   fn has_class(&self, name: &str) -> bool { name == "primary" }
+//!   fn attribute(&self, _name: &str) -> Option<&str> { None }
+//! }
+//!
+//! let mut cascade = Cascade::new();
+//! cascade.add_stylesheet(Origin::UserAgent, &Stylesheet::from_string("button { color: black; padding: 1px }").unwrap());
+//! cascade.add_stylesheet(Origin::Author, &Stylesheet::from_string("
+//!   #send { color: green }
+//!   button.primary { color: blue !important }
+//!   .primary { color: red }
+//! ").unwrap());
+//!
+//! let style = cascade.resolve(&Button);
+//! assert_eq!(style["color"].declaration.value, "blue");
+//! assert_eq!(style["padding"].origin, Origin::UserAgent);
+//! ```
+
+
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+use crate::computed_style::{compute, ComputedStyle};
+use crate::css_declaration::CSSDeclaration;
+use crate::css_declaration_list::CSSDeclarationList;
+use crate::matching::Element;
+use crate::nesting::NestingMode;
+use crate::rule::Rule;
+use crate::selector::{Selector, SelectorList, Specificity};
+use crate::stylesheet::Stylesheet;
+
+
+/// Where a stylesheet comes from, which decides its precedence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Origin {
+  /// The browser's default styles.
+  UserAgent,
+  /// Styles set by the reader.
+  User,
+  /// Styles of the document.
+  Author,
+}
+
+/// The condition of a conditional group rule, passed to the callback given
+/// to `Cascade::with_conditions`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Condition {
+  /// The media query list of `@media`.
+  Media(String),
+  /// The condition of `@supports`.
+  Supports(String),
+  /// The condition of `@container`.
+  Container(String),
+}
+
+/// A declaration that applies to an element, with what decides its place in
+/// the cascade.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CascadedDeclaration {
+  pub declaration: CSSDeclaration,
+  pub origin: Origin,
+  /// The full name of the cascade layer, such as `base.reset`. Anonymous
+  /// layers are numbered: `<anonymous>1`, `<anonymous>2`, ...
+  pub layer: Option<String>,
+  /// The specificity of the most specific matching selector, or `None` for a
+  /// declaration of the element's `style` attribute.
+  pub specificity: Option<Specificity>,
+}
+
+/// A style rule collected from a stylesheet, with its selectors parsed.
+#[derive(Debug, Clone)]
+struct CascadeRule {
+  selectors: SelectorList,
+  declarations: CSSDeclarationList,
+  origin: Origin,
+  layer: Option<String>,
+  /// The position of the layer: sublayer indexes from the outermost layer,
+  /// each level ending with `usize::MAX` for the layer's own rules, so that
+  /// unlayered rules sort after every layer.
+  layer_order: Vec<usize>,
+  conditions: Vec<Condition>,
+}
+
+/// The layers declared in one origin, in the order they were first seen.
+#[derive(Debug, Default)]
+struct LayerRegistry {
+  orders: HashMap<String, Vec<usize>>,
+  sublayer_counts: HashMap<String, usize>,
+  anonymous_count: usize,
+}
+
+impl LayerRegistry {
+  /// Returns the full name and order of the sublayer `name` of `parent`,
+  /// registering it on first use. `None` registers an anonymous layer.
+  fn sublayer(&mut self, parent: Option<&str>, parent_order: &[usize], name: Option<&str>) -> (String, Vec<usize>) {
+    let segment = match name {
+      Some(name) => name.to_string(),
+      None => {
+        self.anonymous_count += 1;
+        format!("<anonymous>{}", self.anonymous_count)
+      }
+    };
+    let full_name = match parent {
+      Some(parent) => format!("{}.{}", parent, segment),
+      None => segment,
+    };
+
+    if let Some(order) = self.orders.get(&full_name) {
+      return (full_name, order.clone());
+    }
+
+    let count = self.sublayer_counts.entry(parent.unwrap_or("").to_string()).or_default();
+    let mut order = parent_order[..parent_order.len() - 1].to_vec();
+    order.push(*count);
+    order.push(usize::MAX);
+    *count += 1;
+    self.orders.insert(full_name.clone(), order.clone());
+
+    (full_name, order)
+  }
+
+  /// Registers a dotted layer name such as `base.reset` inside `parent`.
+  fn layer(&mut self, parent: Option<&str>, parent_order: &[usize], name: Option<&str>) -> (String, Vec<usize>) {
+    let Some(name) = name else {
+      return self.sublayer(parent, parent_order, None);
+    };
+
+    let mut current = (parent.map(str::to_string), parent_order.to_vec());
+    for segment in name.split('.') {
+      let (full_name, order) = self.sublayer(current.0.as_deref(), &current.1, Some(segment.trim()));
+      current = (Some(full_name), order);
+    }
+
+    (current.0.unwrap_or_default(), current.1)
+  }
+}
+
+/// Collects stylesheets and resolves the cascade for elements.
+pub struct Cascade {
+  rules: Vec<CascadeRule>,
+  layers: HashMap<Origin, LayerRegistry>,
+  conditions: Box<dyn Fn(&Condition) -> bool>,
+}
+
+impl Default for Cascade {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl std::fmt::Debug for Cascade {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("Cascade").field("rules", &self.rules).finish_non_exhaustive()
+  }
+}
+
+/// The property name as it is cascaded: ASCII lowercase, except for custom
+/// properties, which are case-sensitive.
+pub(crate) fn property_key(name: &str) -> String {
+  if name.starts_with("--") { name.to_string() } else { name.to_ascii_lowercase() }
+}
+
+impl Cascade {
+  pub fn new() -> Self {
+    Cascade {
+      rules: Vec::new(),
+      layers: HashMap::new(),
+      conditions: Box::new(|_| false),
+    }
+  }
+
+  /// Sets the callback deciding whether the rules inside an `@media`,
+  /// `@supports` or `@container` rule apply.
+  pub fn with_conditions(mut self, conditions: impl Fn(&Condition) -> bool + 'static) -> Self {
+    self.conditions = Box::new(conditions);
+    self
+  }
+
+  /// Adds a stylesheet. Its rules come after the rules of the stylesheets
+  /// added before, in source order. Selectors that cannot be parsed are
+  /// skipped, as browsers drop such rules.
+  pub fn add_stylesheet(&mut self, origin: Origin, stylesheet: &Stylesheet) {
+    let flat = stylesheet.flatten_nesting(NestingMode::Is);
+    let mut layers = self.layers.remove(&origin).unwrap_or_default();
+    self.collect(&flat.rules, origin, (None, &[usize::MAX]), &[], &mut layers);
+    self.layers.insert(origin, layers);
+  }
+
+  fn collect(
+    &mut self,
+    rules: &[Rule],
+    origin: Origin,
+    layer: (Option<&str>, &[usize]),
+    conditions: &[Condition],
+    layers: &mut LayerRegistry,
+  ) {
+    let (layer_name, layer_order) = layer;
+    let nested = |this: &mut Self, rules: &[Rule], condition: Option<Condition>, layers: &mut LayerRegistry| {
+      let conditions: Vec<Condition> = conditions.iter().cloned().chain(condition).collect();
+      this.collect(rules, origin, (layer_name, layer_order), &conditions, layers);
+    };
+
+    for rule in rules {
+      match rule {
+        Rule::Style(style_rule) => {
+          let Ok(selectors) = style_rule.selector_list() else {
+            continue;
+          };
+          self.rules.push(CascadeRule {
+            selectors,
+            declarations: style_rule.declarations.clone(),
+            origin,
+            layer: layer_name.map(str::to_string),
+            layer_order: layer_order.to_vec(),
+            conditions: conditions.to_vec(),
+          });
+        }
+        Rule::Media(media) => nested(self, &media.rules, Some(Condition::Media(media.media.clone())), layers),
+        Rule::Supports(supports) => {
+          nested(self, &supports.rules, Some(Condition::Supports(supports.condition.clone())), layers)
+        }
+        Rule::Container(container) => {
+          nested(self, &container.rules, Some(Condition::Container(container.condition.clone())), layers)
+        }
+        Rule::Layer(layer_rule) => match &layer_rule.rules {
+          // `@layer a, b;` only sets the order of the layers
+          None => {
+            for name in &layer_rule.names {
+              layers.layer(layer_name, layer_order, Some(name));
+            }
+          }
+          Some(rules) => {
+            let (name, order) = layers.layer(layer_name, layer_order, layer_rule.names.first().map(String::as_str));
+            self.collect(rules, origin, (Some(&name), &order), conditions, layers);
+          }
+        },
+        _ => {}
+      }
+    }
+  }
+
+  /// Returns every declaration that applies to `element`, from the lowest to
+  /// the highest precedence.
+  pub fn matching_declarations<E: Element>(&self, element: &E) -> Vec<CascadedDeclaration> {
+    // (declaration, layer order, position in source order)
+    let mut matched: Vec<(CascadedDeclaration, &[usize], usize)> = Vec::new();
+    let mut position = 0;
+
+    for rule in &self.rules {
+      let applies = rule.conditions.iter().all(|condition| (self.conditions)(condition));
+      let specificity = rule.selectors.selectors.iter()
+        .filter(|selector| applies && selector.matches(element))
+        .map(Selector::specificity)
+        .max();
+
+      for declaration in &rule.declarations.declarations {
+        position += 1;
+        if let Some(specificity) = specificity {
+          let cascaded = CascadedDeclaration {
+            declaration: declaration.clone(),
+            origin: rule.origin,
+            layer: rule.layer.clone(),
+            specificity: Some(specificity),
+          };
+          matched.push((cascaded, &rule.layer_order, position));
+        }
+      }
+    }
+
+    let unlayered = [usize::MAX];
+    if let Some(style) = element.attribute("style") {
+      let declarations = CSSDeclarationList::from_string_recovering(style).0.declarations;
+      for declaration in declarations {
+        position += 1;
+        let cascaded = CascadedDeclaration { declaration, origin: Origin::Author, layer: None, specificity: None };
+        matched.push((cascaded, &unlayered, position));
+      }
+    }
+
+    matched.sort_by(|a, b| compare(&a.0, a.1, a.2, &b.0, b.1, b.2));
+    matched.into_iter().map(|(cascaded, _, _)| cascaded).collect()
+  }
+
+  /// Returns the winning declaration for each property of `element`, keyed
+  /// by property name (lowercased, except for custom properties).
+  pub fn resolve<E: Element>(&self, element: &E) -> BTreeMap<String, CascadedDeclaration> {
+    self.matching_declarations(element)
+      .into_iter()
+      .map(|cascaded| (property_key(&cascaded.declaration.name), cascaded))
+      .collect()
+  }
+
+  /// Returns the complete style of `element`: cascaded values, with inherited
+  /// and initial values filled in. The styles of its ancestors are computed
+  /// along the way; use `computed_style_with_parent` to reuse them instead.
+  pub fn computed_style<E: Element>(&self, element: &E) -> ComputedStyle {
+    let parent = element.parent_element().map(|parent| self.computed_style(&parent));
+    self.computed_style_with_parent(element, parent.as_ref())
+  }
+
+  /// Returns the complete style of `element`, given the style of its parent
+  /// (`None` for the root element).
+  pub fn computed_style_with_parent<E: Element>(&self, element: &E, parent: Option<&ComputedStyle>) -> ComputedStyle {
+    compute(&self.matching_declarations(element), parent)
+  }
+}
+
+/// The precedence of an origin and importance, from lowest to highest.
+pub(crate) fn origin_rank(origin: Origin, important: bool) -> u8 {
+  match (important, origin) {
+    (false, Origin::UserAgent) => 0,
+    (false, Origin::User) => 1,
+    (false, Origin::Author) => 2,
+    (true, Origin::Author) => 3,
+    (true, Origin::User) => 4,
+    (true, Origin::UserAgent) => 5,
+  }
+}
+
+fn compare(
+  a: &CascadedDeclaration,
+  a_layer: &[usize],
+  a_position: usize,
+  b: &CascadedDeclaration,
+  b_layer: &[usize],
+  b_position: usize,
+) -> Ordering {
+  let important = a.declaration.important;
+  let layers = match important {
+    false => a_layer.cmp(b_layer),
+    true => b_layer.cmp(a_layer),
+  };
+
+  origin_rank(a.origin, important)
+    .cmp(&origin_rank(b.origin, b.declaration.important))
+    // The style attribute (no specificity) wins over style rules, before layers are compared
+    .then(a.specificity.is_none().cmp(&b.specificity.is_none()))
+    .then(layers)
+    .then(a.specificity.cmp(&b.specificity))
+    .then(a_position.cmp(&b_position))
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// A `<p>` inside a `<div>`, with optional classes and a style attribute.
+  #[derive(Clone, PartialEq)]
+  struct Paragraph {
+    class: &'static str,
+    style: Option<&'static str>,
+  }
+
+  impl Element for Paragraph {
+    fn parent_element(&self) -> Option<Self> {
+      None
+    }
+
+    fn previous_sibling_element(&self) -> Option<Self> {
+      None
+    }
+
+    fn next_sibling_element(&self) -> Option<Self> {
+      None
+    }
+
+    fn first_child_element(&self) -> Option<Self> {
+      None
+    }
+
+    fn local_name(&self) -> &str {
+      "p"
+    }
+
+    fn id(&self) -> Option<&str> {
+      None
+    }
+
+    fn has_class(&self, name: &str) -> bool {
+      self.class.split(' ').any(|class| class == name)
+    }
+
+    fn attribute(&self, name: &str) -> Option<&str> {
+      match name {
+        "style" => self.style,
+        _ => None,
+      }
+    }
+  }
+
+  const NOTE: Paragraph = Paragraph { class: "note", style: None };
+
+  fn cascade(sheets: &[(Origin, &str)]) -> Cascade {
+    let mut cascade = Cascade::new();
+    for (origin, css) in sheets {
+      cascade.add_stylesheet(*origin, &Stylesheet::from_string(css).unwrap());
+    }
+    cascade
+  }
+
+  fn value(cascade: &Cascade, element: &Paragraph, property: &str) -> Option<String> {
+    cascade.resolve(element).get(property).map(|cascaded| cascaded.declaration.value.clone())
+  }
+
+  #[test]
+  fn test_source_order_and_specificity() {
+    let cascade = cascade(&[(Origin::Author, "p { color: red } p { color: blue } .note { margin: 0 } p { margin: 1px }")]);
+    assert_eq!(value(&cascade, &NOTE, "color").as_deref(), Some("blue"));
+    assert_eq!(value(&cascade, &NOTE, "margin").as_deref(), Some("0"));
+  }
+
+  #[test]
+  fn test_selector_list_uses_most_specific_matching_selector() {
+    let cascade = cascade(&[(Origin::Author, ".note, div { color: red } p.x, p { color: blue }")]);
+    assert_eq!(value(&cascade, &NOTE, "color").as_deref(), Some("red"));
+    let resolved = cascade.resolve(&NOTE);
+    assert_eq!(resolved["color"].specificity, Some(Specificity::new(0, 1, 0)));
+  }
+
+  #[test]
+  fn test_origins_and_importance() {
+    let cascade = cascade(&[
+      (Origin::Author, "p { color: red; margin: 0 !important; padding: 1px !important }"),
+      (Origin::User, "p { color: green; margin: 1px !important }"),
+      (Origin::UserAgent, "p { color: blue; display: block; padding: 2px !important }"),
+    ]);
+    assert_eq!(value(&cascade, &NOTE, "color").as_deref(), Some("red"));
+    assert_eq!(value(&cascade, &NOTE, "margin").as_deref(), Some("1px"));
+    assert_eq!(value(&cascade, &NOTE, "padding").as_deref(), Some("2px"));
+    assert_eq!(value(&cascade, &NOTE, "display").as_deref(), Some("block"));
+  }
+
+  #[test]
+  fn test_important_beats_specificity() {
+    let cascade = cascade(&[(Origin::Author, "p { color: red !important } p.note { color: blue }")]);
+    assert_eq!(value(&cascade, &NOTE, "color").as_deref(), Some("red"));
+  }
+
+  #[test]
+  fn test_style_attribute() {
+    let cascade = cascade(&[(Origin::Author, "p.note { color: red; margin: 0 !important }")]);
+    let element = Paragraph { class: "note", style: Some("color: blue; margin: 1px") };
+    assert_eq!(value(&cascade, &element, "color").as_deref(), Some("blue"));
+    assert_eq!(value(&cascade, &element, "margin").as_deref(), Some("0"));
+    assert_eq!(cascade.resolve(&element)["color"].specificity, None);
+  }
+
+  #[test]
+  fn test_style_attribute_beats_layered_important_rules() {
+    let cascade = cascade(&[(Origin::Author, "@layer base { p.note { color: red !important; margin: 0 } }")]);
+    let element = Paragraph { class: "note", style: Some("color: blue !important; margin: 1px") };
+    assert_eq!(value(&cascade, &element, "color").as_deref(), Some("blue"));
+    assert_eq!(value(&cascade, &element, "margin").as_deref(), Some("1px"));
+  }
+
+  #[test]
+  fn test_layers() {
+    let cascade = cascade(&[(Origin::Author, "
+      @layer base, theme;
+      p { margin: 3px }
+      @layer theme { p.note { color: red; padding: 1px !important } }
+      @layer base { p.note { color: green; margin: 1px; padding: 2px !important } .note { color: blue } }
+    ")]);
+    // Later layers win, and unlayered styles win over all layers
+    assert_eq!(value(&cascade, &NOTE, "color").as_deref(), Some("red"));
+    assert_eq!(value(&cascade, &NOTE, "margin").as_deref(), Some("3px"));
+    // Earlier layers win for important declarations
+    assert_eq!(value(&cascade, &NOTE, "padding").as_deref(), Some("2px"));
+    assert_eq!(cascade.resolve(&NOTE)["padding"].layer.as_deref(), Some("base"));
+  }
+
+  #[test]
+  fn test_nested_layers() {
+    let cascade = cascade(&[(Origin::Author, "
+      @layer a { p { color: red } @layer b { p { color: blue } } }
+      @layer a.c { p { color: green; margin: 0 } }
+      @layer a { p { margin: 1px } }
+    ")]);
+    // A layer's own rules win over its sublayers
+    assert_eq!(value(&cascade, &NOTE, "color").as_deref(), Some("red"));
+    assert_eq!(value(&cascade, &NOTE, "margin").as_deref(), Some("1px"));
+    assert_eq!(cascade.matching_declarations(&NOTE)[0].layer.as_deref(), Some("a.b"));
+  }
+
+  #[test]
+  fn test_conditional_rules() {
+    let css = "p { color: red } @media print { p { color: black } } @supports (display: grid) { p { display: grid } }";
+    let ignored = cascade(&[(Origin::Author, css)]);
+    assert_eq!(value(&ignored, &NOTE, "color").as_deref(), Some("red"));
+    assert_eq!(value(&ignored, &NOTE, "display"), None);
+
+    let mut print = Cascade::new().with_conditions(|condition| condition != &Condition::Supports("(display: grid)".to_string()));
+    print.add_stylesheet(Origin::Author, &Stylesheet::from_string(css).unwrap());
+    assert_eq!(value(&print, &NOTE, "color").as_deref(), Some("black"));
+    assert_eq!(value(&print, &NOTE, "display"), None);
+  }
+
+  #[test]
+  fn test_nested_rules_and_unparsable_selectors() {
+    let mut cascade = cascade(&[(Origin::Author, ".note { color: red; &:not(.x) { color: blue } }")]);
+    assert_eq!(value(&cascade, &NOTE, "color").as_deref(), Some("blue"));
+
+    let declarations = CSSDeclarationList::from_string("color: green").unwrap();
+    let invalid = Rule::Style(crate::css_rule::CSSRule::new("p[", &declarations));
+    cascade.add_stylesheet(Origin::Author, &Stylesheet::new(Some(vec![invalid])));
+    assert_eq!(value(&cascade, &NOTE, "color").as_deref(), Some("blue"));
+  }
+
+  #[test]
+  fn test_property_names_are_case_insensitive() {
+    let cascade = cascade(&[(Origin::Author, "p { COLOR: red; --Main: 1 } .note { color: blue; --main: 2 }")]);
+    let resolved = cascade.resolve(&NOTE);
+    assert_eq!(resolved["color"].declaration.value, "blue");
+    assert_eq!(resolved["--Main"].declaration.value, "1");
+    assert_eq!(resolved["--main"].declaration.value, "2");
+  }
+
+  #[test]
+  fn test_matching_declarations_order() {
+    let cascade = cascade(&[(Origin::Author, "p { color: red !important } .note { color: blue } p { margin: 0 }")]);
+    let values: Vec<String> = cascade.matching_declarations(&NOTE).iter().map(|c| c.declaration.to_string()).collect();
+    assert_eq!(values, vec!["margin: 0;", "color: blue;", "color: red !important;"]);
+  }
+}
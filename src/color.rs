@@ -0,0 +1,1359 @@
+//! CSS Colors
+//!
+//! This module provides a `Color` type that parses every color syntax of CSS
+//! Color Levels 4 and 5: named colors, `transparent`, `currentcolor`, system
+//! colors, hex colors, `rgb()` / `hsl()` (legacy comma-separated and modern
+//! syntax), `hwb()`, `lab()` / `lch()` / `oklab()` / `oklch()`, `color()` with
+//! the predefined color spaces, `color-mix()` and relative colors such as
+//! `rgb(from red r g 50)`.
+//!
+//! An `AbsoluteColor` holds three components and an alpha in one of the
+//! `ColorSpace`s. It converts exactly between spaces (through CIE XYZ), and
+//! maps out-of-gamut colors into a space with the CSS gamut mapping
+//! algorithm (reducing OKLCH chroma). Missing (`none`) components are
+//! treated as zero.
+//!
+//! ## Main API
+//!
+//! - `Color::from_string()` - Parse a color
+//! - `Color::from_component()` - Interpret a parsed `ComponentValue` as a color
+//! - `Color::resolve()` - The absolute color, given the value of `currentcolor`
+//! - `AbsoluteColor::to_space()` / `AbsoluteColor::to_gamut()` - Convert between color spaces
+//! - `Color::serialize()` - Write a color in a chosen `ColorFormat`
+//! - `Stylesheet::normalize_colors()` - Rewrite every color of a stylesheet in one format
+//! - `Display` trait implementation for converting back to CSS string (CSSOM serialization)
+//!
+//! ## Examples
+//!
+//! ```rust
+//! use css_structs::{Color, ColorFormat, ColorSpace, Stylesheet};
+//!
+//! let color = Color::from_string("hsl(120deg 100% 25% / 50%)").unwrap();
+//! assert_eq!(color.to_string(), "rgba(0, 128, 0, 0.5)");
+//! assert_eq!(color.serialize(ColorFormat::Hex), "#00800080");
+//! assert_eq!(color.serialize(ColorFormat::Oklch), "oklch(0.518287 0.17636 142.495345 / 0.5)");
+//!
+//! let lab = Color::from_string("lab(50 40 -20)").unwrap().absolute().unwrap().to_space(ColorSpace::XyzD50);
+//! assert_eq!(lab.space, ColorSpace::XyzD50);
+//!
+//! // Out-of-gamut colors are mapped into sRGB when serialized in an sRGB format
+//! let p3 = Color::from_string("color(display-p3 0 1 0)").unwrap();
+//! assert_eq!(p3.serialize(ColorFormat::Hex), "#00fb29");
+//!
+//! let mix = Color::from_string("color-mix(in srgb, red 25%, blue)").unwrap();
+//! assert_eq!(mix.serialize(ColorFormat::Rgb), "rgb(64, 0, 191)");
+//!
+//! let mut stylesheet = Stylesheet::from_string("a { color: RED; border: 1px solid rgb(0 0 255) }").unwrap();
+//! stylesheet.normalize_colors(ColorFormat::Hex);
+//! assert_eq!(stylesheet.to_string(), "a { color: #ff0000; border: 1px solid #0000ff; }");
+//! ```
+
+
+use std::f64::consts::PI;
+use std::fmt;
+use crate::dimension::{Dimension, Unit};
+use crate::parse_error::{ParseError, ParseErrorKind};
+use crate::properties::standard_name;
+use crate::value::{format_number, replace_components, ComponentValue, Value};
+
+
+/// A color space in which an `AbsoluteColor` stores its components.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ColorSpace {
+  /// Red, green and blue from 0 to 1.
+  Srgb,
+  SrgbLinear,
+  DisplayP3,
+  A98Rgb,
+  ProphotoRgb,
+  Rec2020,
+  XyzD50,
+  XyzD65,
+  /// Hue in degrees, saturation and lightness from 0 to 100.
+  Hsl,
+  /// Hue in degrees, whiteness and blackness from 0 to 100.
+  Hwb,
+  /// Lightness from 0 to 100, and the `a` and `b` axes.
+  Lab,
+  /// Lightness from 0 to 100, chroma and hue in degrees.
+  Lch,
+  /// Lightness from 0 to 1, and the `a` and `b` axes.
+  Oklab,
+  /// Lightness from 0 to 1, chroma and hue in degrees.
+  Oklch,
+}
+
+/// A color whose components are known.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AbsoluteColor {
+  pub space: ColorSpace,
+  pub components: [f64; 3],
+  /// Opacity from 0 to 1.
+  pub alpha: f64,
+}
+
+/// The way `color-mix()` interpolates hues in a polar color space.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HueInterpolation {
+  #[default]
+  Shorter,
+  Longer,
+  Increasing,
+  Decreasing,
+}
+
+/// A `color-mix()` that cannot be computed yet, because one of its colors is
+/// `currentcolor` or a system color.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColorMix {
+  pub space: ColorSpace,
+  pub hue: HueInterpolation,
+  pub first: Color,
+  pub first_percentage: Option<f64>,
+  pub second: Color,
+  pub second_percentage: Option<f64>,
+}
+
+/// A CSS color value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Color {
+  CurrentColor,
+  /// A system color such as `Canvas` or `LinkText`, lowercased.
+  System(String),
+  Absolute(AbsoluteColor),
+  Mix(Box<ColorMix>),
+}
+
+/// The syntax `Color::serialize` writes a color in. The sRGB-based formats
+/// (hex, `rgb()`, `hsl()` and `hwb()`) map the color into the sRGB gamut first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorFormat {
+  /// `#rrggbb`, or `#rrggbbaa` when the color is not opaque.
+  Hex,
+  /// `rgb(r, g, b)`, or `rgba(r, g, b, a)` when the color is not opaque.
+  Rgb,
+  Hsl,
+  Hwb,
+  Lab,
+  Lch,
+  Oklab,
+  Oklch,
+  /// `color()` in one of the predefined RGB or XYZ spaces. Other spaces use
+  /// their own function.
+  Color(ColorSpace),
+}
+
+/// The named colors of CSS Color 4, sorted.
+static NAMED_COLORS: &[(&str, u32)] = &[
+  ("aliceblue", 0xf0f8ff), ("antiquewhite", 0xfaebd7), ("aqua", 0x00ffff), ("aquamarine", 0x7fffd4),
+  ("azure", 0xf0ffff), ("beige", 0xf5f5dc), ("bisque", 0xffe4c4), ("black", 0x000000),
+  ("blanchedalmond", 0xffebcd), ("blue", 0x0000ff), ("blueviolet", 0x8a2be2), ("brown", 0xa52a2a),
+  ("burlywood", 0xdeb887), ("cadetblue", 0x5f9ea0), ("chartreuse", 0x7fff00), ("chocolate", 0xd2691e),
+  ("coral", 0xff7f50), ("cornflowerblue", 0x6495ed), ("cornsilk", 0xfff8dc), ("crimson", 0xdc143c),
+  ("cyan", 0x00ffff), ("darkblue", 0x00008b), ("darkcyan", 0x008b8b), ("darkgoldenrod", 0xb8860b),
+  ("darkgray", 0xa9a9a9), ("darkgreen", 0x006400), ("darkgrey", 0xa9a9a9), ("darkkhaki", 0xbdb76b),
+  ("darkmagenta", 0x8b008b), ("darkolivegreen", 0x556b2f), ("darkorange", 0xff8c00), ("darkorchid", 0x9932cc),
+  ("darkred", 0x8b0000), ("darksalmon", 0xe9967a), ("darkseagreen", 0x8fbc8f), ("darkslateblue", 0x483d8b),
+  ("darkslategray", 0x2f4f4f), ("darkslategrey", 0x2f4f4f), ("darkturquoise", 0x00ced1), ("darkviolet", 0x9400d3),
+  ("deeppink", 0xff1493), ("deepskyblue", 0x00bfff), ("dimgray", 0x696969), ("dimgrey", 0x696969),
+  ("dodgerblue", 0x1e90ff), ("firebrick", 0xb22222), ("floralwhite", 0xfffaf0), ("forestgreen", 0x228b22),
+  ("fuchsia", 0xff00ff), ("gainsboro", 0xdcdcdc), ("ghostwhite", 0xf8f8ff), ("gold", 0xffd700),
+  ("goldenrod", 0xdaa520), ("gray", 0x808080), ("green", 0x008000), ("greenyellow", 0xadff2f),
+  ("grey", 0x808080), ("honeydew", 0xf0fff0), ("hotpink", 0xff69b4), ("indianred", 0xcd5c5c),
+  ("indigo", 0x4b0082), ("ivory", 0xfffff0), ("khaki", 0xf0e68c), ("lavender", 0xe6e6fa),
+  ("lavenderblush", 0xfff0f5), ("lawngreen", 0x7cfc00), ("lemonchiffon", 0xfffacd), ("lightblue", 0xadd8e6),
+  ("lightcoral", 0xf08080), ("lightcyan", 0xe0ffff), ("lightgoldenrodyellow", 0xfafad2), ("lightgray", 0xd3d3d3),
+  ("lightgreen", 0x90ee90), ("lightgrey", 0xd3d3d3), ("lightpink", 0xffb6c1), ("lightsalmon", 0xffa07a),
+  ("lightseagreen", 0x20b2aa), ("lightskyblue", 0x87cefa), ("lightslategray", 0x778899), ("lightslategrey", 0x778899),
+  ("lightsteelblue", 0xb0c4de), ("lightyellow", 0xffffe0), ("lime", 0x00ff00), ("limegreen", 0x32cd32),
+  ("linen", 0xfaf0e6), ("magenta", 0xff00ff), ("maroon", 0x800000), ("mediumaquamarine", 0x66cdaa),
+  ("mediumblue", 0x0000cd), ("mediumorchid", 0xba55d3), ("mediumpurple", 0x9370db), ("mediumseagreen", 0x3cb371),
+  ("mediumslateblue", 0x7b68ee), ("mediumspringgreen", 0x00fa9a), ("mediumturquoise", 0x48d1cc), ("mediumvioletred", 0xc71585),
+  ("midnightblue", 0x191970), ("mintcream", 0xf5fffa), ("mistyrose", 0xffe4e1), ("moccasin", 0xffe4b5),
+  ("navajowhite", 0xffdead), ("navy", 0x000080), ("oldlace", 0xfdf5e6), ("olive", 0x808000),
+  ("olivedrab", 0x6b8e23), ("orange", 0xffa500), ("orangered", 0xff4500), ("orchid", 0xda70d6),
+  ("palegoldenrod", 0xeee8aa), ("palegreen", 0x98fb98), ("paleturquoise", 0xafeeee), ("palevioletred", 0xdb7093),
+  ("papayawhip", 0xffefd5), ("peachpuff", 0xffdab9), ("peru", 0xcd853f), ("pink", 0xffc0cb),
+  ("plum", 0xdda0dd), ("powderblue", 0xb0e0e6), ("purple", 0x800080), ("rebeccapurple", 0x663399),
+  ("red", 0xff0000), ("rosybrown", 0xbc8f8f), ("royalblue", 0x4169e1), ("saddlebrown", 0x8b4513),
+  ("salmon", 0xfa8072), ("sandybrown", 0xf4a460), ("seagreen", 0x2e8b57), ("seashell", 0xfff5ee),
+  ("sienna", 0xa0522d), ("silver", 0xc0c0c0), ("skyblue", 0x87ceeb), ("slateblue", 0x6a5acd),
+  ("slategray", 0x708090), ("slategrey", 0x708090), ("snow", 0xfffafa), ("springgreen", 0x00ff7f),
+  ("steelblue", 0x4682b4), ("tan", 0xd2b48c), ("teal", 0x008080), ("thistle", 0xd8bfd8),
+  ("tomato", 0xff6347), ("turquoise", 0x40e0d0), ("violet", 0xee82ee), ("wheat", 0xf5deb3),
+  ("white", 0xffffff), ("whitesmoke", 0xf5f5f5), ("yellow", 0xffff00), ("yellowgreen", 0x9acd32),
+];
+
+static SYSTEM_COLORS: &[&str] = &[
+  "accentcolor", "accentcolortext", "activetext", "buttonborder", "buttonface", "buttontext", "canvas",
+  "canvastext", "field", "fieldtext", "graytext", "highlight", "highlighttext", "linktext", "mark",
+  "marktext", "selecteditem", "selecteditemtext", "visitedtext",
+];
+
+type Matrix = [[f64; 3]; 3];
+
+const SRGB_TO_XYZ: Matrix = [
+  [0.41239079926595934, 0.357584339383878, 0.1804807884018343],
+  [0.21263900587151027, 0.715168678767756, 0.07219231536073371],
+  [0.01933081871559182, 0.11919477979462598, 0.9505321522496607],
+];
+const XYZ_TO_SRGB: Matrix = [
+  [3.2409699419045226, -1.537383177570094, -0.4986107602930034],
+  [-0.9692436362808796, 1.8759675015077202, 0.04155505740717559],
+  [0.05563007969699366, -0.20397695888897652, 1.0569715142428786],
+];
+const P3_TO_XYZ: Matrix = [
+  [0.4865709486482162, 0.26566769316909306, 0.1982172852343625],
+  [0.2289745640697488, 0.6917385218365064, 0.079286914093745],
+  [0.0, 0.04511338185890264, 1.043944368900976],
+];
+const XYZ_TO_P3: Matrix = [
+  [2.493496911941425, -0.9313836179191239, -0.40271078445071684],
+  [-0.8294889695615747, 1.7626640603183463, 0.023624685841943577],
+  [0.03584583024378447, -0.07617238926804182, 0.9568845240076872],
+];
+const A98_TO_XYZ: Matrix = [
+  [0.5766690429101305, 0.1855582379065463, 0.1882286462349947],
+  [0.29734497525053605, 0.6273635662554661, 0.07529145849399788],
+  [0.02703136138641234, 0.07068885253582723, 0.9913375368376388],
+];
+const XYZ_TO_A98: Matrix = [
+  [2.0415879038107465, -0.5650069742788596, -0.34473135077832956],
+  [-0.9692436362808795, 1.8759675015077202, 0.04155505740717557],
+  [0.013444280632031142, -0.11836239223101838, 1.0151749943912054],
+];
+/// ProPhoto RGB is relative to D50.
+const PROPHOTO_TO_XYZ_D50: Matrix = [
+  [0.7977666449006423, 0.13518129740053308, 0.0313477341283922],
+  [0.2880748288194013, 0.711835234241873, 0.00008993693872564],
+  [0.0, 0.0, 0.8251046025104602],
+];
+const XYZ_D50_TO_PROPHOTO: Matrix = [
+  [1.3457868816471583, -0.25557208737979464, -0.05110186497554526],
+  [-0.5446307051249019, 1.5082477428451468, 0.02052744743642139],
+  [0.0, 0.0, 1.2119675456389452],
+];
+const REC2020_TO_XYZ: Matrix = [
+  [0.6369580483012914, 0.14461690358620832, 0.1688809751641721],
+  [0.2627002120112671, 0.6779980715188708, 0.05930171646986196],
+  [0.0, 0.028072693049087428, 1.060985057710791],
+];
+const XYZ_TO_REC2020: Matrix = [
+  [1.716651187971268, -0.355670783776392, -0.253366281373660],
+  [-0.666684351832489, 1.616481236634939, 0.0157685458139111],
+  [0.017639857445311, -0.042770613257809, 0.942103121235474],
+];
+/// Bradford chromatic adaptation between the D65 and D50 white points.
+const D65_TO_D50: Matrix = [
+  [1.0479298208405488, 0.022946793341019088, -0.05019222954313557],
+  [0.029627815688159344, 0.990434484573249, -0.01707382502938514],
+  [-0.009243058152591178, 0.015055144896577895, 0.7518742899580008],
+];
+const D50_TO_D65: Matrix = [
+  [0.9554734527042182, -0.023098536874261423, 0.0632593086610217],
+  [-0.028369706963208136, 1.0099954580058226, 0.021041398966943008],
+  [0.012314001688319899, -0.020507696433477912, 1.3303659366080753],
+];
+const XYZ_TO_LMS: Matrix = [
+  [0.819022437996703, 0.3619062600528904, -0.1288737815209879],
+  [0.0329836539323885, 0.9292868615863434, 0.0361446663506424],
+  [0.0481771893596242, 0.2642395317527308, 0.6335478284694309],
+];
+const LMS_TO_OKLAB: Matrix = [
+  [0.210454268309314, 0.7936177747023054, -0.0040720430116193],
+  [1.9779985324311684, -2.42859224204858, 0.450593709617411],
+  [0.0259040424655478, 0.7827717124575296, -0.8086757548730775],
+];
+const OKLAB_TO_LMS: Matrix = [
+  [1.0, 0.3963377773761749, 0.2158037573099136],
+  [1.0, -0.1055613458156586, -0.0638541728258133],
+  [1.0, -0.0894841775298119, -1.2914855480194092],
+];
+const LMS_TO_XYZ: Matrix = [
+  [1.2268798758459243, -0.5578149944602171, 0.2813910456659647],
+  [-0.0405757452148008, 1.112286803280317, -0.0717110580655164],
+  [-0.0763729366746601, -0.4214933324022432, 1.5869240198367816],
+];
+const D50_WHITE: [f64; 3] = [0.3457 / 0.3585, 1.0, (1.0 - 0.3457 - 0.3585) / 0.3585];
+
+fn multiply(matrix: &Matrix, vector: [f64; 3]) -> [f64; 3] {
+  matrix.map(|row| row[0] * vector[0] + row[1] * vector[1] + row[2] * vector[2])
+}
+
+/// Applies a transfer function to each component, keeping the sign so that
+/// out-of-range values extend the curve symmetrically.
+fn transfer(components: [f64; 3], function: impl Fn(f64) -> f64) -> [f64; 3] {
+  components.map(|c| c.signum() * function(c.abs()))
+}
+
+fn srgb_to_linear(c: f64) -> f64 {
+  if c <= 0.04045 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) }
+}
+
+fn srgb_from_linear(c: f64) -> f64 {
+  if c <= 0.0031308 { c * 12.92 } else { 1.055 * c.powf(1.0 / 2.4) - 0.055 }
+}
+
+fn prophoto_to_linear(c: f64) -> f64 {
+  if c <= 16.0 / 512.0 { c / 16.0 } else { c.powf(1.8) }
+}
+
+fn prophoto_from_linear(c: f64) -> f64 {
+  if c >= 1.0 / 512.0 { c.powf(1.0 / 1.8) } else { c * 16.0 }
+}
+
+const REC2020_ALPHA: f64 = 1.09929682680944;
+const REC2020_BETA: f64 = 0.018053968510807;
+
+fn rec2020_to_linear(c: f64) -> f64 {
+  if c < REC2020_BETA * 4.5 { c / 4.5 } else { ((c + REC2020_ALPHA - 1.0) / REC2020_ALPHA).powf(1.0 / 0.45) }
+}
+
+fn rec2020_from_linear(c: f64) -> f64 {
+  if c > REC2020_BETA { REC2020_ALPHA * c.powf(0.45) - (REC2020_ALPHA - 1.0) } else { c * 4.5 }
+}
+
+fn normalize_hue(hue: f64) -> f64 {
+  hue.rem_euclid(360.0)
+}
+
+fn hsl_to_srgb([hue, saturation, lightness]: [f64; 3]) -> [f64; 3] {
+  let hue = normalize_hue(hue);
+  let (saturation, lightness) = (saturation / 100.0, lightness / 100.0);
+  let channel = |n: f64| {
+    let k = (n + hue / 30.0) % 12.0;
+    let a = saturation * lightness.min(1.0 - lightness);
+    lightness - a * (k - 3.0).min(9.0 - k).clamp(-1.0, 1.0)
+  };
+  [channel(0.0), channel(8.0), channel(4.0)]
+}
+
+/// Returns the hue in degrees of an sRGB color, and the difference between
+/// its largest and smallest channels.
+fn srgb_hue([red, green, blue]: [f64; 3]) -> (f64, f64) {
+  let max = red.max(green).max(blue);
+  let min = red.min(green).min(blue);
+  let delta = max - min;
+  if delta == 0.0 {
+    return (0.0, 0.0);
+  }
+
+  let hue = if max == red {
+    (green - blue) / delta + if green < blue { 6.0 } else { 0.0 }
+  } else if max == green {
+    (blue - red) / delta + 2.0
+  } else {
+    (red - green) / delta + 4.0
+  };
+  (hue * 60.0, delta)
+}
+
+fn srgb_to_hsl(rgb: [f64; 3]) -> [f64; 3] {
+  let (mut hue, delta) = srgb_hue(rgb);
+  let max = rgb[0].max(rgb[1]).max(rgb[2]);
+  let lightness = max - delta / 2.0;
+  let mut saturation = match lightness == 0.0 || lightness == 1.0 || delta == 0.0 {
+    true => 0.0,
+    false => (max - lightness) / lightness.min(1.0 - lightness),
+  };
+  // Colors outside the sRGB gamut can yield a negative saturation
+  if saturation < 0.0 {
+    hue += 180.0;
+    saturation = saturation.abs();
+  }
+  [normalize_hue(hue), saturation * 100.0, lightness * 100.0]
+}
+
+fn hwb_to_srgb([hue, whiteness, blackness]: [f64; 3]) -> [f64; 3] {
+  let (whiteness, blackness) = (whiteness / 100.0, blackness / 100.0);
+  if whiteness + blackness >= 1.0 {
+    let gray = whiteness / (whiteness + blackness);
+    return [gray; 3];
+  }
+  hsl_to_srgb([hue, 100.0, 50.0]).map(|c| c * (1.0 - whiteness - blackness) + whiteness)
+}
+
+fn srgb_to_hwb(rgb: [f64; 3]) -> [f64; 3] {
+  let (hue, _) = srgb_hue(rgb);
+  let whiteness = rgb[0].min(rgb[1]).min(rgb[2]);
+  let blackness = 1.0 - rgb[0].max(rgb[1]).max(rgb[2]);
+  [normalize_hue(hue), whiteness * 100.0, blackness * 100.0]
+}
+
+const LAB_KAPPA: f64 = 24389.0 / 27.0;
+const LAB_EPSILON: f64 = 216.0 / 24389.0;
+
+fn xyz_d50_to_lab(xyz: [f64; 3]) -> [f64; 3] {
+  let f = [0, 1, 2].map(|i| {
+    let value = xyz[i] / D50_WHITE[i];
+    if value > LAB_EPSILON { value.cbrt() } else { (LAB_KAPPA * value + 16.0) / 116.0 }
+  });
+  [116.0 * f[1] - 16.0, 500.0 * (f[0] - f[1]), 200.0 * (f[1] - f[2])]
+}
+
+fn lab_to_xyz_d50([lightness, a, b]: [f64; 3]) -> [f64; 3] {
+  let f1 = (lightness + 16.0) / 116.0;
+  let f0 = a / 500.0 + f1;
+  let f2 = f1 - b / 200.0;
+  let cube = |f: f64| if f.powi(3) > LAB_EPSILON { f.powi(3) } else { (116.0 * f - 16.0) / LAB_KAPPA };
+  let y = if lightness > LAB_KAPPA * LAB_EPSILON { f1.powi(3) } else { lightness / LAB_KAPPA };
+  [cube(f0) * D50_WHITE[0], y * D50_WHITE[1], cube(f2) * D50_WHITE[2]]
+}
+
+fn rectangular_to_polar([lightness, a, b]: [f64; 3]) -> [f64; 3] {
+  let chroma = (a * a + b * b).sqrt();
+  [lightness, chroma, normalize_hue(b.atan2(a) * 180.0 / PI)]
+}
+
+fn polar_to_rectangular([lightness, chroma, hue]: [f64; 3]) -> [f64; 3] {
+  let radians = hue * PI / 180.0;
+  [lightness, chroma * radians.cos(), chroma * radians.sin()]
+}
+
+fn xyz_to_oklab(xyz: [f64; 3]) -> [f64; 3] {
+  multiply(&LMS_TO_OKLAB, multiply(&XYZ_TO_LMS, xyz).map(f64::cbrt))
+}
+
+fn oklab_to_xyz(oklab: [f64; 3]) -> [f64; 3] {
+  multiply(&LMS_TO_XYZ, multiply(&OKLAB_TO_LMS, oklab).map(|c| c.powi(3)))
+}
+
+impl ColorSpace {
+  /// Parses the name of a color space as used by `color()` and `color-mix()`.
+  pub fn from_name(name: &str) -> Option<Self> {
+    let space = match name.to_ascii_lowercase().as_str() {
+      "srgb" => ColorSpace::Srgb,
+      "srgb-linear" => ColorSpace::SrgbLinear,
+      "display-p3" => ColorSpace::DisplayP3,
+      "a98-rgb" => ColorSpace::A98Rgb,
+      "prophoto-rgb" => ColorSpace::ProphotoRgb,
+      "rec2020" => ColorSpace::Rec2020,
+      "xyz-d50" => ColorSpace::XyzD50,
+      "xyz" | "xyz-d65" => ColorSpace::XyzD65,
+      "hsl" => ColorSpace::Hsl,
+      "hwb" => ColorSpace::Hwb,
+      "lab" => ColorSpace::Lab,
+      "lch" => ColorSpace::Lch,
+      "oklab" => ColorSpace::Oklab,
+      "oklch" => ColorSpace::Oklch,
+      _ => return None,
+    };
+    Some(space)
+  }
+
+  pub fn name(self) -> &'static str {
+    match self {
+      ColorSpace::Srgb => "srgb",
+      ColorSpace::SrgbLinear => "srgb-linear",
+      ColorSpace::DisplayP3 => "display-p3",
+      ColorSpace::A98Rgb => "a98-rgb",
+      ColorSpace::ProphotoRgb => "prophoto-rgb",
+      ColorSpace::Rec2020 => "rec2020",
+      ColorSpace::XyzD50 => "xyz-d50",
+      ColorSpace::XyzD65 => "xyz-d65",
+      ColorSpace::Hsl => "hsl",
+      ColorSpace::Hwb => "hwb",
+      ColorSpace::Lab => "lab",
+      ColorSpace::Lch => "lch",
+      ColorSpace::Oklab => "oklab",
+      ColorSpace::Oklch => "oklch",
+    }
+  }
+
+  /// Whether the space can be used in `color()`: the predefined RGB and XYZ spaces.
+  pub fn is_predefined(self) -> bool {
+    !matches!(self, ColorSpace::Hsl | ColorSpace::Hwb | ColorSpace::Lab | ColorSpace::Lch | ColorSpace::Oklab | ColorSpace::Oklch)
+  }
+
+  /// The index of the hue component in a polar space.
+  fn hue_index(self) -> Option<usize> {
+    match self {
+      ColorSpace::Hsl | ColorSpace::Hwb => Some(0),
+      ColorSpace::Lch | ColorSpace::Oklch => Some(2),
+      _ => None,
+    }
+  }
+
+  /// Whether the space has gamut limits: the RGB spaces, including `hsl` and
+  /// `hwb` which share the sRGB gamut.
+  fn is_bounded(self) -> bool {
+    self.is_predefined() && !matches!(self, ColorSpace::XyzD50 | ColorSpace::XyzD65)
+      || matches!(self, ColorSpace::Hsl | ColorSpace::Hwb)
+  }
+}
+
+impl AbsoluteColor {
+  pub fn new(space: ColorSpace, components: [f64; 3], alpha: f64) -> Self {
+    AbsoluteColor { space, components, alpha }
+  }
+
+  /// An opaque sRGB color from a `0xrrggbb` number.
+  fn from_rgb24(rgb: u32) -> Self {
+    let channel = |shift: u32| f64::from((rgb >> shift) & 0xff) / 255.0;
+    AbsoluteColor::new(ColorSpace::Srgb, [channel(16), channel(8), channel(0)], 1.0)
+  }
+
+  fn to_xyz_d65(self) -> [f64; 3] {
+    let c = self.components;
+    match self.space {
+      ColorSpace::Srgb => multiply(&SRGB_TO_XYZ, transfer(c, srgb_to_linear)),
+      ColorSpace::SrgbLinear => multiply(&SRGB_TO_XYZ, c),
+      ColorSpace::DisplayP3 => multiply(&P3_TO_XYZ, transfer(c, srgb_to_linear)),
+      ColorSpace::A98Rgb => multiply(&A98_TO_XYZ, transfer(c, |c| c.powf(563.0 / 256.0))),
+      ColorSpace::ProphotoRgb => multiply(&D50_TO_D65, multiply(&PROPHOTO_TO_XYZ_D50, transfer(c, prophoto_to_linear))),
+      ColorSpace::Rec2020 => multiply(&REC2020_TO_XYZ, transfer(c, rec2020_to_linear)),
+      ColorSpace::XyzD50 => multiply(&D50_TO_D65, c),
+      ColorSpace::XyzD65 => c,
+      ColorSpace::Hsl => multiply(&SRGB_TO_XYZ, transfer(hsl_to_srgb(c), srgb_to_linear)),
+      ColorSpace::Hwb => multiply(&SRGB_TO_XYZ, transfer(hwb_to_srgb(c), srgb_to_linear)),
+      ColorSpace::Lab => multiply(&D50_TO_D65, lab_to_xyz_d50(c)),
+      ColorSpace::Lch => multiply(&D50_TO_D65, lab_to_xyz_d50(polar_to_rectangular(c))),
+      ColorSpace::Oklab => oklab_to_xyz(c),
+      ColorSpace::Oklch => oklab_to_xyz(polar_to_rectangular(c)),
+    }
+  }
+
+  fn from_xyz_d65(space: ColorSpace, xyz: [f64; 3], alpha: f64) -> Self {
+    let srgb = || transfer(multiply(&XYZ_TO_SRGB, xyz), srgb_from_linear);
+    let components = match space {
+      ColorSpace::Srgb => srgb(),
+      ColorSpace::SrgbLinear => multiply(&XYZ_TO_SRGB, xyz),
+      ColorSpace::DisplayP3 => transfer(multiply(&XYZ_TO_P3, xyz), srgb_from_linear),
+      ColorSpace::A98Rgb => transfer(multiply(&XYZ_TO_A98, xyz), |c| c.powf(256.0 / 563.0)),
+      ColorSpace::ProphotoRgb => transfer(multiply(&XYZ_D50_TO_PROPHOTO, multiply(&D65_TO_D50, xyz)), prophoto_from_linear),
+      ColorSpace::Rec2020 => transfer(multiply(&XYZ_TO_REC2020, xyz), rec2020_from_linear),
+      ColorSpace::XyzD50 => multiply(&D65_TO_D50, xyz),
+      ColorSpace::XyzD65 => xyz,
+      ColorSpace::Hsl => srgb_to_hsl(srgb()),
+      ColorSpace::Hwb => srgb_to_hwb(srgb()),
+      ColorSpace::Lab => xyz_d50_to_lab(multiply(&D65_TO_D50, xyz)),
+      ColorSpace::Lch => rectangular_to_polar(xyz_d50_to_lab(multiply(&D65_TO_D50, xyz))),
+      ColorSpace::Oklab => xyz_to_oklab(xyz),
+      ColorSpace::Oklch => rectangular_to_polar(xyz_to_oklab(xyz)),
+    };
+    AbsoluteColor::new(space, components, alpha)
+  }
+
+  /// Converts the color to another color space. The conversion is exact, so
+  /// the result may be outside the gamut of `space` (see `to_gamut`).
+  pub fn to_space(&self, space: ColorSpace) -> AbsoluteColor {
+    if space == self.space {
+      return *self;
+    }
+    match (self.space, space) {
+      (ColorSpace::Lab, ColorSpace::Lch) | (ColorSpace::Oklab, ColorSpace::Oklch) => {
+        AbsoluteColor::new(space, rectangular_to_polar(self.components), self.alpha)
+      }
+      (ColorSpace::Lch, ColorSpace::Lab) | (ColorSpace::Oklch, ColorSpace::Oklab) => {
+        AbsoluteColor::new(space, polar_to_rectangular(self.components), self.alpha)
+      }
+      (ColorSpace::Hsl, ColorSpace::Srgb) => AbsoluteColor::new(space, hsl_to_srgb(self.components), self.alpha),
+      (ColorSpace::Hwb, ColorSpace::Srgb) => AbsoluteColor::new(space, hwb_to_srgb(self.components), self.alpha),
+      (ColorSpace::Srgb, ColorSpace::Hsl) => AbsoluteColor::new(space, srgb_to_hsl(self.components), self.alpha),
+      (ColorSpace::Srgb, ColorSpace::Hwb) => AbsoluteColor::new(space, srgb_to_hwb(self.components), self.alpha),
+      _ => AbsoluteColor::from_xyz_d65(space, self.to_xyz_d65(), self.alpha),
+    }
+  }
+
+  /// Whether the color fits in the gamut of `space`. Spaces without gamut
+  /// limits, such as `lab` or `xyz`, contain every color.
+  pub fn in_gamut(&self, space: ColorSpace) -> bool {
+    if !space.is_bounded() {
+      return true;
+    }
+    let rgb_space = if space.is_predefined() { space } else { ColorSpace::Srgb };
+    self.to_space(rgb_space).components.iter().all(|c| (-1e-6..=1.0 + 1e-6).contains(c))
+  }
+
+  /// Converts the color to `space` and maps it into the gamut of that space
+  /// with the CSS Color 4 gamut mapping algorithm, which lowers the OKLCH
+  /// chroma until the color fits.
+  pub fn to_gamut(&self, space: ColorSpace) -> AbsoluteColor {
+    if self.in_gamut(space) {
+      return self.to_space(space);
+    }
+
+    let rgb_space = if space.is_predefined() { space } else { ColorSpace::Srgb };
+    let clip = |color: &AbsoluteColor| {
+      let mut clipped = color.to_space(rgb_space);
+      clipped.components = clipped.components.map(|c| c.clamp(0.0, 1.0));
+      clipped
+    };
+    let delta_e = |a: &AbsoluteColor, b: &AbsoluteColor| {
+      let (a, b) = (a.to_space(ColorSpace::Oklab).components, b.to_space(ColorSpace::Oklab).components);
+      ((a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2) + (a[2] - b[2]).powi(2)).sqrt()
+    };
+
+    let origin = self.to_space(ColorSpace::Oklch);
+    let lightness = origin.components[0];
+    let result = if lightness >= 1.0 {
+      AbsoluteColor::new(rgb_space, [1.0; 3], self.alpha)
+    } else if lightness <= 0.0 {
+      AbsoluteColor::new(rgb_space, [0.0; 3], self.alpha)
+    } else {
+      const JND: f64 = 0.02;
+      const EPSILON: f64 = 0.0001;
+      let mut current = origin;
+      let mut clipped = clip(&current);
+      let (mut min, mut max, mut min_in_gamut) = (0.0, origin.components[1], true);
+
+      if delta_e(&clipped, &current) >= JND {
+        while max - min > EPSILON {
+          let chroma = (min + max) / 2.0;
+          current.components[1] = chroma;
+          if min_in_gamut && current.in_gamut(rgb_space) {
+            min = chroma;
+            continue;
+          }
+          clipped = clip(&current);
+          let error = delta_e(&clipped, &current);
+          if error < JND {
+            if JND - error < EPSILON {
+              break;
+            }
+            min_in_gamut = false;
+            min = chroma;
+          } else {
+            max = chroma;
+          }
+        }
+      }
+      clipped
+    };
+
+    result.to_space(space)
+  }
+
+  fn srgb_bytes(&self) -> [u8; 3] {
+    self.to_gamut(ColorSpace::Srgb).components.map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8)
+  }
+
+  /// Writes the color in `format`.
+  pub fn serialize(&self, format: ColorFormat) -> String {
+    let alpha = format_number(self.alpha);
+    let opaque = self.alpha >= 1.0;
+    let suffix = if opaque { String::new() } else { format!(" / {}", alpha) };
+    let numbers = |color: AbsoluteColor| color.components.map(format_number).join(" ");
+
+    match format {
+      ColorFormat::Hex => {
+        let [red, green, blue] = self.srgb_bytes();
+        match opaque {
+          true => format!("#{:02x}{:02x}{:02x}", red, green, blue),
+          false => format!("#{:02x}{:02x}{:02x}{:02x}", red, green, blue, (self.alpha * 255.0).round() as u8),
+        }
+      }
+      ColorFormat::Rgb => {
+        let [red, green, blue] = self.srgb_bytes();
+        match opaque {
+          true => format!("rgb({}, {}, {})", red, green, blue),
+          false => format!("rgba({}, {}, {}, {})", red, green, blue, alpha),
+        }
+      }
+      ColorFormat::Hsl | ColorFormat::Hwb => {
+        let space = if format == ColorFormat::Hsl { ColorSpace::Hsl } else { ColorSpace::Hwb };
+        let [hue, first, second] = self.to_gamut(space).components.map(format_number);
+        format!("{}({} {}% {}%{})", space.name(), hue, first, second, suffix)
+      }
+      ColorFormat::Lab => format!("lab({}{})", numbers(self.to_space(ColorSpace::Lab)), suffix),
+      ColorFormat::Lch => format!("lch({}{})", numbers(self.to_space(ColorSpace::Lch)), suffix),
+      ColorFormat::Oklab => format!("oklab({}{})", numbers(self.to_space(ColorSpace::Oklab)), suffix),
+      ColorFormat::Oklch => format!("oklch({}{})", numbers(self.to_space(ColorSpace::Oklch)), suffix),
+      ColorFormat::Color(space) => match space {
+        ColorSpace::Hsl => self.serialize(ColorFormat::Hsl),
+        ColorSpace::Hwb => self.serialize(ColorFormat::Hwb),
+        ColorSpace::Lab => self.serialize(ColorFormat::Lab),
+        ColorSpace::Lch => self.serialize(ColorFormat::Lch),
+        ColorSpace::Oklab => self.serialize(ColorFormat::Oklab),
+        ColorSpace::Oklch => self.serialize(ColorFormat::Oklch),
+        space => format!("color({} {}{})", space.name(), numbers(self.to_space(space)), suffix),
+      },
+    }
+  }
+
+  /// The shortest way to write the color as a named color or a hex color with
+  /// three, four, six or eight digits. Returns `None` when neither can
+  /// represent it, for a color in another space than the sRGB syntaxes or an
+  /// alpha that is not a whole number of 255ths.
+  pub(crate) fn shortest_srgb(&self) -> Option<String> {
+    if !matches!(self.space, ColorSpace::Srgb | ColorSpace::Hsl | ColorSpace::Hwb) {
+      return None;
+    }
+    let alpha = (self.alpha.clamp(0.0, 1.0) * 255.0).round();
+    if (alpha / 255.0 - self.alpha).abs() > 1e-6 {
+      return None;
+    }
+
+    let [red, green, blue] = self.srgb_bytes();
+    let mut bytes = vec![red, green, blue];
+    if alpha < 255.0 {
+      bytes.push(alpha as u8);
+    }
+    let hex = match bytes.iter().all(|byte| byte >> 4 == byte & 0xf) {
+      true => bytes.iter().map(|byte| format!("{:x}", byte & 0xf)).collect::<String>(),
+      false => bytes.iter().map(|byte| format!("{:02x}", byte)).collect::<String>(),
+    };
+    let hex = format!("#{}", hex);
+
+    let rgb = u32::from_be_bytes([0, red, green, blue]);
+    NAMED_COLORS
+      .iter()
+      .filter(|(name, value)| alpha == 255.0 && *value == rgb && name.len() < hex.len())
+      .map(|(name, _)| name.to_string())
+      .min_by_key(|name| name.len())
+      .or(Some(hex))
+  }
+
+  /// The format a color is serialized in by default: `rgb()` for the sRGB
+  /// syntaxes, otherwise the function of its own space.
+  fn natural_format(&self) -> ColorFormat {
+    match self.space {
+      ColorSpace::Srgb | ColorSpace::Hsl | ColorSpace::Hwb => ColorFormat::Rgb,
+      space => ColorFormat::Color(space),
+    }
+  }
+}
+
+/// Whether a hue is powerless, because the color is achromatic.
+fn hue_is_powerless(color: &AbsoluteColor) -> bool {
+  let [_, second, third] = color.components;
+  match color.space {
+    ColorSpace::Hsl => second.abs() < 1e-3,
+    ColorSpace::Hwb => second + third >= 100.0 - 1e-3,
+    ColorSpace::Lch => second.abs() < 1e-3,
+    ColorSpace::Oklch => second.abs() < 1e-5,
+    _ => false,
+  }
+}
+
+/// Adjusts two hues for interpolation according to `method`.
+fn fix_hues(first: f64, second: f64, method: HueInterpolation) -> (f64, f64) {
+  let (first, second) = (normalize_hue(first), normalize_hue(second));
+  let difference = second - first;
+  match method {
+    HueInterpolation::Shorter if difference > 180.0 => (first + 360.0, second),
+    HueInterpolation::Shorter if difference < -180.0 => (first, second + 360.0),
+    HueInterpolation::Longer if 0.0 < difference && difference < 180.0 => (first + 360.0, second),
+    HueInterpolation::Longer if -180.0 < difference && difference <= 0.0 => (first, second + 360.0),
+    HueInterpolation::Increasing if second < first => (first, second + 360.0),
+    HueInterpolation::Decreasing if first < second => (first + 360.0, second),
+    _ => (first, second),
+  }
+}
+
+/// Mixes two colors as `color-mix()` does, with premultiplied alpha.
+/// Returns `None` when both percentages are zero.
+fn mix(
+  space: ColorSpace,
+  method: HueInterpolation,
+  first: &AbsoluteColor,
+  first_percentage: Option<f64>,
+  second: &AbsoluteColor,
+  second_percentage: Option<f64>,
+) -> Option<AbsoluteColor> {
+  let (p1, p2) = match (first_percentage, second_percentage) {
+    (None, None) => (50.0, 50.0),
+    (Some(p1), None) => (p1, 100.0 - p1),
+    (None, Some(p2)) => (100.0 - p2, p2),
+    (Some(p1), Some(p2)) => (p1, p2),
+  };
+  let sum = p1 + p2;
+  if sum <= 0.0 {
+    return None;
+  }
+  let multiplier = (sum / 100.0).min(1.0);
+  let t = p2 / sum;
+
+  let mut a = first.to_space(space);
+  let mut b = second.to_space(space);
+  let hue = space.hue_index();
+  if let Some(index) = hue {
+    match (hue_is_powerless(&a), hue_is_powerless(&b)) {
+      (true, false) => a.components[index] = b.components[index],
+      (false, true) => b.components[index] = a.components[index],
+      _ => {}
+    }
+    let (h1, h2) = fix_hues(a.components[index], b.components[index], method);
+    a.components[index] = h1;
+    b.components[index] = h2;
+  }
+
+  let alpha = a.alpha * (1.0 - t) + b.alpha * t;
+  let components = std::array::from_fn(|i| match Some(i) == hue {
+    true => normalize_hue(a.components[i] * (1.0 - t) + b.components[i] * t),
+    false => {
+      let premultiplied = a.components[i] * a.alpha * (1.0 - t) + b.components[i] * b.alpha * t;
+      if alpha == 0.0 { premultiplied } else { premultiplied / alpha }
+    }
+  });
+
+  Some(AbsoluteColor::new(space, components, alpha * multiplier))
+}
+
+impl ColorMix {
+  /// Computes the mix, given the value of `currentcolor`. System colors
+  /// cannot be computed and return `None`.
+  pub fn mix(&self, current_color: &AbsoluteColor) -> Option<AbsoluteColor> {
+    let first = self.first.resolve(current_color)?;
+    let second = self.second.resolve(current_color)?;
+    mix(self.space, self.hue, &first, self.first_percentage, &second, self.second_percentage)
+  }
+}
+
+/// How a channel of a color function is read: a percentage of a reference
+/// range, or a hue.
+#[derive(Clone, Copy)]
+enum Channel {
+  Scaled(f64),
+  Hue,
+}
+
+/// Returns the angle in degrees of a hue: a number or an angle.
+fn hue_degrees(value: &ComponentValue) -> Option<f64> {
+  match value {
+    ComponentValue::Number(degrees) => Some(*degrees),
+    value => Dimension::from_component(value)?.to(Unit::Deg).map(|angle| angle.value),
+  }
+}
+
+/// Reads one channel. `keywords` are the channels of the origin color of a
+/// relative color, such as `r` in `rgb(from red r 0 0)`.
+fn channel(value: &ComponentValue, kind: Channel, keywords: &[(&str, f64)]) -> Option<f64> {
+  if let Some(name) = value.as_ident() {
+    if name.eq_ignore_ascii_case("none") {
+      return Some(0.0);
+    }
+    return keywords.iter().find(|(keyword, _)| name.eq_ignore_ascii_case(keyword)).map(|(_, value)| *value);
+  }
+
+  match (value, kind) {
+    (ComponentValue::Number(number), _) => Some(*number),
+    (ComponentValue::Percentage(percentage), Channel::Scaled(scale)) => Some(percentage / 100.0 * scale),
+    (value, Channel::Hue) => hue_degrees(value),
+    _ => None,
+  }
+}
+
+fn alpha(value: &ComponentValue, keywords: &[(&str, f64)]) -> Option<f64> {
+  channel(value, Channel::Scaled(1.0), keywords).map(|alpha| alpha.clamp(0.0, 1.0))
+}
+
+/// The channels of a color function in the modern syntax, `a b c [/ alpha]`,
+/// optionally relative to an origin color (`from <color> a b c`).
+/// `names` are the channel keywords of a relative color.
+fn modern_channels(
+  arguments: &[&ComponentValue],
+  space: ColorSpace,
+  kinds: [Channel; 3],
+  names: [&str; 3],
+  scale: f64,
+) -> Option<([f64; 3], f64)> {
+  let (origin, arguments) = match arguments.split_first() {
+    Some((from, rest)) if from.is_ident("from") => {
+      let (origin, rest) = rest.split_first()?;
+      (Some(Color::from_component(origin)?.absolute()?.to_space(space)), rest)
+    }
+    _ => (None, arguments),
+  };
+
+  let keywords: Vec<(&str, f64)> = match origin {
+    Some(origin) => names
+      .iter()
+      .zip(origin.components)
+      .map(|(name, value)| (*name, value * scale))
+      .chain([("alpha", origin.alpha)])
+      .collect(),
+    None => Vec::new(),
+  };
+
+  let (channels, alpha_value) = match arguments {
+    [a, b, c] => ([a, b, c], None),
+    [a, b, c, ComponentValue::Slash, alpha] => ([a, b, c], Some(alpha)),
+    _ => return None,
+  };
+
+  let mut components = [0.0; 3];
+  for i in 0..3 {
+    components[i] = channel(channels[i], kinds[i], &keywords)? / scale;
+  }
+  let alpha = match alpha_value {
+    Some(value) => alpha(value, &keywords)?,
+    None => origin.map_or(1.0, |origin| origin.alpha),
+  };
+
+  Some((components, alpha))
+}
+
+/// Splits comma-separated arguments, each of which must be a single value.
+fn legacy_arguments<'a>(arguments: &[&'a ComponentValue]) -> Option<Vec<&'a ComponentValue>> {
+  let groups: Vec<&[&ComponentValue]> = arguments.split(|value| **value == ComponentValue::Comma).collect();
+  groups.iter().map(|group| match group {
+    [value] => Some(*value),
+    _ => None,
+  }).collect()
+}
+
+fn parse_rgb(arguments: &[&ComponentValue]) -> Option<AbsoluteColor> {
+  if arguments.contains(&&ComponentValue::Comma) {
+    let values = legacy_arguments(arguments)?;
+    let (channels, alpha_value) = match values.as_slice() {
+      [r, g, b] => ([*r, *g, *b], None),
+      [r, g, b, a] => ([*r, *g, *b], Some(*a)),
+      _ => return None,
+    };
+    let numbers = channels.iter().all(|c| matches!(c, ComponentValue::Number(_)));
+    let percentages = channels.iter().all(|c| matches!(c, ComponentValue::Percentage(_)));
+    if !numbers && !percentages {
+      return None;
+    }
+    let mut components = [0.0; 3];
+    for i in 0..3 {
+      components[i] = (channel(channels[i], Channel::Scaled(255.0), &[])? / 255.0).clamp(0.0, 1.0);
+    }
+    let alpha = match alpha_value {
+      Some(value) if !value.is_ident("none") => alpha(value, &[])?,
+      Some(_) => return None,
+      None => 1.0,
+    };
+    return Some(AbsoluteColor::new(ColorSpace::Srgb, components, alpha));
+  }
+
+  let kinds = [Channel::Scaled(255.0); 3];
+  let (components, alpha) = modern_channels(arguments, ColorSpace::Srgb, kinds, ["r", "g", "b"], 255.0)?;
+  Some(AbsoluteColor::new(ColorSpace::Srgb, components.map(|c| c.clamp(0.0, 1.0)), alpha))
+}
+
+fn parse_hsl(arguments: &[&ComponentValue]) -> Option<AbsoluteColor> {
+  let kinds = [Channel::Hue, Channel::Scaled(100.0), Channel::Scaled(100.0)];
+  if arguments.contains(&&ComponentValue::Comma) {
+    let values = legacy_arguments(arguments)?;
+    let (channels, alpha_value) = match values.as_slice() {
+      [h, s, l] => ([*h, *s, *l], None),
+      [h, s, l, a] => ([*h, *s, *l], Some(*a)),
+      _ => return None,
+    };
+    if !channels[1..].iter().all(|c| matches!(c, ComponentValue::Percentage(_))) {
+      return None;
+    }
+    let hue = hue_degrees(channels[0])?;
+    let saturation = channel(channels[1], kinds[1], &[])?.max(0.0);
+    let lightness = channel(channels[2], kinds[2], &[])?;
+    let alpha = match alpha_value {
+      Some(value) if !value.is_ident("none") => alpha(value, &[])?,
+      Some(_) => return None,
+      None => 1.0,
+    };
+    return Some(AbsoluteColor::new(ColorSpace::Hsl, [hue, saturation, lightness], alpha));
+  }
+
+  let (components, alpha) = modern_channels(arguments, ColorSpace::Hsl, kinds, ["h", "s", "l"], 1.0)?;
+  Some(AbsoluteColor::new(ColorSpace::Hsl, [components[0], components[1].max(0.0), components[2]], alpha))
+}
+
+fn parse_color_function(arguments: &[&ComponentValue]) -> Option<AbsoluteColor> {
+  // The color space follows the origin color of a relative color
+  let (space_index, prefix) = match arguments.first() {
+    Some(from) if from.is_ident("from") => (2, 2),
+    _ => (0, 0),
+  };
+  let space = ColorSpace::from_name(arguments.get(space_index)?.as_ident()?).filter(|space| space.is_predefined())?;
+  let names = match space {
+    ColorSpace::XyzD50 | ColorSpace::XyzD65 => ["x", "y", "z"],
+    _ => ["r", "g", "b"],
+  };
+
+  let mut rest: Vec<&ComponentValue> = arguments[..prefix].to_vec();
+  rest.extend(&arguments[space_index + 1..]);
+  let (components, alpha) = modern_channels(&rest, space, [Channel::Scaled(1.0); 3], names, 1.0)?;
+  Some(AbsoluteColor::new(space, components, alpha))
+}
+
+fn parse_color_mix(arguments: &[&ComponentValue]) -> Option<Color> {
+  let groups: Vec<&[&ComponentValue]> = arguments.split(|value| **value == ComponentValue::Comma).collect();
+  let (space, hue, colors) = match groups.as_slice() {
+    [interpolation, first, second] => {
+      let (space, hue) = match interpolation {
+        [keyword, space] if keyword.is_ident("in") => (ColorSpace::from_name(space.as_ident()?)?, HueInterpolation::Shorter),
+        [keyword, space, method, hue] if keyword.is_ident("in") && hue.is_ident("hue") => {
+          let space = ColorSpace::from_name(space.as_ident()?)?;
+          space.hue_index()?;
+          let method = match method.as_ident()?.to_ascii_lowercase().as_str() {
+            "shorter" => HueInterpolation::Shorter,
+            "longer" => HueInterpolation::Longer,
+            "increasing" => HueInterpolation::Increasing,
+            "decreasing" => HueInterpolation::Decreasing,
+            _ => return None,
+          };
+          (space, method)
+        }
+        _ => return None,
+      };
+      (space, hue, [*first, *second])
+    }
+    [first, second] => (ColorSpace::Oklab, HueInterpolation::Shorter, [*first, *second]),
+    _ => return None,
+  };
+
+  let parse_item = |item: &[&ComponentValue]| -> Option<(Color, Option<f64>)> {
+    let (color, percentage) = match item {
+      [color] => (*color, None),
+      [ComponentValue::Percentage(p), color] | [color, ComponentValue::Percentage(p)] => (*color, Some(*p)),
+      _ => return None,
+    };
+    if percentage.is_some_and(|p| !(0.0..=100.0).contains(&p)) {
+      return None;
+    }
+    Some((Color::from_component(color)?, percentage))
+  };
+  let (first, first_percentage) = parse_item(colors[0])?;
+  let (second, second_percentage) = parse_item(colors[1])?;
+  if first_percentage.unwrap_or(0.0) + second_percentage.unwrap_or(0.0) == 0.0
+    && first_percentage.is_some()
+    && second_percentage.is_some()
+  {
+    return None;
+  }
+
+  let color_mix = ColorMix { space, hue, first, first_percentage, second, second_percentage };
+  match (color_mix.first.absolute(), color_mix.second.absolute()) {
+    (Some(first), Some(second)) => mix(space, hue, first, first_percentage, second, second_percentage).map(Color::Absolute),
+    _ => Some(Color::Mix(Box::new(color_mix))),
+  }
+}
+
+fn parse_hex(hex: &str) -> Option<AbsoluteColor> {
+  if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+    return None;
+  }
+  let digits: Vec<u8> = match hex.len() {
+    3 | 4 => hex.chars().map(|c| c.to_digit(16).unwrap() as u8 * 17).collect(),
+    6 | 8 => (0..hex.len()).step_by(2).map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap()).collect(),
+    _ => return None,
+  };
+  let channel = |i: usize| f64::from(digits[i]) / 255.0;
+  let alpha = if digits.len() == 4 { channel(3) } else { 1.0 };
+  Some(AbsoluteColor::new(ColorSpace::Srgb, [channel(0), channel(1), channel(2)], alpha))
+}
+
+impl Color {
+  /// Interprets a single component value as a color. Returns `None` when it
+  /// is not a valid color.
+  pub fn from_component(value: &ComponentValue) -> Option<Color> {
+    match value {
+      ComponentValue::Ident(name) => {
+        let name = name.to_ascii_lowercase();
+        match name.as_str() {
+          "currentcolor" => Some(Color::CurrentColor),
+          "transparent" => Some(Color::Absolute(AbsoluteColor::new(ColorSpace::Srgb, [0.0; 3], 0.0))),
+          _ if SYSTEM_COLORS.contains(&name.as_str()) => Some(Color::System(name)),
+          _ => NAMED_COLORS
+            .binary_search_by(|(named, _)| (*named).cmp(&name))
+            .ok()
+            .map(|index| Color::Absolute(AbsoluteColor::from_rgb24(NAMED_COLORS[index].1))),
+        }
+      }
+      ComponentValue::Hash(hex) => parse_hex(hex).map(Color::Absolute),
+      ComponentValue::Function { name, arguments } => {
+        let arguments: Vec<&ComponentValue> = arguments.iter().filter(|value| **value != ComponentValue::Whitespace).collect();
+        let color = match name.to_ascii_lowercase().as_str() {
+          "rgb" | "rgba" => parse_rgb(&arguments)?,
+          "hsl" | "hsla" => parse_hsl(&arguments)?,
+          "hwb" => {
+            let kinds = [Channel::Hue, Channel::Scaled(100.0), Channel::Scaled(100.0)];
+            let (components, alpha) = modern_channels(&arguments, ColorSpace::Hwb, kinds, ["h", "w", "b"], 1.0)?;
+            AbsoluteColor::new(ColorSpace::Hwb, components, alpha)
+          }
+          "lab" => {
+            let kinds = [Channel::Scaled(100.0), Channel::Scaled(125.0), Channel::Scaled(125.0)];
+            let (components, alpha) = modern_channels(&arguments, ColorSpace::Lab, kinds, ["l", "a", "b"], 1.0)?;
+            AbsoluteColor::new(ColorSpace::Lab, [components[0].clamp(0.0, 100.0), components[1], components[2]], alpha)
+          }
+          "lch" => {
+            let kinds = [Channel::Scaled(100.0), Channel::Scaled(150.0), Channel::Hue];
+            let (components, alpha) = modern_channels(&arguments, ColorSpace::Lch, kinds, ["l", "c", "h"], 1.0)?;
+            AbsoluteColor::new(ColorSpace::Lch, [components[0].clamp(0.0, 100.0), components[1].max(0.0), components[2]], alpha)
+          }
+          "oklab" => {
+            let kinds = [Channel::Scaled(1.0), Channel::Scaled(0.4), Channel::Scaled(0.4)];
+            let (components, alpha) = modern_channels(&arguments, ColorSpace::Oklab, kinds, ["l", "a", "b"], 1.0)?;
+            AbsoluteColor::new(ColorSpace::Oklab, [components[0].clamp(0.0, 1.0), components[1], components[2]], alpha)
+          }
+          "oklch" => {
+            let kinds = [Channel::Scaled(1.0), Channel::Scaled(0.4), Channel::Hue];
+            let (components, alpha) = modern_channels(&arguments, ColorSpace::Oklch, kinds, ["l", "c", "h"], 1.0)?;
+            AbsoluteColor::new(ColorSpace::Oklch, [components[0].clamp(0.0, 1.0), components[1].max(0.0), components[2]], alpha)
+          }
+          "color" => parse_color_function(&arguments)?,
+          "color-mix" => return parse_color_mix(&arguments),
+          _ => return None,
+        };
+        Some(Color::Absolute(color))
+      }
+      _ => None,
+    }
+  }
+
+  pub fn from_string(input: &str) -> Result<Color, ParseError> {
+    let value = Value::from_string(input)?;
+    match value.components.as_slice() {
+      [component] => Color::from_component(component),
+      _ => None,
+    }
+    .ok_or_else(|| ParseError::new(input, input.trim_start(), ParseErrorKind::InvalidValue, "a color"))
+  }
+
+  /// Returns the color if its components are known without context.
+  pub fn absolute(&self) -> Option<&AbsoluteColor> {
+    match self {
+      Color::Absolute(color) => Some(color),
+      _ => None,
+    }
+  }
+
+  /// Returns the absolute color, given the value of `currentcolor`. System
+  /// colors depend on the user agent and return `None`.
+  pub fn resolve(&self, current_color: &AbsoluteColor) -> Option<AbsoluteColor> {
+    match self {
+      Color::CurrentColor => Some(*current_color),
+      Color::System(_) => None,
+      Color::Absolute(color) => Some(*color),
+      Color::Mix(color_mix) => color_mix.mix(current_color),
+    }
+  }
+
+  /// Writes the color in `format`. Colors that depend on context, such as
+  /// `currentcolor`, are written as they are.
+  pub fn serialize(&self, format: ColorFormat) -> String {
+    match self {
+      Color::Absolute(color) => color.serialize(format),
+      _ => self.to_string(),
+    }
+  }
+}
+
+impl fmt::Display for ColorMix {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "color-mix(in {}", self.space.name())?;
+    if self.hue != HueInterpolation::Shorter {
+      let method = match self.hue {
+        HueInterpolation::Shorter => "shorter",
+        HueInterpolation::Longer => "longer",
+        HueInterpolation::Increasing => "increasing",
+        HueInterpolation::Decreasing => "decreasing",
+      };
+      write!(f, " {} hue", method)?;
+    }
+    for (color, percentage) in [(&self.first, self.first_percentage), (&self.second, self.second_percentage)] {
+      write!(f, ", {}", color)?;
+      if let Some(percentage) = percentage {
+        write!(f, " {}%", format_number(percentage))?;
+      }
+    }
+    write!(f, ")")
+  }
+}
+
+impl fmt::Display for AbsoluteColor {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{}", self.serialize(self.natural_format()))
+  }
+}
+
+impl fmt::Display for Color {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Color::CurrentColor => write!(f, "currentcolor"),
+      Color::System(name) => write!(f, "{}", name),
+      Color::Absolute(color) => write!(f, "{}", color),
+      Color::Mix(color_mix) => write!(f, "{}", color_mix),
+    }
+  }
+}
+
+/// Whether a property takes colors, so that identifiers such as `red` in its
+/// value are colors rather than, say, animation names.
+pub(crate) fn takes_color_keywords(name: &str) -> bool {
+  let Some(name) = standard_name(name) else {
+    return false;
+  };
+  name.ends_with("color")
+    || matches!(
+      name,
+      "background" | "border" | "border-top" | "border-right" | "border-bottom" | "border-left"
+        | "border-block" | "border-block-start" | "border-block-end" | "border-inline"
+        | "border-inline-start" | "border-inline-end" | "box-shadow" | "column-rule" | "fill"
+        | "outline" | "stroke" | "text-decoration" | "text-emphasis" | "text-shadow"
+    )
+}
+
+/// Rewrites the colors in the value of the property `name` in `format`,
+/// keeping the rest of the value as written. Returns `None` when the value
+/// has no color to rewrite or cannot be parsed.
+pub(crate) fn normalize_value(name: &str, value: &str, format: ColorFormat) -> Option<String> {
+  let keywords = takes_color_keywords(name);
+  let mut changed = false;
+  let normalized = replace_components(value, &mut |component, text| {
+    let color = Color::from_component(component).filter(|_| keywords || !matches!(component, ComponentValue::Ident(_)))?;
+    let serialized = color.serialize(format);
+    // Colors already written in `format` keep their text
+    match Value::from_string(&serialized) {
+      Ok(Value { components }) if components.len() == 1 && components[0] != *component => {
+        changed = true;
+        Some(serialized)
+      }
+      _ => Some(text.to_string()),
+    }
+  })?;
+  changed.then_some(normalized)
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::stylesheet::Stylesheet;
+
+  fn absolute(input: &str) -> AbsoluteColor {
+    *Color::from_string(input).unwrap().absolute().unwrap()
+  }
+
+  fn assert_close(actual: [f64; 3], expected: [f64; 3]) {
+    for i in 0..3 {
+      assert!((actual[i] - expected[i]).abs() < 1e-3, "{:?} != {:?}", actual, expected);
+    }
+  }
+
+  #[test]
+  fn test_keywords_and_hex() {
+    assert_eq!(Color::from_string("RebeccaPurple").unwrap().to_string(), "rgb(102, 51, 153)");
+    assert_eq!(Color::from_string("transparent").unwrap().to_string(), "rgba(0, 0, 0, 0)");
+    assert_eq!(Color::from_string("currentColor").unwrap(), Color::CurrentColor);
+    assert_eq!(Color::from_string("CanvasText").unwrap(), Color::System("canvastext".to_string()));
+    assert_eq!(Color::from_string("#f00").unwrap().to_string(), "rgb(255, 0, 0)");
+    assert_eq!(Color::from_string("#ff000080").unwrap().serialize(ColorFormat::Hex), "#ff000080");
+    assert_eq!(absolute("#0f08").alpha, 136.0 / 255.0);
+    assert!(Color::from_string("#ff00f").is_err());
+    assert!(Color::from_string("notacolor").is_err());
+  }
+
+  #[test]
+  fn test_rgb_and_hsl_syntaxes() {
+    assert_eq!(absolute("rgb(255, 0, 0)"), absolute("rgb(100% 0% 0%)"));
+    assert_eq!(absolute("rgba(255, 0, 0, 0.5)"), absolute("rgb(255 0 0 / 50%)"));
+    assert_eq!(absolute("rgb(300 -5 0)").components, [1.0, 0.0, 0.0]);
+    assert_eq!(absolute("rgb(none 0 0)").components, [0.0; 3]);
+    assert!(Color::from_string("rgb(255, 0%, 0)").is_err());
+    assert!(Color::from_string("rgb(255, 0, none)").is_err());
+    assert!(Color::from_string("rgb(255 0)").is_err());
+
+    assert_eq!(Color::from_string("hsl(120, 100%, 50%)").unwrap().to_string(), "rgb(0, 255, 0)");
+    assert_eq!(Color::from_string("hsla(0.5turn 100 50 / 0.25)").unwrap().to_string(), "rgba(0, 255, 255, 0.25)");
+    assert!(Color::from_string("hsl(120, 100, 50)").is_err());
+    assert_eq!(Color::from_string("hwb(0 0% 0%)").unwrap().to_string(), "rgb(255, 0, 0)");
+    assert_eq!(Color::from_string("hwb(0 60% 60%)").unwrap().to_string(), "rgb(128, 128, 128)");
+  }
+
+  #[test]
+  fn test_lab_and_color_function() {
+    assert_eq!(absolute("lab(50% 40 -20)").components, [50.0, 40.0, -20.0]);
+    assert_eq!(absolute("lch(150 100% 0.5turn)").components, [100.0, 150.0, 180.0]);
+    assert_eq!(absolute("oklab(50% 100% -100%)").components, [0.5, 0.4, -0.4]);
+    assert_eq!(absolute("oklch(0.7 0.1 20deg / 0.5)").alpha, 0.5);
+    assert_eq!(absolute("color(display-p3 1 50% 0)").components, [1.0, 0.5, 0.0]);
+    assert_eq!(absolute("color(xyz 0.1 0.2 0.3)").space, ColorSpace::XyzD65);
+    assert!(Color::from_string("color(lab 1 2 3)").is_err());
+    assert_eq!(Color::from_string("color(rec2020 0 1 0)").unwrap().to_string(), "color(rec2020 0 1 0)");
+    assert_eq!(Color::from_string("lch(50 30 270)").unwrap().to_string(), "lch(50 30 270)");
+  }
+
+  #[test]
+  fn test_conversions() {
+    let red = absolute("red");
+    assert_close(red.to_space(ColorSpace::Lab).components, [54.2905, 80.8049, 69.8910]);
+    assert_close(red.to_space(ColorSpace::Oklch).components, [0.62796, 0.25768, 29.2339]);
+    assert_close(red.to_space(ColorSpace::DisplayP3).components, [0.91749, 0.20029, 0.13856]);
+    assert_close(red.to_space(ColorSpace::Hsl).components, [0.0, 100.0, 50.0]);
+
+    for space in [
+      ColorSpace::SrgbLinear, ColorSpace::A98Rgb, ColorSpace::ProphotoRgb, ColorSpace::Rec2020, ColorSpace::XyzD50,
+      ColorSpace::Hwb, ColorSpace::Lch, ColorSpace::Oklab,
+    ] {
+      let color = absolute("#3c8dbc");
+      assert_close(color.to_space(space).to_space(ColorSpace::Srgb).components, color.components);
+    }
+  }
+
+  #[test]
+  fn test_gamut_mapping() {
+    let green = absolute("color(display-p3 0 1 0)");
+    assert!(!green.in_gamut(ColorSpace::Srgb));
+    assert!(green.in_gamut(ColorSpace::Rec2020));
+    let mapped = green.to_gamut(ColorSpace::Srgb);
+    assert!(mapped.components.iter().all(|c| (0.0..=1.0).contains(c)));
+    assert_close(absolute("lab(100 0 0)").to_gamut(ColorSpace::Srgb).components, [1.0; 3]);
+  }
+
+  #[test]
+  fn test_serialize() {
+    let color = absolute("rgb(51 102 153 / 0.5)");
+    assert_eq!(color.serialize(ColorFormat::Rgb), "rgba(51, 102, 153, 0.5)");
+    assert_eq!(color.serialize(ColorFormat::Hsl), "hsl(210 50% 40% / 0.5)");
+    assert_eq!(color.serialize(ColorFormat::Hwb), "hwb(210 20% 40% / 0.5)");
+    assert_eq!(color.serialize(ColorFormat::Color(ColorSpace::Srgb)), "color(srgb 0.2 0.4 0.6 / 0.5)");
+    assert_eq!(color.serialize(ColorFormat::Color(ColorSpace::Lab)), color.serialize(ColorFormat::Lab));
+    assert_eq!(absolute("white").serialize(ColorFormat::Oklab), "oklab(1 0 0)");
+    assert_eq!(Color::CurrentColor.serialize(ColorFormat::Hex), "currentcolor");
+  }
+
+  #[test]
+  fn test_color_mix() {
+    assert_eq!(Color::from_string("color-mix(in srgb, red, blue)").unwrap().serialize(ColorFormat::Rgb), "rgb(128, 0, 128)");
+    assert_eq!(
+      Color::from_string("color-mix(in srgb, red 30%, blue 30%)").unwrap().serialize(ColorFormat::Rgb),
+      "rgba(128, 0, 128, 0.6)",
+    );
+    let hue = absolute("color-mix(in hsl longer hue, hsl(0 100% 50%), hsl(90 100% 50%))");
+    assert_close(hue.components, [225.0, 100.0, 50.0]);
+    let gray = absolute("color-mix(in oklch, white, oklch(0.5 0.2 120))");
+    assert_close([gray.components[2], 0.0, 0.0], [120.0, 0.0, 0.0]);
+    assert!(Color::from_string("color-mix(in srgb, red 0%, blue 0%)").is_err());
+    assert!(Color::from_string("color-mix(in lab longer hue, red, blue)").is_err());
+
+    let pending = Color::from_string("color-mix(in srgb, currentcolor 25%, white)").unwrap();
+    assert_eq!(pending.to_string(), "color-mix(in srgb, currentcolor 25%, rgb(255, 255, 255))");
+    let resolved = pending.resolve(&absolute("black")).unwrap();
+    assert_eq!(resolved.serialize(ColorFormat::Hex), "#bfbfbf");
+  }
+
+  #[test]
+  fn test_relative_colors() {
+    assert_eq!(Color::from_string("rgb(from red r g 255)").unwrap().to_string(), "rgb(255, 0, 255)");
+    assert_eq!(Color::from_string("hsl(from green h s l / 0.5)").unwrap().to_string(), "rgba(0, 128, 0, 0.5)");
+    assert_eq!(absolute("color(from red display-p3 r g b)").space, ColorSpace::DisplayP3);
+    assert_eq!(absolute("oklch(from #0000 l c h)").alpha, 0.0);
+    assert!(Color::from_string("rgb(from red x g b)").is_err());
+  }
+
+  #[test]
+  fn test_normalize_colors() {
+    let mut stylesheet = Stylesheet::from_string(
+      "a { color: hsl(0 100% 50%); animation: red 1s; background: url(a.png) Blue; --x: rgb(0 0 0) }
+       @media print { b { border-color: currentcolor transparent } }"
+    ).unwrap();
+    stylesheet.normalize_colors(ColorFormat::Hex);
+    assert_eq!(
+      stylesheet.to_string(),
+      "a { color: #ff0000; animation: red 1s; background: url(a.png) #0000ff; --x: #000000; } \
+       @media print { b { border-color: currentcolor #00000000; } }"
+    );
+
+    let mut stylesheet = Stylesheet::from_string(
+      "a { background: URL( 'a.png' )  linear-gradient( to right,red 10% ,  rgb(0 0 255) ) ; color: #f00 }"
+    ).unwrap();
+    stylesheet.normalize_colors(ColorFormat::Hex);
+    assert_eq!(
+      stylesheet.to_string(),
+      "a { background: URL( 'a.png' )  linear-gradient( to right,#ff0000 10% ,  #0000ff ); color: #ff0000; }"
+    );
+  }
+}
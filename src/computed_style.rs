@@ -0,0 +1,447 @@
+//! CSS Computed Style
+//!
+//! This module turns the output of the cascade into a complete style for an
+//! element (CSS Cascading and Inheritance Level 5, "defaulting"). Every
+//! longhand in the `properties` registry gets a value: the cascaded one, the
+//! parent's value for inherited properties that were not set, or the initial
+//! value otherwise. Custom properties are always inherited.
+//!
+//! The CSS-wide keywords are resolved on the way:
+//!
+//! - `inherit` takes the parent's value, or the initial value at the root.
+//! - `initial` takes the initial value.
+//! - `unset` acts as `inherit` for inherited properties and `initial` otherwise.
+//! - `revert` rolls back to the value from the previous origin (author to user
+//!   to user-agent), or acts as `unset` when there is none.
+//! - `revert-layer` rolls back to the value from the previous cascade layer of
+//!   the same origin, or acts as `revert` when there is none.
+//!
+//! Shorthand declarations are expanded into their longhands (see `shorthand`)
+//! at their place in the cascade, so `margin: 1px` overrides an earlier
+//! `margin-top`. A declaration of `all` applies its keyword to every longhand
+//! in the registry except `direction` and `unicode-bidi`.
+//!
+//! Custom properties are then resolved and `var()` references substituted
+//! (see `variables`). A custom property in a reference cycle has no value,
+//! and a property whose references cannot be substituted acts as `unset`.
+//!
+//! Values are otherwise kept as written, so `1em` stays `1em` rather than
+//! becoming a length in pixels.
+//!
+//! ## Main API
+//!
+//! - `Cascade::computed_style()` - The style of an element, computing its ancestors' styles
+//! - `Cascade::computed_style_with_parent()` - The style of an element given its parent's style
+//! - `ComputedStyle::get()` - The value of a property
+//!
+//! ## Examples
+//!
+//! ```rust
+//! use css_structs::{Cascade, Element, Origin, Stylesheet};
+//!
+//! // <body><p></p></body>
+//! #[derive(Clone, PartialEq)]
+//! struct Node(&'static str);
+//!
+//! impl Element for Node {
+//!   fn parent_element(&self) -> Option<Self> { (self.0 == "p").then_some(Node("body")) }
+//!   fn previous_sibling_element(&self) -> Option<Self> { None }
+//!   fn next_sibling_element(&self) -> Option<Self> { None }
+//!   fn first_child_element(&self) -> Option<Self> { (self.0 == "body").then_some(Node("p")) }
+//!   fn local_name(&self) -> &str { self.0 }
+//!   fn id(&self) -> Option<&str> { None }
+//!   fn has_class(&self, _name: &str) -> bool { false }
+//!   fn attribute(&self, _name: &str) -> Option<&str> { None }
+//! }
+//!
+//! let mut cascade = Cascade::new();
+//! cascade.add_stylesheet(Origin::Author, &Stylesheet::from_string(
+//!   "body { color: navy; padding: 8px; border-color: red } p { border-color: inherit }"
+//! ).unwrap());
+//!
+//! let style = cascade.computed_style(&Node("p"));
+//! assert_eq!(style.get("color"), Some("navy"));          // inherited
+//! assert_eq!(style.get("padding-top"), Some("0"));       // initial
+//! assert_eq!(style.get("border-top-color"), Some("red")); // from the shorthands
+//! assert_eq!(style.get("border-color"), None);           // only longhands have values
+//! assert_eq!(style.get("display"), Some("inline"));
+//! ```
+
+
+use std::collections::BTreeMap;
+use crate::cascade::{origin_rank, property_key, CascadedDeclaration};
+use crate::css_declaration::CSSDeclaration;
+use crate::properties::{properties, property};
+use crate::shorthand::expand;
+use crate::variables::{resolve_custom_properties, substitute_variables};
+
+
+/// The value of every property of an element, after cascading, inheritance
+/// and defaulting.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ComputedStyle {
+  /// Values keyed by property name, lowercased except for custom properties.
+  pub values: BTreeMap<String, String>,
+}
+
+impl ComputedStyle {
+  /// Returns the value of a property. Names are ASCII case-insensitive,
+  /// except for custom properties.
+  pub fn get(&self, name: &str) -> Option<&str> {
+    self.values.get(&property_key(name)).map(String::as_str)
+  }
+}
+
+fn is_inherited(name: &str) -> bool {
+  name.starts_with("--") || property(name).is_some_and(|property| property.inherited)
+}
+
+fn initial_value(name: &str) -> Option<String> {
+  property(name).filter(|property| !property.shorthand).map(|property| property.initial.to_string())
+}
+
+/// The value of a property that was not set: inherited or initial.
+fn default_value(name: &str, parent: Option<&ComputedStyle>) -> Option<String> {
+  match is_inherited(name) {
+    true => parent.and_then(|parent| parent.get(name)).map(str::to_string).or_else(|| initial_value(name)),
+    false => initial_value(name),
+  }
+}
+
+/// Resolves the value given by `declarations[index]`, the declarations that
+/// apply to the property `name` sorted by precedence. `None` means the
+/// property has no value.
+fn resolve(name: &str, declarations: &[&CascadedDeclaration], index: usize, parent: Option<&ComputedStyle>) -> Option<String> {
+  let current = declarations[index];
+  let value = current.declaration.value.trim();
+  let rank = origin_rank(current.origin, current.declaration.important);
+
+  let revert = || {
+    let previous = declarations[..index]
+      .iter()
+      .rposition(|other| other.origin != current.origin && origin_rank(other.origin, other.declaration.important) < rank);
+    match previous {
+      Some(previous) => resolve(name, declarations, previous, parent),
+      None => default_value(name, parent),
+    }
+  };
+
+  match value.to_ascii_lowercase().as_str() {
+    "inherit" => parent.and_then(|parent| parent.get(name)).map(str::to_string).or_else(|| initial_value(name)),
+    "initial" => initial_value(name),
+    "unset" => default_value(name, parent),
+    "revert" => revert(),
+    "revert-layer" => {
+      let previous = declarations[..index].iter().rposition(|other| {
+        other.origin == current.origin
+          && other.declaration.important == current.declaration.important
+          && other.layer != current.layer
+      });
+      match previous {
+        Some(previous) => resolve(name, declarations, previous, parent),
+        None => revert(),
+      }
+    }
+    // `all` only accepts the CSS-wide keywords
+    _ if current.declaration.name.eq_ignore_ascii_case("all") => default_value(name, parent),
+    _ => Some(value.to_string()),
+  }
+}
+
+/// Computes the style of an element from the declarations that apply to it,
+/// sorted by precedence (see `Cascade::matching_declarations`), and the style
+/// of its parent.
+pub(crate) fn compute(declarations: &[CascadedDeclaration], parent: Option<&ComputedStyle>) -> ComputedStyle {
+  let mut custom: Vec<String> = declarations
+    .iter()
+    .map(|cascaded| property_key(&cascaded.declaration.name))
+    .filter(|name| name.starts_with("--"))
+    .collect();
+  if let Some(parent) = parent {
+    custom.extend(parent.values.keys().filter(|name| name.starts_with("--")).cloned());
+  }
+  let variables = resolve_custom_properties(&cascaded_values(custom, declarations, parent));
+
+  let declarations = expand_shorthands(declarations, &variables);
+  let mut names: Vec<String> = properties()
+    .iter()
+    .filter(|property| !property.shorthand)
+    .map(|property| property.name.to_string())
+    .collect();
+  names.extend(
+    declarations
+      .iter()
+      .map(|cascaded| property_key(&cascaded.declaration.name))
+      .filter(|name| name != "all" && !name.starts_with("--")),
+  );
+
+  let mut values = cascaded_values(names, &declarations, parent);
+  substitute_all(&mut values, &variables, parent);
+  values.extend(variables);
+  ComputedStyle { values }
+}
+
+/// Replaces every shorthand declaration with its longhands, at the same place
+/// in the cascade, so that a later shorthand overrides an earlier longhand.
+/// `var()` references in a shorthand are substituted first; when they cannot
+/// be, or the result is not a valid value, the longhands act as `unset`.
+/// Shorthands that cannot be expanded otherwise are kept.
+fn expand_shorthands(declarations: &[CascadedDeclaration], variables: &BTreeMap<String, String>) -> Vec<CascadedDeclaration> {
+  let mut expanded = Vec::with_capacity(declarations.len());
+  for cascaded in declarations {
+    let declaration = &cascaded.declaration;
+    let longhands = match property(&declaration.name).filter(|property| property.shorthand && !property.longhands.is_empty()) {
+      Some(property) if declaration.value.to_ascii_lowercase().contains("var(") => {
+        let substituted = substitute_variables(&declaration.value, variables).and_then(|value| expand(property.name, &value));
+        let unset = || property.longhands.iter().map(|longhand| (*longhand, "unset".to_string())).collect();
+        Some(substituted.unwrap_or_else(unset))
+      }
+      Some(property) => expand(property.name, &declaration.value),
+      None => None,
+    };
+    match longhands {
+      Some(longhands) => expanded.extend(longhands.into_iter().map(|(name, value)| CascadedDeclaration {
+        declaration: CSSDeclaration::new(name, &value, Some(declaration.important)),
+        ..cascaded.clone()
+      })),
+      None => expanded.push(cascaded.clone()),
+    }
+  }
+  expanded
+}
+
+/// The cascaded value of each property in `names`, after defaulting.
+fn cascaded_values(mut names: Vec<String>, declarations: &[CascadedDeclaration], parent: Option<&ComputedStyle>) -> BTreeMap<String, String> {
+  let resets_all = |name: &str| !name.starts_with("--") && name != "direction" && name != "unicode-bidi";
+  names.sort();
+  names.dedup();
+
+  let mut values = BTreeMap::new();
+  for name in names {
+    let applying: Vec<&CascadedDeclaration> = declarations
+      .iter()
+      .filter(|cascaded| {
+        let key = property_key(&cascaded.declaration.name);
+        key == name || key == "all" && resets_all(&name)
+      })
+      .collect();
+
+    let value = match applying.len() {
+      0 => default_value(&name, parent),
+      len => resolve(&name, &applying, len - 1, parent),
+    };
+    if let Some(value) = value {
+      values.insert(name, value);
+    }
+  }
+  values
+}
+
+/// Substitutes the resolved custom properties into the other properties in
+/// `values`, which take their default value when they are invalid at
+/// computed-value time.
+fn substitute_all(values: &mut BTreeMap<String, String>, variables: &BTreeMap<String, String>, parent: Option<&ComputedStyle>) {
+  let names: Vec<String> = values.keys().cloned().collect();
+  for name in names {
+    match substitute_variables(&values[&name], variables).or_else(|| default_value(&name, parent)) {
+      Some(value) => values.insert(name, value),
+      None => values.remove(&name),
+    };
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use crate::cascade::{Cascade, Origin};
+  use crate::matching::Element;
+  use crate::stylesheet::Stylesheet;
+
+  /// `<div class="outer"><p class="inner"></p></div>`
+  #[derive(Clone, PartialEq)]
+  struct Node {
+    inner: bool,
+  }
+
+  const OUTER: Node = Node { inner: false };
+  const INNER: Node = Node { inner: true };
+
+  impl Element for Node {
+    fn parent_element(&self) -> Option<Self> {
+      self.inner.then_some(OUTER)
+    }
+
+    fn previous_sibling_element(&self) -> Option<Self> {
+      None
+    }
+
+    fn next_sibling_element(&self) -> Option<Self> {
+      None
+    }
+
+    fn first_child_element(&self) -> Option<Self> {
+      (!self.inner).then_some(INNER)
+    }
+
+    fn local_name(&self) -> &str {
+      if self.inner { "p" } else { "div" }
+    }
+
+    fn id(&self) -> Option<&str> {
+      None
+    }
+
+    fn has_class(&self, name: &str) -> bool {
+      name == if self.inner { "inner" } else { "outer" }
+    }
+
+    fn attribute(&self, _name: &str) -> Option<&str> {
+      None
+    }
+  }
+
+  fn cascade(sheets: &[(Origin, &str)]) -> Cascade {
+    let mut cascade = Cascade::new();
+    for (origin, css) in sheets {
+      cascade.add_stylesheet(*origin, &Stylesheet::from_string(css).unwrap());
+    }
+    cascade
+  }
+
+  #[test]
+  fn test_inherited_and_initial_values() {
+    let cascade = cascade(&[(Origin::Author, "div { color: red; font-size: 20px; margin-top: 4px; --gap: 2px }")]);
+    let style = cascade.computed_style(&INNER);
+    assert_eq!(style.get("color"), Some("red"));
+    assert_eq!(style.get("font-size"), Some("20px"));
+    assert_eq!(style.get("--gap"), Some("2px"));
+    assert_eq!(style.get("margin-top"), Some("0"));
+    assert_eq!(style.get("Font-Weight"), Some("normal"));
+    assert_eq!(cascade.computed_style(&OUTER).get("margin-top"), Some("4px"));
+  }
+
+  #[test]
+  fn test_inherit_initial_unset() {
+    let cascade = cascade(&[(Origin::Author, "
+      div { color: red; margin-top: 4px; border-top-color: blue }
+      p { margin-top: inherit; color: initial; border-top-color: unset; text-align: unset; font-size: inherit }
+    ")]);
+    let style = cascade.computed_style(&INNER);
+    assert_eq!(style.get("margin-top"), Some("4px"));
+    assert_eq!(style.get("color"), Some("canvastext"));
+    assert_eq!(style.get("border-top-color"), Some("currentcolor"));
+    assert_eq!(style.get("text-align"), Some("start"));
+    assert_eq!(style.get("font-size"), Some("medium"));
+  }
+
+  #[test]
+  fn test_inherit_at_root() {
+    let cascade = cascade(&[(Origin::Author, "div { color: inherit; width: inherit; --x: inherit }")]);
+    let style = cascade.computed_style(&OUTER);
+    assert_eq!(style.get("color"), Some("canvastext"));
+    assert_eq!(style.get("width"), Some("auto"));
+    assert_eq!(style.get("--x"), None);
+  }
+
+  #[test]
+  fn test_revert() {
+    let cascade = cascade(&[
+      (Origin::UserAgent, "p { display: block; margin-top: 1em }"),
+      (Origin::User, "p { margin-top: 2em }"),
+      (Origin::Author, "p { display: flex; margin-top: 0 } .inner { display: revert; margin-top: revert; width: revert }"),
+    ]);
+    let style = cascade.computed_style(&INNER);
+    assert_eq!(style.get("display"), Some("block"));
+    assert_eq!(style.get("margin-top"), Some("2em"));
+    assert_eq!(style.get("width"), Some("auto"));
+  }
+
+  #[test]
+  fn test_revert_layer() {
+    let cascade = cascade(&[
+      (Origin::UserAgent, "p { display: block }"),
+      (Origin::Author, "
+        @layer base { p { color: green; display: flex } }
+        @layer theme { p { color: blue } }
+        p { color: revert-layer; display: revert-layer }
+        @layer theme { .inner { display: revert-layer } }
+      "),
+    ]);
+    let style = cascade.computed_style(&INNER);
+    assert_eq!(style.get("color"), Some("blue"));
+    // Unlayered `revert-layer` goes to `theme`, whose `revert-layer` goes to `base`
+    assert_eq!(style.get("display"), Some("flex"));
+  }
+
+  #[test]
+  fn test_revert_layer_without_previous_layer_reverts_origin() {
+    let cascade = cascade(&[
+      (Origin::UserAgent, "p { display: block }"),
+      (Origin::Author, "p { display: revert-layer }"),
+    ]);
+    assert_eq!(cascade.computed_style(&INNER).get("display"), Some("block"));
+  }
+
+  #[test]
+  fn test_all() {
+    let cascade = cascade(&[(Origin::Author, "div { color: red; direction: rtl } p { margin-top: 3px; direction: ltr; all: unset; --x: 1 }")]);
+    let style = cascade.computed_style(&INNER);
+    assert_eq!(style.get("margin-top"), Some("0"));
+    assert_eq!(style.get("color"), Some("red"));
+    assert_eq!(style.get("direction"), Some("ltr"));
+    assert_eq!(style.get("--x"), Some("1"));
+    assert_eq!(style.get("all"), None);
+  }
+
+  #[test]
+  fn test_unknown_properties_are_not_inherited() {
+    let cascade = cascade(&[(Origin::Author, "div { -x-custom: 1; font: 12px serif }")]);
+    assert_eq!(cascade.computed_style(&OUTER).get("-x-custom"), Some("1"));
+    assert_eq!(cascade.computed_style(&INNER).get("-x-custom"), None);
+  }
+
+  #[test]
+  fn test_variables() {
+    let cascade = cascade(&[(Origin::Author, "
+      div { --gap: 4px; --a: var(--b); --b: var(--a); color: red }
+      p { --pad: var(--gap) 2px; padding-top: var(--pad); margin-top: var(--a, 1px); color: var(--a); width: var(--nope, var(--gap)) }
+    ")]);
+    let style = cascade.computed_style(&INNER);
+    assert_eq!(style.get("--gap"), Some("4px"));
+    assert_eq!(style.get("--pad"), Some("4px 2px"));
+    assert_eq!(style.get("--a"), None);
+    assert_eq!(style.get("padding-top"), Some("4px 2px"));
+    assert_eq!(style.get("margin-top"), Some("1px"));
+    // Invalid at computed-value time: `unset`, which inherits `color`
+    assert_eq!(style.get("color"), Some("red"));
+    assert_eq!(style.get("width"), Some("4px"));
+  }
+
+  #[test]
+  fn test_shorthands() {
+    let cascade = cascade(&[(Origin::Author, "
+      div { --side: 2px; font: italic 12px serif }
+      p { margin: 10px; margin-top: 5px; padding: var(--side) 3px; border-width: var(--nope) }
+      p { margin: 1px; font-size: 20px }
+    ")]);
+    let style = cascade.computed_style(&INNER);
+    assert_eq!(style.get("margin-top"), Some("1px"));
+    assert_eq!(style.get("margin-left"), Some("1px"));
+    assert_eq!(style.get("margin"), None);
+    assert_eq!(style.get("padding-top"), Some("2px"));
+    assert_eq!(style.get("padding-right"), Some("3px"));
+    assert_eq!(style.get("border-top-width"), Some("medium"));
+    assert_eq!(style.get("font-style"), Some("italic"));
+    assert_eq!(style.get("font-family"), Some("serif"));
+    assert_eq!(style.get("font-size"), Some("20px"));
+  }
+
+  #[test]
+  fn test_computed_style_with_parent() {
+    let cascade = cascade(&[(Origin::Author, "p { line-height: inherit }")]);
+    let mut parent = cascade.computed_style(&OUTER);
+    parent.values.insert("line-height".to_string(), "1.5".to_string());
+    let style = cascade.computed_style_with_parent(&INNER, Some(&parent));
+    assert_eq!(style.get("line-height"), Some("1.5"));
+  }
+}
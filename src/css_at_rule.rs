@@ -0,0 +1,885 @@
+//! CSS At-Rules
+//!
+//! This module provides the representation of the standard CSS at-rules that
+//! can appear in a stylesheet. They are reached through the `Rule` enum, which
+//! takes care of parsing them.
+//!
+//! - Conditional group rules hold a nested rule list: `CSSMediaRule`,
+//!   `CSSSupportsRule`, `CSSContainerRule`, `CSSLayerRule` (block form),
+//!   `CSSScopeRule` and `CSSStartingStyleRule`
+//! - Statement rules end with `;`: `CSSImportRule`, `CSSNamespaceRule`,
+//!   `CSSCharsetRule` and `CSSLayerRule` (statement form)
+//! - Descriptor rules hold a `CSSDeclarationList`: `CSSFontFaceRule`,
+//!   `CSSPageRule`, `CSSCounterStyleRule`, `CSSPropertyRule`,
+//!   `CSSFontPaletteValuesRule` and `CSSViewTransitionRule`
+//! - `CSSKeyframesRule` holds a list of `CSSKeyframe` blocks
+//! - `CSSUnknownAtRule` keeps any other at-rule verbatim
+//!
+//! ## Examples
+//!
+//! ```rust
+//! use css_structs::{Rule, CSSMediaRule, CSSRule, CSSDeclarationList};
+//!
+//! let declarations = CSSDeclarationList::from_string("color: black").unwrap();
+//! let media = CSSMediaRule::new("print", vec![Rule::Style(CSSRule::new("body", &declarations))]);
+//! assert_eq!(media.to_string(), "@media print { body { color: black; } }");
+//! ```
+
+
+use std::fmt;
+use nom::{
+  branch::alt,
+  bytes::complete::{tag_no_case, take},
+  character::complete::{char, multispace0, multispace1},
+  combinator::{map, opt},
+  multi::many0,
+  sequence::{delimited, preceded, terminated},
+  Parser,
+};
+use crate::css_comment::CSSComment;
+use crate::css_declaration::CSSDeclaration;
+use crate::css_declaration_list::CSSDeclarationList;
+use crate::css_rule::CSSRule;
+use crate::helpers::{parse_string, scan_block, scan_until, serialize_string, skip_invalid, trivia};
+use crate::parse_error::{close_at_end, record, recovering, ParseErrorKind, ParseResult, SyntaxError};
+use crate::rule::Rule;
+use crate::span::NodeSpan;
+
+
+fn write_comments(f: &mut fmt::Formatter<'_>, comments: &[CSSComment]) -> fmt::Result {
+  if f.alternate() {
+    for comment in comments {
+      write!(f, "{} ", comment)?;
+    }
+  }
+
+  Ok(())
+}
+
+fn write_rules(f: &mut fmt::Formatter<'_>, rules: &[Rule], trailing_comments: &[CSSComment]) -> fmt::Result {
+  let trailing_comments = trailing_comments.iter().filter(|_| f.alternate()).map(|comment| comment.to_string());
+  let rules = rules
+    .iter()
+    .map(|rule| if f.alternate() { format!("{:#}", rule) } else { rule.to_string() })
+    .chain(trailing_comments)
+    .collect::<Vec<_>>()
+    .join(" ");
+
+  write!(f, "{{ {} }}", rules)
+}
+
+fn write_declarations(f: &mut fmt::Formatter<'_>, declarations: &CSSDeclarationList) -> fmt::Result {
+  if f.alternate() {
+    write!(f, "{{ {:#} }}", declarations)
+  } else {
+    write!(f, "{{ {} }}", declarations)
+  }
+}
+
+fn write_prelude(f: &mut fmt::Formatter<'_>, prelude: &str) -> fmt::Result {
+  if prelude.is_empty() {
+    Ok(())
+  } else {
+    write!(f, "{} ", prelude)
+  }
+}
+
+fn scan_unquoted_url(input: &str) -> ParseResult<'_, &str> {
+  take(scan_until(input, &[')', ' ', '\t', '\n'])).parse(input)
+}
+
+/// Parses the `layer` keyword only when it is not the start of a longer identifier.
+fn layer_keyword(input: &str) -> ParseResult<'_, &str> {
+  let (rest, keyword) = tag_no_case("layer").parse(input)?;
+  match rest.chars().next() {
+    Some(c) if c.is_alphanumeric() || c == '-' || c == '_' => {
+      SyntaxError::error(input, ParseErrorKind::UnexpectedToken, "`layer`")
+    }
+    _ => Ok((rest, keyword)),
+  }
+}
+
+/// Parses a `url(...)` or a quoted string, as used by `@import` and `@namespace`.
+fn parse_url(input: &str) -> ParseResult<'_, String> {
+  alt((
+    parse_string,
+    delimited(
+      (tag_no_case("url("), multispace0),
+      alt((
+        parse_string,
+        map(scan_unquoted_url, |url: &str| url.to_string()),
+      )),
+      (multispace0, char(')')),
+    ),
+  )).parse(input)
+}
+
+/// Parses a parenthesized function argument with balanced parentheses, e.g. `supports(...)`.
+fn parse_function<'a>(name: &'static str) -> impl Parser<&'a str, Output = String, Error = SyntaxError<'a>> {
+  preceded(
+    (tag_no_case(name), char('(')),
+    map(
+      terminated(|input: &'a str| take(scan_until(input, &[')'])).parse(input), char(')')),
+      |argument: &str| argument.trim().to_string()
+    ),
+  )
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CSSMediaRule {
+  pub media: String,
+  pub rules: Vec<Rule>,
+  /// Comments at the end of the block, after its last rule.
+  pub trailing_comments: Vec<CSSComment>,
+  pub comments: Vec<CSSComment>,
+  /// The whole rule, from the `@` to its closing `}` or `;`.
+  pub span: NodeSpan,
+}
+
+impl CSSMediaRule {
+  pub fn new(media: &str, rules: Vec<Rule>) -> Self {
+    CSSMediaRule {
+      media: media.to_string(),
+      rules,
+      comments: Vec::new(),
+      trailing_comments: Vec::new(),
+      span: NodeSpan(None),
+    }
+  }
+}
+
+impl fmt::Display for CSSMediaRule {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write_comments(f, &self.comments)?;
+    write!(f, "@media ")?;
+    write_prelude(f, &self.media)?;
+    write_rules(f, &self.rules, &self.trailing_comments)
+  }
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CSSSupportsRule {
+  pub condition: String,
+  pub rules: Vec<Rule>,
+  /// Comments at the end of the block, after its last rule.
+  pub trailing_comments: Vec<CSSComment>,
+  pub comments: Vec<CSSComment>,
+  /// The whole rule, from the `@` to its closing `}` or `;`.
+  pub span: NodeSpan,
+}
+
+impl CSSSupportsRule {
+  pub fn new(condition: &str, rules: Vec<Rule>) -> Self {
+    CSSSupportsRule {
+      condition: condition.to_string(),
+      rules,
+      comments: Vec::new(),
+      trailing_comments: Vec::new(),
+      span: NodeSpan(None),
+    }
+  }
+}
+
+impl fmt::Display for CSSSupportsRule {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write_comments(f, &self.comments)?;
+    write!(f, "@supports ")?;
+    write_prelude(f, &self.condition)?;
+    write_rules(f, &self.rules, &self.trailing_comments)
+  }
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CSSContainerRule {
+  pub condition: String,
+  pub rules: Vec<Rule>,
+  /// Comments at the end of the block, after its last rule.
+  pub trailing_comments: Vec<CSSComment>,
+  pub comments: Vec<CSSComment>,
+  /// The whole rule, from the `@` to its closing `}` or `;`.
+  pub span: NodeSpan,
+}
+
+impl CSSContainerRule {
+  pub fn new(condition: &str, rules: Vec<Rule>) -> Self {
+    CSSContainerRule {
+      condition: condition.to_string(),
+      rules,
+      comments: Vec::new(),
+      trailing_comments: Vec::new(),
+      span: NodeSpan(None),
+    }
+  }
+}
+
+impl fmt::Display for CSSContainerRule {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write_comments(f, &self.comments)?;
+    write!(f, "@container ")?;
+    write_prelude(f, &self.condition)?;
+    write_rules(f, &self.rules, &self.trailing_comments)
+  }
+}
+
+
+/// `@layer` in both its statement form (`@layer a, b;`, `rules` is `None`)
+/// and its block form (`@layer a { ... }`, at most one name).
+#[derive(Debug, Clone, PartialEq)]
+pub struct CSSLayerRule {
+  pub names: Vec<String>,
+  pub rules: Option<Vec<Rule>>,
+  /// Comments at the end of the block, after its last rule.
+  pub trailing_comments: Vec<CSSComment>,
+  pub comments: Vec<CSSComment>,
+  /// The whole rule, from the `@` to its closing `}` or `;`.
+  pub span: NodeSpan,
+}
+
+impl CSSLayerRule {
+  pub(crate) fn parse_names(prelude: &str) -> Option<Vec<String>> {
+    if prelude.is_empty() {
+      return Some(Vec::new());
+    }
+
+    prelude
+      .split(',')
+      .map(|name| {
+        let name = name.trim();
+        let valid = name.split('.').all(|part| {
+          matches!(CSSDeclaration::parse_identifier(part), Ok((rest, _)) if rest.is_empty())
+        });
+        valid.then(|| name.to_string())
+      })
+      .collect()
+  }
+
+  pub fn new(names: Vec<String>, rules: Option<Vec<Rule>>) -> Self {
+    CSSLayerRule {
+      names,
+      rules,
+      comments: Vec::new(),
+      trailing_comments: Vec::new(),
+      span: NodeSpan(None),
+    }
+  }
+}
+
+impl fmt::Display for CSSLayerRule {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write_comments(f, &self.comments)?;
+    match &self.rules {
+      Some(rules) => {
+        write!(f, "@layer ")?;
+        write_prelude(f, &self.names.join(", "))?;
+        write_rules(f, rules, &self.trailing_comments)
+      }
+      None => write!(f, "@layer {};", self.names.join(", ")),
+    }
+  }
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CSSScopeRule {
+  pub prelude: String,
+  pub rules: Vec<Rule>,
+  /// Comments at the end of the block, after its last rule.
+  pub trailing_comments: Vec<CSSComment>,
+  pub comments: Vec<CSSComment>,
+  /// The whole rule, from the `@` to its closing `}` or `;`.
+  pub span: NodeSpan,
+}
+
+impl CSSScopeRule {
+  pub fn new(prelude: &str, rules: Vec<Rule>) -> Self {
+    CSSScopeRule {
+      prelude: prelude.to_string(),
+      rules,
+      comments: Vec::new(),
+      trailing_comments: Vec::new(),
+      span: NodeSpan(None),
+    }
+  }
+}
+
+impl fmt::Display for CSSScopeRule {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write_comments(f, &self.comments)?;
+    write!(f, "@scope ")?;
+    write_prelude(f, &self.prelude)?;
+    write_rules(f, &self.rules, &self.trailing_comments)
+  }
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CSSStartingStyleRule {
+  pub rules: Vec<Rule>,
+  /// Comments at the end of the block, after its last rule.
+  pub trailing_comments: Vec<CSSComment>,
+  pub comments: Vec<CSSComment>,
+  /// The whole rule, from the `@` to its closing `}` or `;`.
+  pub span: NodeSpan,
+}
+
+impl CSSStartingStyleRule {
+  pub fn new(rules: Vec<Rule>) -> Self {
+    CSSStartingStyleRule {
+      rules,
+      comments: Vec::new(),
+      trailing_comments: Vec::new(),
+      span: NodeSpan(None),
+    }
+  }
+}
+
+impl fmt::Display for CSSStartingStyleRule {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write_comments(f, &self.comments)?;
+    write!(f, "@starting-style ")?;
+    write_rules(f, &self.rules, &self.trailing_comments)
+  }
+}
+
+
+/// `@import`. An anonymous layer (`layer` without a name) is `Some("")`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CSSImportRule {
+  pub url: String,
+  pub layer: Option<String>,
+  pub supports: Option<String>,
+  pub media: String,
+  pub comments: Vec<CSSComment>,
+  /// The whole rule, from the `@` to its closing `}` or `;`.
+  pub span: NodeSpan,
+}
+
+impl CSSImportRule {
+  fn parse_prelude(input: &str) -> ParseResult<'_, CSSImportRule> {
+    let (input, url) = parse_url(input)?;
+    let (input, layer) = opt(preceded(
+      multispace0,
+      alt((
+        parse_function("layer"),
+        map(layer_keyword, |_| String::new()),
+      )),
+    )).parse(input)?;
+    let (input, supports) = opt(preceded(multispace0, parse_function("supports"))).parse(input)?;
+
+    Ok(("", CSSImportRule {
+      url,
+      layer,
+      supports,
+      media: input.trim().to_string(),
+      comments: Vec::new(),
+      span: NodeSpan(None),
+    }))
+  }
+
+  pub(crate) fn from_prelude(prelude: &str) -> Option<Self> {
+    Self::parse_prelude(prelude).ok().map(|(_, rule)| rule)
+  }
+
+  pub fn new(url: &str, media: &str) -> Self {
+    CSSImportRule {
+      url: url.to_string(),
+      layer: None,
+      supports: None,
+      media: media.to_string(),
+      comments: Vec::new(),
+      span: NodeSpan(None),
+    }
+  }
+}
+
+impl fmt::Display for CSSImportRule {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write_comments(f, &self.comments)?;
+    write!(f, "@import url({})", serialize_string(&self.url))?;
+    match self.layer.as_deref() {
+      Some("") => write!(f, " layer")?,
+      Some(layer) => write!(f, " layer({})", layer)?,
+      None => {}
+    }
+    if let Some(supports) = &self.supports {
+      write!(f, " supports({})", supports)?;
+    }
+    if !self.media.is_empty() {
+      write!(f, " {}", self.media)?;
+    }
+    write!(f, ";")
+  }
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CSSNamespaceRule {
+  pub prefix: Option<String>,
+  pub url: String,
+  pub comments: Vec<CSSComment>,
+  /// The whole rule, from the `@` to its closing `}` or `;`.
+  pub span: NodeSpan,
+}
+
+impl CSSNamespaceRule {
+  pub(crate) fn from_prelude(prelude: &str) -> Option<Self> {
+    let (rest, (prefix, url)) = alt((
+      map(parse_url, |url| (None, url)),
+      (map(terminated(CSSDeclaration::parse_identifier, multispace1), Some), parse_url),
+    )).parse(prelude).ok()?;
+
+    rest.trim().is_empty().then(|| CSSNamespaceRule {
+      prefix,
+      url,
+      comments: Vec::new(),
+      span: NodeSpan(None),
+    })
+  }
+
+  pub fn new(prefix: Option<&str>, url: &str) -> Self {
+    CSSNamespaceRule {
+      prefix: prefix.map(|prefix| prefix.to_string()),
+      url: url.to_string(),
+      comments: Vec::new(),
+      span: NodeSpan(None),
+    }
+  }
+}
+
+impl fmt::Display for CSSNamespaceRule {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write_comments(f, &self.comments)?;
+    write!(f, "@namespace ")?;
+    if let Some(prefix) = &self.prefix {
+      write!(f, "{} ", prefix)?;
+    }
+    write!(f, "url({});", serialize_string(&self.url))
+  }
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CSSCharsetRule {
+  pub encoding: String,
+  pub comments: Vec<CSSComment>,
+  /// The whole rule, from the `@` to its closing `}` or `;`.
+  pub span: NodeSpan,
+}
+
+impl CSSCharsetRule {
+  pub(crate) fn from_prelude(prelude: &str) -> Option<Self> {
+    match parse_string(prelude) {
+      Ok(("", encoding)) => Some(CSSCharsetRule::new(&encoding)),
+      _ => None,
+    }
+  }
+
+  pub fn new(encoding: &str) -> Self {
+    CSSCharsetRule {
+      encoding: encoding.to_string(),
+      comments: Vec::new(),
+      span: NodeSpan(None),
+    }
+  }
+}
+
+impl fmt::Display for CSSCharsetRule {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write_comments(f, &self.comments)?;
+    write!(f, "@charset {};", serialize_string(&self.encoding))
+  }
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CSSFontFaceRule {
+  pub declarations: CSSDeclarationList,
+  pub comments: Vec<CSSComment>,
+  /// The whole rule, from the `@` to its closing `}` or `;`.
+  pub span: NodeSpan,
+}
+
+impl CSSFontFaceRule {
+  pub fn new(declarations: &CSSDeclarationList) -> Self {
+    CSSFontFaceRule {
+      declarations: declarations.clone(),
+      comments: Vec::new(),
+      span: NodeSpan(None),
+    }
+  }
+}
+
+impl fmt::Display for CSSFontFaceRule {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write_comments(f, &self.comments)?;
+    write!(f, "@font-face ")?;
+    write_declarations(f, &self.declarations)
+  }
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CSSPageRule {
+  pub selector: String,
+  pub declarations: CSSDeclarationList,
+  pub comments: Vec<CSSComment>,
+  /// The whole rule, from the `@` to its closing `}` or `;`.
+  pub span: NodeSpan,
+}
+
+impl CSSPageRule {
+  pub fn new(selector: &str, declarations: &CSSDeclarationList) -> Self {
+    CSSPageRule {
+      selector: selector.to_string(),
+      declarations: declarations.clone(),
+      comments: Vec::new(),
+      span: NodeSpan(None),
+    }
+  }
+}
+
+impl fmt::Display for CSSPageRule {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write_comments(f, &self.comments)?;
+    write!(f, "@page ")?;
+    write_prelude(f, &self.selector)?;
+    write_declarations(f, &self.declarations)
+  }
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CSSCounterStyleRule {
+  pub name: String,
+  pub declarations: CSSDeclarationList,
+  pub comments: Vec<CSSComment>,
+  /// The whole rule, from the `@` to its closing `}` or `;`.
+  pub span: NodeSpan,
+}
+
+impl CSSCounterStyleRule {
+  pub fn new(name: &str, declarations: &CSSDeclarationList) -> Self {
+    CSSCounterStyleRule {
+      name: name.to_string(),
+      declarations: declarations.clone(),
+      comments: Vec::new(),
+      span: NodeSpan(None),
+    }
+  }
+}
+
+impl fmt::Display for CSSCounterStyleRule {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write_comments(f, &self.comments)?;
+    write!(f, "@counter-style ")?;
+    write_prelude(f, &self.name)?;
+    write_declarations(f, &self.declarations)
+  }
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CSSPropertyRule {
+  pub name: String,
+  pub declarations: CSSDeclarationList,
+  pub comments: Vec<CSSComment>,
+  /// The whole rule, from the `@` to its closing `}` or `;`.
+  pub span: NodeSpan,
+}
+
+impl CSSPropertyRule {
+  pub fn new(name: &str, declarations: &CSSDeclarationList) -> Self {
+    CSSPropertyRule {
+      name: name.to_string(),
+      declarations: declarations.clone(),
+      comments: Vec::new(),
+      span: NodeSpan(None),
+    }
+  }
+}
+
+impl fmt::Display for CSSPropertyRule {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write_comments(f, &self.comments)?;
+    write!(f, "@property ")?;
+    write_prelude(f, &self.name)?;
+    write_declarations(f, &self.declarations)
+  }
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CSSFontPaletteValuesRule {
+  pub name: String,
+  pub declarations: CSSDeclarationList,
+  pub comments: Vec<CSSComment>,
+  /// The whole rule, from the `@` to its closing `}` or `;`.
+  pub span: NodeSpan,
+}
+
+impl CSSFontPaletteValuesRule {
+  pub fn new(name: &str, declarations: &CSSDeclarationList) -> Self {
+    CSSFontPaletteValuesRule {
+      name: name.to_string(),
+      declarations: declarations.clone(),
+      comments: Vec::new(),
+      span: NodeSpan(None),
+    }
+  }
+}
+
+impl fmt::Display for CSSFontPaletteValuesRule {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write_comments(f, &self.comments)?;
+    write!(f, "@font-palette-values ")?;
+    write_prelude(f, &self.name)?;
+    write_declarations(f, &self.declarations)
+  }
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CSSViewTransitionRule {
+  pub declarations: CSSDeclarationList,
+  pub comments: Vec<CSSComment>,
+  /// The whole rule, from the `@` to its closing `}` or `;`.
+  pub span: NodeSpan,
+}
+
+impl CSSViewTransitionRule {
+  pub fn new(declarations: &CSSDeclarationList) -> Self {
+    CSSViewTransitionRule {
+      declarations: declarations.clone(),
+      comments: Vec::new(),
+      span: NodeSpan(None),
+    }
+  }
+}
+
+impl fmt::Display for CSSViewTransitionRule {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write_comments(f, &self.comments)?;
+    write!(f, "@view-transition ")?;
+    write_declarations(f, &self.declarations)
+  }
+}
+
+
+/// A single keyframe block inside `@keyframes`, e.g. `50%, 75% { opacity: 0.5 }`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CSSKeyframe {
+  pub selector: String,
+  pub declarations: CSSDeclarationList,
+  pub comments: Vec<CSSComment>,
+}
+
+impl CSSKeyframe {
+  pub fn new(selector: &str, declarations: &CSSDeclarationList) -> Self {
+    CSSKeyframe {
+      selector: selector.to_string(),
+      declarations: declarations.clone(),
+      comments: Vec::new(),
+    }
+  }
+}
+
+impl fmt::Display for CSSKeyframe {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write_comments(f, &self.comments)?;
+    write!(f, "{} ", self.selector)?;
+    write_declarations(f, &self.declarations)
+  }
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CSSKeyframesRule {
+  pub name: String,
+  pub vendor_prefix: Option<String>,
+  pub keyframes: Vec<CSSKeyframe>,
+  /// Comments at the end of the block, after its last keyframe.
+  pub trailing_comments: Vec<CSSComment>,
+  pub comments: Vec<CSSComment>,
+  /// The whole rule, from the `@` to its closing `}` or `;`.
+  pub span: NodeSpan,
+}
+
+impl CSSKeyframesRule {
+  /// Returns `Some(prefix)` when `at_keyword` names a (possibly vendor-prefixed) `@keyframes`.
+  pub(crate) fn vendor_prefix(at_keyword: &str) -> Option<Option<String>> {
+    let prefix = at_keyword.strip_suffix("keyframes")?;
+
+    match prefix {
+      "" => Some(None),
+      "-webkit-" | "-moz-" | "-o-" | "-ms-" => Some(Some(prefix.to_string())),
+      _ => None,
+    }
+  }
+
+  /// Parses the keyframes of a block whose `{` has been consumed, up to and
+  /// including the closing `}`, and the comments after the last keyframe.
+  pub(crate) fn parse_keyframes(input: &str) -> ParseResult<'_, (Vec<CSSKeyframe>, Vec<CSSComment>)> {
+    let keyframe = |input| {
+      let (input, mut comments) = trivia(input)?;
+      let (input, (selector, selector_comments, _)) = CSSRule::parse_selector_with_comments(input)?;
+      let (input, declarations) = CSSRule::parse_declarations_block(input)?;
+      comments.extend(selector_comments);
+
+      Ok((input, CSSKeyframe { selector, declarations, comments }))
+    };
+
+    let mut keyframe_list = many0(keyframe);
+    let (mut input, mut keyframes) = keyframe_list.parse(input)?;
+    loop {
+      let (rest, comments) = trivia(input)?;
+      let error = match rest.strip_prefix('}') {
+        Some(rest) => return Ok((rest, (keyframes, comments))),
+        None if rest.is_empty() => return close_at_end(rest, (keyframes, comments)),
+        None => match CSSRule::parse(rest) {
+          Err(nom::Err::Error(error)) | Err(nom::Err::Failure(error)) => error,
+          _ => SyntaxError::new(rest, ParseErrorKind::UnexpectedToken, "a keyframe"),
+        },
+      };
+      if !recovering() {
+        return Err(nom::Err::Failure(error));
+      }
+
+      // Skip the invalid keyframe and carry on with the next one
+      record(error);
+      let (rest, mut more) = keyframe_list.parse(skip_invalid(rest, &['{', '}']))?;
+      keyframes.append(&mut more);
+      input = rest;
+    }
+  }
+
+  pub fn new(name: &str, keyframes: Vec<CSSKeyframe>) -> Self {
+    CSSKeyframesRule {
+      name: name.to_string(),
+      vendor_prefix: None,
+      keyframes,
+      comments: Vec::new(),
+      trailing_comments: Vec::new(),
+      span: NodeSpan(None),
+    }
+  }
+}
+
+impl fmt::Display for CSSKeyframesRule {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write_comments(f, &self.comments)?;
+    let trailing_comments = self.trailing_comments.iter().filter(|_| f.alternate()).map(|comment| comment.to_string());
+    let keyframes = self.keyframes
+      .iter()
+      .map(|keyframe| if f.alternate() { format!("{:#}", keyframe) } else { keyframe.to_string() })
+      .chain(trailing_comments)
+      .collect::<Vec<_>>()
+      .join(" ");
+
+    write!(f, "@{}keyframes {} {{ {} }}", self.vendor_prefix.as_deref().unwrap_or(""), self.name, keyframes)
+  }
+}
+
+
+/// An at-rule this crate does not model, kept verbatim. `block` holds the raw
+/// contents between the braces, or `None` for a statement at-rule.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CSSUnknownAtRule {
+  pub name: String,
+  pub prelude: String,
+  pub block: Option<String>,
+  pub comments: Vec<CSSComment>,
+  /// The whole rule, from the `@` to its closing `}` or `;`.
+  pub span: NodeSpan,
+}
+
+impl CSSUnknownAtRule {
+  pub(crate) fn parse_block(input: &str) -> ParseResult<'_, String> {
+    let Some(len) = scan_block(input) else {
+      let rest = &input[input.len()..];
+      return close_at_end(rest, input.to_string());
+    };
+
+    map(terminated(take(len), char('}')), |block: &str| block.to_string()).parse(input)
+  }
+
+  pub fn new(name: &str, prelude: &str, block: Option<&str>) -> Self {
+    CSSUnknownAtRule {
+      name: name.to_string(),
+      prelude: prelude.to_string(),
+      block: block.map(|block| block.to_string()),
+      comments: Vec::new(),
+      span: NodeSpan(None),
+    }
+  }
+}
+
+impl fmt::Display for CSSUnknownAtRule {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write_comments(f, &self.comments)?;
+    write!(f, "@{}", self.name)?;
+    if !self.prelude.is_empty() {
+      write!(f, " {}", self.prelude)?;
+    }
+    match &self.block {
+      Some(block) => write!(f, " {{{}}}", block),
+      None => write!(f, ";"),
+    }
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_parse_url() {
+    assert_eq!(parse_url("url(a.css) x"), Ok((" x", "a.css".to_string())));
+    assert_eq!(parse_url("url( 'a b.css' )"), Ok(("", "a b.css".to_string())));
+    assert_eq!(parse_url("\"a.css\""), Ok(("", "a.css".to_string())));
+    assert!(parse_url("a.css").is_err());
+  }
+
+  #[test]
+  fn test_import_prelude_anonymous_layer() {
+    let import = CSSImportRule::from_prelude("\"a.css\" layer print").unwrap();
+    assert_eq!(import.layer.as_deref(), Some(""));
+    assert_eq!(import.media, "print");
+
+    let import = CSSImportRule::from_prelude("\"a.css\" layered-media").unwrap();
+    assert_eq!(import.layer, None);
+    assert_eq!(import.media, "layered-media");
+  }
+
+  #[test]
+  fn test_import_prelude_nested_supports() {
+    let import = CSSImportRule::from_prelude("url(a.css) supports(not (display: grid))").unwrap();
+    assert_eq!(import.supports.as_deref(), Some("not (display: grid)"));
+    assert_eq!(import.media, "");
+  }
+
+  #[test]
+  fn test_layer_names() {
+    assert_eq!(CSSLayerRule::parse_names("a, b.c"), Some(vec!["a".to_string(), "b.c".to_string()]));
+    assert_eq!(CSSLayerRule::parse_names(""), Some(vec![]));
+    assert_eq!(CSSLayerRule::parse_names("a b"), None);
+  }
+
+  #[test]
+  fn test_keyframes_vendor_prefix() {
+    assert_eq!(CSSKeyframesRule::vendor_prefix("keyframes"), Some(None));
+    assert_eq!(CSSKeyframesRule::vendor_prefix("-moz-keyframes"), Some(Some("-moz-".to_string())));
+    assert_eq!(CSSKeyframesRule::vendor_prefix("media"), None);
+  }
+
+  #[test]
+  fn test_display_with_comments() {
+    let mut media = CSSMediaRule::new("print", vec![]);
+    media.comments.push(CSSComment::new(" print "));
+    assert_eq!(media.to_string(), "@media print {  }");
+    assert_eq!(format!("{:#}", media), "/* print */ @media print {  }");
+  }
+}
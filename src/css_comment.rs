@@ -0,0 +1,151 @@
+//! CSS Comment Parser
+//!
+//! This module provides parsing and representation for CSS comments
+//! (`/* ... */` blocks that may appear between rules, inside selectors,
+//! between declarations and inside values).
+//!
+//! Comments are not part of the CSS data model, so the parsers strip them from
+//! selectors and values. They are kept as `CSSComment` nodes attached to the
+//! `CSSRule` or `CSSDeclaration` that follows them, and written back out when
+//! the alternate `Display` flag (`{:#}`) is used. Comments at the end of a
+//! block or stylesheet, or in an empty block, are kept in the
+//! `trailing_comments` of the block and written after its contents.
+//!
+//! ## Main API
+//!
+//! - `CSSComment::from_string()` - Parse a CSS comment from a string
+//! - `CSSComment::new()` - Create a new comment programmatically
+//! - `Display` trait implementation for converting back to CSS string
+//!
+//! ## Examples
+//!
+//! ```rust
+//! use css_structs::{CSSComment, CSSRule};
+//!
+//! // Parse from string
+//! let comment = CSSComment::from_string("/* brand colors */").unwrap();
+//! assert_eq!(comment.text, " brand colors ");
+//!
+//! // Comments are attached to the nearest rule or declaration
+//! let rule = CSSRule::from_string("/* card */ .card { color: red; }").unwrap();
+//! assert_eq!(rule.comments.len(), 1);
+//! assert_eq!(rule.to_string(), ".card { color: red; }");
+//! assert_eq!(format!("{:#}", rule), "/* card */ .card { color: red; }");
+//! ```
+
+
+use std::fmt;
+use crate::parse_error::{expect, ParseError, ParseErrorKind, ParseResult, SyntaxError};
+use nom::{
+  bytes::complete::tag,
+  Parser,
+};
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CSSComment {
+  pub text: String,
+}
+
+impl CSSComment {
+  pub(crate) fn parse(input: &str) -> ParseResult<'_, CSSComment> {
+    let (input, _) = expect(ParseErrorKind::UnexpectedToken, "`/*`", tag("/*")).parse(input)?;
+
+    match input.find("*/") {
+      Some(end) => Ok((&input[end + 2..], CSSComment::new(&input[..end]))),
+      None => SyntaxError::error(&input[input.len()..], ParseErrorKind::UnterminatedComment, "`*/`"),
+    }
+  }
+
+  pub fn from_string(input: &str) -> Result<CSSComment, ParseError> {
+    let (rest, comment) = Self::parse(input.trim_start())
+      .map_err(|error| ParseError::from_nom(input, error))?;
+
+    if !rest.trim().is_empty() {
+      return Err(ParseError::new(input, rest.trim_start(), ParseErrorKind::UnexpectedToken, "end of input"));
+    }
+
+    Ok(comment)
+  }
+
+  pub fn new(text: &str) -> Self {
+    CSSComment {
+      text: text.to_string(),
+    }
+  }
+}
+
+impl fmt::Display for CSSComment {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "/*{}*/", self.text)
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_parse_simple_comment() {
+    let (remaining, comment) = CSSComment::parse("/* hello */ body").unwrap();
+    assert_eq!(remaining, " body");
+    assert_eq!(comment.text, " hello ");
+  }
+
+  #[test]
+  fn test_parse_empty_comment() {
+    let (remaining, comment) = CSSComment::parse("/**/").unwrap();
+    assert_eq!(remaining, "");
+    assert_eq!(comment.text, "");
+  }
+
+  #[test]
+  fn test_parse_multiline_comment() {
+    let (_, comment) = CSSComment::parse("/*\n * line one\n * line two\n */").unwrap();
+    assert_eq!(comment.text, "\n * line one\n * line two\n ");
+  }
+
+  #[test]
+  fn test_parse_comment_with_css_inside() {
+    let (_, comment) = CSSComment::parse("/* a { color: red; } */").unwrap();
+    assert_eq!(comment.text, " a { color: red; } ");
+  }
+
+  #[test]
+  fn test_parse_fails_unterminated() {
+    assert!(CSSComment::parse("/* never closed").is_err());
+  }
+
+  #[test]
+  fn test_parse_fails_not_a_comment() {
+    assert!(CSSComment::parse("body {}").is_err());
+  }
+
+  #[test]
+  fn test_from_string() {
+    let comment = CSSComment::from_string("  /* note */  ").unwrap();
+    assert_eq!(comment, CSSComment::new(" note "));
+  }
+
+  #[test]
+  fn test_from_string_errors() {
+    let error = CSSComment::from_string("/* open").unwrap_err();
+    assert_eq!(error.kind, ParseErrorKind::UnterminatedComment);
+    assert_eq!(error.offset, 7);
+
+    let error = CSSComment::from_string("body").unwrap_err();
+    assert_eq!(error.kind, ParseErrorKind::UnexpectedToken);
+    assert_eq!(error.expected, "`/*`");
+
+    let error = CSSComment::from_string("/* a */ b").unwrap_err();
+    assert_eq!(error.offset, 8);
+    assert_eq!(error.expected, "end of input");
+  }
+
+  #[test]
+  fn test_to_string() {
+    let comment = CSSComment::new(" note ");
+    assert_eq!(comment.to_string(), "/* note */");
+  }
+}
@@ -7,6 +7,9 @@
 //! 
 //! - `CSSDeclaration::from_string()` - Parse a CSS declaration from a string
 //! - `CSSDeclaration::new()` - Create a new declaration programmatically  
+//! - `CSSDeclaration::parsed_value()` - Parse the value into component values (see the `value` module)
+//! - `CSSDeclaration::expand_shorthand()` - The longhand declarations of a shorthand (see the `shorthand` module)
+//! - `CSSDeclaration::math_functions()` - The `calc()` and other math functions in the value (see the `calc` module)
 //! - `Display` trait implementation for converting back to CSS string
 //!
 //! ## Examples
@@ -23,17 +26,27 @@
 //! // Create programmatically  
 //! let decl = CSSDeclaration::new("margin", "10px", None);
 //! println!("{}", decl); // "margin: 10px;"
+//!
+//! // Comments are stripped from the value and kept on the declaration
+//! let decl = CSSDeclaration::from_string("margin: 0 /* top */ auto").unwrap();
+//! assert_eq!(decl.value, "0 auto");
+//! assert_eq!(format!("{:#}", decl), "/* top */ margin: 0 auto;");
 //! ```
 
 
 use std::fmt;
-use crate::helpers::is_non_ascii;
+use crate::calc::{collect_math_functions, CalcNode};
+use crate::css_comment::CSSComment;
+use crate::helpers::{is_css_whitespace, is_non_ascii, scan_until, strip_comments, trivia};
+use crate::parse_error::{expect, ParseError, ParseErrorKind, ParseResult, SyntaxError};
+use crate::shorthand::expand;
+use crate::span::{span_of, with_source, NodeSpan};
+use crate::value::Value;
 use nom::{
-  bytes::complete::{tag, is_not, take_while1, take_while},
-  character::complete::{char, multispace0},
-  combinator::{recognize, map, opt},
-  sequence::{delimited, preceded, separated_pair, pair},
-  IResult,
+  bytes::complete::{tag_no_case, take_while1, take_while},
+  character::complete::char,
+  combinator::{consumed, recognize, map},
+  sequence::{delimited, preceded, terminated, pair},
   Parser,
 };
 
@@ -42,10 +55,17 @@ pub struct CSSDeclaration {
   pub name: String,
   pub value: String,
   pub important: bool,
+  pub comments: Vec<CSSComment>,
+  /// The whole declaration, from the name to the end of the value or `!important`.
+  pub span: NodeSpan,
+  pub name_span: NodeSpan,
+  pub value_span: NodeSpan,
+  /// The `!important` annotation, when present.
+  pub important_span: NodeSpan,
 }
 
 impl CSSDeclaration {
-  fn parse_identifier(input: &str) -> IResult<&str, String> {
+  pub(crate) fn parse_identifier(input: &str) -> ParseResult<'_, String> {
     map(
       recognize(
         pair(
@@ -60,48 +80,159 @@ impl CSSDeclaration {
     ).parse(input)
   }
 
-  fn parse_value(input: &str) -> IResult<&str, (String, bool)> {
-    map(
-      pair(
-        // Parse the main value (everything except !important)
-        map(is_not(";{}!"), |s: &str| s.trim().to_string()),
-
-        // Parse optional !important
-        opt(preceded(
-          multispace0,
-          preceded(tag("!"), preceded(multispace0, tag("important")))
-        ))
-      ),
-      |(value, important)| (value, important.is_some())
-    ).parse(input)
+  /// Scans a value up to a top-level `stops` character, following CSS Syntax
+  /// "consume a declaration": strings, escapes and `()`/`[]`/`{}` blocks are
+  /// skipped, and a `!important` only counts when nothing but whitespace and
+  /// comments follows it. Returns the raw value and the `!important` text.
+  fn scan_value<'a>(input: &'a str, stops: &[char]) -> ParseResult<'a, (&'a str, Option<&'a str>)> {
+    let terminators = [stops, &['!']].concat();
+    let mut offset = 0;
+
+    loop {
+      offset += scan_until(&input[offset..], &terminators);
+      let Some(after_bang) = input[offset..].strip_prefix('!') else { break };
+
+      if let Ok((after, _)) = preceded(trivia, tag_no_case("important")).parse(after_bang) {
+        let (end, _) = trivia(after)?;
+        if end.is_empty() || end.starts_with(stops) {
+          let important = &input[offset..input.len() - after.len()];
+          return Ok((after, (&input[..offset], Some(important))));
+        }
+      }
+      offset += 1;
+    }
+
+    Ok((&input[offset..], (&input[..offset], None)))
   }
 
-  fn parse_declaration(input: &str) -> IResult<&str, (String, (String, bool))> {
-    separated_pair(
-      preceded(multispace0, Self::parse_identifier),
-      delimited(multispace0, char(':'), multispace0),
-      Self::parse_value,
-    ).parse(input)
+  fn clean_value(raw: (&str, Option<&str>)) -> (String, bool) {
+    let (value, important) = raw;
+    (strip_comments(value).0.trim().to_string(), important.is_some())
+  }
+
+  fn parse_raw_value(input: &str) -> ParseResult<'_, (&str, Option<&str>)> {
+    let (rest, (value, important)) = Self::scan_value(input, &[';', '{', '}'])?;
+    if value.is_empty() {
+      return SyntaxError::error(input, ParseErrorKind::InvalidDeclaration, "a value");
+    }
+
+    Ok((rest, (value, important)))
+  }
+
+  fn parse_value(input: &str) -> ParseResult<'_, (String, bool)> {
+    map(Self::parse_raw_value, Self::clean_value).parse(input)
   }
 
-  pub(crate) fn parse(input: &str) -> IResult<&str, CSSDeclaration> {
-    let (input, (name, (value, important))) = Self::parse_declaration(input)?;
+  /// Custom property values may contain `{}` blocks and may be empty.
+  fn parse_raw_custom_property_value(input: &str) -> ParseResult<'_, (&str, Option<&str>)> {
+    Self::scan_value(input, &[';', '}'])
+  }
+
+  /// Parses a declaration into the raw text of its name, value and `!important`.
+  fn parse_raw_declaration(input: &str) -> ParseResult<'_, (&str, (&str, Option<&str>))> {
+    let (input, name) = terminated(
+      preceded(trivia, expect(ParseErrorKind::InvalidDeclaration, "a property name", recognize(Self::parse_identifier))),
+      delimited(trivia, expect(ParseErrorKind::InvalidDeclaration, "`:`", char(':')), trivia)
+    ).parse(input)?;
+
+    let (input, value) = if name.starts_with("--") {
+      Self::parse_raw_custom_property_value(input)?
+    } else {
+      Self::parse_raw_value(input)?
+    };
 
-    Ok((input, CSSDeclaration { name, value, important }))
+    Ok((input, (name, value)))
   }
 
-  pub fn from_string(input: &str) -> Result<CSSDeclaration, String> {
-    let (_, decl) = Self::parse(input)
-      .map_err(|_| "Failed to parse CSS declaration".to_string())?; 
+  fn parse_declaration(input: &str) -> ParseResult<'_, (String, (String, bool))> {
+    map(Self::parse_raw_declaration, |(name, value)| (name.to_string(), Self::clean_value(value))).parse(input)
+  }
+
+  pub(crate) fn parse(input: &str) -> ParseResult<'_, CSSDeclaration> {
+    let (input, (source, (name, raw_value))) = consumed(Self::parse_raw_declaration).parse(input)?;
+    let (_, mut comments) = strip_comments(source);
+    let (value, important) = Self::clean_value(raw_value);
+
+    let start = source.len() - trivia(source)?.0.len();
+    let value_text = raw_value.0.trim_end_matches(is_css_whitespace);
+    let end = match raw_value.1 {
+      Some(important) => important,
+      None => value_text,
+    };
+    let end = end.as_ptr() as usize - source.as_ptr() as usize + end.len();
+
+    // Comments between `!important` and the end of the declaration
+    let (rest, trailing) = trivia(input)?;
+    let input = if trailing.is_empty() { input } else { rest };
+    comments.extend(trailing);
+
+    Ok((input, CSSDeclaration {
+      name: name.to_string(),
+      value,
+      important,
+      comments,
+      span: NodeSpan(span_of(&source[start..end.max(start)])),
+      name_span: NodeSpan(span_of(name)),
+      value_span: NodeSpan(span_of(value_text)),
+      important_span: NodeSpan(raw_value.1.and_then(span_of)),
+    }))
+  }
+
+  pub fn from_string(input: &str) -> Result<CSSDeclaration, ParseError> {
+    let (rest, decl) = with_source(input, || Self::parse(input))
+      .map_err(|error| ParseError::from_nom(input, error))?;
+
+    let (rest, _) = trivia(rest).map_err(|error| ParseError::from_nom(input, error))?;
+    let rest = rest.strip_prefix(';').unwrap_or(rest);
+    let (rest, _) = trivia(rest).map_err(|error| ParseError::from_nom(input, error))?;
+    if !rest.is_empty() {
+      return Err(ParseError::new(input, rest, ParseErrorKind::UnexpectedToken, "`;` or end of input"));
+    }
 
     Ok(decl)
   }
 
+  /// Parses the value into component values. `value` itself is left as
+  /// written.
+  pub fn parsed_value(&self) -> Result<Value, ParseError> {
+    Value::from_string(&self.value)
+  }
+
+  /// Expands a shorthand declaration such as `margin: 0 auto` into its
+  /// longhand declarations, which keep the `!important` flag. Comments stay
+  /// with the first longhand. Returns `None` when the property is not a
+  /// supported shorthand or the value cannot be expanded.
+  pub fn expand_shorthand(&self) -> Option<Vec<CSSDeclaration>> {
+    let longhands = expand(&self.name, &self.value)?;
+    let mut declarations: Vec<CSSDeclaration> = longhands
+      .into_iter()
+      .map(|(name, value)| CSSDeclaration::new(name, &value, Some(self.important)))
+      .collect();
+    declarations[0].comments = self.comments.clone();
+    Some(declarations)
+  }
+
+  /// The outermost math functions in the value, such as `calc()` or
+  /// `clamp()`, including those nested in other functions. Invalid
+  /// expressions are left out.
+  pub fn math_functions(&self) -> Vec<CalcNode> {
+    let mut functions = Vec::new();
+    if let Ok(value) = self.parsed_value() {
+      collect_math_functions(&value.components, &mut functions);
+    }
+    functions
+  }
+
   pub fn new(name: &str, value: &str, important: Option<bool>) -> Self {
     CSSDeclaration {
       name: name.to_string(),
       value: value.to_string(),
       important: important.unwrap_or(false),
+      comments: Vec::new(),
+      span: NodeSpan(None),
+      name_span: NodeSpan(None),
+      value_span: NodeSpan(None),
+      important_span: NodeSpan(None),
     }
   }
 
@@ -109,6 +240,12 @@ impl CSSDeclaration {
 
 impl fmt::Display for CSSDeclaration {
   fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    if f.alternate() {
+      for comment in &self.comments {
+        write!(f, "{} ", comment)?;
+      }
+    }
+
     if self.important {
       write!(f, "{}: {} !important;", self.name, self.value)
     } else {
@@ -577,6 +714,57 @@ mod tests {
     assert_eq!(decl.important, false);
   }
 
+  #[test]
+  fn test_spans() {
+    let source = "  margin : 0 /* x */ auto  ! important ;";
+    let decl = CSSDeclaration::from_string(source).unwrap();
+    assert_eq!(decl.span.unwrap().text(source), "margin : 0 /* x */ auto  ! important");
+    assert_eq!(decl.name_span.unwrap().text(source), "margin");
+    assert_eq!(decl.value_span.unwrap().text(source), "0 /* x */ auto");
+    assert_eq!(decl.important_span.unwrap().text(source), "! important");
+    assert_eq!(decl.name_span.unwrap().start.column, 3);
+
+    let source = "--empty:;";
+    let decl = CSSDeclaration::from_string(source).unwrap();
+    assert_eq!(decl.span.unwrap().text(source), "--empty:");
+    assert_eq!(decl.value_span.unwrap().text(source), "");
+    assert_eq!(*decl.important_span, None);
+  }
+
+  #[test]
+  fn test_spans_absent_when_built_or_parsed_directly() {
+    assert_eq!(*CSSDeclaration::new("color", "red", None).span, None);
+    let (_, decl) = CSSDeclaration::parse("color: red").unwrap();
+    assert_eq!(*decl.span, None);
+    assert_eq!(decl, CSSDeclaration::from_string("color: red").unwrap());
+  }
+
+  #[test]
+  fn test_from_string_errors() {
+    let error = CSSDeclaration::from_string("color red").unwrap_err();
+    assert_eq!(error.kind, ParseErrorKind::InvalidDeclaration);
+    assert_eq!(error.offset, 6);
+    assert_eq!(error.expected, "`:`");
+    assert_eq!(error.found, "`red`");
+
+    let error = CSSDeclaration::from_string(": red").unwrap_err();
+    assert_eq!(error.kind, ParseErrorKind::InvalidDeclaration);
+    assert_eq!(error.expected, "a property name");
+    assert_eq!(error.found, "`:`");
+
+    let error = CSSDeclaration::from_string("color:").unwrap_err();
+    assert_eq!(error.kind, ParseErrorKind::InvalidDeclaration);
+    assert_eq!(error.expected, "a value");
+    assert_eq!(error.found, "end of input");
+
+    let error = CSSDeclaration::from_string("color: red; margin: 0").unwrap_err();
+    assert_eq!(error.kind, ParseErrorKind::UnexpectedToken);
+    assert_eq!(error.offset, 12);
+
+    let error = CSSDeclaration::from_string("color: red /* open").unwrap_err();
+    assert_eq!(error.kind, ParseErrorKind::UnterminatedComment);
+  }
+
   #[test]
   fn test_to_string() {
     let decl = CSSDeclaration::from_string("color: red;").unwrap();
@@ -584,6 +772,120 @@ mod tests {
     assert_eq!(decl_str, "color: red;");
   }
 
+  #[test]
+  fn parse_value_strips_comments() {
+    let (remaining, (value, important)) = CSSDeclaration::parse_value("1px /* width */ solid red; x").unwrap();
+    assert_eq!(value, "1px solid red");
+    assert_eq!(important, false);
+    assert_eq!(remaining, "; x");
+  }
+
+  #[test]
+  fn parse_value_comment_containing_terminators() {
+    let (remaining, (value, _)) = CSSDeclaration::parse_value("red /* ; } ! */; x").unwrap();
+    assert_eq!(value, "red");
+    assert_eq!(remaining, "; x");
+  }
+
+  #[test]
+  fn parse_value_comment_before_important() {
+    let (_, (value, important)) = CSSDeclaration::parse_value("red /* why */ ! /* really */ important").unwrap();
+    assert_eq!(value, "red");
+    assert_eq!(important, true);
+  }
+
+  #[test]
+  fn parse_value_string_with_semicolon() {
+    let (remaining, (value, important)) = CSSDeclaration::parse_value("\";\"; color: red").unwrap();
+    assert_eq!(value, "\";\"");
+    assert_eq!(important, false);
+    assert_eq!(remaining, "; color: red");
+  }
+
+  #[test]
+  fn parse_value_string_with_bang() {
+    let (remaining, (value, important)) = CSSDeclaration::parse_value("\"!\" !important; x").unwrap();
+    assert_eq!(value, "\"!\"");
+    assert_eq!(important, true);
+    assert_eq!(remaining, "; x");
+  }
+
+  #[test]
+  fn parse_value_string_with_escapes_and_braces() {
+    let (remaining, (value, _)) = CSSDeclaration::parse_value("'it\\'s } {' attr(x)}").unwrap();
+    assert_eq!(value, "'it\\'s } {' attr(x)");
+    assert_eq!(remaining, "}");
+  }
+
+  #[test]
+  fn parse_value_unquoted_data_url() {
+    let input = "url(data:image/png;base64,iVBORw0KGgo=) no-repeat; x";
+    let (remaining, (value, _)) = CSSDeclaration::parse_value(input).unwrap();
+    assert_eq!(value, "url(data:image/png;base64,iVBORw0KGgo=) no-repeat");
+    assert_eq!(remaining, "; x");
+  }
+
+  #[test]
+  fn parse_value_nested_functions() {
+    let (_, (value, important)) = CSSDeclaration::parse_value("var(--a, calc((1px + 2px) * 3)) ! IMPORTANT").unwrap();
+    assert_eq!(value, "var(--a, calc((1px + 2px) * 3))");
+    assert_eq!(important, true);
+  }
+
+  #[test]
+  fn parse_value_bang_not_at_end() {
+    let (remaining, (value, important)) = CSSDeclaration::parse_value("red !important blue; x").unwrap();
+    assert_eq!(value, "red !important blue");
+    assert_eq!(important, false);
+    assert_eq!(remaining, "; x");
+
+    let (_, (value, important)) = CSSDeclaration::parse_value("red ! ie").unwrap();
+    assert_eq!(value, "red ! ie");
+    assert_eq!(important, false);
+  }
+
+  #[test]
+  fn parse_declaration_custom_property_with_braces() {
+    let (remaining, (name, (value, important))) = CSSDeclaration::parse_declaration("--theme: { color: red; }; x").unwrap();
+    assert_eq!(name, "--theme");
+    assert_eq!(value, "{ color: red; }");
+    assert_eq!(important, false);
+    assert_eq!(remaining, "; x");
+  }
+
+  #[test]
+  fn parse_declaration_custom_property_empty() {
+    let (remaining, (name, (value, _))) = CSSDeclaration::parse_declaration("--empty:; x").unwrap();
+    assert_eq!(name, "--empty");
+    assert_eq!(value, "");
+    assert_eq!(remaining, "; x");
+  }
+
+  #[test]
+  fn test_from_string_content_values() {
+    assert_eq!(CSSDeclaration::from_string("content: \";\"").unwrap().value, "\";\"");
+    assert_eq!(CSSDeclaration::from_string("content: \"!\"").unwrap().value, "\"!\"");
+    assert_eq!(CSSDeclaration::from_string("content: \"/* x */\"").unwrap().comments.len(), 0);
+  }
+
+  #[test]
+  fn test_parse_collects_comments() {
+    let (remaining, decl) = CSSDeclaration::parse("/* a */ color /* b */ : /* c */ red /* d */ !important /* e */;").unwrap();
+    assert_eq!(remaining, ";");
+    assert_eq!(decl.name, "color");
+    assert_eq!(decl.value, "red");
+    assert_eq!(decl.important, true);
+    let texts: Vec<_> = decl.comments.iter().map(|c| c.text.as_str()).collect();
+    assert_eq!(texts, vec![" a ", " b ", " c ", " d ", " e "]);
+  }
+
+  #[test]
+  fn test_to_string_with_comments() {
+    let decl = CSSDeclaration::from_string("/* primary */ color: red").unwrap();
+    assert_eq!(decl.to_string(), "color: red;");
+    assert_eq!(format!("{:#}", decl), "/* primary */ color: red;");
+  }
+
   #[test]
   fn test_to_string_important() {
     let decl = CSSDeclaration::new("color", "red", Some(true));
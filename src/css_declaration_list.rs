@@ -8,7 +8,12 @@
 //! 
 //! - `CSSDeclarationList::from_string()` - Parse a CSS declaration list from a string
 //! - `CSSDeclarationList::new()` - Create a new declaration list programmatically  
+//! - `CSSDeclarationList::from_string_recovering()` - Parse a declaration list, skipping invalid declarations
 //! - `remove_declaration()` - Remove declarations by property name
+//! - `remove_overridden()` - Remove the declarations that never win (see the `minify` module)
+//! - `expand_shorthands()` - Replace shorthands with their longhands (see the `shorthand` module)
+//! - `collapse_shorthands()` - Combine longhands into the shortest equivalent shorthands
+//! - `format()` - Pretty-print the declarations (see the `format` module)
 //! - `Display` trait implementation for converting back to CSS string
 //!
 //! ## Examples
@@ -32,65 +37,231 @@
 
 
 use std::fmt;
-use nom::{
-  character::complete::{char, multispace0},
-  combinator::opt,
-  multi::many0,
-  sequence::{delimited, preceded},
-  IResult,
-  Parser, 
-};
+use crate::css_comment::CSSComment;
 use crate::css_declaration::CSSDeclaration;
+use crate::format::{format_declarations, FormatOptions};
+use crate::helpers::{skip_invalid, trivia};
+use crate::minify::remove_overridden;
+use crate::parse_error::{record, recovering, with_recovery, ParseError, ParseErrorKind, ParseResult, SyntaxError};
+use crate::properties::{property, standard_name};
+use crate::shorthand::{collapse, COLLAPSIBLE};
+use crate::span::with_source;
 
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct CSSDeclarationList {
   pub declarations: Vec<CSSDeclaration>,
+  /// Comments after the last declaration, or in an empty list.
+  pub trailing_comments: Vec<CSSComment>,
 }
 
 impl CSSDeclarationList {
-  fn parse_declarations(input: &str) -> IResult<&str, Vec<CSSDeclaration>> {
-    many0(
-      preceded(
-        many0(delimited(multispace0, char(';'), multispace0)),
-        delimited(
-          multispace0,
-          CSSDeclaration::parse,
-          opt(char(';')),
-        )
-      )
-    ).parse(input)
-  }
-
-  pub(crate) fn parse(input: &str) -> IResult<&str, CSSDeclarationList> {
+  fn parse_declarations(input: &str) -> ParseResult<'_, Vec<CSSDeclaration>> {
+    let mut declarations: Vec<CSSDeclaration> = Vec::new();
+    let mut input = input;
+
+    loop {
+      // Skip empty declarations, keeping any comments found between them
+      let (rest, mut comments) = match Self::parse_separators(input) {
+        Err(nom::Err::Failure(error)) if recovering() => {
+          record(error);
+          return Ok(("", declarations));
+        }
+        result => result?,
+      };
+
+      match CSSDeclaration::parse(rest) {
+        Err(nom::Err::Error(error)) | Err(nom::Err::Failure(error)) if recovering() && !rest.is_empty() && !rest.starts_with('}') => {
+          record(error);
+          input = skip_invalid(rest, &[';', '}']);
+        }
+        Err(nom::Err::Failure(error)) => return Err(nom::Err::Failure(error)),
+        Ok((rest, mut declaration)) => {
+          comments.append(&mut declaration.comments);
+          declaration.comments = comments;
+          declarations.push(declaration);
+          input = rest.strip_prefix(';').unwrap_or(rest);
+        }
+        // Trailing comments are left for the list
+        Err(_) => return Ok((input, declarations)),
+      }
+    }
+  }
+
+  pub(crate) fn parse_separators(input: &str) -> ParseResult<'_, Vec<CSSComment>> {
+    let (mut input, mut comments) = trivia(input)?;
+    while let Some(after) = input.strip_prefix(';') {
+      let (after, more) = trivia(after)?;
+      comments.extend(more);
+      input = after;
+    }
+
+    Ok((input, comments))
+  }
+
+  pub(crate) fn parse(input: &str) -> ParseResult<'_, CSSDeclarationList> {
     let (input, declarations) = Self::parse_declarations(input)?;
+    let (rest, trailing_comments) = Self::parse_separators(input)?;
+    let input = if trailing_comments.is_empty() { input } else { rest };
 
-    Ok((input, CSSDeclarationList { declarations }))
+    Ok((input, CSSDeclarationList { declarations, trailing_comments }))
   }
 
-  pub fn from_string(css_block: &str) -> Result<Self, String> {
-    let (_, declaration_list) = Self::parse(css_block)
-      .map_err(|_| "Failed to parse CSS declarations list".to_string())?;
+  pub fn from_string(css_block: &str) -> Result<Self, ParseError> {
+    let (rest, declaration_list) = with_source(css_block, || Self::parse(css_block))
+      .and_then(|(rest, list)| Ok((trivia(rest)?.0, list)))
+      .map_err(|error| ParseError::from_nom(css_block, error))?;
+
+    if !rest.is_empty() {
+      // Report why the next declaration could not be parsed
+      return Err(match CSSDeclaration::parse(rest) {
+        Err(error) => ParseError::from_nom(css_block, error),
+        Ok(_) => ParseError::new(css_block, rest, ParseErrorKind::UnexpectedToken, "`;`"),
+      });
+    }
 
     Ok(declaration_list)
   }
 
+  /// Parses a declaration list, skipping invalid declarations instead of failing.
+  /// Returns the valid declarations together with the errors that were found.
+  pub fn from_string_recovering(css_block: &str) -> (Self, Vec<ParseError>) {
+    with_source(css_block, || with_recovery(css_block, || {
+      let mut input = css_block;
+      let mut declarations = Vec::new();
+      let mut trailing_comments = Vec::new();
+
+      loop {
+        if let Ok((rest, mut list)) = Self::parse(input) {
+          declarations.append(&mut list.declarations);
+          trailing_comments = list.trailing_comments;
+          input = rest;
+        }
+        // A stray `}` ends the block the declarations were parsed in
+        match trivia(input) {
+          Ok(("", _)) => break,
+          Ok((rest, _)) => {
+            record(SyntaxError::new(rest, ParseErrorKind::UnexpectedToken, "a declaration"));
+            input = &rest[1..];
+          }
+          Err(_) => break,
+        }
+      }
+
+      CSSDeclarationList { declarations, trailing_comments }
+    }))
+  }
+
   pub fn remove_declaration(&mut self, decl_name: &str) {
     self.declarations.retain(|decl| decl.name != decl_name);
   }
 
+  /// Removes the declarations that never win: those followed by a
+  /// declaration of the same property, or of a shorthand that sets it, with
+  /// the same or a higher importance, and normal declarations of a property
+  /// that is also set with `!important`. Exact duplicates are always removed,
+  /// but when the winning value has a function, a keyword that CSS 2.1 does
+  /// not define for the property or a unit newer than CSS 2, the others are
+  /// kept as fallbacks for browsers that do not support it, as in
+  /// `color: #000; color: rgba(0, 0, 0, 0.5)` or `display: block; display: flow-root`.
+  pub fn remove_overridden(&mut self) {
+    remove_overridden(&mut self.declarations);
+  }
+
+  /// Replaces every shorthand declaration with its longhands, in place, so
+  /// that `margin: 0 auto` becomes the four `margin-*` declarations.
+  /// Shorthands that cannot be expanded, such as those using `var()`, are
+  /// kept.
+  pub fn expand_shorthands(&mut self) {
+    self.declarations = self.declarations
+      .drain(..)
+      .flat_map(|declaration| declaration.expand_shorthand().unwrap_or_else(|| vec![declaration]))
+      .collect();
+  }
+
+  /// Combines longhands back into shorthands, such as the four `margin-*`
+  /// declarations into the shortest equivalent `margin`. See
+  /// `shorthand::collapse` for the supported shorthands.
+  ///
+  /// A shorthand is only written when every one of its longhands is set,
+  /// including those it only resets such as `font-kerning` for `font`, the
+  /// declarations that win for them are all `!important` or all normal, none
+  /// uses `var()`, no other declaration sets some of them, such as
+  /// `border-top` for `border`, and none of them has a fallback that
+  /// `remove_overridden` keeps, as in `margin-left: 1px; margin-left: max(1px, 2vw)`.
+  /// It takes the place of the first longhand.
+  pub fn collapse_shorthands(&mut self) {
+    for name in COLLAPSIBLE {
+      self.collapse_shorthand(name);
+    }
+  }
+
+  fn collapse_shorthand(&mut self, name: &str) {
+    let Some(info) = property(name) else { return };
+    let is_longhand = |declaration: &CSSDeclaration| standard_name(&declaration.name).is_some_and(|standard| info.longhands.contains(&standard));
+    let overlaps = |declaration: &CSSDeclaration| {
+      property(&declaration.name).is_some_and(|other| other.longhands.iter().any(|longhand| info.longhands.contains(longhand)))
+    };
+    if self.declarations.iter().any(overlaps) {
+      return;
+    }
+
+    // The declaration that wins for each longhand: the last `!important` one, or the last one
+    let mut winners: Vec<&CSSDeclaration> = Vec::new();
+    for longhand in info.longhands {
+      let setting = || self.declarations.iter().filter(|declaration| standard_name(&declaration.name) == Some(longhand));
+      match setting().rev().find(|declaration| declaration.important).or_else(|| setting().next_back()) {
+        Some(winner) => winners.push(winner),
+        None => return,
+      }
+    }
+    let important = winners[0].important;
+    if winners.iter().any(|winner| winner.important != important) {
+      return;
+    }
+    // A shorthand would drop the fallbacks along with what they fall back from
+    let mut kept: Vec<CSSDeclaration> = self.declarations.iter().filter(|declaration| is_longhand(declaration)).cloned().collect();
+    remove_overridden(&mut kept);
+    if kept.len() > info.longhands.len() {
+      return;
+    }
+    let values: Vec<&str> = winners.iter().map(|winner| winner.value.as_str()).collect();
+    let Some(value) = collapse(name, &values) else { return };
+
+    let position = self.declarations.iter().position(is_longhand).unwrap_or(0);
+    let mut shorthand = CSSDeclaration::new(name, &value, Some(important));
+    shorthand.comments = self.declarations.iter().filter(|declaration| is_longhand(declaration)).flat_map(|declaration| declaration.comments.clone()).collect();
+    self.declarations.retain(|declaration| !is_longhand(declaration));
+    self.declarations.insert(position, shorthand);
+  }
+
+  /// Pretty-prints the declarations without indentation, one per line or all
+  /// on one line as `options` asks.
+  pub fn format(&self, options: &FormatOptions) -> String {
+    format_declarations(self, options)
+  }
+
   pub fn new() -> Self {
     CSSDeclarationList {
       declarations: Vec::new(),
+      trailing_comments: Vec::new(),
     }
   }
 }
 
+impl Default for CSSDeclarationList {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
 impl fmt::Display for CSSDeclarationList {
   fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let trailing_comments = self.trailing_comments.iter().filter(|_| f.alternate()).map(|comment| comment.to_string());
     let list_str = self.declarations
       .iter()
-      .map(|decl| decl.to_string())
+      .map(|decl| if f.alternate() { format!("{:#}", decl) } else { decl.to_string() })
+      .chain(trailing_comments)
       .collect::<Vec<_>>()
       .join(" ");
 
@@ -293,6 +464,37 @@ mod tests {
     assert_eq!(list.declarations.len(), 0);
   }
 
+  #[test]
+  fn test_from_string_recovering() {
+    let (list, errors) = CSSDeclarationList::from_string_recovering("color red; margin: 0; padding: ; } top: 0");
+    assert_eq!(list.to_string(), "margin: 0; top: 0;");
+    assert_eq!(errors.len(), 3);
+    assert_eq!(errors[0].kind, ParseErrorKind::InvalidDeclaration);
+    assert_eq!(errors[0].offset, 6);
+    assert_eq!(errors[1].offset, 31);
+    assert_eq!(errors[2].found, "`}`");
+
+    let (list, errors) = CSSDeclarationList::from_string_recovering("color: red; /* open");
+    assert_eq!(list.declarations.len(), 1);
+    assert_eq!(errors[0].kind, ParseErrorKind::UnterminatedComment);
+  }
+
+  #[test]
+  fn test_from_string_errors() {
+    let error = CSSDeclarationList::from_string("color: red;\nmargin 0;").unwrap_err();
+    assert_eq!(error.kind, ParseErrorKind::InvalidDeclaration);
+    assert_eq!((error.line, error.column), (2, 8));
+    assert_eq!(error.expected, "`:`");
+
+    let error = CSSDeclarationList::from_string("color: red; } extra").unwrap_err();
+    assert_eq!(error.offset, 12);
+    assert_eq!(error.expected, "a property name");
+    assert_eq!(error.found, "`}`");
+
+    let error = CSSDeclarationList::from_string("color: red; /* open").unwrap_err();
+    assert_eq!(error.kind, ParseErrorKind::UnterminatedComment);
+  }
+
   #[test]
   fn test_to_string_output() {
     let input = "color: red; padding: 10px;";
@@ -301,6 +503,60 @@ mod tests {
     assert_eq!(output, "color: red; padding: 10px;");
   }
 
+  #[test]
+  fn test_parse_declarations_values_with_terminators() {
+    let input = "content: \";\"; background: url(data:image/png;base64,AAAA); --x: { a: b }; color: red";
+    let (remaining, declarations) = CSSDeclarationList::parse_declarations(input).unwrap();
+
+    assert_eq!(remaining, "");
+    assert_eq!(declarations.len(), 4);
+    assert_eq!(declarations[0], CSSDeclaration::new("content", "\";\"", None));
+    assert_eq!(declarations[1], CSSDeclaration::new("background", "url(data:image/png;base64,AAAA)", None));
+    assert_eq!(declarations[2], CSSDeclaration::new("--x", "{ a: b }", None));
+    assert_eq!(declarations[3], CSSDeclaration::new("color", "red", None));
+  }
+
+  #[test]
+  fn test_parse_declarations_with_comments() {
+    let input = "/* a */ color: red; /* b */ ; margin: 0 /* c */ auto; /* d */";
+    let (remaining, declarations) = CSSDeclarationList::parse_declarations(input).unwrap();
+
+    assert_eq!(remaining, " /* d */");
+    assert_eq!(declarations.len(), 2);
+    assert_eq!(declarations[0].value, "red");
+    assert_eq!(declarations[0].comments.len(), 1);
+    assert_eq!(declarations[1].value, "0 auto");
+    let texts: Vec<_> = declarations[1].comments.iter().map(|c| c.text.as_str()).collect();
+    assert_eq!(texts, vec![" b ", " c "]);
+  }
+
+  #[test]
+  fn test_trailing_comments() {
+    let list = CSSDeclarationList::from_string("color: red; /* tail */").unwrap();
+    assert!(list.declarations[0].comments.is_empty());
+    assert_eq!(list.trailing_comments, vec![CSSComment::new(" tail ")]);
+    assert_eq!(format!("{:#}", list), "color: red; /* tail */");
+
+    let list = CSSDeclarationList::from_string(" /* only */ ").unwrap();
+    assert_eq!(format!("{:#}", list), "/* only */");
+  }
+
+  #[test]
+  fn test_parse_declarations_only_comments() {
+    let input = " /* nothing here */ ";
+    let (remaining, declarations) = CSSDeclarationList::parse_declarations(input).unwrap();
+
+    assert_eq!(remaining, input);
+    assert!(declarations.is_empty());
+  }
+
+  #[test]
+  fn test_to_string_with_comments() {
+    let list = CSSDeclarationList::from_string("/* text */ color: red; padding: 10px;").unwrap();
+    assert_eq!(list.to_string(), "color: red; padding: 10px;");
+    assert_eq!(format!("{:#}", list), "/* text */ color: red; padding: 10px;");
+  }
+
   #[test]
   fn test_remove_declaration() {
     let input = "color: red; padding: 10px;";
@@ -309,4 +565,88 @@ mod tests {
     assert_eq!(list.declarations.len(), 1);
     assert_eq!(list.declarations[0], CSSDeclaration::new("padding", "10px", None));
   }
+
+  #[test]
+  fn test_expand_shorthands() {
+    let mut list = CSSDeclarationList::from_string("color: red; padding: 1px 2px !important; margin: var(--m); gap: 3px").unwrap();
+    list.expand_shorthands();
+    assert_eq!(list.to_string(), concat!(
+      "color: red; padding-top: 1px !important; padding-right: 2px !important; padding-bottom: 1px !important; ",
+      "padding-left: 2px !important; margin: var(--m); row-gap: 3px; column-gap: 3px;",
+    ));
+  }
+
+  #[test]
+  fn test_collapse_shorthands() {
+    let collapsed = |input: &str| {
+      let mut list = CSSDeclarationList::from_string(input).unwrap();
+      list.collapse_shorthands();
+      list.to_string()
+    };
+    assert_eq!(
+      collapsed("color: red; margin-top: 0; margin-right: auto; margin-bottom: 0; margin-left: auto; row-gap: 1px; column-gap: 1px"),
+      "color: red; margin: 0 auto; gap: 1px;",
+    );
+    // The winning declarations decide
+    assert_eq!(
+      collapsed("padding-top: 9px !important; padding-right: 1px !important; padding-bottom: 1px !important; padding-left: 1px !important; padding-top: 1px !important; padding-top: 5px"),
+      "padding: 1px !important;",
+    );
+    assert_eq!(
+      collapsed("padding-top: 1px !important; padding-right: 1px; padding-bottom: 1px; padding-left: 1px"),
+      "padding-top: 1px !important; padding-right: 1px; padding-bottom: 1px; padding-left: 1px;",
+    );
+    assert_eq!(
+      collapsed("top: 1px !important; right: 2px !important; bottom: 1px !important; left: 2px !important"),
+      "inset: 1px 2px !important;",
+    );
+    assert_eq!(
+      collapsed("margin-top: var(--m); margin-right: 0; margin-bottom: 0; margin-left: 0"),
+      "margin-top: var(--m); margin-right: 0; margin-bottom: 0; margin-left: 0;",
+    );
+    // Fallbacks for browsers without `max()` or `clamp()` are kept
+    for input in [
+      "margin-top: 1px; margin-right: 1px; margin-bottom: 1px; margin-left: 1px; margin-left: max(1px, 2vw);",
+      "row-gap: 1px; column-gap: 1px; column-gap: clamp(1px, 2vw, 3px);",
+    ] {
+      assert_eq!(collapsed(input), input);
+    }
+    assert_eq!(collapsed("margin-top: 1px; margin-right: 1px; margin-bottom: 1px; margin-left: 2px; margin-left: 1px"), "margin: 1px;");
+    // Missing longhand, and a shorthand that decides the order
+    assert_eq!(collapsed("margin-top: 0; margin-right: 0; margin-bottom: 0"), "margin-top: 0; margin-right: 0; margin-bottom: 0;");
+    assert_eq!(
+      collapsed("margin-top: 0; margin: 1px; margin-right: 0; margin-bottom: 0; margin-left: 0"),
+      "margin-top: 0; margin: 1px; margin-right: 0; margin-bottom: 0; margin-left: 0;",
+    );
+  }
+
+  #[test]
+  fn test_collapse_expanded_shorthands() {
+    for input in [
+      "border: 1px solid red;",
+      "border-radius: 4px 2px / 3px;",
+      "font: italic bold 12px/1.5 Arial, sans-serif;",
+      "background: url(\"a.png\") center / cover no-repeat, red;",
+      "flex: 1;",
+      "place-items: center start;",
+    ] {
+      let mut list = CSSDeclarationList::from_string(input).unwrap();
+      list.expand_shorthands();
+      list.collapse_shorthands();
+      assert_eq!(list.to_string(), input);
+    }
+
+    let mut list = CSSDeclarationList::from_string("border: 1px solid; border-top-color: red").unwrap();
+    list.expand_shorthands();
+    list.collapse_shorthands();
+    assert_eq!(list.to_string(), concat!(
+      "border-width: 1px; border-style: solid; border-color: red currentcolor currentcolor; ",
+      "border-image-source: none; border-image-slice: 100%; border-image-width: 1; border-image-outset: 0; border-image-repeat: stretch;",
+    ));
+
+    // `border` and `font` also reset longhands they cannot set
+    let mut list = CSSDeclarationList::from_string("border-width: 1px; border-style: solid; border-color: red").unwrap();
+    list.collapse_shorthands();
+    assert_eq!(list.to_string(), "border-width: 1px; border-style: solid; border-color: red;");
+  }
 }
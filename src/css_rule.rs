@@ -4,10 +4,20 @@
 //! (selector-declaration block pairs like `div { color: red; margin: 10px }` or 
 //! `h1.title, h2.subtitle { font-weight: bold; padding: 1em }`).
 //!
+//! Rules may contain nested rules as described by the CSS Nesting Module, such
+//! as `.card { color: red; &:hover { color: blue } @media print { color: black } }`.
+//! Declarations before the first nested rule are kept in `declarations`, nested
+//! rules in `rules`. Declarations that follow a nested rule are kept in `rules`
+//! as a `Rule::NestedDeclarations`, so the original order is preserved.
+//!
 //! ## Main API
 //! 
 //! - `CSSRule::from_string()` - Parse a CSS rule from a string
 //! - `CSSRule::new()` - Create a new rule programmatically  
+//! - `CSSRule::selector_list()` - Parse the selector into a `SelectorList`
+//! - `CSSRule::specificities()` - The specificity of each selector
+//! - `CSSRule::matches()` - Whether the rule applies to an `Element`
+//! - `CSSRule::format()` - Pretty-print the rule (see the `format` module)
 //! - `Display` trait implementation for converting back to CSS string
 //!
 //! ## Examples
@@ -24,77 +34,323 @@
 //! let mut declarations = CSSDeclarationList::from_string("padding: 1em").unwrap();
 //! let rule = CSSRule::new("h1", &declarations);
 //! println!("{}", rule); // "h1 { padding: 1em }"
+//!
+//! // Comments in the selector are stripped and kept on the rule
+//! let rule = CSSRule::from_string("h1 /* title */ { color: red }").unwrap();
+//! assert_eq!(rule.selector, "h1");
+//! assert_eq!(format!("{:#}", rule), "/* title */ h1 { color: red; }");
+//!
+//! // Nested rules
+//! let rule = CSSRule::from_string(".card { color: red; &:hover { color: blue } top: 0 }").unwrap();
+//! assert_eq!(rule.declarations.declarations.len(), 1);
+//! assert_eq!(rule.rules.len(), 2);
+//! assert_eq!(rule.to_string(), ".card { color: red; &:hover { color: blue; } top: 0; }");
 //! ```
 
 
 use std::fmt;
 use nom::{
-  IResult,
-  bytes::complete::take_until,
-  character::complete::{char, multispace0},
-  sequence::{terminated, delimited},
+  character::complete::char,
+  combinator::map,
   Parser,
 };
+use crate::css_comment::CSSComment;
+use crate::css_declaration::CSSDeclaration;
 use crate::css_declaration_list::CSSDeclarationList;
+use crate::format::{format_rule, FormatOptions};
+use crate::helpers::{is_css_whitespace, scan_until, skip_invalid, strip_comments, trivia};
+use crate::matching::Element;
+use crate::parse_error::{close_at_end, expect, record, recovering, ParseError, ParseErrorKind, ParseResult, SyntaxError};
+use crate::rule::Rule;
+use crate::selector::{Selector, SelectorList, Specificity};
+use crate::span::{span_of, with_source, NodeSpan};
 
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct CSSRule {
   pub selector: String,
   pub declarations: CSSDeclarationList,
+  pub comments: Vec<CSSComment>,
+  /// Nested rules, along with the declarations that follow them.
+  pub rules: Vec<Rule>,
+  /// Comments at the end of the block, after its last nested rule. Without
+  /// nested rules, they are the `trailing_comments` of `declarations`.
+  pub trailing_comments: Vec<CSSComment>,
+  /// The whole rule, from the selector to the closing `}`.
+  pub span: NodeSpan,
+  pub selector_span: NodeSpan,
 }
 
 impl CSSRule {
-  fn parse_selector(input: &str) -> IResult<&str, String> {
-    let (input, selector) = terminated(take_until("{"), char('{')).parse(input)?;
+  fn scan_selector(input: &str) -> ParseResult<'_, &str> {
+    if input.starts_with('@') {
+      return SyntaxError::error(input, ParseErrorKind::InvalidSelector, "a selector");
+    }
+
+    let len = scan_until(input, &['{', '}']);
+    let (selector, rest) = input.split_at(len);
+    if rest.starts_with("/*") {
+      return SyntaxError::error(rest, ParseErrorKind::UnterminatedComment, "`*/`");
+    }
+
+    let (rest, _) = expect(ParseErrorKind::UnexpectedToken, "`{`", char('{')).parse(rest)?;
+    Ok((rest, selector))
+  }
+
+  /// Parses a selector up to and including the `{`, returning it without
+  /// comments, the comments, and the raw selector text for its span.
+  pub(crate) fn parse_selector_with_comments(input: &str) -> ParseResult<'_, (String, Vec<CSSComment>, &str)> {
+    let (rest, raw) = Self::scan_selector(input)?;
+    let (selector, comments) = strip_comments(raw);
+    let selector = selector.trim();
+
+    if selector.is_empty() {
+      return SyntaxError::error(input, ParseErrorKind::InvalidSelector, "a selector");
+    }
 
-    Ok((input, selector.trim().to_string()))
+    Ok((rest, (selector.to_string(), comments, raw.trim_end_matches(is_css_whitespace))))
   }
 
-  fn parse_declarations_block(input: &str) -> IResult<&str, CSSDeclarationList> {
-    let (input, declarations) = terminated(
-      delimited(
-        multispace0,
-        CSSDeclarationList::parse,
-        multispace0
-      ),
-      char('}')
-    ).parse(input)?;
+  fn parse_selector(input: &str) -> ParseResult<'_, String> {
+    map(Self::parse_selector_with_comments, |(selector, _, _)| selector).parse(input)
+  }
 
+  /// Parses the declarations of a block whose `{` has been consumed, up to and
+  /// including the closing `}`. Once inside the block the rule is committed,
+  /// so errors here are failures that explain why the next declaration failed.
+  pub(crate) fn parse_declarations_block(input: &str) -> ParseResult<'_, CSSDeclarationList> {
+    let (input, mut declarations) = CSSDeclarationList::parse(input)?;
+    let (input, mut comments) = trivia(input)?;
+    let input = match input.strip_prefix('}') {
+      Some(rest) => rest,
+      None if input.is_empty() => close_at_end(input, ())?.0,
+      None => {
+        let error = match CSSDeclaration::parse(input) {
+          Err(nom::Err::Error(error)) | Err(nom::Err::Failure(error)) => error,
+          _ => SyntaxError::new(input, ParseErrorKind::UnexpectedToken, "`;` or `}`"),
+        };
+        return Err(nom::Err::Failure(error));
+      }
+    };
+
+    declarations.trailing_comments.append(&mut comments);
     Ok((input, declarations))
   }
 
-  pub(crate) fn parse(input: &str) -> IResult<&str, CSSRule> {
-    let (input, selector) = Self::parse_selector(input)?;
-    let (input, declarations) = Self::parse_declarations_block(input)?; 
+  /// Parses a declaration or a nested rule inside a style rule block. Following
+  /// CSS Nesting, anything that is not a complete declaration is parsed as a
+  /// nested style rule.
+  fn parse_block_item(input: &str) -> ParseResult<'_, BlockItem> {
+    if input.starts_with('@') {
+      return map(Rule::parse_nested, BlockItem::Rule).parse(input);
+    }
+
+    let declaration = CSSDeclaration::parse(input);
+    let complete = match &declaration {
+      Ok((rest, _)) => {
+        let (after, _) = trivia(rest)?;
+        after.is_empty() || after.starts_with([';', '}'])
+      }
+      Err(_) => false,
+    };
+
+    // A nested rule's selector ends at a `{`, never at a `;`. Anything else was
+    // meant to be a declaration, so explain why it is not one.
+    if complete || !input[scan_until(input, &[';', '{', '}'])..].starts_with('{') {
+      return declaration.map(|(rest, declaration)| (rest, BlockItem::Declaration(declaration)));
+    }
+
+    map(Self::parse, |rule| BlockItem::Rule(Rule::Style(rule))).parse(input)
+  }
+
+  /// Parses the contents of a style rule block whose `{` has been consumed, up
+  /// to and including the closing `}`: declarations mixed with nested rules.
+  /// Returns the declarations before the first nested rule, the nested rules
+  /// with the declarations that follow them, and the comments at the end of
+  /// the block.
+  pub(crate) fn parse_block_contents(input: &str) -> ParseResult<'_, (CSSDeclarationList, Vec<Rule>, Vec<CSSComment>)> {
+    let mut declarations = CSSDeclarationList::new();
+    let mut rules: Vec<Rule> = Vec::new();
+    let mut input = input;
+
+    loop {
+      let (rest, comments) = match CSSDeclarationList::parse_separators(input) {
+        Err(nom::Err::Failure(error)) if recovering() => {
+          record(error);
+          ("", Vec::new())
+        }
+        result => result?,
+      };
+
+      if rest.is_empty() || rest.starts_with('}') {
+        let block = (declarations, rules, comments);
+        return match rest.strip_prefix('}') {
+          Some(rest) => Ok((rest, block)),
+          None => close_at_end(rest, block),
+        };
+      }
+
+      let mut comments = comments;
+      match Self::parse_block_item(rest) {
+        Ok((rest, BlockItem::Declaration(mut declaration))) => {
+          comments.append(&mut declaration.comments);
+          declaration.comments = comments;
+          match rules.last_mut() {
+            None => declarations.declarations.push(declaration),
+            Some(Rule::NestedDeclarations(nested)) => nested.declarations.declarations.push(declaration),
+            Some(_) => rules.push(Rule::NestedDeclarations(CSSNestedDeclarations {
+              declarations: CSSDeclarationList { declarations: vec![declaration], trailing_comments: Vec::new() },
+              comments: Vec::new(),
+            })),
+          }
+          input = rest;
+        }
+        Ok((rest, BlockItem::Rule(mut rule))) => {
+          let own = std::mem::replace(rule.comments_mut(), comments);
+          rule.comments_mut().extend(own);
+          rules.push(rule);
+          input = rest;
+        }
+        Err(nom::Err::Error(error)) | Err(nom::Err::Failure(error)) if recovering() => {
+          record(error);
+          input = skip_invalid(rest, &['{', ';', '}']);
+        }
+        // Once inside the block the rule is committed
+        Err(nom::Err::Error(error)) => return Err(nom::Err::Failure(error)),
+        Err(error) => return Err(error),
+      }
+    }
+  }
+
+  pub(crate) fn parse(input: &str) -> ParseResult<'_, CSSRule> {
+    let (start, mut comments) = trivia(input)?;
+    let (input, (selector, selector_comments, raw_selector)) = Self::parse_selector_with_comments(start)?;
+    let (input, (mut declarations, rules, mut trailing_comments)) = Self::parse_block_contents(input)?;
+    comments.extend(selector_comments);
+    if rules.is_empty() {
+      declarations.trailing_comments.append(&mut trailing_comments);
+    }
 
     Ok((
       input,
       CSSRule {
         selector,
         declarations,
+        comments,
+        rules,
+        trailing_comments,
+        span: NodeSpan(span_of(&start[..start.len() - input.len()])),
+        selector_span: NodeSpan(span_of(raw_selector)),
       },
     ))
   }
 
-  pub fn from_string(input: &str) -> Result<CSSRule, String> {
-    let (_, css_rule) = Self::parse(input)
-      .map_err(|_| "Failed to parse CSS rule".to_string())?;
+  pub fn from_string(input: &str) -> Result<CSSRule, ParseError> {
+    let (rest, css_rule) = with_source(input, || Self::parse(input))
+      .and_then(|(rest, rule)| Ok((trivia(rest)?.0, rule)))
+      .map_err(|error| ParseError::from_nom(input, error))?;
+
+    if !rest.is_empty() {
+      return Err(ParseError::new(input, rest, ParseErrorKind::UnexpectedToken, "end of input"));
+    }
 
     Ok(css_rule)
   }
 
+  /// Parses the selector into a `SelectorList`. Relative selectors such as
+  /// `> a` are accepted, as they are valid in nested rules.
+  pub fn selector_list(&self) -> Result<SelectorList, ParseError> {
+    SelectorList::from_string_relative(&self.selector)
+  }
+
+  /// Returns whether the rule's selector matches `element`. Fails when the
+  /// selector cannot be parsed.
+  pub fn matches<E: Element>(&self, element: &E) -> Result<bool, ParseError> {
+    Ok(self.selector_list()?.matches(element))
+  }
+
+  /// Returns the specificity of each selector in the selector list.
+  pub fn specificities(&self) -> Result<Vec<Specificity>, ParseError> {
+    Ok(self.selector_list()?.selectors.iter().map(Selector::specificity).collect())
+  }
+
+  /// Pretty-prints the rule and its nested rules, laid out as `options` asks.
+  /// The output never ends with a newline.
+  pub fn format(&self, options: &FormatOptions) -> String {
+    format_rule(self, options)
+  }
+
   pub fn new(selector: &str, declarations: &CSSDeclarationList) -> Self {
     CSSRule {
       selector: selector.to_string(),
       declarations: declarations.clone(),
+      comments: Vec::new(),
+      rules: Vec::new(),
+      trailing_comments: Vec::new(),
+      span: NodeSpan(None),
+      selector_span: NodeSpan(None),
     }
   }
 }
 
 impl fmt::Display for CSSRule {
   fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-    write!(f, "{} {{ {} }}", self.selector, self.declarations.to_string())
+    let declarations = if f.alternate() {
+      for comment in &self.comments {
+        write!(f, "{} ", comment)?;
+      }
+      format!("{:#}", self.declarations)
+    } else {
+      self.declarations.to_string()
+    };
+
+    let trailing_comments = self.trailing_comments.iter().filter(|_| f.alternate()).map(|comment| comment.to_string());
+    let block = (!declarations.is_empty()).then_some(declarations)
+      .into_iter()
+      .chain(self.rules.iter().map(|rule| if f.alternate() { format!("{:#}", rule) } else { rule.to_string() }))
+      .chain(trailing_comments)
+      .collect::<Vec<_>>()
+      .join(" ");
+
+    write!(f, "{} {{ {} }}", self.selector, block)
+  }
+}
+
+
+/// An item of a style rule block.
+enum BlockItem {
+  Declaration(CSSDeclaration),
+  Rule(Rule),
+}
+
+/// Declarations that follow a nested rule inside a style rule, kept apart so
+/// that their order relative to the nested rules is preserved. They apply to
+/// the same elements as the declarations of the enclosing rule.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CSSNestedDeclarations {
+  pub declarations: CSSDeclarationList,
+  pub comments: Vec<CSSComment>,
+}
+
+impl CSSNestedDeclarations {
+  pub fn new(declarations: &CSSDeclarationList) -> Self {
+    CSSNestedDeclarations {
+      declarations: declarations.clone(),
+      comments: Vec::new(),
+    }
+  }
+}
+
+impl fmt::Display for CSSNestedDeclarations {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    if f.alternate() {
+      for comment in &self.comments {
+        write!(f, "{} ", comment)?;
+      }
+      write!(f, "{:#}", self.declarations)
+    } else {
+      write!(f, "{}", self.declarations)
+    }
   }
 }
 
@@ -247,6 +503,181 @@ mod tests {
     assert_eq!(selector, ".nav");
   }
 
+  #[test]
+  fn test_parse_selector_with_comment() {
+    let input = ".a /* outer */ > .b/* tight */.c {color: red}";
+    let (remaining, selector) = CSSRule::parse_selector(input).unwrap();
+
+    assert_eq!(remaining, "color: red}");
+    assert_eq!(selector, ".a > .b.c");
+  }
+
+  #[test]
+  fn test_parse_selector_comment_containing_brace() {
+    let input = "div /* { */ {color: red}";
+    let (remaining, selector) = CSSRule::parse_selector(input).unwrap();
+
+    assert_eq!(remaining, "color: red}");
+    assert_eq!(selector, "div");
+  }
+
+  #[test]
+  fn test_rule_with_comments() {
+    let input = "/* lead */ h1 /* sel */ { /* first */ color: red; /* end */ }";
+    let (_, rule) = CSSRule::parse(input).unwrap();
+    assert_eq!(rule.selector, "h1");
+    assert_eq!(rule.comments, vec![CSSComment::new(" lead "), CSSComment::new(" sel ")]);
+    assert_eq!(rule.declarations.declarations.len(), 1);
+    assert_eq!(rule.declarations.declarations[0].comments, vec![CSSComment::new(" first ")]);
+    assert_eq!(rule.declarations.trailing_comments, vec![CSSComment::new(" end ")]);
+    assert_eq!(format!("{:#}", rule), "/* lead */ /* sel */ h1 { /* first */ color: red; /* end */ }");
+  }
+
+  #[test]
+  fn test_empty_rule_keeps_comments() {
+    let input = ".empty { /* todo */ }";
+    let (_, rule) = CSSRule::parse(input).unwrap();
+    assert!(rule.declarations.declarations.is_empty());
+    assert_eq!(rule.declarations.trailing_comments, vec![CSSComment::new(" todo ")]);
+    assert_eq!(format!("{:#}", rule), ".empty { /* todo */ }");
+  }
+
+  #[test]
+  fn test_to_string_with_comments() {
+    let rule = CSSRule::from_string("/* lead */ h1 { /* first */ color: red; }").unwrap();
+    assert_eq!(rule.to_string(), "h1 { color: red; }");
+    assert_eq!(format!("{:#}", rule), "/* lead */ h1 { /* first */ color: red; }");
+  }
+
+  #[test]
+  fn test_parse_selector_fails_empty_selector() {
+    let result = CSSRule::parse_selector("  { color: red }");
+    assert!(matches!(result, Err(nom::Err::Error(SyntaxError { kind: ParseErrorKind::InvalidSelector, .. }))));
+  }
+
+  #[test]
+  fn test_spans() {
+    let source = "/* card */\n.card > a /* link */ {\n  color: red;\n  top: 0\n}\n";
+    let rule = CSSRule::from_string(source).unwrap();
+    let span = rule.span.unwrap();
+    assert_eq!(span.text(source), ".card > a /* link */ {\n  color: red;\n  top: 0\n}");
+    assert_eq!((span.start.line, span.start.column), (2, 1));
+    assert_eq!((span.end.line, span.end.column), (5, 2));
+    assert_eq!(rule.selector_span.unwrap().text(source), ".card > a /* link */");
+
+    let top = &rule.declarations.declarations[1];
+    assert_eq!(top.span.unwrap().text(source), "top: 0");
+    assert_eq!((top.name_span.unwrap().start.line, top.name_span.unwrap().start.column), (4, 3));
+    let mut built = CSSRule::new(".card > a", &CSSDeclarationList::from_string("color: red; top: 0").unwrap());
+    built.comments = rule.comments.clone();
+    assert_eq!(rule, built);
+  }
+
+  #[test]
+  fn test_nested_rules() {
+    let input = ".card { color: red; &:hover { color: blue } .title { font-weight: bold } }";
+    let rule = CSSRule::from_string(input).unwrap();
+    assert_eq!(rule.declarations.declarations, vec![CSSDeclaration::new("color", "red", None)]);
+    assert_eq!(rule.rules.len(), 2);
+    assert_eq!(rule.rules[0].as_style_rule().unwrap().selector, "&:hover");
+    assert_eq!(rule.rules[1].as_style_rule().unwrap().selector, ".title");
+    assert_eq!(rule.to_string(), ".card { color: red; &:hover { color: blue; } .title { font-weight: bold; } }");
+    assert_eq!(CSSRule::from_string(&rule.to_string()).unwrap(), rule);
+  }
+
+  #[test]
+  fn test_nested_rules_keep_declaration_order() {
+    let input = "a { top: 0; > b { top: 1px } left: 0; right: 0; c:hover { top: 2px } bottom: 0 }";
+    let rule = CSSRule::from_string(input).unwrap();
+    assert_eq!(rule.declarations.declarations.len(), 1);
+    assert_eq!(rule.rules.len(), 4);
+    match &rule.rules[1] {
+      Rule::NestedDeclarations(nested) => assert_eq!(nested.to_string(), "left: 0; right: 0;"),
+      other => panic!("expected nested declarations, got {:?}", other),
+    }
+    assert_eq!(rule.rules[2].as_style_rule().unwrap().selector, "c:hover");
+    assert!(matches!(rule.rules[3], Rule::NestedDeclarations(_)));
+    assert_eq!(rule.to_string(), "a { top: 0; > b { top: 1px; } left: 0; right: 0; c:hover { top: 2px; } bottom: 0; }");
+  }
+
+  #[test]
+  fn test_nested_group_rules() {
+    let input = ".a { color: red; @media (min-width: 600px) { color: blue; & > b { top: 0 } } }";
+    let rule = CSSRule::from_string(input).unwrap();
+    let Rule::Media(media) = &rule.rules[0] else { panic!("expected @media") };
+    assert_eq!(media.rules.len(), 2);
+    match &media.rules[0] {
+      Rule::NestedDeclarations(nested) => assert_eq!(nested.declarations.declarations[0].name, "color"),
+      other => panic!("expected nested declarations, got {:?}", other),
+    }
+    assert_eq!(media.rules[1].as_style_rule().unwrap().selector, "& > b");
+    assert_eq!(rule.to_string(), ".a { color: red; @media (min-width: 600px) { color: blue; & > b { top: 0; } } }");
+  }
+
+  #[test]
+  fn test_nested_rules_with_comments() {
+    let input = "a { /* one */ top: 0; /* two */ b { left: 0 } /* three */ }";
+    let rule = CSSRule::from_string(input).unwrap();
+    assert_eq!(rule.to_string(), "a { top: 0; b { left: 0; } }");
+    assert_eq!(format!("{:#}", rule), "a { /* one */ top: 0; /* two */ b { left: 0; } /* three */ }");
+    assert_eq!(rule.trailing_comments, vec![CSSComment::new(" three ")]);
+  }
+
+  #[test]
+  fn test_nested_rule_errors() {
+    let error = CSSRule::from_string("a { color red; b { top: 0 } }").unwrap_err();
+    assert_eq!(error.kind, ParseErrorKind::InvalidDeclaration);
+    assert_eq!(error.found, "`red`");
+
+    let error = CSSRule::from_string("a { b { top: 0 }").unwrap_err();
+    assert_eq!(error.kind, ParseErrorKind::UnexpectedEndOfInput);
+
+    let error = CSSRule::from_string("a { @media { b: c; top }").unwrap_err();
+    assert_eq!(error.kind, ParseErrorKind::InvalidDeclaration);
+  }
+
+  #[test]
+  fn test_nested_rule_spans() {
+    let source = "a {\n  & b { top: 0 }\n}";
+    let rule = CSSRule::from_string(source).unwrap();
+    let nested = rule.rules[0].as_style_rule().unwrap();
+    assert_eq!(nested.span.unwrap().text(source), "& b { top: 0 }");
+    assert_eq!(nested.span.unwrap().start.line, 2);
+  }
+
+  #[test]
+  fn test_from_string_errors() {
+    let error = CSSRule::from_string("h1 { color: red; margin 0 }").unwrap_err();
+    assert_eq!(error.kind, ParseErrorKind::InvalidDeclaration);
+    assert_eq!(error.offset, 24);
+    assert_eq!(error.expected, "`:`");
+
+    let error = CSSRule::from_string("h1 { color: red;").unwrap_err();
+    assert_eq!(error.kind, ParseErrorKind::UnexpectedEndOfInput);
+    assert_eq!(error.expected, "`}`");
+    assert_eq!(error.offset, 16);
+
+    let error = CSSRule::from_string("h1 color: red").unwrap_err();
+    assert_eq!(error.kind, ParseErrorKind::UnexpectedEndOfInput);
+    assert_eq!(error.expected, "`{`");
+
+    let error = CSSRule::from_string("{ color: red }").unwrap_err();
+    assert_eq!(error.kind, ParseErrorKind::InvalidSelector);
+
+    let error = CSSRule::from_string("h1 { color: red; top }").unwrap_err();
+    assert_eq!(error.kind, ParseErrorKind::InvalidDeclaration);
+    assert_eq!(error.offset, 21);
+    assert_eq!(error.found, "`}`");
+
+    let error = CSSRule::from_string("h1 { color: red } p").unwrap_err();
+    assert_eq!(error.kind, ParseErrorKind::UnexpectedToken);
+    assert_eq!(error.offset, 18);
+
+    let error = CSSRule::from_string("h1 /* open { color: red }").unwrap_err();
+    assert_eq!(error.kind, ParseErrorKind::UnterminatedComment);
+    assert_eq!(error.offset, 3);
+  }
+
   #[test]
   fn test_basic_rule() {
     let input = "h1 { color: red; padding: 10px; }";
@@ -0,0 +1,483 @@
+//! CSS Dimensions and Units
+//!
+//! This module provides a typed `Dimension`: a number with one of the units
+//! of CSS Values and Units Level 4. Every `Unit` belongs to a `UnitKind`:
+//! lengths (absolute, font-relative, viewport and container units), angles,
+//! times, frequencies, resolutions and flex fractions.
+//!
+//! Absolute units convert between each other exactly, with a single rounding
+//! step: `1in` is exactly `2.54cm`, `96px` and `72pt`. Relative units resolve
+//! to pixels given a `ResolutionContext`, which holds the font metrics of the
+//! element and of the root, and the viewport and container sizes.
+//!
+//! ## Main API
+//!
+//! - `Dimension::from_string()` - Parse a dimension such as `1.5rem`
+//! - `Dimension::from_component()` - Interpret a parsed `ComponentValue` as a dimension
+//! - `Dimension::to()` - Convert to another absolute unit of the same kind
+//! - `Dimension::resolve()` - Resolve to the canonical unit (`px`, `deg`, `s`, `hz` or `dppx`) given a context
+//! - `Unit::from_name()` / `Unit::kind()` - Unit lookup and classification
+//! - `Display` trait implementation for converting back to CSS string
+//!
+//! ## Examples
+//!
+//! ```rust
+//! use css_structs::{Dimension, ResolutionContext, Unit, UnitKind};
+//!
+//! let length = Dimension::from_string("1.5in").unwrap();
+//! assert_eq!(length.unit.kind(), UnitKind::Length);
+//! assert_eq!(length.to(Unit::Cm).unwrap().to_string(), "3.81cm");
+//! assert_eq!(length.to(Unit::Px).unwrap().value, 144.0);
+//! assert!(length.to(Unit::Deg).is_none());
+//!
+//! let time = Dimension::from_string("200ms").unwrap();
+//! assert_eq!(time.to(Unit::S).unwrap().to_string(), "0.2s");
+//!
+//! // Relative units need a context
+//! let mut context = ResolutionContext::new(1280.0, 720.0);
+//! context.root_font.font_size = 20.0;
+//! assert_eq!(Dimension::from_string("1.5rem").unwrap().resolve(&context).unwrap().to_string(), "30px");
+//! assert_eq!(Dimension::from_string("50vw").unwrap().resolve(&context).unwrap().value, 640.0);
+//! assert_eq!(Dimension::from_string("10vmin").unwrap().resolve(&context).unwrap().value, 72.0);
+//! ```
+
+
+use std::f64::consts::PI;
+use std::fmt;
+use crate::parse_error::{ParseError, ParseErrorKind};
+use crate::value::{format_number, ComponentValue, Value};
+
+
+/// The kind of quantity a unit measures.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum UnitKind {
+  Length,
+  Angle,
+  Time,
+  Frequency,
+  Resolution,
+  /// The `fr` unit of grid tracks.
+  Flex,
+}
+
+/// A CSS unit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Unit {
+  // Absolute lengths
+  Px, Cm, Mm, Q, In, Pt, Pc,
+  // Font-relative lengths, of the element and of the root
+  Em, Rem, Ex, Rex, Cap, Rcap, Ch, Rch, Ic, Ric, Lh, Rlh,
+  // Viewport lengths: default, small, large and dynamic viewports
+  Vw, Vh, Vi, Vb, Vmin, Vmax,
+  Svw, Svh, Svi, Svb, Svmin, Svmax,
+  Lvw, Lvh, Lvi, Lvb, Lvmin, Lvmax,
+  Dvw, Dvh, Dvi, Dvb, Dvmin, Dvmax,
+  // Container query lengths
+  Cqw, Cqh, Cqi, Cqb, Cqmin, Cqmax,
+  // Angles
+  Deg, Grad, Rad, Turn,
+  // Times
+  S, Ms,
+  // Frequencies
+  Hz, Khz,
+  // Resolutions
+  Dpi, Dpcm, Dppx, X,
+  // Flex
+  Fr,
+}
+
+/// Every unit with its name, sorted by name.
+static UNITS: &[(&str, Unit)] = &[
+  ("cap", Unit::Cap), ("ch", Unit::Ch), ("cm", Unit::Cm), ("cqb", Unit::Cqb), ("cqh", Unit::Cqh),
+  ("cqi", Unit::Cqi), ("cqmax", Unit::Cqmax), ("cqmin", Unit::Cqmin), ("cqw", Unit::Cqw), ("deg", Unit::Deg),
+  ("dpcm", Unit::Dpcm), ("dpi", Unit::Dpi), ("dppx", Unit::Dppx), ("dvb", Unit::Dvb), ("dvh", Unit::Dvh),
+  ("dvi", Unit::Dvi), ("dvmax", Unit::Dvmax), ("dvmin", Unit::Dvmin), ("dvw", Unit::Dvw), ("em", Unit::Em),
+  ("ex", Unit::Ex), ("fr", Unit::Fr), ("grad", Unit::Grad), ("hz", Unit::Hz), ("ic", Unit::Ic),
+  ("in", Unit::In), ("khz", Unit::Khz), ("lh", Unit::Lh), ("lvb", Unit::Lvb), ("lvh", Unit::Lvh),
+  ("lvi", Unit::Lvi), ("lvmax", Unit::Lvmax), ("lvmin", Unit::Lvmin), ("lvw", Unit::Lvw), ("mm", Unit::Mm),
+  ("ms", Unit::Ms), ("pc", Unit::Pc), ("pt", Unit::Pt), ("px", Unit::Px), ("q", Unit::Q),
+  ("rad", Unit::Rad), ("rcap", Unit::Rcap), ("rch", Unit::Rch), ("rem", Unit::Rem), ("rex", Unit::Rex),
+  ("ric", Unit::Ric), ("rlh", Unit::Rlh), ("s", Unit::S), ("svb", Unit::Svb), ("svh", Unit::Svh),
+  ("svi", Unit::Svi), ("svmax", Unit::Svmax), ("svmin", Unit::Svmin), ("svw", Unit::Svw), ("turn", Unit::Turn),
+  ("vb", Unit::Vb), ("vh", Unit::Vh), ("vi", Unit::Vi), ("vmax", Unit::Vmax), ("vmin", Unit::Vmin),
+  ("vw", Unit::Vw), ("x", Unit::X),
+];
+
+/// The font metrics that font-relative units resolve against, in pixels.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FontMetrics {
+  /// `em`
+  pub font_size: f64,
+  /// `ex`
+  pub x_height: f64,
+  /// `cap`
+  pub cap_height: f64,
+  /// `ch`: the advance of the `0` glyph.
+  pub zero_advance: f64,
+  /// `ic`: the advance of the `水` glyph.
+  pub ideograph_advance: f64,
+  /// `lh`
+  pub line_height: f64,
+}
+
+/// The sizes relative units resolve against, in pixels.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ResolutionContext {
+  /// The font of the element, for `em`, `ex`, `cap`, `ch`, `ic` and `lh`.
+  pub font: FontMetrics,
+  /// The font of the root element, for `rem`, `rex`, `rcap`, `rch`, `ric` and `rlh`.
+  pub root_font: FontMetrics,
+  /// The default and dynamic viewport, for `vw` and `dvw` and their kin.
+  pub viewport_width: f64,
+  pub viewport_height: f64,
+  /// The small viewport, with every dynamic toolbar expanded, for `svw`.
+  pub small_viewport_width: f64,
+  pub small_viewport_height: f64,
+  /// The large viewport, with every dynamic toolbar retracted, for `lvw`.
+  pub large_viewport_width: f64,
+  pub large_viewport_height: f64,
+  /// The size of the query container, for `cqw` and its kin. Without a
+  /// container, these units use the small viewport.
+  pub container_width: Option<f64>,
+  pub container_height: Option<f64>,
+  /// Whether the writing mode is vertical, which makes `vi` and `cqi`
+  /// follow the height, and `vb` and `cqb` the width.
+  pub vertical: bool,
+}
+
+/// A number with a unit, such as `1.5rem` or `200ms`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Dimension {
+  pub value: f64,
+  pub unit: Unit,
+}
+
+impl Unit {
+  /// Looks up a unit by name, ignoring ASCII case.
+  pub fn from_name(name: &str) -> Option<Unit> {
+    let name = name.to_ascii_lowercase();
+    UNITS.binary_search_by(|(unit, _)| (*unit).cmp(name.as_str())).ok().map(|index| UNITS[index].1)
+  }
+
+  /// The name of the unit, as serialized. Every unit is lowercase except `Q`.
+  pub fn name(self) -> &'static str {
+    match self {
+      Unit::Q => "Q",
+      unit => UNITS.iter().find(|(_, other)| *other == unit).map_or("", |(name, _)| name),
+    }
+  }
+
+  pub fn kind(self) -> UnitKind {
+    match self {
+      Unit::Deg | Unit::Grad | Unit::Rad | Unit::Turn => UnitKind::Angle,
+      Unit::S | Unit::Ms => UnitKind::Time,
+      Unit::Hz | Unit::Khz => UnitKind::Frequency,
+      Unit::Dpi | Unit::Dpcm | Unit::Dppx | Unit::X => UnitKind::Resolution,
+      Unit::Fr => UnitKind::Flex,
+      _ => UnitKind::Length,
+    }
+  }
+
+  /// Whether the unit converts to the others of its kind without context.
+  /// Only font-relative, viewport and container lengths, and `fr`, do not.
+  pub fn is_absolute(self) -> bool {
+    self != Unit::Fr && (self.ratio().is_some() || self == Unit::Rad)
+  }
+
+  /// The unit every unit of a kind converts to: `px`, `deg`, `s`, `hz`,
+  /// `dppx` or `fr`.
+  pub fn canonical(self) -> Unit {
+    match self.kind() {
+      UnitKind::Length => Unit::Px,
+      UnitKind::Angle => Unit::Deg,
+      UnitKind::Time => Unit::S,
+      UnitKind::Frequency => Unit::Hz,
+      UnitKind::Resolution => Unit::Dppx,
+      UnitKind::Flex => Unit::Fr,
+    }
+  }
+
+  /// The size of the unit in the canonical unit of its kind, as an exact
+  /// fraction. `rad` is irrational and handled apart.
+  fn ratio(self) -> Option<(f64, f64)> {
+    let ratio = match self {
+      Unit::Px => (1.0, 1.0),
+      Unit::In => (96.0, 1.0),
+      Unit::Cm => (4800.0, 127.0),
+      Unit::Mm => (480.0, 127.0),
+      Unit::Q => (120.0, 127.0),
+      Unit::Pt => (4.0, 3.0),
+      Unit::Pc => (16.0, 1.0),
+      Unit::Deg => (1.0, 1.0),
+      Unit::Grad => (9.0, 10.0),
+      Unit::Turn => (360.0, 1.0),
+      Unit::S => (1.0, 1.0),
+      Unit::Ms => (1.0, 1000.0),
+      Unit::Hz => (1.0, 1.0),
+      Unit::Khz => (1000.0, 1.0),
+      Unit::Dppx | Unit::X => (1.0, 1.0),
+      Unit::Dpi => (1.0, 96.0),
+      Unit::Dpcm => (127.0, 4800.0),
+      Unit::Fr => (1.0, 1.0),
+      _ => return None,
+    };
+    Some(ratio)
+  }
+}
+
+impl FontMetrics {
+  /// Metrics for a font of `font_size` pixels, with the fallbacks CSS uses
+  /// when the font does not provide them: `0.5em` for `ex` and `ch`, `1em`
+  /// for `ic`. The cap height defaults to `0.7em` and the line height to
+  /// `1.2em`, typical values for `normal`.
+  pub fn new(font_size: f64) -> Self {
+    FontMetrics {
+      font_size,
+      x_height: font_size * 0.5,
+      cap_height: font_size * 0.7,
+      zero_advance: font_size * 0.5,
+      ideograph_advance: font_size,
+      line_height: font_size * 1.2,
+    }
+  }
+}
+
+impl ResolutionContext {
+  /// A context for a viewport of the given size, used for the small, large
+  /// and dynamic viewports alike, with 16px fonts and no container.
+  pub fn new(viewport_width: f64, viewport_height: f64) -> Self {
+    ResolutionContext {
+      font: FontMetrics::new(16.0),
+      root_font: FontMetrics::new(16.0),
+      viewport_width,
+      viewport_height,
+      small_viewport_width: viewport_width,
+      small_viewport_height: viewport_height,
+      large_viewport_width: viewport_width,
+      large_viewport_height: viewport_height,
+      container_width: None,
+      container_height: None,
+      vertical: false,
+    }
+  }
+
+  /// The size in pixels of one relative length unit.
+  fn length_unit(&self, unit: Unit) -> Option<f64> {
+    let percent = |size: f64| size / 100.0;
+    // width, height, inline size, block size, min and max of a viewport
+    let axes = |width: f64, height: f64, unit: usize| {
+      let (inline, block) = if self.vertical { (height, width) } else { (width, height) };
+      percent([width, height, inline, block, width.min(height), width.max(height)][unit])
+    };
+    let viewport = |unit| axes(self.viewport_width, self.viewport_height, unit);
+    let small = |unit| axes(self.small_viewport_width, self.small_viewport_height, unit);
+    let large = |unit| axes(self.large_viewport_width, self.large_viewport_height, unit);
+    let container = |unit| axes(
+      self.container_width.unwrap_or(self.small_viewport_width),
+      self.container_height.unwrap_or(self.small_viewport_height),
+      unit,
+    );
+
+    let size = match unit {
+      Unit::Em => self.font.font_size,
+      Unit::Rem => self.root_font.font_size,
+      Unit::Ex => self.font.x_height,
+      Unit::Rex => self.root_font.x_height,
+      Unit::Cap => self.font.cap_height,
+      Unit::Rcap => self.root_font.cap_height,
+      Unit::Ch => self.font.zero_advance,
+      Unit::Rch => self.root_font.zero_advance,
+      Unit::Ic => self.font.ideograph_advance,
+      Unit::Ric => self.root_font.ideograph_advance,
+      Unit::Lh => self.font.line_height,
+      Unit::Rlh => self.root_font.line_height,
+      Unit::Vw | Unit::Dvw => viewport(0),
+      Unit::Vh | Unit::Dvh => viewport(1),
+      Unit::Vi | Unit::Dvi => viewport(2),
+      Unit::Vb | Unit::Dvb => viewport(3),
+      Unit::Vmin | Unit::Dvmin => viewport(4),
+      Unit::Vmax | Unit::Dvmax => viewport(5),
+      Unit::Svw => small(0),
+      Unit::Svh => small(1),
+      Unit::Svi => small(2),
+      Unit::Svb => small(3),
+      Unit::Svmin => small(4),
+      Unit::Svmax => small(5),
+      Unit::Lvw => large(0),
+      Unit::Lvh => large(1),
+      Unit::Lvi => large(2),
+      Unit::Lvb => large(3),
+      Unit::Lvmin => large(4),
+      Unit::Lvmax => large(5),
+      Unit::Cqw => container(0),
+      Unit::Cqh => container(1),
+      Unit::Cqi => container(2),
+      Unit::Cqb => container(3),
+      Unit::Cqmin => container(4),
+      Unit::Cqmax => container(5),
+      _ => return None,
+    };
+    Some(size)
+  }
+}
+
+impl Dimension {
+  pub fn new(value: f64, unit: Unit) -> Self {
+    Dimension { value, unit }
+  }
+
+  /// Interprets a parsed dimension token. Returns `None` for other values
+  /// and unknown units.
+  pub fn from_component(value: &ComponentValue) -> Option<Dimension> {
+    match value {
+      ComponentValue::Dimension { value, unit } => Unit::from_name(unit).map(|unit| Dimension::new(*value, unit)),
+      _ => None,
+    }
+  }
+
+  pub fn from_string(input: &str) -> Result<Dimension, ParseError> {
+    let value = Value::from_string(input)?;
+    match value.components.as_slice() {
+      [component] => Dimension::from_component(component),
+      _ => None,
+    }
+    .ok_or_else(|| ParseError::new(input, input.trim_start(), ParseErrorKind::InvalidValue, "a dimension"))
+  }
+
+  /// Converts to another unit of the same kind. Only absolute units convert
+  /// without context; returns `None` otherwise.
+  pub fn to(&self, unit: Unit) -> Option<Dimension> {
+    if unit == self.unit {
+      return Some(*self);
+    }
+    if unit.kind() != self.unit.kind() {
+      return None;
+    }
+
+    let value = match (self.unit.ratio(), unit.ratio()) {
+      // A single division keeps exact conversions exact
+      (Some((from_numerator, from_denominator)), Some((to_numerator, to_denominator))) => {
+        self.value * (from_numerator * to_denominator) / (from_denominator * to_numerator)
+      }
+      (Some((numerator, denominator)), None) if unit == Unit::Rad => self.value * numerator * PI / (denominator * 180.0),
+      (None, Some((numerator, denominator))) if self.unit == Unit::Rad => {
+        self.value * 180.0 * denominator / (PI * numerator)
+      }
+      _ => return None,
+    };
+    Some(Dimension::new(value, unit))
+  }
+
+  /// Converts to the canonical unit of its kind (see `Unit::canonical`),
+  /// resolving relative lengths against `context`.
+  pub fn resolve(&self, context: &ResolutionContext) -> Option<Dimension> {
+    match context.length_unit(self.unit) {
+      Some(size) => Some(Dimension::new(self.value * size, Unit::Px)),
+      None => self.to(self.unit.canonical()),
+    }
+  }
+}
+
+impl fmt::Display for Unit {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{}", self.name())
+  }
+}
+
+impl fmt::Display for Dimension {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{}{}", format_number(self.value), self.unit)
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn dimension(input: &str) -> Dimension {
+    Dimension::from_string(input).unwrap()
+  }
+
+  #[test]
+  fn test_units_table() {
+    assert!(UNITS.windows(2).all(|pair| pair[0].0 < pair[1].0));
+    for (name, unit) in UNITS {
+      assert_eq!(unit.name().to_ascii_lowercase(), *name);
+      assert_eq!(Unit::from_name(name), Some(*unit));
+    }
+  }
+
+  #[test]
+  fn test_parse() {
+    assert_eq!(dimension("1.5REM"), Dimension::new(1.5, Unit::Rem));
+    assert_eq!(dimension(" 10q "), Dimension::new(10.0, Unit::Q));
+    assert_eq!(dimension("10Q").to_string(), "10Q");
+    assert_eq!(dimension("-2.5e1dvmax").to_string(), "-25dvmax");
+    assert_eq!(Dimension::from_string("10").unwrap_err().kind, ParseErrorKind::InvalidValue);
+    assert!(Dimension::from_string("10foo").is_err());
+    assert!(Dimension::from_string("10px 2px").is_err());
+  }
+
+  #[test]
+  fn test_kinds() {
+    assert_eq!(Unit::Cqmin.kind(), UnitKind::Length);
+    assert_eq!(Unit::Turn.kind(), UnitKind::Angle);
+    assert_eq!(Unit::Ms.kind(), UnitKind::Time);
+    assert_eq!(Unit::Khz.kind(), UnitKind::Frequency);
+    assert_eq!(Unit::X.kind(), UnitKind::Resolution);
+    assert_eq!(Unit::Fr.kind(), UnitKind::Flex);
+    assert!(Unit::Pt.is_absolute());
+    assert!(Unit::Rad.is_absolute());
+    assert!(!Unit::Em.is_absolute());
+    assert!(!Unit::Fr.is_absolute());
+    assert_eq!(Unit::Dpi.canonical(), Unit::Dppx);
+  }
+
+  #[test]
+  fn test_exact_conversions() {
+    assert_eq!(dimension("1in").to(Unit::Cm).unwrap().value, 2.54);
+    assert_eq!(dimension("1in").to(Unit::Mm).unwrap().value, 25.4);
+    assert_eq!(dimension("1in").to(Unit::Pt).unwrap().value, 72.0);
+    assert_eq!(dimension("1pc").to(Unit::Pt).unwrap().value, 12.0);
+    assert_eq!(dimension("40Q").to(Unit::Cm).unwrap().value, 1.0);
+    assert_eq!(dimension("2.54cm").to(Unit::Px).unwrap().value, 96.0);
+    assert_eq!(dimension("0.5turn").to(Unit::Grad).unwrap().value, 200.0);
+    assert_eq!(dimension("180deg").to(Unit::Rad).unwrap().value, PI);
+    assert_eq!(dimension("1rad").to(Unit::Deg).unwrap().to_string(), "57.29578deg");
+    assert_eq!(dimension("1500ms").to(Unit::S).unwrap().value, 1.5);
+    assert_eq!(dimension("2khz").to(Unit::Hz).unwrap().value, 2000.0);
+    assert_eq!(dimension("96dpi").to(Unit::Dppx).unwrap().value, 1.0);
+    assert_eq!(dimension("2x").to(Unit::Dpcm).unwrap().to_string(), "75.590551dpcm");
+    assert!(dimension("1em").to(Unit::Px).is_none());
+    assert!(dimension("1s").to(Unit::Hz).is_none());
+  }
+
+  #[test]
+  fn test_resolve() {
+    let mut context = ResolutionContext::new(1000.0, 500.0);
+    context.font = FontMetrics::new(10.0);
+    context.small_viewport_height = 400.0;
+    context.large_viewport_width = 1200.0;
+
+    let px = |input: &str, context: &ResolutionContext| dimension(input).resolve(context).map(|dimension| dimension.value);
+    assert_eq!(px("2em", &context), Some(20.0));
+    assert_eq!(px("2rem", &context), Some(32.0));
+    assert_eq!(px("2ex", &context), Some(10.0));
+    assert_eq!(px("1lh", &context), Some(12.0));
+    assert_eq!(px("10vw", &context), Some(100.0));
+    assert_eq!(px("10vi", &context), Some(100.0));
+    assert_eq!(px("10vmax", &context), Some(100.0));
+    assert_eq!(px("10svh", &context), Some(40.0));
+    assert_eq!(px("10lvw", &context), Some(120.0));
+    assert_eq!(px("10cqh", &context), Some(40.0));
+    assert_eq!(px("1in", &context), Some(96.0));
+    assert_eq!(dimension("90deg").resolve(&context), Some(Dimension::new(90.0, Unit::Deg)));
+
+    context.container_width = Some(300.0);
+    context.vertical = true;
+    assert_eq!(px("10cqi", &context), Some(40.0));
+    assert_eq!(px("10cqb", &context), Some(30.0));
+    assert_eq!(px("10vb", &context), Some(100.0));
+  }
+}
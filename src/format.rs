@@ -0,0 +1,582 @@
+//! CSS Pretty-Printing
+//!
+//! This module writes stylesheets, rules and declaration lists laid out for
+//! people to read, as a code formatter does, while `Display` writes each
+//! rule on one line. The layout is set by `FormatOptions`: the indentation,
+//! whether each declaration and each selector of a list gets its own line,
+//! the blank lines between rules, where opening braces go, and how hex
+//! colors and strings are written.
+//!
+//! Whitespace inside selectors, values and at-rule preludes is normalized:
+//! runs of whitespace become one space, commas are followed by a space and
+//! combinators are surrounded by spaces. Custom property values are kept as
+//! written. Comments are kept, each on its own line before the rule or
+//! declaration it belongs to.
+//!
+//! Formatting is idempotent: parsing the output and formatting it again with
+//! the same options gives the same text.
+//!
+//! ## Main API
+//!
+//! - `Stylesheet::format()` - Format a whole stylesheet
+//! - `CSSRule::format()` - Format a single rule with its nested rules
+//! - `CSSDeclarationList::format()` - Format declarations without a block
+//! - `FormatOptions` - The layout, with `Default` for two-space indentation and one declaration per line
+//!
+//! ## Examples
+//!
+//! ```rust
+//! use css_structs::Stylesheet;
+//! use css_structs::format::{BraceStyle, FormatOptions, Indent};
+//!
+//! let stylesheet = Stylesheet::from_string("h1,h2{color:#FFF;font-family:'Open Sans'}@media print{a{color:red}}").unwrap();
+//! let formatted = stylesheet.format(&FormatOptions::default());
+//! assert_eq!(formatted, "\
+//! h1,
+//! h2 {
+//!   color: #fff;
+//!   font-family: \"Open Sans\";
+//! }
+//!
+//! @media print {
+//!   a {
+//!     color: red;
+//!   }
+//! }
+//! ");
+//! assert_eq!(Stylesheet::from_string(&formatted).unwrap().format(&FormatOptions::default()), formatted);
+//!
+//! let options = FormatOptions {
+//!   indent: Indent::Tabs,
+//!   brace_style: BraceStyle::NextLine,
+//!   selector_per_line: false,
+//!   blank_lines_between_rules: 0,
+//!   final_newline: false,
+//!   ..FormatOptions::default()
+//! };
+//! assert_eq!(stylesheet.format(&options), "h1, h2\n{\n\tcolor: #fff;\n\tfont-family: \"Open Sans\";\n}\n@media print\n{\n\ta\n\t{\n\t\tcolor: red;\n\t}\n}");
+//! ```
+
+
+use crate::css_at_rule::CSSLayerRule;
+use crate::css_comment::CSSComment;
+use crate::css_declaration_list::CSSDeclarationList;
+use crate::css_rule::CSSRule;
+use crate::helpers::{is_css_whitespace, split_top_level, string_len};
+use crate::rule::Rule;
+
+
+/// How each level of nesting is indented.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Indent {
+  /// The given number of spaces.
+  Spaces(usize),
+  /// One tab.
+  Tabs,
+}
+
+/// Where the `{` of a block goes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BraceStyle {
+  /// At the end of the selector or prelude: `a {`.
+  #[default]
+  SameLine,
+  /// On a line of its own, under the selector or prelude.
+  NextLine,
+}
+
+/// How strings are quoted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum QuoteStyle {
+  /// `"..."`
+  #[default]
+  Double,
+  /// `'...'`
+  Single,
+  /// As written.
+  Preserve,
+}
+
+/// The layout used by `Stylesheet::format()`, `CSSRule::format()` and
+/// `CSSDeclarationList::format()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormatOptions {
+  pub indent: Indent,
+  /// Whether each declaration gets its own line. Otherwise a rule without
+  /// nested rules is written on one line, `a { color: red; top: 0; }`.
+  pub declaration_per_line: bool,
+  /// The number of empty lines between two rules of the same block.
+  pub blank_lines_between_rules: usize,
+  /// Whether each selector of a selector list gets its own line.
+  pub selector_per_line: bool,
+  pub brace_style: BraceStyle,
+  /// Whether hex colors such as `#FFF` are lowercased.
+  pub lowercase_hex: bool,
+  pub quote_style: QuoteStyle,
+  /// Whether a formatted stylesheet ends with a newline.
+  pub final_newline: bool,
+}
+
+impl Default for FormatOptions {
+  fn default() -> Self {
+    FormatOptions {
+      indent: Indent::Spaces(2),
+      declaration_per_line: true,
+      blank_lines_between_rules: 1,
+      selector_per_line: true,
+      brace_style: BraceStyle::SameLine,
+      lowercase_hex: true,
+      quote_style: QuoteStyle::Double,
+      final_newline: true,
+    }
+  }
+}
+
+/// The kind of text `tidy` normalizes.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Text {
+  Selector,
+  Value,
+  Prelude,
+}
+
+struct Formatter<'a> {
+  options: &'a FormatOptions,
+}
+
+impl Formatter<'_> {
+  fn indent(&self, level: usize) -> String {
+    match self.options.indent {
+      Indent::Spaces(width) => " ".repeat(width * level),
+      Indent::Tabs => "\t".repeat(level),
+    }
+  }
+
+  fn separator(&self) -> String {
+    "\n".repeat(1 + self.options.blank_lines_between_rules)
+  }
+
+  fn comments(&self, comments: &[CSSComment], level: usize) -> String {
+    comments.iter().map(|comment| format!("{}{}\n", self.indent(level), comment)).collect()
+  }
+
+  fn selector(&self, selector: &str, level: usize) -> String {
+    let selector = tidy(selector, Text::Selector, self.options);
+    match self.options.selector_per_line {
+      true => split_top_level(&selector, ',').join(&format!(",\n{}", self.indent(level))),
+      false => selector,
+    }
+  }
+
+  /// The rules of a block or stylesheet, then the comments after them.
+  fn rules(&self, rules: &[Rule], trailing_comments: &[CSSComment], level: usize) -> Vec<String> {
+    rules
+      .iter()
+      .map(|rule| self.rule(rule, level))
+      .chain((!trailing_comments.is_empty()).then(|| self.comments(trailing_comments, level).trim_end().to_string()))
+      .collect()
+  }
+
+  fn rule(&self, rule: &Rule, level: usize) -> String {
+    let prelude = |prelude: &str| tidy(prelude, Text::Prelude, self.options);
+    let header = |text: String| format!("{}{}", self.indent(level), text);
+    let formatted = match rule {
+      Rule::Style(rule) => return self.style_rule(rule, level),
+      Rule::NestedDeclarations(rule) => self.declaration_lines(&rule.declarations, level),
+      Rule::Media(rule) => self.group(&header(at_rule("media", &prelude(&rule.media))), &rule.rules, &rule.trailing_comments, level),
+      Rule::Supports(rule) => self.group(&header(at_rule("supports", &prelude(&rule.condition))), &rule.rules, &rule.trailing_comments, level),
+      Rule::Container(rule) => self.group(&header(at_rule("container", &prelude(&rule.condition))), &rule.rules, &rule.trailing_comments, level),
+      Rule::Scope(rule) => self.group(&header(at_rule("scope", &prelude(&rule.prelude))), &rule.rules, &rule.trailing_comments, level),
+      Rule::StartingStyle(rule) => self.group(&header(at_rule("starting-style", "")), &rule.rules, &rule.trailing_comments, level),
+      Rule::Layer(CSSLayerRule { names, rules: Some(rules), trailing_comments, .. }) => self.group(&header(at_rule("layer", &names.join(", "))), rules, trailing_comments, level),
+      Rule::FontFace(rule) => self.block(&header(at_rule("font-face", "")), &rule.declarations, &[], level),
+      Rule::Page(rule) => self.block(&header(at_rule("page", &prelude(&rule.selector))), &rule.declarations, &[], level),
+      Rule::CounterStyle(rule) => self.block(&header(at_rule("counter-style", &rule.name)), &rule.declarations, &[], level),
+      Rule::Property(rule) => self.block(&header(at_rule("property", &rule.name)), &rule.declarations, &[], level),
+      Rule::FontPaletteValues(rule) => self.block(&header(at_rule("font-palette-values", &rule.name)), &rule.declarations, &[], level),
+      Rule::ViewTransition(rule) => self.block(&header(at_rule("view-transition", "")), &rule.declarations, &[], level),
+      Rule::Keyframes(rule) => {
+        let name = format!("{}keyframes", rule.vendor_prefix.as_deref().unwrap_or(""));
+        let keyframes = rule.keyframes
+          .iter()
+          .map(|keyframe| {
+            let header = format!("{}{}", self.indent(level + 1), prelude(&keyframe.selector));
+            format!("{}{}", self.comments(&keyframe.comments, level + 1), self.block(&header, &keyframe.declarations, &[], level + 1))
+          })
+          .chain((!rule.trailing_comments.is_empty()).then(|| self.comments(&rule.trailing_comments, level + 1).trim_end().to_string()))
+          .collect();
+        self.open(&header(at_rule(&name, &rule.name)), keyframes, level)
+      }
+      Rule::Unknown(rule) => {
+        let header = header(at_rule(&rule.name, &prelude(&rule.prelude)));
+        match rule.block.as_deref().map(prelude) {
+          Some(block) if block.is_empty() => format!("{} {{}}", header),
+          Some(block) => format!("{} {{ {} }}", header, block),
+          None => format!("{};", header),
+        }
+      }
+      // `@charset` must be written exactly as `@charset "...";`
+      Rule::Charset(rule) => header(rule.to_string()),
+      // `@import`, `@namespace` and `@layer a, b;`
+      rule => header(prelude(&rule.to_string())),
+    };
+
+    format!("{}{}", self.comments(rule.comments(), level), formatted)
+  }
+
+  fn style_rule(&self, rule: &CSSRule, level: usize) -> String {
+    let header = format!("{}{}", self.indent(level), self.selector(&rule.selector, level));
+    let block = match rule.rules.is_empty() {
+      true => self.block(&header, &rule.declarations, &[], level),
+      false => {
+        let mut items = self.declaration_items(&rule.declarations, level);
+        items.extend(self.rules(&rule.rules, &rule.trailing_comments, level + 1));
+        self.open(&header, items, level)
+      }
+    };
+    format!("{}{}", self.comments(&rule.comments, level), block)
+  }
+
+  fn group(&self, header: &str, rules: &[Rule], trailing_comments: &[CSSComment], level: usize) -> String {
+    self.open(header, self.rules(rules, trailing_comments, level + 1), level)
+  }
+
+  /// Writes a block with declarations followed by nested rules. `header` is
+  /// already indented.
+  fn block(&self, header: &str, declarations: &CSSDeclarationList, rules: &[Rule], level: usize) -> String {
+    if !self.options.declaration_per_line && rules.is_empty() && !is_empty(declarations) {
+      return format!("{} {{ {} }}", header, self.declaration_lines(declarations, 0));
+    }
+
+    let mut items = self.declaration_items(declarations, level);
+    items.extend(rules.iter().map(|rule| self.rule(rule, level + 1)));
+    self.open(header, items, level)
+  }
+
+  /// The declarations of a block as its first item, if there are any.
+  fn declaration_items(&self, declarations: &CSSDeclarationList, level: usize) -> Vec<String> {
+    match is_empty(declarations) {
+      true => Vec::new(),
+      false => vec![self.declaration_lines(declarations, level + 1)],
+    }
+  }
+
+  /// Writes `{`, the items separated by blank lines, and `}`.
+  fn open(&self, header: &str, items: Vec<String>, level: usize) -> String {
+    if items.is_empty() {
+      return format!("{} {{}}", header);
+    }
+    let brace = match self.options.brace_style {
+      BraceStyle::SameLine => " {".to_string(),
+      BraceStyle::NextLine => format!("\n{}{{", self.indent(level)),
+    };
+    format!("{}{}\n{}\n{}}}", header, brace, items.join(&self.separator()), self.indent(level))
+  }
+
+  /// Writes declarations one per line with their comments, or all on one
+  /// line when `declaration_per_line` is off.
+  fn declaration_lines(&self, declarations: &CSSDeclarationList, level: usize) -> String {
+    let indent = self.indent(level);
+    let per_line = self.options.declaration_per_line;
+    let lines = declarations.declarations
+      .iter()
+      .map(|declaration| {
+        let value = match declaration.name.starts_with("--") {
+          true => declaration.value.trim_matches(is_css_whitespace).to_string(),
+          false => tidy(&declaration.value, Text::Value, self.options),
+        };
+        let important = if declaration.important { " !important" } else { "" };
+        let comments = match per_line {
+          true => self.comments(&declaration.comments, level),
+          false => declaration.comments.iter().map(|comment| format!("{} ", comment)).collect(),
+        };
+        let indent = if per_line { indent.as_str() } else { "" };
+        format!("{}{}{}: {}{};", comments, indent, declaration.name, value, important)
+      })
+      .chain(match per_line {
+        true => self.comments(&declarations.trailing_comments, level).lines().map(str::to_string).collect::<Vec<_>>(),
+        false => declarations.trailing_comments.iter().map(|comment| comment.to_string()).collect(),
+      })
+      .collect::<Vec<_>>();
+
+    match per_line {
+      true => lines.join("\n"),
+      false => format!("{}{}", indent, lines.join(" ")),
+    }
+  }
+}
+
+/// Formats `rules` at the top level, see `Stylesheet::format()`.
+pub(crate) fn format_rules(rules: &[Rule], trailing_comments: &[CSSComment], options: &FormatOptions) -> String {
+  let formatter = Formatter { options };
+  let mut output = formatter.rules(rules, trailing_comments, 0).join(&formatter.separator());
+  if options.final_newline && !output.is_empty() {
+    output.push('\n');
+  }
+  output
+}
+
+pub(crate) fn format_rule(rule: &CSSRule, options: &FormatOptions) -> String {
+  Formatter { options }.style_rule(rule, 0)
+}
+
+pub(crate) fn format_declarations(declarations: &CSSDeclarationList, options: &FormatOptions) -> String {
+  Formatter { options }.declaration_lines(declarations, 0)
+}
+
+fn is_empty(declarations: &CSSDeclarationList) -> bool {
+  declarations.declarations.is_empty() && declarations.trailing_comments.is_empty()
+}
+
+fn at_rule(name: &str, prelude: &str) -> String {
+  match prelude.is_empty() {
+    true => format!("@{}", name),
+    false => format!("@{} {}", name, prelude),
+  }
+}
+
+/// Normalizes the whitespace of a selector, value or prelude, and rewrites
+/// its strings and hex colors as `options` asks.
+fn tidy(text: &str, kind: Text, options: &FormatOptions) -> String {
+  let mut output = String::with_capacity(text.len());
+  let mut rest = text.trim_matches(is_css_whitespace);
+  let mut space = false;
+  let mut depth = 0usize;
+  // Whether the last token opened a block, so that no space follows it
+  let mut open = false;
+
+  while let Some(c) = rest.chars().next() {
+    if is_css_whitespace(c) {
+      space = true;
+      rest = &rest[c.len_utf8()..];
+      continue;
+    }
+
+    let len = match c {
+      '"' | '\'' => string_len(rest).max(1),
+      '\\' => 1 + rest[1..].chars().next().map_or(0, char::len_utf8),
+      '#' => 1 + rest[1..].find(|c: char| !(c.is_alphanumeric() || c == '-' || c == '_')).unwrap_or(rest.len() - 1),
+      'u' | 'U' if rest.get(..4).is_some_and(|start| start.eq_ignore_ascii_case("url("))
+        && !output.ends_with(|c: char| c.is_alphanumeric() || c == '-' || c == '_')
+        && !rest[4..].trim_start_matches(is_css_whitespace).starts_with(['"', '\'']) =>
+      {
+        rest.find(')').map_or(rest.len(), |end| end + 1)
+      }
+      c => c.len_utf8(),
+    };
+    let token = &rest[..len];
+    let combinator = kind == Text::Selector && depth == 0 && matches!(c, '>' | '+' | '~');
+    if (space || combinator) && !output.is_empty() && !open && !matches!(c, ')' | ']' | ',') {
+      output.push(' ');
+    }
+
+    match c {
+      '"' | '\'' => output.push_str(&quote(token, options.quote_style)),
+      '#' if kind == Text::Value && options.lowercase_hex && matches!(len - 1, 3 | 4 | 6 | 8)
+        && token[1..].chars().all(|c| c.is_ascii_hexdigit()) =>
+      {
+        output.push_str(&token.to_ascii_lowercase());
+      }
+      'u' | 'U' if len > 1 => output.push_str(&format!("{}{})", &token[..4], token[4..len - 1].trim_matches(is_css_whitespace))),
+      _ => output.push_str(token),
+    }
+    match c {
+      '(' | '[' => depth += 1,
+      ')' | ']' => depth = depth.saturating_sub(1),
+      _ => {}
+    }
+    open = matches!(c, '(' | '[');
+    space = c == ',' || combinator;
+    rest = &rest[len..];
+  }
+
+  output
+}
+
+/// Rewrites a string token with the quotes of `style`. Escapes are kept as
+/// written, such as the `\f101` of an icon font, except for escaped quotes
+/// that no longer need it.
+fn quote(token: &str, style: QuoteStyle) -> String {
+  let quote = match style {
+    QuoteStyle::Double => '"',
+    QuoteStyle::Single => '\'',
+    QuoteStyle::Preserve => return token.to_string(),
+  };
+  let mut chars = token.chars();
+  let Some(original) = chars.next().filter(|c| matches!(c, '"' | '\'')) else {
+    return token.to_string();
+  };
+
+  let mut output = String::with_capacity(token.len() + 2);
+  output.push(quote);
+  while let Some(c) = chars.next() {
+    match c {
+      '\\' => match chars.next() {
+        Some(c) if c == original && c != quote => output.push(c),
+        Some(c) => {
+          output.push('\\');
+          output.push(c);
+        }
+        // A backslash at the end of the input is ignored
+        None => {}
+      },
+      c if c == original => break,
+      c if c == quote => {
+        output.push('\\');
+        output.push(c);
+      }
+      c => output.push(c),
+    }
+  }
+  output.push(quote);
+  output
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::stylesheet::Stylesheet;
+
+  const SAMPLE: &str = "
+    @charset \"utf-8\";
+    @import url('base.css') screen;
+    /* Headings */
+    h1,h2>span , h3~p{color:#ABCDEF;font:12px/1.5 'Open Sans',serif;background:URL( a.png ) no-repeat}
+    a{color:red;&:hover{color:blue}--x:  a,b ;b{margin:0 !important}}
+    @media screen and (min-width:100px){.a{top:0}/* last */}
+    @keyframes spin{from{opacity:0}to{opacity:1}}
+    @font-face{font-family:\"A\";src:url(a.woff2)}
+    li:nth-child( 2n + 1 )::before{content:'\"q\"'}
+    @layer base, theme;
+  ";
+
+  fn format(css: &str, options: &FormatOptions) -> String {
+    Stylesheet::from_string(css).unwrap().format(options)
+  }
+
+  fn assert_idempotent(options: &FormatOptions) {
+    let formatted = format(SAMPLE, options);
+    assert_eq!(format(&formatted, options), formatted, "{}", formatted);
+  }
+
+  #[test]
+  fn test_default_layout() {
+    assert_eq!(format(SAMPLE, &FormatOptions::default()), "\
+@charset \"utf-8\";
+
+@import url(\"base.css\") screen;
+
+/* Headings */
+h1,
+h2 > span,
+h3 ~ p {
+  color: #abcdef;
+  font: 12px/1.5 \"Open Sans\", serif;
+  background: URL(a.png) no-repeat;
+}
+
+a {
+  color: red;
+
+  &:hover {
+    color: blue;
+  }
+
+  --x: a,b;
+
+  b {
+    margin: 0 !important;
+  }
+}
+
+@media screen and (min-width:100px) {
+  .a {
+    top: 0;
+  }
+
+  /* last */
+}
+
+@keyframes spin {
+  from {
+    opacity: 0;
+  }
+
+  to {
+    opacity: 1;
+  }
+}
+
+@font-face {
+  font-family: \"A\";
+  src: url(a.woff2);
+}
+
+li:nth-child(2n + 1)::before {
+  content: \"\\\"q\\\"\";
+}
+
+@layer base, theme;
+");
+    assert_idempotent(&FormatOptions::default());
+  }
+
+  #[test]
+  fn test_options() {
+    let options = FormatOptions {
+      indent: Indent::Spaces(4),
+      declaration_per_line: false,
+      blank_lines_between_rules: 0,
+      selector_per_line: false,
+      lowercase_hex: false,
+      quote_style: QuoteStyle::Single,
+      final_newline: false,
+      ..FormatOptions::default()
+    };
+    assert_eq!(
+      format("h1,h2{color:#ABC;font-family:\"it's\"}@media print{a{top:0}b{c{top:1px}}}", &options),
+      "h1, h2 { color: #ABC; font-family: 'it\\'s'; }\n@media print {\n    a { top: 0; }\n    b {\n        c { top: 1px; }\n    }\n}"
+    );
+    assert_idempotent(&options);
+
+    let options = FormatOptions { indent: Indent::Tabs, brace_style: BraceStyle::NextLine, blank_lines_between_rules: 2, ..FormatOptions::default() };
+    assert_eq!(format("a{top:0}b{}", &options), "a\n{\n\ttop: 0;\n}\n\n\nb {}\n");
+    assert_idempotent(&options);
+
+    let preserve = FormatOptions { quote_style: QuoteStyle::Preserve, ..FormatOptions::default() };
+    assert_eq!(format("a{content:'x'}", &preserve), "a {\n  content: 'x';\n}\n");
+    assert_idempotent(&preserve);
+  }
+
+  #[test]
+  fn test_quotes_keep_escapes() {
+    let options = FormatOptions { final_newline: false, ..FormatOptions::default() };
+    let format = |css: &str| format(css, &options);
+    assert_eq!(format(".i::before{content:'\\f101'}"), ".i::before {\n  content: \"\\f101\";\n}");
+    assert_eq!(format("a{content:'a\\d b\\c c'}"), "a {\n  content: \"a\\d b\\c c\";\n}");
+    assert_eq!(format("a{content:'\\'\"\\\\'}"), "a {\n  content: \"'\\\"\\\\\";\n}");
+    assert_eq!(format("a{content:\"\\\"x\"}"), "a {\n  content: \"\\\"x\";\n}");
+  }
+
+  #[test]
+  fn test_rule_and_declarations() {
+    let options = FormatOptions::default();
+    let rule = CSSRule::from_string("a,b{/* note */color:red;top:0}").unwrap();
+    assert_eq!(rule.format(&options), "a,\nb {\n  /* note */\n  color: red;\n  top: 0;\n}");
+
+    let declarations = CSSDeclarationList::from_string("color:RGBA(0,0,0,.5);margin:0  auto").unwrap();
+    assert_eq!(declarations.format(&options), "color: RGBA(0, 0, 0, .5);\nmargin: 0 auto;");
+    let options = FormatOptions { declaration_per_line: false, ..options };
+    assert_eq!(declarations.format(&options), "color: RGBA(0, 0, 0, .5); margin: 0 auto;");
+  }
+
+  #[test]
+  fn test_trailing_comments() {
+    let options = FormatOptions::default();
+    assert_eq!(format("@media screen{/* only */}", &options), "@media screen {\n  /* only */\n}\n");
+    assert_eq!(format("a{b:c}/* end */", &options), "a {\n  b: c;\n}\n\n/* end */\n");
+    assert_eq!(format("a{b:c;/* tail */}", &options), "a {\n  b: c;\n  /* tail */\n}\n");
+    assert_eq!(format("a{b:c;/* tail */}", &FormatOptions { declaration_per_line: false, ..options }), "a { b: c; /* tail */ }\n");
+  }
+}
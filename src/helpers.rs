@@ -1,5 +1,339 @@
+use crate::css_comment::CSSComment;
+use crate::parse_error::{ParseErrorKind, ParseResult, SyntaxError};
+
+
 pub fn is_non_ascii(c: char) -> bool {
-  return c as u32 > 127
+  c as u32 > 127
+}
+
+pub fn is_css_whitespace(c: char) -> bool {
+  matches!(c, ' ' | '\t' | '\n' | '\r' | '\x0C')
+}
+
+/// Returns the 1-based line and column (in characters) of a byte offset.
+pub fn line_column(source: &str, offset: usize) -> (usize, usize) {
+  let before = &source[..offset];
+  let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+
+  (before.matches('\n').count() + 1, before[line_start..].chars().count() + 1)
+}
+
+/// Consumes any mix of whitespace and comments, returning the comments found.
+/// Fails on a comment that is never closed.
+pub fn trivia(input: &str) -> ParseResult<'_, Vec<CSSComment>> {
+  let mut comments = Vec::new();
+  let mut rest = input.trim_start_matches(is_css_whitespace);
+
+  while rest.starts_with("/*") {
+    let Ok((after, comment)) = CSSComment::parse(rest) else {
+      return SyntaxError::failure(rest, ParseErrorKind::UnterminatedComment, "`*/`");
+    };
+    comments.push(comment);
+    rest = after.trim_start_matches(is_css_whitespace);
+  }
+
+  Ok((rest, comments))
+}
+
+/// Returns the byte length of the string token at the start of `input`
+/// (which must start with a quote), including the closing quote. An
+/// unterminated string ends before the newline or at the end of the input.
+pub fn string_len(input: &str) -> usize {
+  let mut chars = input.char_indices();
+  let quote = match chars.next() {
+    Some((_, quote)) => quote,
+    None => return 0,
+  };
+
+  while let Some((i, c)) = chars.next() {
+    match c {
+      '\\' => {
+        chars.next();
+      }
+      '\n' => return i,
+      c if c == quote => return i + 1,
+      _ => {}
+    }
+  }
+
+  input.len()
+}
+
+/// Returns the byte length of the prefix of `input` that contains none of the
+/// `stops` characters at the top level, following the CSS Syntax rules for
+/// consuming component values: comments, quoted strings and escaped characters
+/// are skipped, and `()`, `[]` and `{}` blocks are balanced. An unterminated
+/// block runs to the end of the input, while scanning stops in front of an
+/// unterminated comment so that callers can report it.
+pub fn scan_until(input: &str, stops: &[char]) -> usize {
+  let mut closers: Vec<char> = Vec::new();
+  let mut chars = input.char_indices().peekable();
+
+  while let Some((i, c)) = chars.next() {
+    if closers.is_empty() && stops.contains(&c) {
+      return i;
+    }
+
+    let skip_to = match c {
+      '/' if input[i + 1..].starts_with('*') => match input[i + 2..].find("*/") {
+        Some(end) => i + 2 + end + 2,
+        None => return i,
+      },
+      '"' | '\'' => i + string_len(&input[i..]),
+      '\\' => i + 1 + input[i + 1..].chars().next().map_or(0, char::len_utf8),
+      '(' => { closers.push(')'); continue }
+      '[' => { closers.push(']'); continue }
+      '{' => { closers.push('}'); continue }
+      ')' | ']' | '}' => {
+        if closers.last() == Some(&c) {
+          closers.pop();
+        }
+        continue
+      }
+      _ => continue,
+    };
+
+    while chars.next_if(|&(j, _)| j < skip_to).is_some() {}
+  }
+
+  input.len()
+}
+
+/// Splits `text` at each top-level `separator`, as found by `scan_until`,
+/// trimming whitespace around the parts.
+pub fn split_top_level(text: &str, separator: char) -> Vec<&str> {
+  let mut parts = Vec::new();
+  let mut rest = text;
+
+  loop {
+    let len = scan_until(rest, &[separator]);
+    parts.push(rest[..len].trim_matches(is_css_whitespace));
+    match rest[len..].strip_prefix(separator) {
+      Some(after) => rest = after,
+      None => return parts,
+    }
+  }
+}
+
+/// Returns the byte offset of the first comment in `text` that is not inside a string.
+fn find_comment(text: &str) -> Option<usize> {
+  let mut offset = 0;
+
+  loop {
+    let found = text[offset..].find(['/', '"', '\'', '\\'])? + offset;
+    let rest = &text[found..];
+
+    offset = match rest.chars().next()? {
+      '/' if rest.starts_with("/*") => return Some(found),
+      '"' | '\'' => found + string_len(rest),
+      '\\' => found + 1 + rest[1..].chars().next().map_or(0, char::len_utf8),
+      _ => found + 1,
+    };
+  }
+}
+
+/// Returns the byte length of the contents of a `{}` block whose opening brace
+/// has already been consumed, up to (not including) the matching `}`.
+/// Nested blocks and comments are skipped. Returns `None` when unbalanced.
+pub fn scan_block(input: &str) -> Option<usize> {
+  let mut depth = 0;
+  let mut offset = 0;
+
+  loop {
+    offset += scan_until(&input[offset..], &['{', '}']);
+    match input[offset..].chars().next()? {
+      '{' => depth += 1,
+      '}' if depth == 0 => return Some(offset),
+      '}' => depth -= 1,
+      _ => return None,
+    }
+    offset += 1;
+  }
+}
+
+/// Skips a malformed rule or declaration during error recovery: everything up
+/// to the first top-level `stops` character, plus the `;` or `{}` block found
+/// there. A `}` is left for the enclosing block, while an unterminated comment
+/// or block swallows the rest of the input.
+pub fn skip_invalid<'a>(input: &'a str, stops: &[char]) -> &'a str {
+  let rest = &input[scan_until(input, stops)..];
+  match rest.chars().next() {
+    Some(';') => &rest[1..],
+    Some('{') => scan_block(&rest[1..]).map_or("", |len| &rest[len + 2..]),
+    _ if rest.starts_with("/*") => "",
+    _ => rest,
+  }
+}
+
+/// Parses a single- or double-quoted CSS string, resolving backslash escapes.
+pub fn parse_string(input: &str) -> ParseResult<'_, String> {
+  let fail = || nom::Err::Error(SyntaxError::new(input, ParseErrorKind::UnexpectedToken, "a string"));
+  let quote = input.chars().next().filter(|c| *c == '"' || *c == '\'').ok_or_else(fail)?;
+  let mut value = String::new();
+  let mut chars = input.char_indices().skip(1);
+
+  while let Some((i, c)) = chars.next() {
+    match c {
+      c if c == quote => return Ok((&input[i + 1..], value)),
+      '\\' => match chars.next() {
+        Some((_, '\n')) => {}
+        Some((j, hex)) if hex.is_ascii_hexdigit() => {
+          let digits = input[j..].chars().take(6).take_while(char::is_ascii_hexdigit).count();
+          let code = u32::from_str_radix(&input[j..j + digits], 16).unwrap_or(0xFFFD);
+          value.push(char::from_u32(code).filter(|c| *c != '\0').unwrap_or('\u{FFFD}'));
+          for _ in 1..digits {
+            chars.next();
+          }
+          if input[j + digits..].starts_with(is_css_whitespace) {
+            chars.next();
+          }
+        }
+        Some((_, escaped)) => value.push(escaped),
+        None => break,
+      },
+      '\n' => break,
+      c => value.push(c),
+    }
+  }
+
+  Err(fail())
+}
+
+/// Serializes a value as a double-quoted CSS string.
+pub fn serialize_string(value: &str) -> String {
+  let mut output = String::with_capacity(value.len() + 2);
+  output.push('"');
+  for c in value.chars() {
+    match c {
+      '"' | '\\' => {
+        output.push('\\');
+        output.push(c);
+      }
+      '\n' => output.push_str("\\a "),
+      c => output.push(c),
+    }
+  }
+  output.push('"');
+  output
+}
+
+fn is_name_char(c: char) -> bool {
+  c.is_ascii_alphanumeric() || c == '-' || c == '_' || is_non_ascii(c)
+}
+
+/// Decodes the escape at the start of `input`, just after the backslash.
+/// Returns the escaped character and the byte length of the escape.
+fn parse_escape(input: &str) -> Option<(char, usize)> {
+  let first = input.chars().next().filter(|c| *c != '\n')?;
+  if !first.is_ascii_hexdigit() {
+    return Some((first, first.len_utf8()));
+  }
+
+  let digits = input.chars().take(6).take_while(char::is_ascii_hexdigit).count();
+  let code = u32::from_str_radix(&input[..digits], 16).unwrap_or(0xFFFD);
+  let c = char::from_u32(code).filter(|c| *c != '\0').unwrap_or('\u{FFFD}');
+  let whitespace = input[digits..].chars().next().filter(|c| is_css_whitespace(*c)).map_or(0, char::len_utf8);
+
+  Some((c, digits + whitespace))
+}
+
+/// Parses a CSS identifier, decoding escapes such as `\:` or `\31 `.
+pub fn parse_ident(input: &str) -> ParseResult<'_, String> {
+  let fail = || nom::Err::Error(SyntaxError::new(input, ParseErrorKind::UnexpectedToken, "an identifier"));
+  let after_dash = input.strip_prefix('-').unwrap_or(input);
+  let starts_ident = match after_dash.chars().next() {
+    Some('-') => input.starts_with('-'),
+    Some('\\') => parse_escape(&after_dash[1..]).is_some(),
+    Some(c) => is_name_char(c) && !c.is_ascii_digit() && c != '-',
+    None => false,
+  };
+  if !starts_ident {
+    return Err(fail());
+  }
+
+  parse_name(input)
+}
+
+/// Parses a run of name characters and escapes, such as the part of a hash
+/// token after the `#`. Unlike an identifier, it may start with a digit.
+pub fn parse_name(input: &str) -> ParseResult<'_, String> {
+  let mut value = String::new();
+  let mut rest = input;
+  loop {
+    match rest.chars().next() {
+      Some('\\') => match parse_escape(&rest[1..]) {
+        Some((c, len)) => {
+          value.push(c);
+          rest = &rest[1 + len..];
+        }
+        None => break,
+      },
+      Some(c) if is_name_char(c) => {
+        value.push(c);
+        rest = &rest[c.len_utf8()..];
+      }
+      _ => break,
+    }
+  }
+
+  if value.is_empty() {
+    return SyntaxError::error(input, ParseErrorKind::UnexpectedToken, "a name");
+  }
+
+  Ok((rest, value))
+}
+
+/// Serializes a value as a CSS identifier, escaping the characters that
+/// could not appear in it as written.
+pub fn serialize_identifier(value: &str) -> String {
+  let mut output = String::with_capacity(value.len());
+  if value == "-" {
+    return "\\-".to_string();
+  }
+
+  for (i, c) in value.chars().enumerate() {
+    let leading_digit = c.is_ascii_digit() && (i == 0 || (i == 1 && value.starts_with('-')));
+    match c {
+      '\0' => output.push('\u{FFFD}'),
+      c if c.is_control() || leading_digit => output.push_str(&format!("\\{:x} ", c as u32)),
+      c if is_name_char(c) => output.push(c),
+      c => {
+        output.push('\\');
+        output.push(c);
+      }
+    }
+  }
+
+  output
+}
+
+/// Removes comments from a piece of CSS text (a selector or a value),
+/// returning the remaining text and the comments that were removed.
+/// A comment is not whitespace, so `.a/**/.b` becomes `.a.b`, and the
+/// whitespace around a comment is kept as a single space.
+pub fn strip_comments(text: &str) -> (String, Vec<CSSComment>) {
+  let mut output = String::with_capacity(text.len());
+  let mut comments = Vec::new();
+  let mut rest = text;
+
+  while let Some(start) = find_comment(rest) {
+    output.push_str(&rest[..start]);
+
+    let (after, comment) = match CSSComment::parse(&rest[start..]) {
+      Ok(parsed) => parsed,
+      Err(_) => ("", CSSComment::new(&rest[start + 2..])),
+    };
+    comments.push(comment);
+
+    rest = if output.is_empty() || output.ends_with(is_css_whitespace) {
+      after.trim_start_matches(is_css_whitespace)
+    } else {
+      after
+    };
+  }
+
+  output.push_str(rest);
+  (output, comments)
 }
 
 #[cfg(test)]
@@ -55,6 +389,141 @@ mod tests {
     assert_eq!(is_non_ascii('©'), true); // Copyright symbol (169)
   }
 
+  #[test]
+  fn test_trivia_collects_comments() {
+    let (remaining, comments) = trivia("  /* a */\n\t/* b */  body").unwrap();
+    assert_eq!(remaining, "body");
+    assert_eq!(comments, vec![CSSComment::new(" a "), CSSComment::new(" b ")]);
+  }
+
+  #[test]
+  fn test_trivia_unterminated_comment() {
+    assert!(matches!(trivia("  /* open"), Err(nom::Err::Failure(_))));
+  }
+
+  #[test]
+  fn test_line_column() {
+    let source = "a {\n  b: c;\n}";
+    assert_eq!(line_column(source, 0), (1, 1));
+    assert_eq!(line_column(source, 6), (2, 3));
+    assert_eq!(line_column(source, source.len()), (3, 2));
+    assert_eq!(line_column("é x", 3), (1, 3));
+  }
+
+  #[test]
+  fn test_trivia_without_comments() {
+    let (remaining, comments) = trivia("   body").unwrap();
+    assert_eq!(remaining, "body");
+    assert!(comments.is_empty());
+  }
+
+  #[test]
+  fn test_scan_until_skips_comments() {
+    assert_eq!(scan_until("red /* ; */ blue; x", &[';']), 16);
+    assert_eq!(scan_until("red", &[';']), 3);
+    assert_eq!(scan_until("a /* { never closed", &['{']), 2);
+  }
+
+  #[test]
+  fn test_scan_until_strings_and_blocks() {
+    assert_eq!(scan_until("\";\" x; y", &[';']), 5);
+    assert_eq!(scan_until("'a\\'b;' x; y", &[';']), 9);
+    assert_eq!(scan_until("url(data:image/png;base64,AAA); y", &[';']), 30);
+    assert_eq!(scan_until("[a;b] {c;d} (e;f); y", &[';']), 17);
+    assert_eq!(scan_until("a\\;b; y", &[';']), 4);
+    assert_eq!(scan_until("a) b; y", &[';']), 4);
+    assert_eq!(scan_until("\"/* not a comment */\"; y", &[';']), 21);
+  }
+
+  #[test]
+  fn test_string_len() {
+    assert_eq!(string_len("\"abc\" x"), 5);
+    assert_eq!(string_len("'a\\'b' x"), 6);
+    assert_eq!(string_len("\"abc\nx"), 4);
+    assert_eq!(string_len("\"abc"), 4);
+  }
+
+  #[test]
+  fn test_strip_comments_ignores_strings() {
+    let (text, comments) = strip_comments("\"/* kept */\" /* removed */ url('a/*b')");
+    assert_eq!(text, "\"/* kept */\" url('a/*b')");
+    assert_eq!(comments, vec![CSSComment::new(" removed ")]);
+  }
+
+  #[test]
+  fn test_scan_block() {
+    assert_eq!(scan_block("a { b } c } d"), Some(10));
+    assert_eq!(scan_block("/* } */ a } d"), Some(10));
+    assert_eq!(scan_block("}"), Some(0));
+    assert_eq!(scan_block("a { b }"), None);
+    assert_eq!(scan_block("a /* } "), None);
+  }
+
+  #[test]
+  fn test_split_top_level() {
+    assert_eq!(split_top_level("a, b ,c", ','), vec!["a", "b", "c"]);
+    assert_eq!(split_top_level(":is(a, b), [x=','], c", ','), vec![":is(a, b)", "[x=',']", "c"]);
+    assert_eq!(split_top_level("a", ','), vec!["a"]);
+  }
+
+  #[test]
+  fn test_skip_invalid() {
+    assert_eq!(skip_invalid("color red; margin: 0", &[';', '}']), " margin: 0");
+    assert_eq!(skip_invalid("color red } b {}", &[';', '}']), "} b {}");
+    assert_eq!(skip_invalid("a b { c; { d } } e {}", &['{', '}']), " e {}");
+    assert_eq!(skip_invalid("@x \"; {\" { } ;b", &['{', ';']), " ;b");
+    assert_eq!(skip_invalid("a { b", &['{']), "");
+    assert_eq!(skip_invalid("a /* b", &['{']), "");
+    assert_eq!(skip_invalid("a b", &['{']), "");
+  }
+
+  #[test]
+  fn test_parse_string() {
+    assert_eq!(parse_string("\"hello\" rest"), Ok((" rest", "hello".to_string())));
+    assert_eq!(parse_string("'it\\'s'"), Ok(("", "it's".to_string())));
+    assert_eq!(parse_string("\"a;b{c}\""), Ok(("", "a;b{c}".to_string())));
+    assert_eq!(parse_string("\"\\41 B\""), Ok(("", "AB".to_string())));
+    assert!(parse_string("\"unterminated").is_err());
+    assert!(parse_string("none").is_err());
+  }
+
+  #[test]
+  fn test_serialize_string() {
+    assert_eq!(serialize_string("hello"), "\"hello\"");
+    assert_eq!(serialize_string("say \"hi\""), "\"say \\\"hi\\\"\"");
+  }
+
+  #[test]
+  fn test_parse_ident() {
+    assert_eq!(parse_ident("nav-item.x"), Ok((".x", "nav-item".to_string())));
+    assert_eq!(parse_ident("--main"), Ok(("", "--main".to_string())));
+    assert_eq!(parse_ident("a\\:b c"), Ok((" c", "a:b".to_string())));
+    assert_eq!(parse_ident("\\31 0x"), Ok(("", "10x".to_string())));
+    assert!(parse_ident("1a").is_err());
+    assert!(parse_ident("-2").is_err());
+    assert!(parse_ident(".a").is_err());
+  }
+
+  #[test]
+  fn test_serialize_identifier() {
+    assert_eq!(serialize_identifier("nav-item"), "nav-item");
+    assert_eq!(serialize_identifier("a:b"), "a\\:b");
+    assert_eq!(serialize_identifier("10x"), "\\31 0x");
+    assert_eq!(serialize_identifier("-"), "\\-");
+  }
+
+  #[test]
+  fn test_strip_comments() {
+    assert_eq!(strip_comments("0 /* top */ auto"), ("0 auto".to_string(), vec![CSSComment::new(" top ")]));
+    assert_eq!(strip_comments("a/**/b").0, "ab");
+    assert_eq!(strip_comments(".b/**/.c").0, ".b.c");
+    assert_eq!(strip_comments("a /**/b").0, "a b");
+    assert_eq!(strip_comments("a/**/ /**/ b").0, "a b");
+    assert_eq!(strip_comments("/* x */ .a").0, ".a");
+    assert_eq!(strip_comments(".a /* x */").0, ".a ");
+    assert_eq!(strip_comments("no comments").0, "no comments");
+  }
+
   #[test]
   fn test_edge_cases() {
     // Test characters just above ASCII range
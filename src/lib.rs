@@ -5,9 +5,29 @@
 //! ## Main Components
 //!
 //! - [`Stylesheet`] - Complete CSS stylesheet parser
+//! - [`rule::Rule`] - Any rule in a stylesheet: a style rule or an at-rule
 //! - [`css_rule::CSSRule`] - Individual CSS rule parser  
+//! - [`css_at_rule`] - At-rules such as `@media`, `@import` and `@font-face`
 //! - [`css_declaration_list::CSSDeclarationList`] - CSS declaration list parser
 //! - [`css_declaration::CSSDeclaration`] - Individual CSS declaration parser
+//! - [`css_comment::CSSComment`] - CSS comment attached to a rule or declaration
+//! - [`cascade::Cascade`] - Resolves the winning declarations for an element
+//! - [`computed_style::ComputedStyle`] - Property values after inheritance and defaulting
+//! - [`properties`] - Built-in property registry: shorthands, inheritance, initial values, aliases
+//! - [`shorthand`] - Expands shorthands such as `margin` or `background` into their longhands
+//! - [`matching`] - Matches selectors against any tree implementing [`Element`]
+//! - [`nesting`] - Lowers nested rules to flat rules
+//! - [`selector::SelectorList`] - Parsed selectors, from `CSSRule::selector_list()`
+//! - [`value::Value`] - Parsed declaration values, from `CSSDeclaration::parsed_value()`
+//! - [`color::Color`] - Colors in every CSS Color 4/5 syntax, with conversion between color spaces
+//! - [`calc::CalcNode`] - `calc()`, `min()`, `clamp()` and the other math functions as expression trees
+//! - [`dimension::Dimension`] - Lengths, angles, times, frequencies and resolutions, with unit conversion
+//! - [`format`] - Configurable, idempotent pretty-printer
+//! - [`lossless`] - Lossless parsing that writes back unedited nodes byte for byte, and text edits for the edited ones
+//! - [`minify`] - Minified serialization, and removal of overridden declarations and redundant rules
+//! - [`variables`] - Substitutes `var()` references, detecting cycles between custom properties
+//! - [`parse_error::ParseError`] - Parse error with its location, returned by every `from_string`
+//! - [`span::Span`] - Location of a parsed node in the source text
 //!
 //! ## Quick Start
 //!
@@ -20,13 +40,56 @@
 //! ```
 
 mod helpers;
+pub mod parse_error;
+pub mod calc;
+pub mod cascade;
+pub mod color;
+pub mod computed_style;
+pub mod css_at_rule;
+pub mod css_comment;
 pub mod css_declaration;
 pub mod css_declaration_list;
 pub mod css_rule;
+pub mod dimension;
+pub mod format;
+pub mod lossless;
+pub mod matching;
+pub mod minify;
+pub mod nesting;
+pub mod properties;
+pub mod rule;
+pub mod selector;
+pub mod shorthand;
+pub mod span;
 pub mod stylesheet;
+pub mod value;
+pub mod variables;
 
 // Re-export main types at the crate root for convenience
 pub use stylesheet::Stylesheet;
-pub use css_rule::CSSRule;
+pub use rule::Rule;
+pub use selector::{Selector, SelectorList, Specificity};
+pub use css_rule::{CSSNestedDeclarations, CSSRule};
+pub use css_at_rule::{
+  CSSMediaRule, CSSSupportsRule, CSSContainerRule, CSSLayerRule, CSSScopeRule,
+  CSSStartingStyleRule, CSSImportRule, CSSNamespaceRule, CSSCharsetRule,
+  CSSFontFaceRule, CSSPageRule, CSSCounterStyleRule, CSSPropertyRule,
+  CSSFontPaletteValuesRule, CSSViewTransitionRule, CSSKeyframesRule,
+  CSSKeyframe, CSSUnknownAtRule,
+};
 pub use css_declaration_list::CSSDeclarationList;
 pub use css_declaration::CSSDeclaration;
+pub use css_comment::CSSComment;
+pub use calc::{CalcNode, MathConstant, MathFunction, Numeric, RoundingStrategy};
+pub use cascade::{Cascade, CascadedDeclaration, Condition, Origin};
+pub use color::{AbsoluteColor, Color, ColorFormat, ColorSpace};
+pub use format::{BraceStyle, FormatOptions, Indent, QuoteStyle};
+pub use lossless::TextEdit;
+pub use dimension::{Dimension, FontMetrics, ResolutionContext, Unit, UnitKind};
+pub use computed_style::ComputedStyle;
+pub use matching::Element;
+pub use nesting::NestingMode;
+pub use parse_error::{ParseError, ParseErrorKind};
+pub use span::{NodeSpan, Position, Span};
+pub use value::{ComponentValue, Value};
+pub use variables::VariableUsage;
@@ -0,0 +1,523 @@
+//! Lossless Parsing
+//!
+//! `Stylesheet::from_string()` keeps what a stylesheet means, not how it is
+//! written: `Display` reformats whitespace, drops optional semicolons and
+//! rewrites the rules it prints. `Stylesheet::from_string_lossless()` parses
+//! the same tree but also keeps the source text, so that every node has its
+//! trivia: the whitespace, comments and punctuation between its span and the
+//! spans of its neighbors.
+//!
+//! `Stylesheet::to_lossless_string()` then writes an unmodified stylesheet
+//! byte for byte as it was parsed. After the tree is edited, only the edited
+//! parts are written anew, and everything else is copied from the source:
+//!
+//! - A changed declaration only has its name, value or `!important` rewritten.
+//! - A changed selector or at-rule prelude only has itself rewritten, and the
+//!   rules and declarations of a block are compared one by one.
+//! - A removed declaration or rule is cut out with its comments and the
+//!   separator before it.
+//! - A new declaration or rule, built with `new`, is written with `Display`,
+//!   after a separator copying the indentation of the block.
+//! - Any other changed rule, such as `@keyframes` or `@import`, is written
+//!   anew with `Display`.
+//!
+//! Nodes are matched with the nodes they were parsed as by their spans, so
+//! nodes whose spans are changed are written anew.
+//!
+//! `Stylesheet::text_edits()` gives the same changes as `TextEdit`s against
+//! the source instead, for codemods and editors: each edit is a byte range of
+//! the source and its replacement, and text kept in place is never part of one.
+//!
+//! ## Main API
+//!
+//! - `Stylesheet::from_string_lossless()` - Parse a stylesheet and keep its source text
+//! - `Stylesheet::to_lossless_string()` - Write the stylesheet, copying the source text of unchanged nodes
+//! - `Stylesheet::text_edits()` - The edits to the source that give `to_lossless_string()`
+//! - `TextEdit::apply()` - Apply edits to the source they were made against
+//!
+//! ## Examples
+//!
+//! ```rust
+//! use css_structs::{CSSDeclaration, Stylesheet, TextEdit};
+//!
+//! let source = "/* Theme */\nh1 {\n  COLOR: Red;\n  margin:0 auto\n}\n";
+//! let mut stylesheet = Stylesheet::from_string_lossless(source).unwrap();
+//! assert_eq!(stylesheet.to_lossless_string(), source);
+//!
+//! let rule = stylesheet.rules[0].as_style_rule_mut().unwrap();
+//! rule.declarations.declarations[1].value = "0".to_string();
+//! rule.declarations.declarations.push(CSSDeclaration::new("padding", "4px", None));
+//! assert_eq!(stylesheet.to_lossless_string(), "/* Theme */\nh1 {\n  COLOR: Red;\n  margin:0;\n  padding: 4px\n}\n");
+//!
+//! let edits = stylesheet.text_edits().unwrap();
+//! assert_eq!(edits.len(), 1);
+//! assert_eq!(&source[edits[0].range.clone()], "0 auto");
+//! assert_eq!(edits[0].replacement, "0;\n  padding: 4px");
+//! assert_eq!(TextEdit::apply(&edits, source), stylesheet.to_lossless_string());
+//! ```
+
+
+use std::ops::Range;
+use crate::css_declaration::CSSDeclaration;
+use crate::css_declaration_list::CSSDeclarationList;
+use crate::helpers::{is_css_whitespace, scan_until};
+use crate::rule::Rule;
+use crate::span::Span;
+
+
+/// The source of a stylesheet parsed with `Stylesheet::from_string_lossless()`,
+/// and its rules as they were parsed.
+#[derive(Debug)]
+pub(crate) struct Original {
+  pub(crate) text: String,
+  pub(crate) rules: Vec<Rule>,
+}
+
+/// A declaration or a rule in a list of rules or a block.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Item<'a> {
+  Declaration(&'a CSSDeclaration),
+  Rule(&'a Rule),
+}
+
+impl Item<'_> {
+  fn span(self) -> Option<Span> {
+    match self {
+      Item::Declaration(declaration) => *declaration.span,
+      Item::Rule(rule) => rule.span(),
+    }
+  }
+
+  fn is_declaration(self) -> bool {
+    matches!(self, Item::Declaration(_))
+  }
+
+  fn to_css(self) -> String {
+    match self {
+      Item::Declaration(declaration) => declaration_text(declaration),
+      Item::Rule(rule) => rule.to_string(),
+    }
+  }
+}
+
+/// The items of a list of rules, with the declarations of nested
+/// declarations in place of them.
+fn rule_items(rules: &[Rule]) -> Vec<Item<'_>> {
+  rules
+    .iter()
+    .flat_map(|rule| match rule {
+      Rule::NestedDeclarations(nested) => nested.declarations.declarations.iter().map(Item::Declaration).collect(),
+      rule => vec![Item::Rule(rule)],
+    })
+    .collect()
+}
+
+/// The items of a block: its declarations, then its nested rules.
+fn block_items<'a>(declarations: &'a CSSDeclarationList, rules: &'a [Rule]) -> Vec<Item<'a>> {
+  declarations.declarations.iter().map(Item::Declaration).chain(rule_items(rules)).collect()
+}
+
+/// The items of the block of a rule, or `None` for a rule without a block
+/// of declarations or rules, such as `@import` or `@keyframes`.
+fn children(rule: &Rule) -> Option<Vec<Item<'_>>> {
+  match rule {
+    Rule::Style(rule) => Some(block_items(&rule.declarations, &rule.rules)),
+    Rule::FontFace(rule) => Some(block_items(&rule.declarations, &[])),
+    Rule::Page(rule) => Some(block_items(&rule.declarations, &[])),
+    Rule::CounterStyle(rule) => Some(block_items(&rule.declarations, &[])),
+    Rule::Property(rule) => Some(block_items(&rule.declarations, &[])),
+    Rule::FontPaletteValues(rule) => Some(block_items(&rule.declarations, &[])),
+    Rule::ViewTransition(rule) => Some(block_items(&rule.declarations, &[])),
+    rule => rule.child_rules().map(|rules| rule_items(rules)),
+  }
+}
+
+/// The rule without its comments, declarations and nested rules, to tell
+/// whether its selector or prelude changed.
+fn shell(rule: &Rule) -> Rule {
+  let mut shell = rule.clone();
+  shell.comments_mut().clear();
+  if let Some(rules) = shell.child_rules_mut() {
+    rules.clear();
+  }
+  match &mut shell {
+    Rule::Style(rule) => rule.declarations = CSSDeclarationList::new(),
+    Rule::FontFace(rule) => rule.declarations = CSSDeclarationList::new(),
+    Rule::Page(rule) => rule.declarations = CSSDeclarationList::new(),
+    Rule::CounterStyle(rule) => rule.declarations = CSSDeclarationList::new(),
+    Rule::Property(rule) => rule.declarations = CSSDeclarationList::new(),
+    Rule::FontPaletteValues(rule) => rule.declarations = CSSDeclarationList::new(),
+    Rule::ViewTransition(rule) => rule.declarations = CSSDeclarationList::new(),
+    _ => {}
+  }
+  match &mut shell {
+    Rule::Style(rule) => rule.trailing_comments.clear(),
+    Rule::Media(rule) => rule.trailing_comments.clear(),
+    Rule::Supports(rule) => rule.trailing_comments.clear(),
+    Rule::Container(rule) => rule.trailing_comments.clear(),
+    Rule::Layer(rule) => rule.trailing_comments.clear(),
+    Rule::Scope(rule) => rule.trailing_comments.clear(),
+    Rule::StartingStyle(rule) => rule.trailing_comments.clear(),
+    _ => {}
+  }
+  shell
+}
+
+/// A declaration without the `;` that `Display` ends it with.
+fn declaration_text(declaration: &CSSDeclaration) -> String {
+  match declaration.important {
+    true => format!("{}: {} !important", declaration.name, declaration.value),
+    false => format!("{}: {}", declaration.name, declaration.value),
+  }
+}
+
+fn range(span: Span) -> Range<usize> {
+  span.start.offset..span.end.offset
+}
+
+/// A change to the source text: the bytes in `range` are replaced with
+/// `replacement`. An empty range inserts, an empty replacement deletes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextEdit {
+  pub range: Range<usize>,
+  pub replacement: String,
+}
+
+impl TextEdit {
+  /// Applies edits sorted by position and not overlapping, as returned by
+  /// `Stylesheet::text_edits()`, to the source they were made against.
+  pub fn apply(edits: &[TextEdit], source: &str) -> String {
+    let mut output = String::with_capacity(source.len());
+    let mut position = 0;
+    for edit in edits {
+      output.push_str(&source[position..edit.range.start]);
+      output.push_str(&edit.replacement);
+      position = edit.range.end;
+    }
+    output.push_str(&source[position..]);
+    output
+  }
+}
+
+/// Builds the output from ranges of the source and new text, and the edits
+/// that turn the source into the output.
+struct Writer<'a> {
+  source: &'a str,
+  output: String,
+  edits: Vec<TextEdit>,
+  /// The end of the source copied so far.
+  position: usize,
+  /// The text written since the last copy.
+  pending: String,
+}
+
+impl<'a> Writer<'a> {
+  fn new(source: &'a str) -> Self {
+    Writer { source, output: String::with_capacity(source.len()), edits: Vec::new(), position: 0, pending: String::new() }
+  }
+
+  fn copy(&mut self, range: Range<usize>) {
+    if range.is_empty() {
+      return;
+    }
+    // An item moved before text already copied is new text at this point
+    if range.start < self.position {
+      return self.write(&self.source[range]);
+    }
+    self.flush(range.start);
+    self.output.push_str(&self.source[range.clone()]);
+    self.position = range.end;
+  }
+
+  fn write(&mut self, text: &str) {
+    self.output.push_str(text);
+    self.pending.push_str(text);
+  }
+
+  /// Records the text written since the last copy as replacing the source
+  /// from the end of that copy to `end`.
+  fn flush(&mut self, end: usize) {
+    if end > self.position || !self.pending.is_empty() {
+      self.edits.push(TextEdit { range: self.position..end, replacement: std::mem::take(&mut self.pending) });
+    }
+  }
+
+  fn finish(mut self) -> (String, Vec<TextEdit>) {
+    self.flush(self.source.len());
+    (self.output, self.edits)
+  }
+
+  /// The part of `range` after the first `;`, if any, and the whitespace and
+  /// `;` that follow: the trivia of the next item once the one before is gone.
+  fn after_separator(&self, range: Range<usize>) -> Range<usize> {
+    let text = &self.source[range.clone()];
+    let len = scan_until(text, &[';']);
+    let start = if len < text.len() { len + 1 } else { 0 };
+    let rest = &text[start..];
+    let start = start + (rest.len() - rest.trim_start_matches(|c| is_css_whitespace(c) || c == ';').len());
+    range.start + start..range.end
+  }
+
+  /// Writes the items of a list within `region` of the source, where
+  /// `original` were parsed.
+  fn list(&mut self, current: &[Item], original: &[Item], region: Range<usize>) {
+    let original: Vec<(Item, Range<usize>)> = original.iter().filter_map(|item| Some((*item, range(item.span()?)))).collect();
+    let gap = |i: usize| match i {
+      0 => region.start..original.first().map_or(region.end, |(_, range)| range.start),
+      i => original[i - 1].1.end..original[i].1.start,
+    };
+    let trail = original.last().map_or(region.end..region.end, |(_, range)| range.end..region.end);
+
+    // New items are separated like the items of the block
+    let sample = &self.source[if original.len() > 1 { gap(1) } else { gap(0) }];
+    let indentation = &sample[sample.trim_end_matches(is_css_whitespace).len()..];
+    let indentation = if indentation.is_empty() { " " } else { indentation }.to_string();
+    let separator = |previous: Item| match previous.is_declaration() {
+      true => format!(";{}", indentation),
+      false => indentation.clone(),
+    };
+
+    self.copy(gap(0));
+    let mut previous: Option<Item> = None;
+    for item in current {
+      let index = item.span().and_then(|span| {
+        original.iter().position(|(other, range)| other.is_declaration() == item.is_declaration() && *range == self::range(span))
+      });
+
+      match (previous, index) {
+        (None, Some(0)) | (None, None) => {}
+        (None, Some(i)) => self.copy(self.after_separator(gap(i))),
+        (Some(previous), Some(i)) if i > 0 => {
+          let gap = gap(i);
+          let text = &self.source[gap.clone()];
+          if previous.is_declaration() && scan_until(text, &[';']) == text.len() {
+            self.write(";");
+          }
+          self.copy(gap);
+        }
+        (Some(previous), _) => self.write(&separator(previous)),
+      }
+
+      match index {
+        Some(i) => self.item(*item, original[i].0),
+        None => self.write(&item.to_css()),
+      }
+      previous = Some(*item);
+    }
+
+    match previous {
+      Some(_) => self.copy(trail),
+      None => self.copy(self.after_separator(trail)),
+    }
+  }
+
+  fn item(&mut self, current: Item, original: Item) {
+    let Some(span) = original.span() else {
+      return self.write(&current.to_css());
+    };
+    if current == original {
+      return self.copy(range(span));
+    }
+
+    match (current, original) {
+      (Item::Declaration(current), Item::Declaration(original)) => self.declaration(current, original, span),
+      (Item::Rule(current), Item::Rule(original)) => self.rule(current, original, span),
+      _ => self.write(&current.to_css()),
+    }
+  }
+
+  fn declaration(&mut self, current: &CSSDeclaration, original: &CSSDeclaration, span: Span) {
+    let (Some(name), Some(value)) = (*original.name_span, *original.value_span) else {
+      return self.write(&declaration_text(current));
+    };
+
+    match current.name == original.name {
+      true => self.copy(range(name)),
+      false => self.write(&current.name),
+    }
+    self.copy(name.end.offset..value.start.offset);
+    match current.value == original.value {
+      true => self.copy(range(value)),
+      false => self.write(&current.value),
+    }
+    match (current.important, original.important) {
+      (true, false) => self.write(" !important"),
+      (false, true) => {}
+      _ => self.copy(value.end.offset..span.end.offset),
+    }
+  }
+
+  fn rule(&mut self, current: &Rule, original: &Rule, span: Span) {
+    let (Some(items), Some(original_items)) = (children(current), children(original)) else {
+      return self.write(&current.to_string());
+    };
+    if std::mem::discriminant(current) != std::mem::discriminant(original) {
+      return self.write(&current.to_string());
+    }
+
+    let text = &self.source[range(span)];
+    let open = span.start.offset + scan_until(text, &['{']);
+    let close = if text.ends_with('}') { span.end.offset - 1 } else { span.end.offset };
+
+    match (current, original) {
+      (Rule::Style(current), Rule::Style(original)) if current.selector != original.selector => {
+        match *original.selector_span {
+          Some(selector) => {
+            self.copy(span.start.offset..selector.start.offset);
+            self.write(&current.selector);
+            self.copy(selector.end.offset..open + 1);
+          }
+          None => self.write(&format!("{} {{", current.selector)),
+        }
+      }
+      _ if shell(current) != shell(original) => {
+        let header = shell(current).to_string();
+        self.write(&format!("{}{{", &header[..scan_until(&header, &['{'])]));
+      }
+      _ => self.copy(span.start.offset..open + 1),
+    }
+    self.list(&items, &original_items, (open + 1).min(close)..close);
+    self.copy(close..span.end.offset);
+  }
+}
+
+/// Writes `rules`, copying the source text of the nodes that are unchanged
+/// since they were parsed as `original.rules`. Returns the output and the
+/// edits that turn the source into it.
+pub(crate) fn write_lossless(rules: &[Rule], original: &Original) -> (String, Vec<TextEdit>) {
+  let mut writer = Writer::new(&original.text);
+  writer.list(&rule_items(rules), &rule_items(&original.rules), 0..original.text.len());
+  writer.finish()
+}
+
+
+#[cfg(test)]
+mod tests {
+  use crate::css_declaration::CSSDeclaration;
+  use crate::css_rule::CSSRule;
+  use crate::css_declaration_list::CSSDeclarationList;
+  use crate::lossless::TextEdit;
+  use crate::rule::Rule;
+  use crate::stylesheet::Stylesheet;
+
+  const SOURCE: &str = "@charset \"utf-8\";\n\
+    /* header */\n\
+    @import url(a.css)  screen ;\n\
+    H1 , h2>SPAN{COLOR : Red ! IMPORTANT;;margin:0 auto/* trailing */}\n\
+    a {\n  color: red;\n  &:hover { color: blue }\n  top: 0\n}\n\
+    @media   screen and (min-width:100px) {\n  .a { top: 0 }\n\n  .b { top: 1px; }\n}\n\
+    @font-face{font-family:'A';src:url(a.woff2)}\n\
+    @keyframes spin { from { opacity: 0 } to { opacity: 1 } }\n\
+    @layer base,theme;\n\
+    @unknown foo { bar }\n";
+
+  fn parse() -> Stylesheet {
+    Stylesheet::from_string_lossless(SOURCE).unwrap()
+  }
+
+  fn style_rule(stylesheet: &mut Stylesheet, index: usize) -> &mut CSSRule {
+    stylesheet.rules[index].as_style_rule_mut().unwrap()
+  }
+
+  #[test]
+  fn test_unmodified_round_trip() {
+    assert_eq!(parse().to_lossless_string(), SOURCE);
+    for source in ["", "  \n", "/* only a comment */", "a{}", "a { b: c; } /* end */\n\n"] {
+      assert_eq!(Stylesheet::from_string_lossless(source).unwrap().to_lossless_string(), source);
+    }
+    // Without a lossless parse, `Display` is used
+    let stylesheet = Stylesheet::from_string("a{b:c}").unwrap();
+    assert_eq!(stylesheet.to_lossless_string(), "a { b: c; }");
+  }
+
+  #[test]
+  fn test_edit_declarations() {
+    let mut stylesheet = parse();
+    let rule = style_rule(&mut stylesheet, 2);
+    rule.declarations.declarations[0].value = "blue".to_string();
+    rule.declarations.declarations[1].important = true;
+    let output = stylesheet.to_lossless_string();
+    assert!(output.contains("H1 , h2>SPAN{COLOR : blue ! IMPORTANT;;margin:0 auto/* trailing */ !important}"), "{}", output);
+    assert_eq!(output.len(), SOURCE.len() - 3 + 4 + 11);
+
+    let mut stylesheet = parse();
+    let rule = style_rule(&mut stylesheet, 3);
+    rule.declarations.declarations[0].name = "background".to_string();
+    rule.declarations.declarations.push(CSSDeclaration::new("left", "0", None));
+    assert!(stylesheet.to_lossless_string().contains("a {\n  background: red;\n  left: 0;\n  &:hover { color: blue }\n  top: 0\n}"));
+  }
+
+  #[test]
+  fn test_remove_declarations() {
+    let remove = |index: usize, name: &str| {
+      let mut stylesheet = parse();
+      style_rule(&mut stylesheet, index).declarations.remove_declaration(name);
+      stylesheet.to_lossless_string().replace(SOURCE, "<unchanged>")
+    };
+    assert!(remove(2, "COLOR").contains("H1 , h2>SPAN{margin:0 auto/* trailing */}"));
+    assert!(remove(2, "margin").contains("H1 , h2>SPAN{COLOR : Red ! IMPORTANT}"));
+    assert!(remove(3, "color").contains("a {\n  &:hover { color: blue }\n  top: 0\n}"));
+
+    let mut stylesheet = parse();
+    let Rule::NestedDeclarations(nested) = &mut style_rule(&mut stylesheet, 3).rules[1] else { panic!() };
+    nested.declarations = CSSDeclarationList::new();
+    assert!(stylesheet.to_lossless_string().contains("a {\n  color: red;\n  &:hover { color: blue }\n}"));
+  }
+
+  #[test]
+  fn test_edit_rules() {
+    let mut stylesheet = parse();
+    style_rule(&mut stylesheet, 2).selector = "h3".to_string();
+    let Rule::Media(media) = &mut stylesheet.rules[4] else { panic!() };
+    media.media = "print".to_string();
+    media.rules.remove(0);
+    let output = stylesheet.to_lossless_string();
+    assert!(output.contains("\nh3{COLOR : Red"), "{}", output);
+    assert!(output.contains("@media print {\n  .b { top: 1px; }\n}\n@font-face"), "{}", output);
+
+    let mut stylesheet = parse();
+    let Rule::Keyframes(keyframes) = &mut stylesheet.rules[6] else { panic!() };
+    keyframes.name = "turn".to_string();
+    let Rule::FontFace(font_face) = &mut stylesheet.rules[5] else { panic!() };
+    font_face.declarations.declarations[0].value = "\"B\"".to_string();
+    stylesheet.rules.remove(1);
+    stylesheet.rules.push(Rule::Style(CSSRule::from_string("p{top:0}").unwrap()));
+    assert_eq!(
+      stylesheet.to_lossless_string(),
+      SOURCE
+        .replace("/* header */\n@import url(a.css)  screen ;\n", "")
+        .replace("font-family:'A'", "font-family:\"B\"")
+        .replace("@keyframes spin { from { opacity: 0 } to { opacity: 1 } }", "@keyframes turn { from { opacity: 0; } to { opacity: 1; } }")
+        .replace("@unknown foo { bar }\n", "@unknown foo { bar }\np { top: 0; }\n")
+    );
+  }
+
+  #[test]
+  fn test_text_edits() {
+    let mut stylesheet = parse();
+    assert_eq!(stylesheet.text_edits(), Some(vec![]));
+    assert_eq!(Stylesheet::from_string("a{}").unwrap().text_edits(), None);
+
+    style_rule(&mut stylesheet, 2).declarations.remove_declaration("margin");
+    style_rule(&mut stylesheet, 3).declarations.declarations[0].value = "blue".to_string();
+    let Rule::Media(media) = &mut stylesheet.rules[4] else { panic!() };
+    media.rules.remove(1);
+
+    let edits = stylesheet.text_edits().unwrap();
+    assert_eq!(
+      edits.iter().map(|edit| (&SOURCE[edit.range.clone()], edit.replacement.as_str())).collect::<Vec<_>>(),
+      [(";;margin:0 auto/* trailing */", ""), ("red", "blue"), ("\n\n  .b { top: 1px; }", "")]
+    );
+    assert_eq!(TextEdit::apply(&edits, SOURCE), stylesheet.to_lossless_string());
+  }
+
+  #[test]
+  fn test_text_edits_of_moved_rules() {
+    let source = "a { top: 0 }\nb { top: 1px }\n";
+    let mut stylesheet = Stylesheet::from_string_lossless(source).unwrap();
+    stylesheet.rules.swap(0, 1);
+    let edits = stylesheet.text_edits().unwrap();
+    assert_eq!(TextEdit::apply(&edits, source), "b { top: 1px }\na { top: 0 }\n");
+    assert!(edits.windows(2).all(|pair| pair[0].range.end <= pair[1].range.start));
+  }
+}
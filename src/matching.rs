@@ -0,0 +1,561 @@
+//! CSS Selector Matching
+//!
+//! This module evaluates parsed selectors against a document tree. The tree is
+//! not provided by this crate: any type implementing the `Element` trait can be
+//! matched, whether it wraps a DOM, an HTML parser's output or a test fixture.
+//!
+//! Combinators, type, id, class and attribute selectors, `:not()`, `:is()`,
+//! `:where()`, `:has()` and the tree-structural pseudo-classes (`:root`,
+//! `:empty`, `:first-child`, `:nth-child(An+B of S)`, `:nth-of-type()`, ...)
+//! are evaluated by the matcher. Other pseudo-classes, such as `:hover` or
+//! `:checked`, are state flags answered by `Element::has_pseudo_class()`. A
+//! selector with a pseudo-element never matches an element, and the nesting
+//! selector `&` matches the root element, as it does outside of a nested rule.
+//!
+//! Type selectors are matched ASCII case-insensitively, as in HTML documents;
+//! ids, classes and attribute values are case-sensitive unless an attribute
+//! selector has the `i` flag.
+//!
+//! ## Main API
+//!
+//! - `Element` - The tree interface to implement
+//! - `CSSRule::matches()` - Whether a rule's selector matches an element
+//! - `SelectorList::matches()` / `Selector::matches()` - Match a parsed selector
+//!
+//! ## Examples
+//!
+//! ```rust
+//! use css_structs::{CSSRule, Element};
+//!
+//! // A one-level tree: a <ul> with <li> children
+//! #[derive(Clone, PartialEq)]
+//! struct Item<'a> { classes: &'a [&'a str], index: usize, count: usize }
+//!
+//! impl Element for Item<'_> {
+//!   fn parent_element(&self) -> Option<Self> { None }
+//!   fn previous_sibling_element(&self) -> Option<Self> {
+//!     (self.index > 0).then(|| Item { index: self.index - 1, ..self.clone() })
+//!   }
+//!   fn next_sibling_element(&self) -> Option<Self> {
+//!     (self.index + 1 < self.count).then(|| Item { index: self.index + 1, ..self.clone() })
+//!   }
+//!   fn first_child_element(&self) -> Option<Self> { None }
+//!   fn local_name(&self) -> &str { "li" }
+//!   fn id(&self) -> Option<&str> { None }
+//!   fn has_class(&self, name: &str) -> bool { self.classes[self.index] == name }
+//!   fn attribute(&self, _name: &str) -> Option<&str> { None }
+//! }
+//!
+//! let classes = ["a", "b", "a"];
+//! let third = Item { classes: &classes, index: 2, count: 3 };
+//!
+//! let rule = CSSRule::from_string("li.a:nth-child(2n+1):not(:first-child) { color: red }").unwrap();
+//! assert!(rule.matches(&third).unwrap());
+//!
+//! let rule = CSSRule::from_string("li.b ~ .a:last-child { color: red }").unwrap();
+//! assert!(rule.matches(&third).unwrap());
+//! ```
+
+
+use crate::selector::{
+  AttributeOperator, AttributeSelector, CaseSensitivity, Combinator, CompoundSelector, NamespacePrefix, Nth,
+  PseudoClass, Selector, SelectorList, SimpleSelector,
+};
+
+
+/// An element of a document tree that selectors can be matched against.
+///
+/// Implementations are usually cheap handles (a reference or an index into an
+/// arena); equality must tell whether two handles refer to the same element.
+/// Only elements take part in matching, so the navigation methods skip text
+/// and comment nodes.
+pub trait Element: Clone + PartialEq {
+  fn parent_element(&self) -> Option<Self>;
+  fn previous_sibling_element(&self) -> Option<Self>;
+  fn next_sibling_element(&self) -> Option<Self>;
+  fn first_child_element(&self) -> Option<Self>;
+
+  /// The element name, such as `div`.
+  fn local_name(&self) -> &str;
+  fn id(&self) -> Option<&str>;
+  fn has_class(&self, name: &str) -> bool;
+  /// The value of an attribute, looked up by name as written in the selector.
+  fn attribute(&self, name: &str) -> Option<&str>;
+
+  /// The `@namespace` prefix of the element's namespace, for `ns|name`
+  /// selectors. `None` means the element is in no namespace.
+  fn namespace_prefix(&self) -> Option<&str> {
+    None
+  }
+
+  /// Whether a state pseudo-class such as `hover`, `focus`, `checked` or
+  /// `disabled` applies. Receives the lowercased name, or the name and its
+  /// arguments for functional pseudo-classes such as `:lang(en)`.
+  fn has_pseudo_class(&self, _name: &str, _arguments: Option<&str>) -> bool {
+    false
+  }
+
+  /// Whether the element has no children, including text. Defaults to having
+  /// no child elements.
+  fn is_empty(&self) -> bool {
+    self.first_child_element().is_none()
+  }
+}
+
+
+/// Returns whether any selector of the list matches `element`.
+pub fn matches_selector_list<E: Element>(selectors: &SelectorList, element: &E) -> bool {
+  selectors.selectors.iter().any(|selector| matches_selector(selector, element))
+}
+
+/// Returns whether `selector` matches `element`. A relative selector is
+/// matched as if it had no leading combinator.
+pub fn matches_selector<E: Element>(selector: &Selector, element: &E) -> bool {
+  let (compounds, combinators) = split(selector);
+  matches_from(&compounds, &combinators, compounds.len() - 1, element, None)
+}
+
+fn split(selector: &Selector) -> (Vec<&CompoundSelector>, Vec<Combinator>) {
+  let compounds = std::iter::once(&selector.first)
+    .chain(selector.rest.iter().map(|(_, compound)| compound))
+    .collect();
+  let combinators = selector.rest.iter().map(|(combinator, _)| *combinator).collect();
+
+  (compounds, combinators)
+}
+
+fn ancestors<E: Element>(element: &E) -> impl Iterator<Item = E> {
+  std::iter::successors(element.parent_element(), E::parent_element)
+}
+
+fn previous_siblings<E: Element>(element: &E) -> impl Iterator<Item = E> {
+  std::iter::successors(element.previous_sibling_element(), E::previous_sibling_element)
+}
+
+fn next_siblings<E: Element>(element: &E) -> impl Iterator<Item = E> {
+  std::iter::successors(element.next_sibling_element(), E::next_sibling_element)
+}
+
+fn children<E: Element>(element: &E) -> impl Iterator<Item = E> {
+  std::iter::successors(element.first_child_element(), E::next_sibling_element)
+}
+
+fn descendants<E: Element>(element: &E) -> Vec<E> {
+  let mut found = Vec::new();
+  for child in children(element) {
+    found.push(child.clone());
+    found.extend(descendants(&child));
+  }
+
+  found
+}
+
+/// Whether `element` is related to `anchor` by `combinator`, e.g. whether it
+/// is a child of `anchor` for `Combinator::Child`.
+fn is_related<E: Element>(anchor: &E, combinator: Combinator, element: &E) -> bool {
+  match combinator {
+    Combinator::Descendant => ancestors(element).any(|ancestor| ancestor == *anchor),
+    Combinator::Child => element.parent_element().as_ref() == Some(anchor),
+    Combinator::NextSibling => element.previous_sibling_element().as_ref() == Some(anchor),
+    Combinator::SubsequentSibling => previous_siblings(element).any(|sibling| sibling == *anchor),
+  }
+}
+
+/// Matches `compounds[..=index]` right to left, with `element` matching
+/// `compounds[index]`. When an `anchor` is given, the leftmost compound must
+/// match an element related to it, as in the relative selectors of `:has()`.
+fn matches_from<E: Element>(
+  compounds: &[&CompoundSelector],
+  combinators: &[Combinator],
+  index: usize,
+  element: &E,
+  anchor: Option<(&E, Combinator)>,
+) -> bool {
+  if !matches_compound(compounds[index], element) {
+    return false;
+  }
+  if index == 0 {
+    return anchor.is_none_or(|(anchor, combinator)| is_related(anchor, combinator, element));
+  }
+
+  let next = |candidate: &E| matches_from(compounds, combinators, index - 1, candidate, anchor);
+  match combinators[index - 1] {
+    Combinator::Descendant => ancestors(element).any(|ancestor| next(&ancestor)),
+    Combinator::Child => element.parent_element().is_some_and(|parent| next(&parent)),
+    Combinator::NextSibling => element.previous_sibling_element().is_some_and(|sibling| next(&sibling)),
+    Combinator::SubsequentSibling => previous_siblings(element).any(|sibling| next(&sibling)),
+  }
+}
+
+/// Whether `anchor` has an element matching the relative `selector`.
+fn matches_relative<E: Element>(selector: &Selector, anchor: &E) -> bool {
+  let combinator = selector.leading_combinator.unwrap_or(Combinator::Descendant);
+  let (compounds, combinators) = split(selector);
+
+  // The rightmost compound is in the subtree of the anchor or, after a
+  // sibling combinator, in the subtrees of its following siblings
+  let candidates = match combinator {
+    Combinator::Descendant | Combinator::Child => descendants(anchor),
+    Combinator::NextSibling | Combinator::SubsequentSibling => next_siblings(anchor)
+      .flat_map(|sibling| std::iter::once(sibling.clone()).chain(descendants(&sibling)))
+      .collect(),
+  };
+
+  candidates.iter().any(|candidate| {
+    matches_from(&compounds, &combinators, compounds.len() - 1, candidate, Some((anchor, combinator)))
+  })
+}
+
+fn matches_compound<E: Element>(compound: &CompoundSelector, element: &E) -> bool {
+  compound.components.iter().all(|component| matches_simple(component, element))
+}
+
+fn matches_namespace<E: Element>(namespace: &Option<NamespacePrefix>, element: &E) -> bool {
+  match namespace {
+    None | Some(NamespacePrefix::Any) => true,
+    Some(NamespacePrefix::None) => element.namespace_prefix().is_none(),
+    Some(NamespacePrefix::Named(prefix)) => element.namespace_prefix() == Some(prefix.as_str()),
+  }
+}
+
+fn matches_simple<E: Element>(selector: &SimpleSelector, element: &E) -> bool {
+  match selector {
+    SimpleSelector::Type { namespace, name } => {
+      matches_namespace(namespace, element) && element.local_name().eq_ignore_ascii_case(name)
+    }
+    SimpleSelector::Universal { namespace } => matches_namespace(namespace, element),
+    SimpleSelector::Id(id) => element.id() == Some(id.as_str()),
+    SimpleSelector::Class(class) => element.has_class(class),
+    SimpleSelector::Attribute(attribute) => matches_attribute(attribute, element),
+    SimpleSelector::PseudoClass(pseudo_class) => matches_pseudo_class(pseudo_class, element),
+    SimpleSelector::PseudoElement(_) => false,
+    SimpleSelector::Nesting => element.parent_element().is_none(),
+  }
+}
+
+fn matches_attribute<E: Element>(selector: &AttributeSelector, element: &E) -> bool {
+  let Some(actual) = element.attribute(&selector.name) else {
+    return false;
+  };
+  let Some((operator, expected)) = &selector.operation else {
+    return true;
+  };
+
+  let (actual, expected) = match selector.case_sensitivity {
+    Some(CaseSensitivity::AsciiCaseInsensitive) => (actual.to_ascii_lowercase(), expected.to_ascii_lowercase()),
+    Some(CaseSensitivity::CaseSensitive) | None => (actual.to_string(), expected.clone()),
+  };
+
+  match operator {
+    AttributeOperator::Equals => actual == expected,
+    AttributeOperator::Includes => {
+      !expected.is_empty()
+        && !expected.contains(char::is_whitespace)
+        && actual.split_ascii_whitespace().any(|word| word == expected)
+    }
+    AttributeOperator::DashMatch => {
+      actual == expected || actual.strip_prefix(expected.as_str()).is_some_and(|rest| rest.starts_with('-'))
+    }
+    AttributeOperator::Prefix => !expected.is_empty() && actual.starts_with(&expected),
+    AttributeOperator::Suffix => !expected.is_empty() && actual.ends_with(&expected),
+    AttributeOperator::Substring => !expected.is_empty() && actual.contains(&expected),
+  }
+}
+
+/// Whether the 1-based `position` is `A*n + B` for some `n >= 0`.
+fn matches_nth(nth: &Nth, position: i32) -> bool {
+  match nth.a {
+    0 => position == nth.b,
+    a => {
+      let steps = position - nth.b;
+      steps % a == 0 && steps / a >= 0
+    }
+  }
+}
+
+/// The 1-based position of `element` among the siblings accepted by `counts`,
+/// counted from the start or, when `from_end` is set, from the end.
+fn position<E: Element>(element: &E, from_end: bool, counts: impl Fn(&E) -> bool) -> i32 {
+  let siblings: Box<dyn Iterator<Item = E>> = match from_end {
+    false => Box::new(previous_siblings(element)),
+    true => Box::new(next_siblings(element)),
+  };
+
+  1 + siblings.filter(|sibling| counts(sibling)).count() as i32
+}
+
+fn matches_nth_child<E: Element>(nth: &Nth, of: &Option<SelectorList>, element: &E, from_end: bool) -> bool {
+  match of {
+    Some(selectors) => {
+      matches_selector_list(selectors, element)
+        && matches_nth(nth, position(element, from_end, |sibling| matches_selector_list(selectors, sibling)))
+    }
+    None => matches_nth(nth, position(element, from_end, |_| true)),
+  }
+}
+
+fn matches_nth_of_type<E: Element>(nth: &Nth, element: &E, from_end: bool) -> bool {
+  let same_type = |sibling: &E| sibling.local_name().eq_ignore_ascii_case(element.local_name());
+  matches_nth(nth, position(element, from_end, same_type))
+}
+
+fn matches_pseudo_class<E: Element>(pseudo_class: &PseudoClass, element: &E) -> bool {
+  let first = Nth { a: 0, b: 1 };
+
+  match pseudo_class {
+    PseudoClass::Not(selectors) => !matches_selector_list(selectors, element),
+    PseudoClass::Is(selectors) | PseudoClass::Where(selectors) => matches_selector_list(selectors, element),
+    PseudoClass::Has(selectors) => selectors.selectors.iter().any(|selector| matches_relative(selector, element)),
+    PseudoClass::NthChild(nth, of) => matches_nth_child(nth, of, element, false),
+    PseudoClass::NthLastChild(nth, of) => matches_nth_child(nth, of, element, true),
+    PseudoClass::NthOfType(nth) => matches_nth_of_type(nth, element, false),
+    PseudoClass::NthLastOfType(nth) => matches_nth_of_type(nth, element, true),
+    PseudoClass::Simple(name) => match name.as_str() {
+      "root" | "scope" => element.parent_element().is_none(),
+      "empty" => element.is_empty(),
+      "first-child" => matches_nth_child(&first, &None, element, false),
+      "last-child" => matches_nth_child(&first, &None, element, true),
+      "only-child" => matches_nth_child(&first, &None, element, false) && matches_nth_child(&first, &None, element, true),
+      "first-of-type" => matches_nth_of_type(&first, element, false),
+      "last-of-type" => matches_nth_of_type(&first, element, true),
+      "only-of-type" => matches_nth_of_type(&first, element, false) && matches_nth_of_type(&first, element, true),
+      name => element.has_pseudo_class(name, None),
+    },
+    PseudoClass::Function { name, arguments } => element.has_pseudo_class(name, Some(arguments)),
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::css_declaration_list::CSSDeclarationList;
+  use crate::css_rule::CSSRule;
+
+  /// A node of the test document, stored in an arena.
+  struct Node {
+    name: &'static str,
+    attributes: Vec<(&'static str, &'static str)>,
+    states: Vec<&'static str>,
+    parent: Option<usize>,
+    children: Vec<usize>,
+  }
+
+  struct Document {
+    nodes: Vec<Node>,
+  }
+
+  #[derive(Clone, Copy)]
+  struct Handle<'a> {
+    document: &'a Document,
+    index: usize,
+  }
+
+  impl PartialEq for Handle<'_> {
+    fn eq(&self, other: &Self) -> bool {
+      self.index == other.index
+    }
+  }
+
+  impl Document {
+    fn new(name: &'static str, attributes: Vec<(&'static str, &'static str)>) -> Self {
+      Document { nodes: vec![Node { name, attributes, states: Vec::new(), parent: None, children: Vec::new() }] }
+    }
+
+    fn add(&mut self, parent: usize, name: &'static str, attributes: Vec<(&'static str, &'static str)>) -> usize {
+      let index = self.nodes.len();
+      self.nodes.push(Node { name, attributes, states: Vec::new(), parent: Some(parent), children: Vec::new() });
+      self.nodes[parent].children.push(index);
+      index
+    }
+
+    fn get(&self, index: usize) -> Handle<'_> {
+      Handle { document: self, index }
+    }
+  }
+
+  impl<'a> Handle<'a> {
+    fn node(&self) -> &'a Node {
+      &self.document.nodes[self.index]
+    }
+
+    fn sibling(&self, offset: isize) -> Option<Self> {
+      let siblings = &self.document.nodes[self.node().parent?].children;
+      let position = siblings.iter().position(|&i| i == self.index)? as isize + offset;
+      let index = *siblings.get(usize::try_from(position).ok()?)?;
+      Some(self.document.get(index))
+    }
+  }
+
+  impl Element for Handle<'_> {
+    fn parent_element(&self) -> Option<Self> {
+      Some(self.document.get(self.node().parent?))
+    }
+
+    fn previous_sibling_element(&self) -> Option<Self> {
+      self.sibling(-1)
+    }
+
+    fn next_sibling_element(&self) -> Option<Self> {
+      self.sibling(1)
+    }
+
+    fn first_child_element(&self) -> Option<Self> {
+      Some(self.document.get(*self.node().children.first()?))
+    }
+
+    fn local_name(&self) -> &str {
+      self.node().name
+    }
+
+    fn id(&self) -> Option<&str> {
+      self.attribute("id")
+    }
+
+    fn has_class(&self, name: &str) -> bool {
+      self.attribute("class").is_some_and(|classes| classes.split_ascii_whitespace().any(|class| class == name))
+    }
+
+    fn attribute(&self, name: &str) -> Option<&str> {
+      self.node().attributes.iter().find(|(key, _)| key.eq_ignore_ascii_case(name)).map(|(_, value)| *value)
+    }
+
+    fn has_pseudo_class(&self, name: &str, _arguments: Option<&str>) -> bool {
+      self.node().states.contains(&name)
+    }
+  }
+
+  /// <html>
+  ///   <body class="page">
+  ///     <ul id="menu">
+  ///       <li class="item first">, <li class="item" lang="en-US">, <li class="item active">
+  ///     </ul>
+  ///     <p><img src="a.png"></p>
+  ///     <p title="Hello World"></p>
+  ///   </body>
+  /// </html>
+  fn document() -> Document {
+    let mut document = Document::new("html", vec![]);
+    let body = document.add(0, "body", vec![("class", "page")]);
+    let ul = document.add(body, "ul", vec![("id", "menu")]);
+    document.add(ul, "li", vec![("class", "item first")]);
+    document.add(ul, "li", vec![("class", "item"), ("lang", "en-US")]);
+    let active = document.add(ul, "li", vec![("class", "item active")]);
+    document.nodes[active].states.push("hover");
+    let p = document.add(body, "p", vec![]);
+    document.add(p, "img", vec![("src", "a.png")]);
+    document.add(body, "p", vec![("title", "Hello World")]);
+    document
+  }
+
+  const BODY: usize = 1;
+  const UL: usize = 2;
+  const FIRST_LI: usize = 3;
+  const SECOND_LI: usize = 4;
+  const THIRD_LI: usize = 5;
+  const FIRST_P: usize = 6;
+  const IMG: usize = 7;
+  const SECOND_P: usize = 8;
+
+  fn matching(document: &Document, selector: &str) -> Vec<usize> {
+    let selectors = SelectorList::from_string(selector).unwrap();
+    (0..document.nodes.len()).filter(|&i| matches_selector_list(&selectors, &document.get(i))).collect()
+  }
+
+  #[test]
+  fn test_match_simple_selectors() {
+    let document = document();
+    assert_eq!(matching(&document, "li"), vec![FIRST_LI, SECOND_LI, THIRD_LI]);
+    assert_eq!(matching(&document, "LI.first"), vec![FIRST_LI]);
+    assert_eq!(matching(&document, "#menu"), vec![UL]);
+    assert_eq!(matching(&document, "*"), (0..document.nodes.len()).collect::<Vec<_>>());
+    assert_eq!(matching(&document, ".item.active, img"), vec![THIRD_LI, IMG]);
+    assert!(matching(&document, "li::before").is_empty());
+  }
+
+  #[test]
+  fn test_match_combinators() {
+    let document = document();
+    assert_eq!(matching(&document, "body li"), vec![FIRST_LI, SECOND_LI, THIRD_LI]);
+    assert_eq!(matching(&document, "body > li"), Vec::<usize>::new());
+    assert_eq!(matching(&document, "html .page > ul > .first"), vec![FIRST_LI]);
+    assert_eq!(matching(&document, ".first + li"), vec![SECOND_LI]);
+    assert_eq!(matching(&document, ".first ~ li"), vec![SECOND_LI, THIRD_LI]);
+    assert_eq!(matching(&document, "ul ~ p img"), vec![IMG]);
+  }
+
+  #[test]
+  fn test_match_attribute_operators() {
+    let document = document();
+    assert_eq!(matching(&document, "[lang]"), vec![SECOND_LI]);
+    assert_eq!(matching(&document, "[lang|=en]"), vec![SECOND_LI]);
+    assert_eq!(matching(&document, "[lang|=en-U]"), Vec::<usize>::new());
+    assert_eq!(matching(&document, "[class~=first]"), vec![FIRST_LI]);
+    assert_eq!(matching(&document, "[class~='item first']"), Vec::<usize>::new());
+    assert_eq!(matching(&document, "[src^=a]"), vec![IMG]);
+    assert_eq!(matching(&document, "[src$='.png']"), vec![IMG]);
+    assert_eq!(matching(&document, "[title*='o W']"), vec![SECOND_P]);
+    assert_eq!(matching(&document, "[title*='']"), Vec::<usize>::new());
+    assert_eq!(matching(&document, "[title='hello world']"), Vec::<usize>::new());
+    assert_eq!(matching(&document, "[title='hello world' i]"), vec![SECOND_P]);
+    assert_eq!(matching(&document, "[title='hello world' s]"), Vec::<usize>::new());
+  }
+
+  #[test]
+  fn test_match_structural_pseudo_classes() {
+    let document = document();
+    assert_eq!(matching(&document, ":root"), vec![0]);
+    assert_eq!(matching(&document, "li:first-child"), vec![FIRST_LI]);
+    assert_eq!(matching(&document, "li:last-child"), vec![THIRD_LI]);
+    assert_eq!(matching(&document, ":only-child"), vec![0, BODY, IMG]);
+    assert_eq!(matching(&document, "p:first-of-type"), vec![FIRST_P]);
+    assert_eq!(matching(&document, "body > :last-of-type"), vec![UL, SECOND_P]);
+    assert_eq!(matching(&document, "p:empty"), vec![SECOND_P]);
+  }
+
+  #[test]
+  fn test_match_nth() {
+    let document = document();
+    assert_eq!(matching(&document, "li:nth-child(odd)"), vec![FIRST_LI, THIRD_LI]);
+    assert_eq!(matching(&document, "li:nth-child(2n)"), vec![SECOND_LI]);
+    assert_eq!(matching(&document, "li:nth-child(-n+2)"), vec![FIRST_LI, SECOND_LI]);
+    assert_eq!(matching(&document, "li:nth-last-child(1)"), vec![THIRD_LI]);
+    assert_eq!(matching(&document, "li:nth-child(n+4)"), Vec::<usize>::new());
+    assert_eq!(matching(&document, ":nth-child(2 of .item)"), vec![SECOND_LI]);
+    assert_eq!(matching(&document, ":nth-child(1 of :not(.first))"), vec![0, BODY, UL, SECOND_LI, IMG]);
+    assert_eq!(matching(&document, "p:nth-of-type(2)"), vec![SECOND_P]);
+    assert_eq!(matching(&document, "p:nth-last-of-type(2)"), vec![FIRST_P]);
+  }
+
+  #[test]
+  fn test_match_logical_pseudo_classes() {
+    let document = document();
+    assert_eq!(matching(&document, "li:not(.first, [lang])"), vec![THIRD_LI]);
+    assert_eq!(matching(&document, ":is(ul, p) > :where(.first, img)"), vec![FIRST_LI, IMG]);
+    assert_eq!(matching(&document, "li:hover"), vec![THIRD_LI]);
+    assert_eq!(matching(&document, "li:focus"), Vec::<usize>::new());
+  }
+
+  #[test]
+  fn test_match_has() {
+    let document = document();
+    assert_eq!(matching(&document, "p:has(img)"), vec![FIRST_P]);
+    assert_eq!(matching(&document, ":has(> img)"), vec![FIRST_P]);
+    assert_eq!(matching(&document, "body:has(> ul > .active)"), vec![BODY]);
+    assert_eq!(matching(&document, "li:has(+ .active)"), vec![SECOND_LI]);
+    assert_eq!(matching(&document, "li:has(~ [lang])"), vec![FIRST_LI]);
+    assert_eq!(matching(&document, "ul:has(~ p img)"), vec![UL]);
+    assert_eq!(matching(&document, "ul:has(li img)"), Vec::<usize>::new());
+  }
+
+  #[test]
+  fn test_rule_matches() {
+    let document = document();
+    let rule = CSSRule::from_string("#menu > li.item:nth-child(2) { color: red }").unwrap();
+    assert!(rule.matches(&document.get(SECOND_LI)).unwrap());
+    assert!(!rule.matches(&document.get(THIRD_LI)).unwrap());
+
+    let rule = CSSRule::new("li[", &CSSDeclarationList::new());
+    assert!(rule.matches(&document.get(FIRST_LI)).is_err());
+  }
+}
@@ -0,0 +1,746 @@
+//! CSS Minification
+//!
+//! This module writes stylesheets in as few bytes as possible and removes the
+//! parts of a stylesheet that cannot change how it applies.
+//!
+//! `Stylesheet::to_minified_string()` serializes without whitespace that is
+//! not needed, comments, or the last `;` of each block, and shortens values:
+//! colors are written as the shortest of their hex and named forms
+//! (`#ff0000` becomes `red`, `white` becomes `#fff`), numbers lose their
+//! leading zero (`0.5` becomes `.5`) and zero lengths lose their unit (`0px`
+//! becomes `0`, except in math functions and `flex`, where a unitless zero
+//! means something else). Custom property values are kept as written.
+//!
+//! `Stylesheet::optimize()` rewrites the rules themselves:
+//!
+//! - Within a declaration list, a declaration is removed when another one of
+//!   the same property, or a shorthand that sets it, wins over it, see
+//!   `CSSDeclarationList::remove_overridden()`. Longhands are then combined
+//!   into shorthands, see `CSSDeclarationList::collapse_shorthands()`.
+//! - Style rules with neither declarations nor nested rules are removed, and
+//!   so are conditional group rules left empty.
+//! - Two adjacent style rules with the same selector become one rule.
+//!   Nothing comes between them, so this keeps the order of every
+//!   declaration in the cascade.
+//! - Two adjacent style rules with the same declarations become one rule
+//!   with both selectors, when neither has nested rules. As a browser drops
+//!   a whole selector list when one selector is invalid, this is only done
+//!   when both selectors parse and neither uses a vendor-prefixed pseudo-class
+//!   or pseudo-element such as `::-moz-selection`.
+//!
+//! ## Main API
+//!
+//! - `Stylesheet::to_minified_string()` - Serialize a stylesheet without unneeded bytes
+//! - `Stylesheet::optimize()` - Remove overridden declarations and empty rules, and merge adjacent rules
+//! - `CSSDeclarationList::remove_overridden()` - Remove the declarations that never win in a list
+//! - `minify_value()` - The shortest form of a declaration value
+//!
+//! ## Examples
+//!
+//! ```rust
+//! use css_structs::Stylesheet;
+//! use css_structs::minify::minify_value;
+//!
+//! let mut stylesheet = Stylesheet::from_string("
+//!   h1 { color: #FF0000; margin: 0px auto; }
+//!   h1 { opacity: 0.50; color: #0000FF; }
+//!   h2 { padding: 0 }
+//!   h3 { padding: 0 }
+//! ").unwrap();
+//! assert_eq!(stylesheet.to_minified_string(), "h1{color:red;margin:0 auto}h1{opacity:.5;color:#00f}h2{padding:0}h3{padding:0}");
+//!
+//! stylesheet.optimize();
+//! assert_eq!(stylesheet.to_minified_string(), "h1{margin:0 auto;opacity:.5;color:#00f}h2,h3{padding:0}");
+//!
+//! assert_eq!(minify_value("box-shadow", "0px 0px 4px rgba(0, 0, 0, 0.5)"), "0 0 4px rgba(0,0,0,.5)");
+//! ```
+
+
+use crate::calc::CalcNode;
+use crate::color::{takes_color_keywords, Color};
+use crate::css_declaration::CSSDeclaration;
+use crate::css_declaration_list::CSSDeclarationList;
+use crate::css_rule::CSSRule;
+use crate::dimension::{Unit, UnitKind};
+use crate::helpers::{is_css_whitespace, serialize_identifier, serialize_string, split_top_level, string_len};
+use crate::properties::{is_standard, property};
+use crate::rule::{for_each_declaration_list_mut, Rule};
+use crate::value::{format_number, ComponentValue, Value};
+
+
+/// Characters around which whitespace is not needed in selectors.
+const SELECTOR: &[char] = &[',', '>', '+', '~'];
+
+/// Characters around which whitespace is not needed in the conditions of
+/// `@media`, `@supports` and `@container`.
+const CONDITION: &[char] = &[',', ':', '<', '>', '='];
+
+/// The units of CSS 2, which every browser supports.
+const CSS2_UNITS: &[&str] = &["px", "em", "ex", "in", "cm", "mm", "pt", "pc", "deg", "grad", "rad", "s", "ms", "hz", "khz"];
+
+/// The named colors of CSS 2.1.
+const CSS2_COLORS: &[&str] = &[
+  "aqua", "black", "blue", "fuchsia", "gray", "green", "lime", "maroon", "navy", "olive", "orange", "purple", "red",
+  "silver", "teal", "white", "yellow",
+];
+
+const CSS2_LINE_STYLES: &[&str] = &["none", "hidden", "dotted", "dashed", "solid", "double", "groove", "ridge", "inset", "outset"];
+const CSS2_LINE_WIDTHS: &[&str] = &["thin", "medium", "thick"];
+const CSS2_FONT_SIZES: &[&str] = &["xx-small", "x-small", "small", "medium", "large", "x-large", "xx-large", "larger", "smaller"];
+const CSS2_GENERIC_FAMILIES: &[&str] = &["serif", "sans-serif", "cursive", "fantasy", "monospace"];
+const CSS2_LIST_STYLE_TYPES: &[&str] = &[
+  "disc", "circle", "square", "decimal", "decimal-leading-zero", "lower-roman", "upper-roman", "lower-greek",
+  "lower-latin", "upper-latin", "armenian", "georgian", "lower-alpha", "upper-alpha", "none",
+];
+
+/// How the values of a property may be shortened.
+struct Context {
+  /// Whether identifiers such as `white` are colors.
+  color_keywords: bool,
+  /// Whether `0px` can be written `0`.
+  zero_lengths: bool,
+}
+
+/// Returns the shortest form of the value of the property `name`: without
+/// unneeded whitespace, with colors, numbers and zero lengths shortened. The
+/// values of custom properties are only trimmed, and values that cannot be
+/// parsed only have their whitespace collapsed.
+pub fn minify_value(name: &str, value: &str) -> String {
+  if name.starts_with("--") {
+    return value.trim_matches(is_css_whitespace).to_string();
+  }
+  let name = name.to_ascii_lowercase();
+  let parsed = match Value::from_string(value) {
+    // `U+0-7F` does not survive being parsed as numbers and identifiers
+    Ok(parsed) if name != "unicode-range" => parsed,
+    _ => return compact(value, &[',']),
+  };
+
+  let context = Context {
+    color_keywords: takes_color_keywords(&name),
+    zero_lengths: !matches!(name.as_str(), "flex" | "-webkit-flex" | "-ms-flex"),
+  };
+  let mut output = String::with_capacity(value.len());
+  write_components(&parsed.components, &context, false, &mut output);
+  output
+}
+
+fn write_components(components: &[ComponentValue], context: &Context, in_math: bool, output: &mut String) {
+  for (i, component) in components.iter().enumerate() {
+    if *component != ComponentValue::Whitespace {
+      write_component(component, context, in_math, output);
+      continue;
+    }
+
+    let previous = i.checked_sub(1).map(|i| &components[i]);
+    let next = components.get(i + 1);
+    let separator = |component: Option<&ComponentValue>| {
+      matches!(component, None | Some(ComponentValue::Comma | ComponentValue::Slash))
+    };
+    if !separator(previous) && !separator(next) && next != Some(&ComponentValue::Whitespace) {
+      output.push(' ');
+    }
+  }
+}
+
+fn write_component(component: &ComponentValue, context: &Context, in_math: bool, output: &mut String) {
+  let mut written = match component {
+    ComponentValue::Number(value) => minify_number(*value),
+    ComponentValue::Percentage(value) => format!("{}%", minify_number(*value)),
+    ComponentValue::Dimension { value, unit } => {
+      let is_length = Unit::from_name(unit).is_some_and(|unit| unit.kind() == UnitKind::Length);
+      if *value == 0.0 && is_length && context.zero_lengths && !in_math {
+        "0".to_string()
+      } else {
+        format!("{}{}", minify_number(*value), serialize_identifier(unit))
+      }
+    }
+    ComponentValue::Url(url) => {
+      let needs_quotes = url.is_empty()
+        || url.chars().any(|c| matches!(c, '"' | '\'' | '(' | ')' | '\\') || is_css_whitespace(c) || c.is_control());
+      match needs_quotes {
+        true => format!("url({})", serialize_string(url)),
+        false => format!("url({})", url),
+      }
+    }
+    ComponentValue::Function { name, arguments } => {
+      let mut written = format!("{}(", serialize_identifier(name));
+      write_components(arguments, context, in_math || CalcNode::is_math_function(name), &mut written);
+      written.push(')');
+      written
+    }
+    ComponentValue::Block { bracket, contents } => {
+      let mut written = bracket.to_string();
+      write_components(contents, context, in_math, &mut written);
+      written.push(match bracket {
+        '[' => ']',
+        '{' => '}',
+        _ => ')',
+      });
+      written
+    }
+    component => component.to_string(),
+  };
+
+  let is_keyword = matches!(component, ComponentValue::Ident(_));
+  if (context.color_keywords || !is_keyword)
+    && let Some(Color::Absolute(color)) = Color::from_component(component)
+    && let Some(shortest) = color.shortest_srgb()
+    && shortest.len() < written.len()
+  {
+    written = shortest;
+  }
+  output.push_str(&written);
+}
+
+/// Formats a number without the leading zero of a fraction: `.5` for `0.5`.
+fn minify_number(value: f64) -> String {
+  let number = format_number(value);
+  if let Some(fraction) = number.strip_prefix("0.") {
+    format!(".{}", fraction)
+  } else if let Some(fraction) = number.strip_prefix("-0.") {
+    format!("-.{}", fraction)
+  } else {
+    number
+  }
+}
+
+/// Collapses each run of whitespace in `text` outside strings to a single
+/// space, and drops it at the ends, after `(` and `[`, before `)` and `]`,
+/// and around the `tight` characters.
+fn compact(text: &str, tight: &[char]) -> String {
+  let mut output = String::with_capacity(text.len());
+  let mut rest = text.trim_matches(is_css_whitespace);
+  let mut space = false;
+  // Whether the last character written allows dropping the space after it
+  let mut after_tight = true;
+
+  while let Some(c) = rest.chars().next() {
+    if is_css_whitespace(c) {
+      space = true;
+      rest = &rest[c.len_utf8()..];
+      continue;
+    }
+
+    let len = match c {
+      '"' | '\'' => string_len(rest).max(1),
+      '\\' => 1 + rest[1..].chars().next().map_or(0, char::len_utf8),
+      c => c.len_utf8(),
+    };
+    let is_tight = tight.contains(&c);
+    if space && !after_tight && !is_tight && !matches!(c, ')' | ']') {
+      output.push(' ');
+    }
+    output.push_str(&rest[..len]);
+    after_tight = is_tight || matches!(c, '(' | '[');
+    space = false;
+    rest = &rest[len..];
+  }
+
+  output
+}
+
+/// Writes `rules` as minified CSS.
+pub(crate) fn write_rules(rules: &[Rule], output: &mut String) {
+  for (i, rule) in rules.iter().enumerate() {
+    write_rule(rule, output);
+    // Declarations must be ended before a rule that follows them
+    if let Rule::NestedDeclarations(rule) = rule
+      && !rule.declarations.declarations.is_empty()
+      && i + 1 < rules.len()
+    {
+      output.push(';');
+    }
+  }
+}
+
+fn write_rule(rule: &Rule, output: &mut String) {
+  match rule {
+    Rule::Style(rule) => {
+      output.push_str(&compact(&rule.selector, SELECTOR));
+      output.push('{');
+      write_declarations(&rule.declarations, output);
+      if !rule.declarations.declarations.is_empty() && !rule.rules.is_empty() {
+        output.push(';');
+      }
+      write_rules(&rule.rules, output);
+      output.push('}');
+    }
+    Rule::NestedDeclarations(rule) => write_declarations(&rule.declarations, output),
+    Rule::Media(rule) => write_group("media", &compact(&rule.media, CONDITION), &rule.rules, output),
+    Rule::Supports(rule) => write_group("supports", &compact(&rule.condition, CONDITION), &rule.rules, output),
+    Rule::Container(rule) => write_group("container", &compact(&rule.condition, CONDITION), &rule.rules, output),
+    Rule::Scope(rule) => write_group("scope", &compact(&rule.prelude, SELECTOR), &rule.rules, output),
+    Rule::StartingStyle(rule) => write_group("starting-style", "", &rule.rules, output),
+    Rule::Layer(rule) => match &rule.rules {
+      Some(rules) => write_group("layer", &rule.names.join(","), rules, output),
+      None => output.push_str(&format!("@layer {};", rule.names.join(","))),
+    },
+    Rule::Import(rule) => {
+      output.push_str(&format!("@import {}", serialize_string(&rule.url)));
+      match rule.layer.as_deref() {
+        Some("") => output.push_str(" layer"),
+        Some(layer) => output.push_str(&format!(" layer({})", layer)),
+        None => {}
+      }
+      if let Some(supports) = &rule.supports {
+        output.push_str(&format!(" supports({})", compact(supports, CONDITION)));
+      }
+      if !rule.media.is_empty() {
+        output.push_str(&format!(" {}", compact(&rule.media, CONDITION)));
+      }
+      output.push(';');
+    }
+    Rule::Namespace(rule) => {
+      output.push_str("@namespace ");
+      if let Some(prefix) = &rule.prefix {
+        output.push_str(&format!("{} ", prefix));
+      }
+      output.push_str(&format!("{};", serialize_string(&rule.url)));
+    }
+    Rule::Charset(rule) => output.push_str(&format!("@charset {};", serialize_string(&rule.encoding))),
+    Rule::FontFace(rule) => write_descriptors("font-face", "", &rule.declarations, output),
+    Rule::Page(rule) => write_descriptors("page", &compact(&rule.selector, &[',']), &rule.declarations, output),
+    Rule::CounterStyle(rule) => write_descriptors("counter-style", &rule.name, &rule.declarations, output),
+    Rule::Property(rule) => write_descriptors("property", &rule.name, &rule.declarations, output),
+    Rule::FontPaletteValues(rule) => write_descriptors("font-palette-values", &rule.name, &rule.declarations, output),
+    Rule::ViewTransition(rule) => write_descriptors("view-transition", "", &rule.declarations, output),
+    Rule::Keyframes(rule) => {
+      output.push_str(&format!("@{}keyframes {}{{", rule.vendor_prefix.as_deref().unwrap_or(""), rule.name));
+      for keyframe in &rule.keyframes {
+        output.push_str(&keyframe_selector(&keyframe.selector));
+        output.push('{');
+        write_declarations(&keyframe.declarations, output);
+        output.push('}');
+      }
+      output.push('}');
+    }
+    Rule::Unknown(rule) => {
+      output.push_str(&format!("@{}", rule.name));
+      if !rule.prelude.is_empty() {
+        output.push_str(&format!(" {}", compact(&rule.prelude, &[])));
+      }
+      match &rule.block {
+        Some(block) => output.push_str(&format!("{{{}}}", compact(block, &[]))),
+        None => output.push(';'),
+      }
+    }
+  }
+}
+
+fn write_prelude(name: &str, prelude: &str, output: &mut String) {
+  output.push('@');
+  output.push_str(name);
+  if !prelude.is_empty() {
+    output.push(' ');
+    output.push_str(prelude);
+  }
+}
+
+fn write_group(name: &str, prelude: &str, rules: &[Rule], output: &mut String) {
+  write_prelude(name, prelude, output);
+  output.push('{');
+  write_rules(rules, output);
+  output.push('}');
+}
+
+fn write_descriptors(name: &str, prelude: &str, declarations: &CSSDeclarationList, output: &mut String) {
+  write_prelude(name, prelude, output);
+  output.push('{');
+  write_declarations(declarations, output);
+  output.push('}');
+}
+
+fn write_declarations(declarations: &CSSDeclarationList, output: &mut String) {
+  for (i, declaration) in declarations.declarations.iter().enumerate() {
+    if i > 0 {
+      output.push(';');
+    }
+    output.push_str(&declaration.name);
+    output.push(':');
+    output.push_str(&minify_value(&declaration.name, &declaration.value));
+    if declaration.important {
+      output.push_str("!important");
+    }
+  }
+}
+
+/// Writes a keyframe selector with `0%` for `from` and `to` for `100%`.
+fn keyframe_selector(selector: &str) -> String {
+  split_top_level(selector, ',')
+    .into_iter()
+    .map(|offset| match offset.to_ascii_lowercase().as_str() {
+      "from" => "0%",
+      "100%" => "to",
+      _ => offset,
+    })
+    .collect::<Vec<_>>()
+    .join(",")
+}
+
+/// Whether `overriding` sets the property of `declaration`: it is the same
+/// property, or a shorthand for it. Vendor-prefixed properties are distinct
+/// from the standard ones, as they are fallbacks for different browsers.
+fn sets_property(overriding: &CSSDeclaration, declaration: &CSSDeclaration) -> bool {
+  if overriding.name.starts_with("--") || declaration.name.starts_with("--") {
+    return overriding.name == declaration.name;
+  }
+  if overriding.name.eq_ignore_ascii_case(&declaration.name) {
+    return true;
+  }
+
+  let longhand = declaration.name.to_ascii_lowercase();
+  is_standard(&overriding.name)
+    && is_standard(&longhand)
+    && property(&overriding.name).is_some_and(|info| info.longhands.contains(&longhand.as_str()))
+}
+
+/// The keywords of a CSS 2.1 property, which every browser accepts for it,
+/// besides `inherit` and the named colors of the properties that take a
+/// color (see `takes_css2_color`). Empty for the other properties.
+fn css2_keywords(name: &str) -> Vec<&'static str> {
+  let keywords: &[&[&str]] = match name {
+    "display" => &[&[
+      "inline", "block", "list-item", "inline-block", "table", "inline-table", "table-row-group", "table-header-group",
+      "table-footer-group", "table-row", "table-column-group", "table-column", "table-cell", "table-caption", "none",
+    ]],
+    "position" => &[&["static", "relative", "absolute", "fixed"]],
+    "float" => &[&["left", "right", "none"]],
+    "clear" => &[&["none", "left", "right", "both"]],
+    "visibility" => &[&["visible", "hidden", "collapse"]],
+    "overflow" => &[&["visible", "hidden", "scroll", "auto"]],
+    "width" | "height" | "top" | "right" | "bottom" | "left" | "margin" | "margin-top" | "margin-right" | "margin-bottom"
+    | "margin-left" | "clip" | "z-index" => &[&["auto"]],
+    "table-layout" => &[&["auto", "fixed"]],
+    "max-width" | "max-height" => &[&["none"]],
+    "border-style" | "border-top-style" | "border-right-style" | "border-bottom-style" | "border-left-style" => &[CSS2_LINE_STYLES],
+    "border-width" | "border-top-width" | "border-right-width" | "border-bottom-width" | "border-left-width" => &[CSS2_LINE_WIDTHS],
+    "border-color" | "border-top-color" | "border-right-color" | "border-bottom-color" | "border-left-color" => &[&["transparent"]],
+    "border" | "border-top" | "border-right" | "border-bottom" | "border-left" => &[CSS2_LINE_STYLES, CSS2_LINE_WIDTHS, &["transparent"]],
+    "outline-style" => &[&CSS2_LINE_STYLES[..1], &CSS2_LINE_STYLES[2..]],
+    "outline-width" => &[CSS2_LINE_WIDTHS],
+    "outline-color" => &[&["invert"]],
+    "outline" => &[&CSS2_LINE_STYLES[..1], &CSS2_LINE_STYLES[2..], CSS2_LINE_WIDTHS, &["invert"]],
+    "background-color" => &[&["transparent"]],
+    "background-image" | "list-style-image" | "quotes" => &[&["none"]],
+    "background-repeat" => &[&["repeat", "repeat-x", "repeat-y", "no-repeat"]],
+    "background-attachment" => &[&["scroll", "fixed"]],
+    "background-position" => &[&["left", "right", "top", "bottom", "center"]],
+    "background" => &[&[
+      "transparent", "none", "repeat", "repeat-x", "repeat-y", "no-repeat", "scroll", "fixed", "left", "right", "top",
+      "bottom", "center",
+    ]],
+    "font-style" => &[&["normal", "italic", "oblique"]],
+    "font-variant" => &[&["normal", "small-caps"]],
+    "font-weight" => &[&["normal", "bold", "bolder", "lighter"]],
+    "font-size" => &[CSS2_FONT_SIZES],
+    "font" => &[
+      &["normal", "italic", "oblique", "small-caps", "bold", "bolder", "lighter"],
+      CSS2_FONT_SIZES,
+      CSS2_GENERIC_FAMILIES,
+      &["caption", "icon", "menu", "message-box", "small-caption", "status-bar"],
+    ],
+    "line-height" | "letter-spacing" | "word-spacing" => &[&["normal"]],
+    "text-align" => &[&["left", "right", "center", "justify"]],
+    "text-decoration" => &[&["none", "underline", "overline", "line-through", "blink"]],
+    "text-transform" => &[&["capitalize", "uppercase", "lowercase", "none"]],
+    "white-space" => &[&["normal", "pre", "nowrap", "pre-wrap", "pre-line"]],
+    "vertical-align" => &[&["baseline", "sub", "super", "top", "text-top", "middle", "bottom", "text-bottom"]],
+    "list-style-type" => &[CSS2_LIST_STYLE_TYPES],
+    "list-style-position" => &[&["inside", "outside"]],
+    "list-style" => &[CSS2_LIST_STYLE_TYPES, &["inside", "outside"]],
+    "cursor" => &[&[
+      "auto", "crosshair", "default", "pointer", "move", "e-resize", "ne-resize", "nw-resize", "n-resize", "se-resize",
+      "sw-resize", "s-resize", "w-resize", "text", "wait", "help", "progress",
+    ]],
+    "direction" => &[&["ltr", "rtl"]],
+    "unicode-bidi" => &[&["normal", "embed", "bidi-override"]],
+    "border-collapse" => &[&["collapse", "separate"]],
+    "empty-cells" => &[&["show", "hide"]],
+    "caption-side" => &[&["top", "bottom"]],
+    "content" => &[&["normal", "none", "open-quote", "close-quote", "no-open-quote", "no-close-quote"]],
+    "page-break-before" | "page-break-after" => &[&["auto", "always", "avoid", "left", "right"]],
+    "page-break-inside" => &[&["auto", "avoid"]],
+    _ => &[],
+  };
+  keywords.concat()
+}
+
+/// Whether a CSS 2.1 property takes a color, and so the CSS 2.1 named colors.
+fn takes_css2_color(name: &str) -> bool {
+  matches!(name, "color" | "background" | "background-color" | "outline" | "outline-color")
+    || name.starts_with("border") && (name.ends_with("color") || matches!(name, "border" | "border-top" | "border-right" | "border-bottom" | "border-left"))
+}
+
+/// Whether every browser accepts `value` once it supports the property
+/// `name`, so that a declaration it overrides cannot be a fallback for it.
+/// Only the keywords CSS 2.1 defines for the property are accepted, so
+/// `display: flow-root` or `width: fit-content` keep what they override.
+/// Functions other than `var()`, units newer than CSS 2 and hex colors with
+/// an alpha are left to their fallbacks too.
+fn is_widely_supported(name: &str, value: &str) -> bool {
+  fn supported(component: &ComponentValue, keyword: &dyn Fn(&str) -> bool) -> bool {
+    match component {
+      ComponentValue::Ident(name) => keyword(&name.to_ascii_lowercase()),
+      ComponentValue::Dimension { unit, .. } => CSS2_UNITS.contains(&unit.to_ascii_lowercase().as_str()),
+      ComponentValue::Hash(hash) => !matches!(hash.len(), 4 | 8) || !hash.chars().all(|c| c.is_ascii_hexdigit()),
+      ComponentValue::Function { name, arguments } => {
+        name.eq_ignore_ascii_case("var") && arguments.iter().all(|argument| supported(argument, keyword))
+      }
+      ComponentValue::Block { contents, .. } => contents.iter().all(|component| supported(component, keyword)),
+      _ => true,
+    }
+  }
+
+  let name = name.to_ascii_lowercase();
+  let (keywords, colors) = (css2_keywords(&name), takes_css2_color(&name));
+  let keyword = |keyword: &str| {
+    keyword == "inherit"
+      || keywords.contains(&keyword)
+      || colors && CSS2_COLORS.contains(&keyword)
+      // Any name is a valid font family
+      || name == "font-family" && !keyword.starts_with('-')
+  };
+  name.starts_with("--") || Value::from_string(value).is_ok_and(|value| value.components.iter().all(|component| supported(component, &keyword)))
+}
+
+/// Removes the declarations that another declaration of the list always wins
+/// over. See `CSSDeclarationList::remove_overridden()`.
+pub(crate) fn remove_overridden(declarations: &mut Vec<CSSDeclaration>) {
+  let overridden: Vec<bool> = {
+    let values: Vec<String> = declarations.iter().map(|declaration| minify_value(&declaration.name, &declaration.value)).collect();
+    let overrides = |winner: usize, loser: usize| {
+      let (overriding, declaration) = (&declarations[winner], &declarations[loser]);
+      let wins = match winner > loser {
+        true => overriding.important || !declaration.important,
+        false => overriding.important && !declaration.important,
+      };
+      let duplicate = overriding.name.eq_ignore_ascii_case(&declaration.name) && values[winner] == values[loser];
+      wins
+        && sets_property(overriding, declaration)
+        && (duplicate || is_widely_supported(&overriding.name, &overriding.value))
+    };
+    (0..declarations.len())
+      .map(|loser| (0..declarations.len()).any(|winner| winner != loser && overrides(winner, loser)))
+      .collect()
+  };
+
+  let mut overridden = overridden.into_iter();
+  declarations.retain(|_| !overridden.next().unwrap_or(false));
+}
+
+fn optimize_declarations(declarations: &mut CSSDeclarationList) {
+  declarations.remove_overridden();
+  declarations.collapse_shorthands();
+}
+
+/// Optimizes `rules` and their nested rules. See `Stylesheet::optimize()`.
+pub(crate) fn optimize_rules(rules: &mut Vec<Rule>) {
+  for_each_declaration_list_mut(rules, &mut optimize_declarations);
+  merge_rules(rules);
+}
+
+fn is_empty(rule: &Rule) -> bool {
+  match rule {
+    Rule::Style(rule) => rule.declarations.declarations.is_empty() && rule.rules.is_empty(),
+    Rule::NestedDeclarations(rule) => rule.declarations.declarations.is_empty(),
+    // An empty `@layer` block still declares the layer's place in the order
+    Rule::Media(_) | Rule::Supports(_) | Rule::Container(_) | Rule::Scope(_) | Rule::StartingStyle(_) => {
+      rule.child_rules().is_some_and(|rules| rules.is_empty())
+    }
+    _ => false,
+  }
+}
+
+fn merge_rules(rules: &mut Vec<Rule>) {
+  for rule in rules.iter_mut() {
+    if let Some(children) = rule.child_rules_mut() {
+      merge_rules(children);
+    }
+  }
+  rules.retain(|rule| !is_empty(rule));
+
+  let mut merged: Vec<Rule> = Vec::with_capacity(rules.len());
+  for rule in rules.drain(..) {
+    if let (Some(Rule::Style(previous)), Rule::Style(next)) = (merged.last_mut(), &rule)
+      && merge(previous, next)
+    {
+      continue;
+    }
+    merged.push(rule);
+  }
+  *rules = merged;
+}
+
+/// Merges `next` into the adjacent `previous` rule when they have the same
+/// selector or the same declarations. Returns whether they were merged.
+fn merge(previous: &mut CSSRule, next: &CSSRule) -> bool {
+  if !previous.rules.is_empty() {
+    return false;
+  }
+
+  if compact(&previous.selector, SELECTOR) == compact(&next.selector, SELECTOR) {
+    previous.declarations.declarations.extend(next.declarations.declarations.iter().cloned());
+    previous.rules = next.rules.clone();
+    previous.comments.extend(next.comments.iter().cloned());
+    *previous.span = None;
+    optimize_declarations(&mut previous.declarations);
+    return true;
+  }
+
+  if next.rules.is_empty() && same_declarations(&previous.declarations, &next.declarations) && can_join(previous) && can_join(next) {
+    previous.selector = format!("{}, {}", previous.selector, next.selector);
+    previous.comments.extend(next.comments.iter().cloned());
+    *previous.span = None;
+    *previous.selector_span = None;
+    return true;
+  }
+
+  false
+}
+
+fn same_declarations(first: &CSSDeclarationList, second: &CSSDeclarationList) -> bool {
+  first.declarations.len() == second.declarations.len()
+    && first.declarations.iter().zip(&second.declarations).all(|(first, second)| {
+      first.name == second.name
+        && first.important == second.important
+        && minify_value(&first.name, &first.value) == minify_value(&second.name, &second.value)
+    })
+}
+
+/// Whether the selector of `rule` can be joined into a selector list without
+/// the risk that a browser drops the whole list.
+fn can_join(rule: &CSSRule) -> bool {
+  rule.selector_list().is_ok() && !rule.selector.contains(":-")
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::stylesheet::Stylesheet;
+
+  fn minified(css: &str) -> String {
+    let minified = Stylesheet::from_string(css).unwrap().to_minified_string();
+    assert!(Stylesheet::from_string(&minified).is_ok(), "{}", minified);
+    minified
+  }
+
+  fn optimized(css: &str) -> String {
+    let mut stylesheet = Stylesheet::from_string(css).unwrap();
+    stylesheet.optimize();
+    stylesheet.to_minified_string()
+  }
+
+  fn remaining(css: &str) -> String {
+    let mut declarations = CSSDeclarationList::from_string(css).unwrap();
+    declarations.remove_overridden();
+    declarations.to_string()
+  }
+
+  #[test]
+  fn test_minify_value() {
+    assert_eq!(minify_value("margin", " 0px  auto "), "0 auto");
+    assert_eq!(minify_value("opacity", "0.50"), ".5");
+    assert_eq!(minify_value("margin", "-0.5em 10.0px"), "-.5em 10px");
+    assert_eq!(minify_value("transition", "opacity 0s, color 0.3s ease-in"), "opacity 0s,color .3s ease-in");
+    assert_eq!(minify_value("font", "italic 12px / 1.5 \"Open Sans\", serif"), "italic 12px/1.5 \"Open Sans\",serif");
+    assert_eq!(minify_value("width", "calc(100% - 0px)"), "calc(100% - 0px)");
+    assert_eq!(minify_value("flex", "1 1 0px"), "1 1 0px");
+    assert_eq!(minify_value("background", "url( 'a.png' ) no-repeat"), "url(a.png) no-repeat");
+    assert_eq!(minify_value("background", "url('a b.png')"), "url(\"a b.png\")");
+    assert_eq!(minify_value("--gap", "  0.50px "), "0.50px");
+    assert_eq!(minify_value("unicode-range", "U+0025-00FF, U+4??"), "U+0025-00FF,U+4??");
+  }
+
+  #[test]
+  fn test_minify_colors() {
+    assert_eq!(minify_value("color", "#FFFFFF"), "#fff");
+    assert_eq!(minify_value("color", "#ff0000"), "red");
+    assert_eq!(minify_value("color", "white"), "#fff");
+    assert_eq!(minify_value("color", "red"), "red");
+    assert_eq!(minify_value("color", "rgb(0, 128, 0)"), "green");
+    assert_eq!(minify_value("color", "rgba(0, 0, 0, 0)"), "#0000");
+    assert_eq!(minify_value("color", "rgba(0, 0, 0, 0.5)"), "rgba(0,0,0,.5)");
+    assert_eq!(minify_value("color", "hsl(0, 100%, 50%)"), "red");
+    assert_eq!(minify_value("color", "color(display-p3 1 0 0)"), "color(display-p3 1 0 0)");
+    assert_eq!(minify_value("border", "1px solid #aabbcc"), "1px solid #abc");
+    assert_eq!(minify_value("background-image", "linear-gradient(#ffffff, white)"), "linear-gradient(#fff,white)");
+    // Not a color in this property
+    assert_eq!(minify_value("animation-name", "white"), "white");
+  }
+
+  #[test]
+  fn test_minified_rules() {
+    assert_eq!(minified("a  >  b , c ~ d { color : red ; }"), "a>b,c~d{color:red}");
+    assert_eq!(minified("a b:nth-child( 2n + 1 ) { margin: 0 !important; }"), "a b:nth-child(2n+1){margin:0!important}");
+    assert_eq!(minified("a[title=\"x  y\"] { }"), "a[title=\"x  y\"]{}");
+    assert_eq!(
+      minified("@media screen and (min-width: 100px), print { a { color: red; } }"),
+      "@media screen and (min-width:100px),print{a{color:red}}"
+    );
+    assert_eq!(minified("a { color: red; &:hover { color: blue; } top: 0; b { top: 1px } }"), "a{color:red;&:hover{color:blue}top:0;b{top:1px}}");
+    assert_eq!(minified("@import url(\"a.css\") layer(base) screen; @layer a, b;"), "@import \"a.css\" layer(base) screen;@layer a,b;");
+    assert_eq!(
+      minified("@keyframes spin { from { opacity: 0; } 50%, 100% { opacity: 1; } }"),
+      "@keyframes spin{0%{opacity:0}50%,to{opacity:1}}"
+    );
+    assert_eq!(minified("@font-face { font-family: \"A\"; src: url(a.woff2) format(\"woff2\"); }"), "@font-face{font-family:\"A\";src:url(a.woff2) format(\"woff2\")}");
+    assert_eq!(minified("/* comment */ a { /* inner */ color: red; }"), "a{color:red}");
+  }
+
+  #[test]
+  fn test_remove_overridden() {
+    assert_eq!(remaining("color: red; color: blue"), "color: blue;");
+    assert_eq!(remaining("color: red !important; color: blue"), "color: red !important;");
+    assert_eq!(remaining("color: red; color: red; top: 0"), "color: red; top: 0;");
+    assert_eq!(remaining("margin-top: 1px; margin: 0"), "margin: 0;");
+    assert_eq!(remaining("margin: 0; margin-top: 1px"), "margin: 0; margin-top: 1px;");
+    assert_eq!(remaining("margin-top: 1px !important; margin: 0"), "margin-top: 1px !important; margin: 0;");
+    assert_eq!(remaining("--x: 1; --X: 2; --x: 3"), "--X: 2; --x: 3;");
+    // Fallbacks for browsers without support for the later value are kept
+    assert_eq!(remaining("color: #000; color: rgba(0, 0, 0, 0.5)"), "color: #000; color: rgba(0, 0, 0, 0.5);");
+    assert_eq!(remaining("display: box; display: -webkit-flex"), "display: box; display: -webkit-flex;");
+    assert_eq!(remaining("width: 10px; width: 10vw"), "width: 10px; width: 10vw;");
+    assert_eq!(remaining("-webkit-transform: none; transform: none"), "-webkit-transform: none; transform: none;");
+    for kept in [
+      "display: block; display: flow-root;",
+      "display: block; display: grid;",
+      "display: block; display: contents;",
+      "width: 100px; width: fit-content;",
+      "position: relative; position: sticky;",
+      "color: red; color: currentcolor;",
+      "margin: 0; margin: initial;",
+    ] {
+      assert_eq!(remaining(kept), kept);
+    }
+    assert_eq!(remaining("display: flex; display: block"), "display: block;");
+    assert_eq!(remaining("display: grid; display: grid"), "display: grid;");
+    assert_eq!(remaining("border: 1px solid; border: thin dotted navy"), "border: thin dotted navy;");
+    assert_eq!(remaining("font-family: serif; font-family: Arial, sans-serif"), "font-family: Arial, sans-serif;");
+    assert_eq!(remaining("width: 1px; width: inherit"), "width: inherit;");
+  }
+
+  #[test]
+  fn test_merge_rules() {
+    assert_eq!(optimized("a { color: red } a { top: 0; color: blue }"), "a{top:0;color:blue}");
+    assert_eq!(optimized("a { color: red } b { color: red } c { color: red }"), "a,b,c{color:red}");
+    assert_eq!(optimized("a { color: red } b { color: blue } a { color: blue }"), "a{color:red}b,a{color:blue}");
+    // Not adjacent: merging `a` rules would move `color: red` after `b`
+    assert_eq!(optimized("a { color: red } b { color: blue } a { top: 0 }"), "a{color:red}b{color:blue}a{top:0}");
+    // A vendor-prefixed or invalid selector would invalidate the whole list
+    assert_eq!(optimized("::-moz-selection { color: red } ::selection { color: red }"), "::-moz-selection{color:red}::selection{color:red}");
+    assert_eq!(optimized("a { } @media print { b { } } c { top: 0 }"), "c{top:0}");
+    assert_eq!(optimized("@layer base { } a { margin-top: 0; margin-right: 0; margin-bottom: 0; margin-left: 0 }"), "@layer base{}a{margin:0}");
+    // `font` would reset `font-kerning` and the other font properties the block does not set
+    let font = "a{font-style:normal;font-variant-caps:normal;font-weight:bold;font-stretch:normal;font-size:12px;line-height:normal;font-family:serif}";
+    assert_eq!(optimized(font), font);
+    // Collapsing into shorthands keeps the fallbacks `remove_overridden()` keeps
+    assert_eq!(
+      optimized("a { margin-top:1px; margin-right:1px; margin-bottom:1px; margin-left:1px; margin-left:2vw }"),
+      "a{margin-top:1px;margin-right:1px;margin-bottom:1px;margin-left:1px;margin-left:2vw}",
+    );
+    assert_eq!(optimized("a { display: block; display: flow-root }"), "a{display:block;display:flow-root}");
+    assert_eq!(optimized("@media print { a { color: red } a { color: red } }"), "@media print{a{color:red}}");
+  }
+}
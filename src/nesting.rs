@@ -0,0 +1,344 @@
+//! CSS Nesting Flattener
+//!
+//! This module lowers nested style rules (CSS Nesting Module) to the flat
+//! rules understood by browsers without nesting support. Nested selectors are
+//! resolved against their parent selector list, and conditional group rules
+//! nested in a style rule (`@media`, `@supports`, `@container`, `@layer`,
+//! `@scope`, `@starting-style`) are hoisted out of it, wrapping the rules
+//! generated for their contents. The order of the generated rules follows the
+//! source, so the cascade is unchanged.
+//!
+//! The nesting selector `&` is resolved in one of two ways (`NestingMode`):
+//!
+//! - `NestingMode::Is` replaces `&` with `:is(<parent list>)`, like the
+//!   specification defines it. When the parent is a single selector that can
+//!   be substituted without changing its meaning, it is substituted as-is.
+//! - `NestingMode::Expand` produces one selector per parent selector instead,
+//!   as preprocessors do, or one per combination of parents when a selector
+//!   has several `&`. `:is()` is only used where a parent cannot be
+//!   substituted as-is, such as `.x &` with a parent `.a .b`.
+//!
+//! ## Main API
+//!
+//! - `Stylesheet::flatten_nesting()` - Rewrite a stylesheet without nested rules
+//! - `flatten_rules()` - Flatten a list of rules nested in a parent selector list
+//! - `resolve_selector()` - Resolve a nested selector against its parents
+//!
+//! ## Examples
+//!
+//! ```rust
+//! use css_structs::{Stylesheet, NestingMode};
+//!
+//! let css = ".a, .b { color: red; &:hover { color: blue } @media print { color: black } }";
+//! let stylesheet = Stylesheet::from_string(css).unwrap();
+//!
+//! let flat = stylesheet.flatten_nesting(NestingMode::Is);
+//! assert_eq!(
+//!   flat.to_string(),
+//!   ".a, .b { color: red; } :is(.a, .b):hover { color: blue; } @media print { .a, .b { color: black; } }"
+//! );
+//!
+//! let flat = stylesheet.flatten_nesting(NestingMode::Expand);
+//! assert_eq!(flat.rules[1].to_string(), ".a:hover, .b:hover { color: blue; }");
+//! ```
+
+
+use crate::css_comment::CSSComment;
+use crate::css_declaration_list::CSSDeclarationList;
+use crate::css_rule::CSSRule;
+use crate::helpers::{is_css_whitespace, scan_until, split_top_level, string_len};
+use crate::rule::Rule;
+use crate::span::NodeSpan;
+
+
+/// How the nesting selector `&` is resolved against a parent selector list.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NestingMode {
+  /// `&` becomes `:is(<parent list>)`.
+  #[default]
+  Is,
+  /// Each parent selector is substituted for `&` in turn, and a selector
+  /// with several `&` gets one selector per combination of parents.
+  Expand,
+}
+
+
+/// Returns the byte offsets of the nesting selectors (`&`) in `selector`,
+/// skipping strings and escaped characters.
+fn nesting_offsets(selector: &str) -> Vec<usize> {
+  let mut offsets = Vec::new();
+  let mut offset = 0;
+
+  while let Some(found) = selector[offset..].find(['&', '"', '\'', '\\']) {
+    let at = offset + found;
+    let rest = &selector[at..];
+    offset = match rest.chars().next() {
+      Some('&') => {
+        offsets.push(at);
+        at + 1
+      }
+      Some('"' | '\'') => at + string_len(rest),
+      _ => at + 1 + rest[1..].chars().next().map_or(0, char::len_utf8),
+    };
+  }
+
+  offsets
+}
+
+/// Whether `selector` is a single compound selector, without combinators.
+fn is_compound(selector: &str) -> bool {
+  scan_until(selector, &[' ', '\t', '\n', '\r', '\x0C', '>', '+', '~']) == selector.len()
+}
+
+/// Replaces each `&` in `selector` with the parent at the same index in
+/// `parents`, falling back to `:is(parent)` where a plain substitution would
+/// change what the selector matches.
+fn substitute(selector: &str, parents: &[&str]) -> String {
+  let mut result = String::new();
+  let mut copied = 0;
+
+  for (offset, parent) in nesting_offsets(selector).into_iter().zip(parents) {
+    let compound = is_compound(parent);
+    let starts_with_type = !parent.starts_with(['.', '#', '[', ':', '&']);
+    let before = selector[..offset].trim_end_matches(is_css_whitespace);
+    let starts_compound = before.is_empty()
+      || before.len() < offset
+      || before.ends_with(['>', '+', '~', '(', ',']);
+    let plain = offset == 0 || (compound && (starts_compound || !starts_with_type));
+
+    result.push_str(&selector[copied..offset]);
+    if plain {
+      result.push_str(parent);
+    } else {
+      result.push_str(&format!(":is({})", parent));
+    }
+    copied = offset + 1;
+  }
+
+  result.push_str(&selector[copied..]);
+  result
+}
+
+/// Resolves a nested selector list against its parent selector list. A nested
+/// selector without `&` is relative to the parent, as if it started with `& `.
+pub fn resolve_selector(selector: &str, parents: &[String], mode: NestingMode) -> Vec<String> {
+  let parent_list = match parents {
+    [parent] => parent.clone(),
+    _ => format!(":is({})", parents.join(", ")),
+  };
+  let mut resolved = Vec::new();
+
+  for nested in split_top_level(selector, ',') {
+    let nested = if nesting_offsets(nested).is_empty() {
+      format!("& {}", nested)
+    } else {
+      nested.to_string()
+    };
+
+    let count = nesting_offsets(&nested).len();
+    match mode {
+      NestingMode::Is => resolved.push(substitute(&nested, &vec![parent_list.as_str(); count])),
+      NestingMode::Expand => {
+        resolved.extend(combinations(parents, count).iter().map(|combination| substitute(&nested, combination)))
+      }
+    }
+  }
+
+  resolved
+}
+
+/// Every way to pick one of `parents` for each of `count` nesting selectors,
+/// so that `& + &` in `a, b` gives `a + a`, `a + b`, `b + a` and `b + b`.
+fn combinations(parents: &[String], count: usize) -> Vec<Vec<&str>> {
+  let mut combinations = vec![Vec::new()];
+  for _ in 0..count {
+    combinations = combinations
+      .into_iter()
+      .flat_map(|combination: Vec<&str>| parents.iter().map(move |parent| {
+        let mut combination = combination.clone();
+        combination.push(parent.as_str());
+        combination
+      }))
+      .collect();
+  }
+  combinations
+}
+
+fn style_rule(selectors: &[String], declarations: &CSSDeclarationList, comments: &[CSSComment], span: NodeSpan) -> Rule {
+  Rule::Style(CSSRule {
+    comments: comments.to_vec(),
+    span,
+    ..CSSRule::new(&selectors.join(", "), declarations)
+  })
+}
+
+fn flatten_style_rule(rule: &CSSRule, parents: Option<&[String]>, mode: NestingMode) -> Vec<Rule> {
+  if rule.rules.is_empty() && parents.is_none() {
+    return vec![Rule::Style(rule.clone())];
+  }
+
+  let selectors = match parents {
+    Some(parents) => resolve_selector(&rule.selector, parents, mode),
+    None => split_top_level(&rule.selector, ',').into_iter().map(str::to_string).collect(),
+  };
+  let mut flat = Vec::new();
+
+  let keep_own_rule = !rule.declarations.declarations.is_empty() || rule.rules.is_empty();
+  if keep_own_rule {
+    flat.push(style_rule(&selectors, &rule.declarations, &rule.comments, rule.span));
+  }
+
+  for child in &rule.rules {
+    match child {
+      Rule::NestedDeclarations(nested) => {
+        flat.push(style_rule(&selectors, &nested.declarations, &nested.comments, rule.span));
+      }
+      child => flat.extend(flatten_rules(std::slice::from_ref(child), Some(&selectors), mode)),
+    }
+  }
+
+  // Without declarations of its own, the rule's comments go with the first rule generated for it
+  if let (false, Some(first)) = (keep_own_rule, flat.first_mut()) {
+    let own = std::mem::replace(first.comments_mut(), rule.comments.clone());
+    first.comments_mut().extend(own);
+  }
+
+  flat
+}
+
+/// Flattens `rules`, nested in the style rule with the `parents` selector list
+/// (`None` at the top level of a stylesheet).
+pub fn flatten_rules(rules: &[Rule], parents: Option<&[String]>, mode: NestingMode) -> Vec<Rule> {
+  let mut flat = Vec::new();
+
+  for rule in rules {
+    match (rule, parents) {
+      (Rule::Style(style), _) => flat.extend(flatten_style_rule(style, parents, mode)),
+      (Rule::NestedDeclarations(nested), Some(parents)) => {
+        flat.push(style_rule(parents, &nested.declarations, &nested.comments, NodeSpan(None)));
+      }
+      (rule, _) => {
+        let mut rule = rule.clone();
+        if let Some(children) = rule.child_rules_mut() {
+          *children = flatten_rules(children, parents, mode);
+        }
+        flat.push(rule);
+      }
+    }
+  }
+
+  flat
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::stylesheet::Stylesheet;
+
+  fn flatten(css: &str, mode: NestingMode) -> String {
+    Stylesheet::from_string(css).unwrap().flatten_nesting(mode).to_string()
+  }
+
+  fn parents(selectors: &[&str]) -> Vec<String> {
+    selectors.iter().map(|selector| selector.to_string()).collect()
+  }
+
+  #[test]
+  fn test_nesting_offsets() {
+    assert_eq!(nesting_offsets("&.a > &"), vec![0, 6]);
+    assert_eq!(nesting_offsets("[title='&'] \\& &"), vec![15]);
+    assert!(nesting_offsets(".a .b").is_empty());
+  }
+
+  #[test]
+  fn test_resolve_relative_selectors() {
+    let parent = parents(&[".card"]);
+    assert_eq!(resolve_selector(".title", &parent, NestingMode::Is), vec![".card .title"]);
+    assert_eq!(resolve_selector("> a, + b", &parent, NestingMode::Is), vec![".card > a", ".card + b"]);
+    assert_eq!(resolve_selector("&:hover", &parent, NestingMode::Is), vec![".card:hover"]);
+  }
+
+  #[test]
+  fn test_resolve_selector_modes() {
+    let list = parents(&[".a", ".b"]);
+    assert_eq!(resolve_selector("&:hover", &list, NestingMode::Is), vec![":is(.a, .b):hover"]);
+    assert_eq!(resolve_selector("&:hover", &list, NestingMode::Expand), vec![".a:hover", ".b:hover"]);
+    assert_eq!(resolve_selector("c, d", &list, NestingMode::Expand), vec![".a c", ".b c", ".a d", ".b d"]);
+  }
+
+  #[test]
+  fn test_expand_each_nesting_selector() {
+    let list = parents(&["a", "b"]);
+    assert_eq!(resolve_selector("& + &", &list, NestingMode::Expand), vec!["a + a", "a + b", "b + a", "b + b"]);
+    assert_eq!(resolve_selector("& + &", &list, NestingMode::Is), vec![":is(a, b) + :is(a, b)"]);
+    assert_eq!(flatten("a,b{&+&{top:0}}", NestingMode::Expand), "a+a, a+b, b+a, b+b { top: 0; }");
+  }
+
+  #[test]
+  fn test_resolve_keeps_meaning_of_complex_parents() {
+    let complex = parents(&[".x .y"]);
+    assert_eq!(resolve_selector("&.active", &complex, NestingMode::Is), vec![".x .y.active"]);
+    assert_eq!(resolve_selector(".z &", &complex, NestingMode::Is), vec![".z :is(.x .y)"]);
+    assert_eq!(resolve_selector(".z &", &complex, NestingMode::Expand), vec![".z :is(.x .y)"]);
+
+    let element = parents(&["div"]);
+    assert_eq!(resolve_selector(".z &", &element, NestingMode::Is), vec![".z div"]);
+    assert_eq!(resolve_selector(".b&", &element, NestingMode::Is), vec![".b:is(div)"]);
+    assert_eq!(resolve_selector("& + &", &parents(&[".a"]), NestingMode::Is), vec![".a + .a"]);
+  }
+
+  #[test]
+  fn test_flatten_nested_rules() {
+    let css = ".card { color: red; &:hover { color: blue } .title { font-weight: bold } }";
+    assert_eq!(
+      flatten(css, NestingMode::Is),
+      ".card { color: red; } .card:hover { color: blue; } .card .title { font-weight: bold; }"
+    );
+  }
+
+  #[test]
+  fn test_flatten_keeps_declaration_order() {
+    let css = "a { top: 0; & b { top: 1px } left: 0 }";
+    assert_eq!(flatten(css, NestingMode::Is), "a { top: 0; } a b { top: 1px; } a { left: 0; }");
+  }
+
+  #[test]
+  fn test_flatten_deep_nesting() {
+    let css = ".a { .b { .c, .d { top: 0 } } }";
+    assert_eq!(flatten(css, NestingMode::Expand), ".a .b .c, .a .b .d { top: 0; }");
+    assert_eq!(flatten(".a, .b { .c { .d { top: 0 } } }", NestingMode::Is), ":is(.a, .b) .c .d { top: 0; }");
+  }
+
+  #[test]
+  fn test_flatten_hoists_group_rules() {
+    let css = ".a { color: red; @media (min-width: 600px) { color: blue; @supports (display: grid) { & > b { display: grid } } } }";
+    assert_eq!(
+      flatten(css, NestingMode::Is),
+      ".a { color: red; } @media (min-width: 600px) { .a { color: blue; } @supports (display: grid) { .a > b { display: grid; } } }"
+    );
+  }
+
+  #[test]
+  fn test_flatten_inside_top_level_group_rules() {
+    let css = "@media print { .a { .b { top: 0 } } } @layer base { .c { & .d { top: 0 } } }";
+    assert_eq!(
+      flatten(css, NestingMode::Is),
+      "@media print { .a .b { top: 0; } } @layer base { .c .d { top: 0; } }"
+    );
+  }
+
+  #[test]
+  fn test_flatten_leaves_flat_stylesheets_unchanged() {
+    let css = "a, b { top: 0; } @import \"x.css\"; @media print { c {  } } @keyframes k { from { top: 0; } }";
+    let stylesheet = Stylesheet::from_string(css).unwrap();
+    assert_eq!(stylesheet.flatten_nesting(NestingMode::Expand), stylesheet);
+  }
+
+  #[test]
+  fn test_flatten_keeps_comments() {
+    let css = "/* card */ .card { /* nested */ & b { top: 0 } }";
+    let stylesheet = Stylesheet::from_string(css).unwrap().flatten_nesting(NestingMode::Is);
+    assert_eq!(format!("{:#}", stylesheet), "/* card */ /* nested */ .card b { top: 0; }");
+  }
+}
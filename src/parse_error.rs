@@ -0,0 +1,363 @@
+//! CSS Parse Errors
+//!
+//! This module provides the `ParseError` type returned by every `from_string`
+//! function. It records what kind of problem was found, where it was found
+//! (byte offset, line and column) and a short description of what the parser
+//! expected versus what it found.
+//!
+//! ## Examples
+//!
+//! ```rust
+//! use css_structs::{Stylesheet, ParseErrorKind};
+//!
+//! let error = Stylesheet::from_string("h1 { color: red }\np { color blue }").unwrap_err();
+//! assert_eq!(error.kind, ParseErrorKind::InvalidDeclaration);
+//! assert_eq!((error.line, error.column), (2, 11));
+//! assert_eq!(error.expected, "`:`");
+//! assert_eq!(error.found, "`blue`");
+//! println!("{}", error); // "2:11: invalid declaration: expected `:`, found `blue`"
+//! ```
+//!
+//! `from_string` is strict and stops at the first error. The `from_string_recovering`
+//! functions instead follow the CSS Syntax error recovery rules, skipping a bad
+//! rule up to the end of its block or a bad declaration up to the next `;`, and
+//! return every error they recorded along the way:
+//!
+//! ```rust
+//! use css_structs::{Stylesheet, ParseErrorKind};
+//!
+//! let (stylesheet, errors) = Stylesheet::from_string_recovering("a { color red; margin: 0 } { top: 0 } b { }");
+//! assert_eq!(stylesheet.to_string(), "a { margin: 0; } b {  }");
+//! assert_eq!(errors.len(), 2);
+//! assert_eq!(errors[0].found, "`red`");
+//! assert_eq!(errors[1].kind, ParseErrorKind::InvalidSelector);
+//! ```
+
+
+use std::cell::RefCell;
+use std::fmt;
+use nom::{
+  IResult,
+  error::{ErrorKind, ParseError as NomParseError},
+  Parser,
+};
+use crate::helpers::line_column;
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseErrorKind {
+  /// Something other than what the grammar allows at this point.
+  UnexpectedToken,
+  /// The input ended before the construct was complete, e.g. a missing `}`.
+  UnexpectedEndOfInput,
+  /// A `/*` without a matching `*/`.
+  UnterminatedComment,
+  /// A style rule without a valid selector.
+  InvalidSelector,
+  /// A declaration without a property name, a `:` or a value.
+  InvalidDeclaration,
+  /// An at-rule whose prelude or block does not match its grammar.
+  InvalidAtRule,
+  /// A value that does not match the grammar of its type, such as a malformed color.
+  InvalidValue,
+}
+
+impl fmt::Display for ParseErrorKind {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let description = match self {
+      ParseErrorKind::UnexpectedToken => "unexpected token",
+      ParseErrorKind::UnexpectedEndOfInput => "unexpected end of input",
+      ParseErrorKind::UnterminatedComment => "unterminated comment",
+      ParseErrorKind::InvalidSelector => "invalid selector",
+      ParseErrorKind::InvalidDeclaration => "invalid declaration",
+      ParseErrorKind::InvalidAtRule => "invalid at-rule",
+      ParseErrorKind::InvalidValue => "invalid value",
+    };
+
+    write!(f, "{}", description)
+  }
+}
+
+
+/// A parse failure. `offset` is a byte offset into the parsed string, while
+/// `line` and `column` are 1-based, with the column counted in characters.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+  pub kind: ParseErrorKind,
+  pub offset: usize,
+  pub line: usize,
+  pub column: usize,
+  pub expected: String,
+  pub found: String,
+}
+
+impl ParseError {
+  /// Describes the token at the start of `input` for the `found` field.
+  fn describe(input: &str) -> String {
+    let identifier_len = input
+      .find(|c: char| !(c.is_alphanumeric() || c == '-' || c == '_' || c as u32 > 127))
+      .unwrap_or(input.len());
+
+    match input.chars().next() {
+      None => "end of input".to_string(),
+      Some(_) if identifier_len > 0 => format!("`{}`", &input[..identifier_len]),
+      Some(c) if c.is_whitespace() => "whitespace".to_string(),
+      Some(c) => format!("`{}`", c),
+    }
+  }
+
+  pub(crate) fn new(source: &str, input: &str, kind: ParseErrorKind, expected: &str) -> Self {
+    let offset = source.len() - input.len();
+    let (line, column) = line_column(source, offset);
+
+    ParseError {
+      kind,
+      offset,
+      line,
+      column,
+      expected: expected.to_string(),
+      found: Self::describe(input),
+    }
+  }
+
+  pub(crate) fn from_nom(source: &str, error: nom::Err<SyntaxError<'_>>) -> Self {
+    match error {
+      nom::Err::Error(error) | nom::Err::Failure(error) => {
+        Self::new(source, error.input, error.kind, &error.expected)
+      }
+      nom::Err::Incomplete(_) => {
+        Self::new(source, "", ParseErrorKind::UnexpectedEndOfInput, "more input")
+      }
+    }
+  }
+}
+
+impl fmt::Display for ParseError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{}:{}: {}: expected {}, found {}", self.line, self.column, self.kind, self.expected, self.found)
+  }
+}
+
+impl std::error::Error for ParseError {}
+
+
+/// The error type threaded through the internal nom parsers. It keeps the
+/// remaining input at the point of failure, which `ParseError` turns into a
+/// position once the full source is known.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct SyntaxError<'a> {
+  pub input: &'a str,
+  pub kind: ParseErrorKind,
+  pub expected: String,
+}
+
+pub(crate) type ParseResult<'a, T> = IResult<&'a str, T, SyntaxError<'a>>;
+
+impl<'a> SyntaxError<'a> {
+  pub fn new(input: &'a str, kind: ParseErrorKind, expected: &str) -> Self {
+    let kind = if input.is_empty() && kind == ParseErrorKind::UnexpectedToken {
+      ParseErrorKind::UnexpectedEndOfInput
+    } else {
+      kind
+    };
+
+    SyntaxError {
+      input,
+      kind,
+      expected: expected.to_string(),
+    }
+  }
+
+  /// A recoverable error, letting combinators such as `alt` and `many0` try something else.
+  pub fn error<T>(input: &'a str, kind: ParseErrorKind, expected: &str) -> ParseResult<'a, T> {
+    Err(nom::Err::Error(Self::new(input, kind, expected)))
+  }
+
+  /// An unrecoverable error, reported as-is by `from_string`.
+  pub fn failure<T>(input: &'a str, kind: ParseErrorKind, expected: &str) -> ParseResult<'a, T> {
+    Err(nom::Err::Failure(Self::new(input, kind, expected)))
+  }
+}
+
+impl<'a> NomParseError<&'a str> for SyntaxError<'a> {
+  fn from_error_kind(input: &'a str, kind: ErrorKind) -> Self {
+    let expected = match kind {
+      ErrorKind::Tag => "a keyword",
+      ErrorKind::TakeWhile1 | ErrorKind::AlphaNumeric | ErrorKind::Alpha => "an identifier",
+      ErrorKind::Eof => "end of input",
+      _ => "valid CSS",
+    };
+
+    SyntaxError::new(input, ParseErrorKind::UnexpectedToken, expected)
+  }
+
+  fn from_char(input: &'a str, c: char) -> Self {
+    SyntaxError::new(input, ParseErrorKind::UnexpectedToken, &format!("`{}`", c))
+  }
+
+  fn append(_: &'a str, _: ErrorKind, other: Self) -> Self {
+    other
+  }
+
+  /// Keeps the error that got further into the input.
+  fn or(self, other: Self) -> Self {
+    if other.input.len() < self.input.len() { other } else { self }
+  }
+}
+
+/// Replaces the error of `parser` with one of `kind`, describing what was `expected`.
+pub(crate) fn expect<'a, O, P>(
+  kind: ParseErrorKind,
+  expected: &'static str,
+  mut parser: P,
+) -> impl Parser<&'a str, Output = O, Error = SyntaxError<'a>>
+where
+  P: Parser<&'a str, Output = O, Error = SyntaxError<'a>>,
+{
+  move |input: &'a str| {
+    parser.parse(input).map_err(|error| error.map(|error| SyntaxError::new(error.input, kind, expected)))
+  }
+}
+
+
+/// An error recorded in recovery mode. Only the length of the remaining input
+/// is kept, as the error outlives the input slice it was found in.
+struct RecoveredError {
+  remaining: usize,
+  kind: ParseErrorKind,
+  expected: String,
+  found: String,
+}
+
+thread_local! {
+  /// The errors recorded so far, or `None` when parsing strictly.
+  static RECOVERED: RefCell<Option<Vec<RecoveredError>>> = const { RefCell::new(None) };
+}
+
+/// Whether the parsers should record errors and skip the invalid input instead of failing.
+pub(crate) fn recovering() -> bool {
+  RECOVERED.with(|recovered| recovered.borrow().is_some())
+}
+
+/// Records `error` so that parsing can continue past it.
+pub(crate) fn record(error: SyntaxError<'_>) {
+  RECOVERED.with(|recovered| {
+    if let Some(errors) = recovered.borrow_mut().as_mut() {
+      errors.push(RecoveredError {
+        remaining: error.input.len(),
+        found: ParseError::describe(error.input),
+        kind: error.kind,
+        expected: error.expected,
+      });
+    }
+  });
+}
+
+/// Handles a block left open at the end of the input, which fails strictly
+/// but is closed implicitly in recovery mode.
+pub(crate) fn close_at_end<T>(input: &str, block: T) -> ParseResult<'_, T> {
+  let error = SyntaxError::new(input, ParseErrorKind::UnexpectedEndOfInput, "`}`");
+  if !recovering() {
+    return Err(nom::Err::Failure(error));
+  }
+
+  record(error);
+  Ok((input, block))
+}
+
+/// Runs `parse` over `source` in recovery mode and returns its result together
+/// with the recorded errors, ordered by position.
+pub(crate) fn with_recovery<T>(source: &str, parse: impl FnOnce() -> T) -> (T, Vec<ParseError>) {
+  let outer = RECOVERED.with(|recovered| recovered.replace(Some(Vec::new())));
+  let result = parse();
+  let recovered = RECOVERED.with(|recovered| recovered.replace(outer)).unwrap_or_default();
+
+  let mut errors = recovered
+    .into_iter()
+    .map(|error| {
+      let offset = source.len() - error.remaining;
+      let (line, column) = line_column(source, offset);
+      ParseError { kind: error.kind, offset, line, column, expected: error.expected, found: error.found }
+    })
+    .collect::<Vec<_>>();
+  errors.sort_by_key(|error| error.offset);
+
+  (result, errors)
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_new_computes_position() {
+    let source = "a {\n  color red;\n}";
+    let error = ParseError::new(source, &source[12..], ParseErrorKind::InvalidDeclaration, "`:`");
+    assert_eq!(error.offset, 12);
+    assert_eq!(error.line, 2);
+    assert_eq!(error.column, 9);
+    assert_eq!(error.found, "`red`");
+  }
+
+  #[test]
+  fn test_describe_found() {
+    assert_eq!(ParseError::describe(""), "end of input");
+    assert_eq!(ParseError::describe("}"), "`}`");
+    assert_eq!(ParseError::describe("  x"), "whitespace");
+    assert_eq!(ParseError::describe("font-size: 1px"), "`font-size`");
+  }
+
+  #[test]
+  fn test_display() {
+    let error = ParseError::new("a { b }", "}", ParseErrorKind::InvalidDeclaration, "`:`");
+    assert_eq!(error.to_string(), "1:7: invalid declaration: expected `:`, found `}`");
+  }
+
+  #[test]
+  fn test_error_is_std_error() {
+    let error: Box<dyn std::error::Error> = Box::new(ParseError::new("", "", ParseErrorKind::UnexpectedEndOfInput, "`{`"));
+    assert_eq!(error.to_string(), "1:1: unexpected end of input: expected `{`, found end of input");
+  }
+
+  #[test]
+  fn test_or_keeps_furthest_error() {
+    let source = "abcdef";
+    let near = SyntaxError::new(&source[1..], ParseErrorKind::UnexpectedToken, "x");
+    let far = SyntaxError::new(&source[4..], ParseErrorKind::UnexpectedToken, "y");
+    assert_eq!(near.clone().or(far.clone()), far);
+    assert_eq!(far.clone().or(near), far);
+  }
+
+  #[test]
+  fn test_end_of_input_kind() {
+    let error = SyntaxError::new("", ParseErrorKind::UnexpectedToken, "`}`");
+    assert_eq!(error.kind, ParseErrorKind::UnexpectedEndOfInput);
+  }
+
+  #[test]
+  fn test_with_recovery_records_errors() {
+    let source = "a {\n  b c\n}";
+    assert!(!recovering());
+    let (value, errors) = with_recovery(source, || {
+      assert!(recovering());
+      record(SyntaxError::new(&source[8..], ParseErrorKind::InvalidDeclaration, "`:`"));
+      record(SyntaxError::new(&source[3..], ParseErrorKind::UnexpectedToken, "`}`"));
+      42
+    });
+    assert!(!recovering());
+    assert_eq!(value, 42);
+    assert_eq!(errors.len(), 2);
+    assert_eq!((errors[0].offset, errors[0].line, errors[0].column), (3, 1, 4));
+    assert_eq!(errors[0].found, "whitespace");
+    assert_eq!((errors[1].offset, errors[1].line, errors[1].column), (8, 2, 5));
+    assert_eq!(errors[1].found, "`c`");
+  }
+
+  #[test]
+  fn test_record_is_ignored_when_strict() {
+    record(SyntaxError::new("x", ParseErrorKind::UnexpectedToken, "`}`"));
+    let (_, errors) = with_recovery("", || ());
+    assert!(errors.is_empty());
+  }
+}
@@ -0,0 +1,737 @@
+//! CSS Property Database
+//!
+//! This module provides a built-in registry of standard CSS properties, keyed
+//! by the name used in `CSSDeclaration.name`. For each property it records:
+//!
+//! - whether it is a shorthand, and the longhands it expands to
+//! - whether it is inherited, and its initial value (for longhands)
+//! - whether it can be animated (including discretely)
+//! - whether it is deprecated
+//!
+//! Vendor-prefixed and legacy aliases, such as `-webkit-transform` or
+//! `word-wrap`, resolve to their standard counterpart. `computed_style` uses
+//! the inherited flag and initial value to fill in values the cascade did not
+//! set.
+//!
+//! ## Main API
+//!
+//! - `property()` - Look up a property or alias by name (ASCII case-insensitive)
+//! - `standard_name()` - The standard name of a property or alias
+//! - `is_standard()` / `is_deprecated()` - Classify a property name
+//! - `properties()` - Every property in the registry
+//!
+//! ## Examples
+//!
+//! ```rust
+//! use css_structs::properties::{is_deprecated, is_standard, property, standard_name};
+//!
+//! let color = property("color").unwrap();
+//! assert!(color.inherited && color.animatable);
+//! assert_eq!(color.initial, "canvastext");
+//!
+//! let margin = property("margin").unwrap();
+//! assert!(margin.shorthand);
+//! assert_eq!(margin.longhands, ["margin-top", "margin-right", "margin-bottom", "margin-left"]);
+//!
+//! // Aliases resolve to the standard property
+//! assert_eq!(standard_name("-webkit-box-flex"), Some("flex-grow"));
+//! assert_eq!(property("-webkit-transform").unwrap().name, "transform");
+//! assert!(!is_standard("-webkit-transform") && is_deprecated("-webkit-transform"));
+//!
+//! assert!(is_deprecated("clip"));
+//! assert!(property("--brand").is_none());
+//! ```
+
+
+/// Metadata of a CSS property.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PropertyInfo {
+  pub name: &'static str,
+  /// Whether the property is a shorthand for `longhands`. `all` is a
+  /// shorthand for every property but `direction` and `unicode-bidi`, which
+  /// are not listed.
+  pub shorthand: bool,
+  pub longhands: &'static [&'static str],
+  /// Whether the property takes its parent's value when not set. For a
+  /// shorthand, whether its longhands are inherited.
+  pub inherited: bool,
+  /// The initial value, as CSS text. Empty for shorthands.
+  pub initial: &'static str,
+  /// Whether the property can be animated or transitioned, including
+  /// discretely.
+  pub animatable: bool,
+  pub deprecated: bool,
+}
+
+const fn longhand(name: &'static str, inherited: bool, initial: &'static str) -> PropertyInfo {
+  PropertyInfo { name, shorthand: false, longhands: &[], inherited, initial, animatable: true, deprecated: false }
+}
+
+const fn inherited(name: &'static str, initial: &'static str) -> PropertyInfo {
+  longhand(name, true, initial)
+}
+
+const fn reset(name: &'static str, initial: &'static str) -> PropertyInfo {
+  longhand(name, false, initial)
+}
+
+const fn shorthand(name: &'static str, longhands: &'static [&'static str]) -> PropertyInfo {
+  PropertyInfo { name, shorthand: true, longhands, inherited: false, initial: "", animatable: true, deprecated: false }
+}
+
+const fn inherited_shorthand(name: &'static str, longhands: &'static [&'static str]) -> PropertyInfo {
+  PropertyInfo { inherited: true, ..shorthand(name, longhands) }
+}
+
+impl PropertyInfo {
+  const fn not_animatable(self) -> Self {
+    PropertyInfo { animatable: false, ..self }
+  }
+
+  const fn deprecated(self) -> Self {
+    PropertyInfo { deprecated: true, ..self }
+  }
+}
+
+/// The property table, sorted by name.
+static PROPERTIES: &[PropertyInfo] = &[
+  reset("accent-color", "auto"),
+  reset("align-content", "normal"),
+  reset("align-items", "normal"),
+  reset("align-self", "auto"),
+  shorthand("all", &[]).not_animatable(),
+  shorthand("animation", &["animation-duration", "animation-timing-function", "animation-delay", "animation-iteration-count", "animation-direction", "animation-fill-mode", "animation-play-state", "animation-name"]).not_animatable(),
+  reset("animation-composition", "replace").not_animatable(),
+  reset("animation-delay", "0s").not_animatable(),
+  reset("animation-direction", "normal").not_animatable(),
+  reset("animation-duration", "0s").not_animatable(),
+  reset("animation-fill-mode", "none").not_animatable(),
+  reset("animation-iteration-count", "1").not_animatable(),
+  reset("animation-name", "none").not_animatable(),
+  reset("animation-play-state", "running").not_animatable(),
+  reset("animation-timeline", "auto").not_animatable(),
+  reset("animation-timing-function", "ease").not_animatable(),
+  reset("appearance", "none"),
+  reset("aspect-ratio", "auto"),
+  reset("backdrop-filter", "none"),
+  reset("backface-visibility", "visible"),
+  shorthand("background", &["background-image", "background-position-x", "background-position-y", "background-size", "background-repeat", "background-attachment", "background-origin", "background-clip", "background-color"]),
+  reset("background-attachment", "scroll"),
+  reset("background-blend-mode", "normal"),
+  reset("background-clip", "border-box"),
+  reset("background-color", "transparent"),
+  reset("background-image", "none"),
+  reset("background-origin", "padding-box"),
+  shorthand("background-position", &["background-position-x", "background-position-y"]),
+  reset("background-position-x", "0%"),
+  reset("background-position-y", "0%"),
+  reset("background-repeat", "repeat"),
+  reset("background-size", "auto"),
+  reset("block-size", "auto"),
+  shorthand("border", &["border-top-width", "border-right-width", "border-bottom-width", "border-left-width", "border-top-style", "border-right-style", "border-bottom-style", "border-left-style", "border-top-color", "border-right-color", "border-bottom-color", "border-left-color", "border-image-source", "border-image-slice", "border-image-width", "border-image-outset", "border-image-repeat"]),
+  shorthand("border-block", &["border-block-start-width", "border-block-end-width", "border-block-start-style", "border-block-end-style", "border-block-start-color", "border-block-end-color"]),
+  shorthand("border-block-color", &["border-block-start-color", "border-block-end-color"]),
+  shorthand("border-block-end", &["border-block-end-width", "border-block-end-style", "border-block-end-color"]),
+  reset("border-block-end-color", "currentcolor"),
+  reset("border-block-end-style", "none"),
+  reset("border-block-end-width", "medium"),
+  shorthand("border-block-start", &["border-block-start-width", "border-block-start-style", "border-block-start-color"]),
+  reset("border-block-start-color", "currentcolor"),
+  reset("border-block-start-style", "none"),
+  reset("border-block-start-width", "medium"),
+  shorthand("border-block-style", &["border-block-start-style", "border-block-end-style"]),
+  shorthand("border-block-width", &["border-block-start-width", "border-block-end-width"]),
+  shorthand("border-bottom", &["border-bottom-width", "border-bottom-style", "border-bottom-color"]),
+  reset("border-bottom-color", "currentcolor"),
+  reset("border-bottom-left-radius", "0"),
+  reset("border-bottom-right-radius", "0"),
+  reset("border-bottom-style", "none"),
+  reset("border-bottom-width", "medium"),
+  inherited("border-collapse", "separate"),
+  shorthand("border-color", &["border-top-color", "border-right-color", "border-bottom-color", "border-left-color"]),
+  reset("border-end-end-radius", "0"),
+  reset("border-end-start-radius", "0"),
+  shorthand("border-image", &["border-image-source", "border-image-slice", "border-image-width", "border-image-outset", "border-image-repeat"]),
+  reset("border-image-outset", "0"),
+  reset("border-image-repeat", "stretch"),
+  reset("border-image-slice", "100%"),
+  reset("border-image-source", "none"),
+  reset("border-image-width", "1"),
+  shorthand("border-inline", &["border-inline-start-width", "border-inline-end-width", "border-inline-start-style", "border-inline-end-style", "border-inline-start-color", "border-inline-end-color"]),
+  shorthand("border-inline-color", &["border-inline-start-color", "border-inline-end-color"]),
+  shorthand("border-inline-end", &["border-inline-end-width", "border-inline-end-style", "border-inline-end-color"]),
+  reset("border-inline-end-color", "currentcolor"),
+  reset("border-inline-end-style", "none"),
+  reset("border-inline-end-width", "medium"),
+  shorthand("border-inline-start", &["border-inline-start-width", "border-inline-start-style", "border-inline-start-color"]),
+  reset("border-inline-start-color", "currentcolor"),
+  reset("border-inline-start-style", "none"),
+  reset("border-inline-start-width", "medium"),
+  shorthand("border-inline-style", &["border-inline-start-style", "border-inline-end-style"]),
+  shorthand("border-inline-width", &["border-inline-start-width", "border-inline-end-width"]),
+  shorthand("border-left", &["border-left-width", "border-left-style", "border-left-color"]),
+  reset("border-left-color", "currentcolor"),
+  reset("border-left-style", "none"),
+  reset("border-left-width", "medium"),
+  shorthand("border-radius", &["border-top-left-radius", "border-top-right-radius", "border-bottom-right-radius", "border-bottom-left-radius"]),
+  shorthand("border-right", &["border-right-width", "border-right-style", "border-right-color"]),
+  reset("border-right-color", "currentcolor"),
+  reset("border-right-style", "none"),
+  reset("border-right-width", "medium"),
+  inherited("border-spacing", "0"),
+  reset("border-start-end-radius", "0"),
+  reset("border-start-start-radius", "0"),
+  shorthand("border-style", &["border-top-style", "border-right-style", "border-bottom-style", "border-left-style"]),
+  shorthand("border-top", &["border-top-width", "border-top-style", "border-top-color"]),
+  reset("border-top-color", "currentcolor"),
+  reset("border-top-left-radius", "0"),
+  reset("border-top-right-radius", "0"),
+  reset("border-top-style", "none"),
+  reset("border-top-width", "medium"),
+  shorthand("border-width", &["border-top-width", "border-right-width", "border-bottom-width", "border-left-width"]),
+  reset("bottom", "auto"),
+  reset("box-decoration-break", "slice"),
+  reset("box-shadow", "none"),
+  reset("box-sizing", "content-box"),
+  reset("break-after", "auto"),
+  reset("break-before", "auto"),
+  reset("break-inside", "auto"),
+  inherited("caption-side", "top"),
+  inherited("caret-color", "auto"),
+  reset("clear", "none"),
+  reset("clip", "auto").deprecated(),
+  reset("clip-path", "none"),
+  inherited("clip-rule", "nonzero"),
+  inherited("color", "canvastext"),
+  inherited("color-interpolation", "srgb"),
+  inherited("color-scheme", "normal"),
+  reset("column-count", "auto"),
+  reset("column-fill", "balance"),
+  reset("column-gap", "normal"),
+  shorthand("column-rule", &["column-rule-width", "column-rule-style", "column-rule-color"]),
+  reset("column-rule-color", "currentcolor"),
+  reset("column-rule-style", "none"),
+  reset("column-rule-width", "medium"),
+  reset("column-span", "none"),
+  reset("column-width", "auto"),
+  shorthand("columns", &["column-width", "column-count"]),
+  reset("contain", "none"),
+  shorthand("container", &["container-name", "container-type"]).not_animatable(),
+  reset("container-name", "none").not_animatable(),
+  reset("container-type", "normal").not_animatable(),
+  reset("content", "normal"),
+  reset("content-visibility", "visible"),
+  reset("counter-increment", "none"),
+  reset("counter-reset", "none"),
+  reset("counter-set", "none"),
+  inherited("cursor", "auto"),
+  inherited("direction", "ltr").not_animatable(),
+  reset("display", "inline"),
+  inherited("dominant-baseline", "auto"),
+  inherited("empty-cells", "show"),
+  inherited("fill", "black"),
+  inherited("fill-opacity", "1"),
+  inherited("fill-rule", "nonzero"),
+  reset("filter", "none"),
+  shorthand("flex", &["flex-grow", "flex-shrink", "flex-basis"]),
+  reset("flex-basis", "auto"),
+  reset("flex-direction", "row"),
+  shorthand("flex-flow", &["flex-direction", "flex-wrap"]),
+  reset("flex-grow", "0"),
+  reset("flex-shrink", "1"),
+  reset("flex-wrap", "nowrap"),
+  reset("float", "none"),
+  reset("flood-color", "black"),
+  reset("flood-opacity", "1"),
+  inherited_shorthand("font", &["font-style", "font-variant-caps", "font-weight", "font-stretch", "font-size", "line-height", "font-family", "font-variant-ligatures", "font-variant-alternates", "font-variant-numeric", "font-variant-east-asian", "font-variant-position", "font-variant-emoji", "font-size-adjust", "font-kerning", "font-language-override", "font-optical-sizing", "font-feature-settings", "font-variation-settings"]),
+  inherited("font-family", "serif"),
+  inherited("font-feature-settings", "normal"),
+  inherited("font-kerning", "auto"),
+  inherited("font-language-override", "normal"),
+  inherited("font-optical-sizing", "auto"),
+  inherited("font-palette", "normal"),
+  inherited("font-size", "medium"),
+  inherited("font-size-adjust", "none"),
+  inherited("font-stretch", "normal"),
+  inherited("font-style", "normal"),
+  inherited_shorthand("font-synthesis", &["font-synthesis-weight", "font-synthesis-style", "font-synthesis-small-caps", "font-synthesis-position"]),
+  inherited("font-synthesis-position", "auto"),
+  inherited("font-synthesis-small-caps", "auto"),
+  inherited("font-synthesis-style", "auto"),
+  inherited("font-synthesis-weight", "auto"),
+  inherited_shorthand("font-variant", &["font-variant-ligatures", "font-variant-caps", "font-variant-alternates", "font-variant-numeric", "font-variant-east-asian", "font-variant-position", "font-variant-emoji"]),
+  inherited("font-variant-alternates", "normal"),
+  inherited("font-variant-caps", "normal"),
+  inherited("font-variant-east-asian", "normal"),
+  inherited("font-variant-emoji", "normal"),
+  inherited("font-variant-ligatures", "normal"),
+  inherited("font-variant-numeric", "normal"),
+  inherited("font-variant-position", "normal"),
+  inherited("font-variation-settings", "normal"),
+  inherited("font-weight", "normal"),
+  inherited("forced-color-adjust", "auto"),
+  shorthand("gap", &["row-gap", "column-gap"]),
+  shorthand("grid", &["grid-template-rows", "grid-template-columns", "grid-template-areas", "grid-auto-rows", "grid-auto-columns", "grid-auto-flow"]),
+  shorthand("grid-area", &["grid-row-start", "grid-column-start", "grid-row-end", "grid-column-end"]),
+  reset("grid-auto-columns", "auto"),
+  reset("grid-auto-flow", "row"),
+  reset("grid-auto-rows", "auto"),
+  shorthand("grid-column", &["grid-column-start", "grid-column-end"]),
+  reset("grid-column-end", "auto"),
+  reset("grid-column-start", "auto"),
+  shorthand("grid-row", &["grid-row-start", "grid-row-end"]),
+  reset("grid-row-end", "auto"),
+  reset("grid-row-start", "auto"),
+  shorthand("grid-template", &["grid-template-rows", "grid-template-columns", "grid-template-areas"]),
+  reset("grid-template-areas", "none"),
+  reset("grid-template-columns", "none"),
+  reset("grid-template-rows", "none"),
+  inherited("hanging-punctuation", "none"),
+  reset("height", "auto"),
+  inherited("hyphens", "manual"),
+  inherited("image-orientation", "from-image"),
+  inherited("image-rendering", "auto"),
+  reset("inline-size", "auto"),
+  shorthand("inset", &["top", "right", "bottom", "left"]),
+  shorthand("inset-block", &["inset-block-start", "inset-block-end"]),
+  reset("inset-block-end", "auto"),
+  reset("inset-block-start", "auto"),
+  shorthand("inset-inline", &["inset-inline-start", "inset-inline-end"]),
+  reset("inset-inline-end", "auto"),
+  reset("inset-inline-start", "auto"),
+  reset("isolation", "auto"),
+  reset("justify-content", "normal"),
+  reset("justify-items", "legacy"),
+  reset("justify-self", "auto"),
+  reset("left", "auto"),
+  inherited("letter-spacing", "normal"),
+  reset("lighting-color", "white"),
+  inherited("line-break", "auto"),
+  inherited("line-height", "normal"),
+  inherited_shorthand("list-style", &["list-style-position", "list-style-image", "list-style-type"]),
+  inherited("list-style-image", "none"),
+  inherited("list-style-position", "outside"),
+  inherited("list-style-type", "disc"),
+  shorthand("margin", &["margin-top", "margin-right", "margin-bottom", "margin-left"]),
+  shorthand("margin-block", &["margin-block-start", "margin-block-end"]),
+  reset("margin-block-end", "0"),
+  reset("margin-block-start", "0"),
+  reset("margin-bottom", "0"),
+  shorthand("margin-inline", &["margin-inline-start", "margin-inline-end"]),
+  reset("margin-inline-end", "0"),
+  reset("margin-inline-start", "0"),
+  reset("margin-left", "0"),
+  reset("margin-right", "0"),
+  reset("margin-top", "0"),
+  inherited_shorthand("marker", &["marker-start", "marker-mid", "marker-end"]),
+  inherited("marker-end", "none"),
+  inherited("marker-mid", "none"),
+  inherited("marker-start", "none"),
+  shorthand("mask", &["mask-image", "mask-mode", "mask-position", "mask-size", "mask-repeat", "mask-origin", "mask-clip", "mask-composite"]),
+  reset("mask-clip", "border-box"),
+  reset("mask-composite", "add"),
+  reset("mask-image", "none"),
+  reset("mask-mode", "match-source"),
+  reset("mask-origin", "border-box"),
+  reset("mask-position", "0% 0%"),
+  reset("mask-repeat", "repeat"),
+  reset("mask-size", "auto"),
+  reset("mask-type", "luminance"),
+  inherited("math-depth", "0"),
+  inherited("math-style", "normal"),
+  reset("max-block-size", "none"),
+  reset("max-height", "none"),
+  reset("max-inline-size", "none"),
+  reset("max-width", "none"),
+  reset("min-block-size", "auto"),
+  reset("min-height", "auto"),
+  reset("min-inline-size", "auto"),
+  reset("min-width", "auto"),
+  reset("mix-blend-mode", "normal"),
+  reset("object-fit", "fill"),
+  reset("object-position", "50% 50%"),
+  shorthand("offset", &["offset-position", "offset-path", "offset-distance", "offset-rotate", "offset-anchor"]),
+  reset("offset-anchor", "auto"),
+  reset("offset-distance", "0"),
+  reset("offset-path", "none"),
+  reset("offset-position", "normal"),
+  reset("offset-rotate", "auto"),
+  reset("opacity", "1"),
+  reset("order", "0"),
+  inherited("orphans", "2"),
+  shorthand("outline", &["outline-color", "outline-style", "outline-width"]),
+  reset("outline-color", "auto"),
+  reset("outline-offset", "0"),
+  reset("outline-style", "none"),
+  reset("outline-width", "medium"),
+  shorthand("overflow", &["overflow-x", "overflow-y"]),
+  reset("overflow-anchor", "auto"),
+  reset("overflow-clip-margin", "0px"),
+  inherited("overflow-wrap", "normal"),
+  reset("overflow-x", "visible"),
+  reset("overflow-y", "visible"),
+  shorthand("overscroll-behavior", &["overscroll-behavior-x", "overscroll-behavior-y"]),
+  reset("overscroll-behavior-x", "auto"),
+  reset("overscroll-behavior-y", "auto"),
+  shorthand("padding", &["padding-top", "padding-right", "padding-bottom", "padding-left"]),
+  shorthand("padding-block", &["padding-block-start", "padding-block-end"]),
+  reset("padding-block-end", "0"),
+  reset("padding-block-start", "0"),
+  reset("padding-bottom", "0"),
+  shorthand("padding-inline", &["padding-inline-start", "padding-inline-end"]),
+  reset("padding-inline-end", "0"),
+  reset("padding-inline-start", "0"),
+  reset("padding-left", "0"),
+  reset("padding-right", "0"),
+  reset("padding-top", "0"),
+  reset("page", "auto"),
+  shorthand("page-break-after", &["break-after"]).deprecated(),
+  shorthand("page-break-before", &["break-before"]).deprecated(),
+  shorthand("page-break-inside", &["break-inside"]).deprecated(),
+  inherited("paint-order", "normal"),
+  reset("perspective", "none"),
+  reset("perspective-origin", "50% 50%"),
+  shorthand("place-content", &["align-content", "justify-content"]),
+  shorthand("place-items", &["align-items", "justify-items"]),
+  shorthand("place-self", &["align-self", "justify-self"]),
+  inherited("pointer-events", "auto"),
+  reset("position", "static"),
+  inherited("print-color-adjust", "economy"),
+  inherited("quotes", "auto"),
+  reset("resize", "none"),
+  reset("right", "auto"),
+  reset("rotate", "none"),
+  reset("row-gap", "normal"),
+  inherited("ruby-align", "space-around"),
+  inherited("ruby-position", "alternate"),
+  reset("scale", "none"),
+  reset("scroll-behavior", "auto"),
+  shorthand("scroll-margin", &["scroll-margin-top", "scroll-margin-right", "scroll-margin-bottom", "scroll-margin-left"]),
+  reset("scroll-margin-bottom", "0"),
+  reset("scroll-margin-left", "0"),
+  reset("scroll-margin-right", "0"),
+  reset("scroll-margin-top", "0"),
+  shorthand("scroll-padding", &["scroll-padding-top", "scroll-padding-right", "scroll-padding-bottom", "scroll-padding-left"]),
+  reset("scroll-padding-bottom", "auto"),
+  reset("scroll-padding-left", "auto"),
+  reset("scroll-padding-right", "auto"),
+  reset("scroll-padding-top", "auto"),
+  reset("scroll-snap-align", "none"),
+  reset("scroll-snap-stop", "normal"),
+  reset("scroll-snap-type", "none"),
+  inherited("scrollbar-color", "auto"),
+  reset("scrollbar-gutter", "auto"),
+  reset("scrollbar-width", "auto"),
+  reset("shape-image-threshold", "0"),
+  reset("shape-margin", "0"),
+  reset("shape-outside", "none"),
+  inherited("shape-rendering", "auto"),
+  reset("stop-color", "black"),
+  reset("stop-opacity", "1"),
+  inherited("stroke", "none"),
+  inherited("stroke-dasharray", "none"),
+  inherited("stroke-dashoffset", "0"),
+  inherited("stroke-linecap", "butt"),
+  inherited("stroke-linejoin", "miter"),
+  inherited("stroke-miterlimit", "4"),
+  inherited("stroke-opacity", "1"),
+  inherited("stroke-width", "1px"),
+  inherited("tab-size", "8"),
+  reset("table-layout", "auto"),
+  inherited("text-align", "start"),
+  inherited("text-align-last", "auto"),
+  inherited("text-anchor", "start"),
+  shorthand("text-decoration", &["text-decoration-line", "text-decoration-style", "text-decoration-color", "text-decoration-thickness"]),
+  reset("text-decoration-color", "currentcolor"),
+  reset("text-decoration-line", "none"),
+  inherited("text-decoration-skip-ink", "auto"),
+  reset("text-decoration-style", "solid"),
+  reset("text-decoration-thickness", "auto"),
+  inherited_shorthand("text-emphasis", &["text-emphasis-style", "text-emphasis-color"]),
+  inherited("text-emphasis-color", "currentcolor"),
+  inherited("text-emphasis-position", "over right"),
+  inherited("text-emphasis-style", "none"),
+  inherited("text-indent", "0"),
+  inherited("text-justify", "auto"),
+  inherited("text-orientation", "mixed"),
+  reset("text-overflow", "clip"),
+  inherited("text-rendering", "auto"),
+  inherited("text-shadow", "none"),
+  inherited("text-size-adjust", "auto"),
+  inherited("text-transform", "none"),
+  inherited("text-underline-offset", "auto"),
+  inherited("text-underline-position", "auto"),
+  inherited_shorthand("text-wrap", &["text-wrap-mode", "text-wrap-style"]),
+  inherited("text-wrap-mode", "wrap"),
+  inherited("text-wrap-style", "auto"),
+  reset("top", "auto"),
+  reset("touch-action", "auto"),
+  reset("transform", "none"),
+  reset("transform-box", "view-box"),
+  reset("transform-origin", "50% 50% 0"),
+  reset("transform-style", "flat"),
+  shorthand("transition", &["transition-property", "transition-duration", "transition-timing-function", "transition-delay", "transition-behavior"]).not_animatable(),
+  reset("transition-behavior", "normal").not_animatable(),
+  reset("transition-delay", "0s").not_animatable(),
+  reset("transition-duration", "0s").not_animatable(),
+  reset("transition-property", "all").not_animatable(),
+  reset("transition-timing-function", "ease").not_animatable(),
+  reset("translate", "none"),
+  reset("unicode-bidi", "normal").not_animatable(),
+  reset("user-select", "auto"),
+  reset("vector-effect", "none"),
+  reset("vertical-align", "baseline"),
+  reset("view-transition-name", "none"),
+  inherited("visibility", "visible"),
+  inherited_shorthand("white-space", &["white-space-collapse", "text-wrap-mode"]),
+  inherited("white-space-collapse", "collapse"),
+  inherited("widows", "2"),
+  reset("width", "auto"),
+  reset("will-change", "auto").not_animatable(),
+  inherited("word-break", "normal"),
+  inherited("word-spacing", "normal"),
+  inherited("writing-mode", "horizontal-tb"),
+  reset("z-index", "auto"),
+  reset("zoom", "1"),
+];
+
+/// Vendor-prefixed and legacy names, sorted, with their standard property.
+static ALIASES: &[(&str, &str)] = &[
+  ("-moz-animation", "animation"),
+  ("-moz-appearance", "appearance"),
+  ("-moz-border-radius", "border-radius"),
+  ("-moz-box-shadow", "box-shadow"),
+  ("-moz-box-sizing", "box-sizing"),
+  ("-moz-column-count", "column-count"),
+  ("-moz-column-gap", "column-gap"),
+  ("-moz-columns", "columns"),
+  ("-moz-hyphens", "hyphens"),
+  ("-moz-opacity", "opacity"),
+  ("-moz-tab-size", "tab-size"),
+  ("-moz-text-decoration-color", "text-decoration-color"),
+  ("-moz-text-decoration-line", "text-decoration-line"),
+  ("-moz-transform", "transform"),
+  ("-moz-transform-origin", "transform-origin"),
+  ("-moz-transition", "transition"),
+  ("-moz-user-select", "user-select"),
+  ("-ms-flex", "flex"),
+  ("-ms-flex-align", "align-items"),
+  ("-ms-flex-direction", "flex-direction"),
+  ("-ms-flex-order", "order"),
+  ("-ms-flex-pack", "justify-content"),
+  ("-ms-flex-wrap", "flex-wrap"),
+  ("-ms-grid-columns", "grid-template-columns"),
+  ("-ms-grid-rows", "grid-template-rows"),
+  ("-ms-hyphens", "hyphens"),
+  ("-ms-overflow-x", "overflow-x"),
+  ("-ms-overflow-y", "overflow-y"),
+  ("-ms-text-size-adjust", "text-size-adjust"),
+  ("-ms-transform", "transform"),
+  ("-ms-transform-origin", "transform-origin"),
+  ("-ms-transition", "transition"),
+  ("-ms-user-select", "user-select"),
+  ("-o-animation", "animation"),
+  ("-o-transform", "transform"),
+  ("-o-transition", "transition"),
+  ("-webkit-align-content", "align-content"),
+  ("-webkit-align-items", "align-items"),
+  ("-webkit-align-self", "align-self"),
+  ("-webkit-animation", "animation"),
+  ("-webkit-animation-delay", "animation-delay"),
+  ("-webkit-animation-direction", "animation-direction"),
+  ("-webkit-animation-duration", "animation-duration"),
+  ("-webkit-animation-fill-mode", "animation-fill-mode"),
+  ("-webkit-animation-iteration-count", "animation-iteration-count"),
+  ("-webkit-animation-name", "animation-name"),
+  ("-webkit-animation-play-state", "animation-play-state"),
+  ("-webkit-animation-timing-function", "animation-timing-function"),
+  ("-webkit-appearance", "appearance"),
+  ("-webkit-backdrop-filter", "backdrop-filter"),
+  ("-webkit-backface-visibility", "backface-visibility"),
+  ("-webkit-background-clip", "background-clip"),
+  ("-webkit-background-origin", "background-origin"),
+  ("-webkit-background-size", "background-size"),
+  ("-webkit-border-bottom-left-radius", "border-bottom-left-radius"),
+  ("-webkit-border-bottom-right-radius", "border-bottom-right-radius"),
+  ("-webkit-border-image", "border-image"),
+  ("-webkit-border-radius", "border-radius"),
+  ("-webkit-border-top-left-radius", "border-top-left-radius"),
+  ("-webkit-border-top-right-radius", "border-top-right-radius"),
+  ("-webkit-box-align", "align-items"),
+  ("-webkit-box-flex", "flex-grow"),
+  ("-webkit-box-ordinal-group", "order"),
+  ("-webkit-box-pack", "justify-content"),
+  ("-webkit-box-shadow", "box-shadow"),
+  ("-webkit-box-sizing", "box-sizing"),
+  ("-webkit-clip-path", "clip-path"),
+  ("-webkit-column-count", "column-count"),
+  ("-webkit-column-gap", "column-gap"),
+  ("-webkit-column-rule", "column-rule"),
+  ("-webkit-column-width", "column-width"),
+  ("-webkit-columns", "columns"),
+  ("-webkit-filter", "filter"),
+  ("-webkit-flex", "flex"),
+  ("-webkit-flex-basis", "flex-basis"),
+  ("-webkit-flex-direction", "flex-direction"),
+  ("-webkit-flex-flow", "flex-flow"),
+  ("-webkit-flex-grow", "flex-grow"),
+  ("-webkit-flex-shrink", "flex-shrink"),
+  ("-webkit-flex-wrap", "flex-wrap"),
+  ("-webkit-font-feature-settings", "font-feature-settings"),
+  ("-webkit-hyphens", "hyphens"),
+  ("-webkit-justify-content", "justify-content"),
+  ("-webkit-line-break", "line-break"),
+  ("-webkit-margin-end", "margin-inline-end"),
+  ("-webkit-margin-start", "margin-inline-start"),
+  ("-webkit-mask", "mask"),
+  ("-webkit-mask-clip", "mask-clip"),
+  ("-webkit-mask-image", "mask-image"),
+  ("-webkit-mask-origin", "mask-origin"),
+  ("-webkit-mask-position", "mask-position"),
+  ("-webkit-mask-repeat", "mask-repeat"),
+  ("-webkit-mask-size", "mask-size"),
+  ("-webkit-order", "order"),
+  ("-webkit-padding-end", "padding-inline-end"),
+  ("-webkit-padding-start", "padding-inline-start"),
+  ("-webkit-perspective", "perspective"),
+  ("-webkit-perspective-origin", "perspective-origin"),
+  ("-webkit-print-color-adjust", "print-color-adjust"),
+  ("-webkit-text-emphasis", "text-emphasis"),
+  ("-webkit-text-emphasis-color", "text-emphasis-color"),
+  ("-webkit-text-emphasis-position", "text-emphasis-position"),
+  ("-webkit-text-emphasis-style", "text-emphasis-style"),
+  ("-webkit-text-size-adjust", "text-size-adjust"),
+  ("-webkit-transform", "transform"),
+  ("-webkit-transform-origin", "transform-origin"),
+  ("-webkit-transform-style", "transform-style"),
+  ("-webkit-transition", "transition"),
+  ("-webkit-transition-delay", "transition-delay"),
+  ("-webkit-transition-duration", "transition-duration"),
+  ("-webkit-transition-property", "transition-property"),
+  ("-webkit-transition-timing-function", "transition-timing-function"),
+  ("-webkit-user-select", "user-select"),
+  ("-webkit-writing-mode", "writing-mode"),
+  ("grid-column-gap", "column-gap"),
+  ("grid-gap", "gap"),
+  ("grid-row-gap", "row-gap"),
+  ("word-wrap", "overflow-wrap"),
+];
+
+fn find(name: &str) -> Option<&'static PropertyInfo> {
+  PROPERTIES
+    .binary_search_by(|property| property.name.cmp(name))
+    .ok()
+    .map(|index| &PROPERTIES[index])
+}
+
+fn alias(name: &str) -> Option<&'static str> {
+  ALIASES
+    .binary_search_by(|(alias, _)| (*alias).cmp(name))
+    .ok()
+    .map(|index| ALIASES[index].1)
+}
+
+/// Looks up a property by name, ignoring ASCII case. Aliases return their
+/// standard property. Custom properties and unknown names return `None`.
+pub fn property(name: &str) -> Option<&'static PropertyInfo> {
+  let name = name.to_ascii_lowercase();
+  find(&name).or_else(|| alias(&name).and_then(find))
+}
+
+/// Returns the standard name of a property: the lowercased name itself, or
+/// the property an alias stands for. Unknown names return `None`.
+pub fn standard_name(name: &str) -> Option<&'static str> {
+  property(name).map(|property| property.name)
+}
+
+/// Whether `name` is a standard property, rather than an alias or unknown.
+pub fn is_standard(name: &str) -> bool {
+  find(&name.to_ascii_lowercase()).is_some()
+}
+
+/// Whether `name` is a deprecated property or an alias, which should be
+/// replaced by its standard name.
+pub fn is_deprecated(name: &str) -> bool {
+  let name = name.to_ascii_lowercase();
+  match find(&name) {
+    Some(property) => property.deprecated,
+    None => alias(&name).is_some(),
+  }
+}
+
+/// Returns every standard property in the registry, sorted by name.
+pub fn properties() -> &'static [PropertyInfo] {
+  PROPERTIES
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_tables_are_sorted() {
+    assert!(PROPERTIES.windows(2).all(|pair| pair[0].name < pair[1].name));
+    assert!(ALIASES.windows(2).all(|pair| pair[0].0 < pair[1].0));
+  }
+
+  #[test]
+  fn test_longhands_and_aliases_are_known() {
+    for property in PROPERTIES {
+      for longhand in property.longhands {
+        assert!(find(longhand).is_some_and(|longhand| !longhand.shorthand), "{} of {}", longhand, property.name);
+      }
+    }
+    for (alias, standard) in ALIASES {
+      assert!(find(standard).is_some(), "{} of {}", standard, alias);
+      assert!(find(alias).is_none(), "{}", alias);
+    }
+  }
+
+  #[test]
+  fn test_lookup() {
+    assert_eq!(property("font-size"), Some(&inherited("font-size", "medium")));
+    assert_eq!(property("DISPLAY").map(|display| display.initial), Some("inline"));
+    assert_eq!(property("line-height").map(|line_height| line_height.inherited), Some(true));
+    assert_eq!(property("width").map(|width| width.inherited), Some(false));
+    assert_eq!(property("--main"), None);
+    assert_eq!(property("colour"), None);
+  }
+
+  #[test]
+  fn test_shorthands() {
+    let border = property("border").unwrap();
+    assert!(border.shorthand);
+    assert_eq!(border.longhands.len(), 17);
+    assert!(border.longhands.contains(&"border-image-source"));
+    assert!(property("font").unwrap().longhands.contains(&"font-variant-ligatures"));
+    assert_eq!(property("flex").unwrap().longhands, ["flex-grow", "flex-shrink", "flex-basis"]);
+    assert!(property("font").unwrap().inherited);
+    assert!(!property("margin-top").unwrap().shorthand);
+    assert!(property("all").unwrap().shorthand);
+  }
+
+  #[test]
+  fn test_animatable_and_deprecated() {
+    assert!(property("opacity").unwrap().animatable);
+    assert!(property("display").unwrap().animatable);
+    assert!(!property("transition-duration").unwrap().animatable);
+    assert!(!property("animation").unwrap().animatable);
+    assert!(property("page-break-before").unwrap().deprecated);
+    assert!(!is_deprecated("break-before"));
+    assert!(is_deprecated("WORD-WRAP"));
+    assert!(!is_deprecated("--x"));
+  }
+
+  #[test]
+  fn test_aliases() {
+    assert_eq!(standard_name("-webkit-box-flex"), Some("flex-grow"));
+    assert_eq!(standard_name("-WebKit-Transform"), Some("transform"));
+    assert_eq!(standard_name("grid-gap"), Some("gap"));
+    assert_eq!(standard_name("Color"), Some("color"));
+    assert_eq!(standard_name("-webkit-unknown"), None);
+    assert!(is_standard("overflow-wrap"));
+    assert!(!is_standard("word-wrap"));
+    assert_eq!(property("-moz-box-sizing").map(|property| property.initial), Some("content-box"));
+  }
+}
@@ -0,0 +1,764 @@
+//! CSS Rule Model
+//!
+//! This module provides the `Rule` enum, which represents any rule that can
+//! appear in a stylesheet or inside a conditional group rule: a style rule
+//! (`CSSRule`) or one of the standard at-rules (`@media`, `@supports`,
+//! `@import`, `@font-face`, `@keyframes`, ...). At-rules this crate does not
+//! model are kept verbatim as `CSSUnknownAtRule`.
+//!
+//! ## Main API
+//!
+//! - `Rule::from_string()` - Parse a single style rule or at-rule from a string
+//! - `Rule::as_style_rule()` - Access the `CSSRule` of a style rule
+//! - `Display` trait implementation for converting back to CSS string
+//!
+//! ## Examples
+//!
+//! ```rust
+//! use css_structs::Rule;
+//!
+//! let rule = Rule::from_string("@media (min-width: 600px) { .a { color: red } }").unwrap();
+//! match &rule {
+//!   Rule::Media(media) => {
+//!     assert_eq!(media.media, "(min-width: 600px)");
+//!     assert_eq!(media.rules.len(), 1);
+//!   }
+//!   _ => unreachable!(),
+//! }
+//! println!("{}", rule); // "@media (min-width: 600px) { .a { color: red; } }"
+//!
+//! let rule = Rule::from_string("h1 { color: red }").unwrap();
+//! assert_eq!(rule.as_style_rule().unwrap().selector, "h1");
+//! ```
+
+
+use std::fmt;
+use nom::{
+  character::complete::char,
+  combinator::map,
+  sequence::preceded,
+  Parser,
+};
+use crate::css_at_rule::*;
+use crate::css_comment::CSSComment;
+use crate::css_declaration::CSSDeclaration;
+use crate::css_declaration_list::CSSDeclarationList;
+use crate::css_rule::{CSSNestedDeclarations, CSSRule};
+use crate::helpers::{scan_until, skip_invalid, strip_comments, trivia};
+use crate::parse_error::{close_at_end, expect, record, recovering, ParseError, ParseErrorKind, ParseResult, SyntaxError};
+use crate::span::{span_of, with_source, NodeSpan, Span};
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Rule {
+  Style(CSSRule),
+  Media(CSSMediaRule),
+  Supports(CSSSupportsRule),
+  Container(CSSContainerRule),
+  Layer(CSSLayerRule),
+  Scope(CSSScopeRule),
+  StartingStyle(CSSStartingStyleRule),
+  Import(CSSImportRule),
+  Namespace(CSSNamespaceRule),
+  Charset(CSSCharsetRule),
+  FontFace(CSSFontFaceRule),
+  Page(CSSPageRule),
+  CounterStyle(CSSCounterStyleRule),
+  Property(CSSPropertyRule),
+  FontPaletteValues(CSSFontPaletteValuesRule),
+  ViewTransition(CSSViewTransitionRule),
+  Keyframes(CSSKeyframesRule),
+  Unknown(CSSUnknownAtRule),
+  /// Declarations following a nested rule inside a style rule or a nested group rule.
+  NestedDeclarations(CSSNestedDeclarations),
+}
+
+impl Rule {
+  fn parse_at_keyword(input: &str) -> ParseResult<'_, String> {
+    preceded(char('@'), expect(ParseErrorKind::InvalidAtRule, "an at-rule name", CSSDeclaration::parse_identifier)).parse(input)
+  }
+
+  fn parse_prelude(input: &str) -> ParseResult<'_, (String, Vec<CSSComment>, char)> {
+    let len = scan_until(input, &['{', ';', '}']);
+    let (raw, rest) = input.split_at(len);
+    let terminator = match rest.chars().next() {
+      Some(terminator @ ('{' | ';')) => terminator,
+      _ if rest.starts_with("/*") => return SyntaxError::failure(rest, ParseErrorKind::UnterminatedComment, "`*/`"),
+      _ => return SyntaxError::failure(rest, ParseErrorKind::InvalidAtRule, "`{` or `;`"),
+    };
+    let (prelude, comments) = strip_comments(raw);
+
+    Ok((&rest[1..], (prelude.trim().to_string(), comments, terminator)))
+  }
+
+  /// Parses the block of a conditional group rule and the comments at its end.
+  /// Nested inside a style rule, the block may contain declarations, kept as a
+  /// leading `Rule::NestedDeclarations`.
+  fn parse_group_block(input: &str, nested: bool) -> ParseResult<'_, (Vec<Rule>, Vec<CSSComment>)> {
+    if !nested {
+      return parse_rule_block(input);
+    }
+
+    let (input, (declarations, mut rules, comments)) = CSSRule::parse_block_contents(input)?;
+    if !declarations.declarations.is_empty() {
+      rules.insert(0, Rule::NestedDeclarations(CSSNestedDeclarations { declarations, comments: Vec::new() }));
+    }
+
+    Ok((input, (rules, comments)))
+  }
+
+  fn parse_at_rule(input: &str, nested: bool) -> ParseResult<'_, Rule> {
+    let (input, name) = Self::parse_at_keyword(input)?;
+    let prelude_start = input.trim_start();
+    let (input, (prelude, comments, terminator)) = Self::parse_prelude(input)?;
+    let lowercase = name.to_ascii_lowercase();
+    let fail = |expected| nom::Err::Failure(SyntaxError::new(prelude_start, ParseErrorKind::InvalidAtRule, expected));
+    let group = |input| Self::parse_group_block(input, nested);
+
+    let (input, mut rule) = match (lowercase.as_str(), terminator) {
+      ("media", '{') => map(group, |(rules, trailing_comments)| Rule::Media(CSSMediaRule { trailing_comments, ..CSSMediaRule::new(&prelude, rules) })).parse(input)?,
+      ("supports", '{') => map(group, |(rules, trailing_comments)| Rule::Supports(CSSSupportsRule { trailing_comments, ..CSSSupportsRule::new(&prelude, rules) })).parse(input)?,
+      ("container", '{') => map(group, |(rules, trailing_comments)| Rule::Container(CSSContainerRule { trailing_comments, ..CSSContainerRule::new(&prelude, rules) })).parse(input)?,
+      ("scope", '{') => map(group, |(rules, trailing_comments)| Rule::Scope(CSSScopeRule { trailing_comments, ..CSSScopeRule::new(&prelude, rules) })).parse(input)?,
+      ("starting-style", '{') => map(group, |(rules, trailing_comments)| Rule::StartingStyle(CSSStartingStyleRule { trailing_comments, ..CSSStartingStyleRule::new(rules) })).parse(input)?,
+      ("layer", '{') => {
+        let names = CSSLayerRule::parse_names(&prelude).filter(|names| names.len() <= 1).ok_or_else(|| fail("at most one layer name"))?;
+        map(group, |(rules, trailing_comments)| Rule::Layer(CSSLayerRule { trailing_comments, ..CSSLayerRule::new(names.clone(), Some(rules)) })).parse(input)?
+      }
+      ("layer", ';') => {
+        let names = CSSLayerRule::parse_names(&prelude).filter(|names| !names.is_empty()).ok_or_else(|| fail("a list of layer names"))?;
+        (input, Rule::Layer(CSSLayerRule::new(names, None)))
+      }
+      ("import", ';') => (input, Rule::Import(CSSImportRule::from_prelude(&prelude).ok_or_else(|| fail("a URL or string"))?)),
+      ("namespace", ';') => (input, Rule::Namespace(CSSNamespaceRule::from_prelude(&prelude).ok_or_else(|| fail("an optional prefix and a URL or string"))?)),
+      ("charset", ';') => (input, Rule::Charset(CSSCharsetRule::from_prelude(&prelude).ok_or_else(|| fail("an encoding string"))?)),
+      ("font-face", '{') => map(parse_declaration_block, |declarations| Rule::FontFace(CSSFontFaceRule::new(&declarations))).parse(input)?,
+      ("page", '{') => map(parse_declaration_block, |declarations| Rule::Page(CSSPageRule::new(&prelude, &declarations))).parse(input)?,
+      ("counter-style", '{') => map(parse_declaration_block, |declarations| Rule::CounterStyle(CSSCounterStyleRule::new(&prelude, &declarations))).parse(input)?,
+      ("property", '{') => map(parse_declaration_block, |declarations| Rule::Property(CSSPropertyRule::new(&prelude, &declarations))).parse(input)?,
+      ("font-palette-values", '{') => map(parse_declaration_block, |declarations| Rule::FontPaletteValues(CSSFontPaletteValuesRule::new(&prelude, &declarations))).parse(input)?,
+      ("view-transition", '{') => map(parse_declaration_block, |declarations| Rule::ViewTransition(CSSViewTransitionRule::new(&declarations))).parse(input)?,
+      (keyframes, '{') if CSSKeyframesRule::vendor_prefix(keyframes).is_some() => {
+        let vendor_prefix = CSSKeyframesRule::vendor_prefix(keyframes).flatten();
+        let (input, (keyframes, trailing_comments)) = CSSKeyframesRule::parse_keyframes(input)?;
+        (input, Rule::Keyframes(CSSKeyframesRule { vendor_prefix, trailing_comments, ..CSSKeyframesRule::new(&prelude, keyframes) }))
+      }
+      (_, '{') => map(CSSUnknownAtRule::parse_block, |block| Rule::Unknown(CSSUnknownAtRule::new(&name, &prelude, Some(&block)))).parse(input)?,
+      (_, _) => (input, Rule::Unknown(CSSUnknownAtRule::new(&name, &prelude, None))),
+    };
+
+    rule.comments_mut().extend(comments);
+    Ok((input, rule))
+  }
+
+  pub(crate) fn parse(input: &str) -> ParseResult<'_, Rule> {
+    Self::parse_rule(input, false)
+  }
+
+  /// Parses a rule nested inside a style rule.
+  pub(crate) fn parse_nested(input: &str) -> ParseResult<'_, Rule> {
+    Self::parse_rule(input, true)
+  }
+
+  fn parse_rule(input: &str, nested: bool) -> ParseResult<'_, Rule> {
+    let (start, comments) = trivia(input)?;
+    let (input, mut rule) = if start.starts_with('@') {
+      let (input, mut rule) = Self::parse_at_rule(start, nested)?;
+      if let Some(span) = rule.span_mut() {
+        *span = NodeSpan(span_of(&start[..start.len() - input.len()]));
+      }
+      (input, rule)
+    } else {
+      map(CSSRule::parse, Rule::Style).parse(start)?
+    };
+
+    let own = std::mem::replace(rule.comments_mut(), comments);
+    rule.comments_mut().extend(own);
+    Ok((input, rule))
+  }
+
+  /// Explains why no rule could be parsed at the start of `input`.
+  pub(crate) fn diagnose(input: &str) -> SyntaxError<'_> {
+    match Self::parse(input) {
+      Err(nom::Err::Error(error)) | Err(nom::Err::Failure(error)) => error,
+      _ => SyntaxError::new(input, ParseErrorKind::UnexpectedToken, "a rule"),
+    }
+  }
+
+  /// Parses a sequence of rules and the comments after the last one.
+  pub(crate) fn parse_list(input: &str) -> ParseResult<'_, (Vec<Rule>, Vec<CSSComment>)> {
+    Self::parse_rules(input, false)
+  }
+
+  /// Parses the rules of a stylesheet (`top_level`) or of a block. In recovery
+  /// mode an invalid rule is recorded and skipped; inside a block, a `}` is
+  /// left for the caller to close the block with. The comments after the
+  /// last rule are returned with the rules.
+  pub(crate) fn parse_rules(input: &str, top_level: bool) -> ParseResult<'_, (Vec<Rule>, Vec<CSSComment>)> {
+    let mut rules = Vec::new();
+    let mut input = input;
+
+    loop {
+      match Self::parse(input) {
+        Ok((rest, rule)) => {
+          rules.push(rule);
+          input = rest;
+        }
+        Err(nom::Err::Error(error)) | Err(nom::Err::Failure(error)) if recovering() => {
+          let start = match trivia(input) {
+            Ok((rest, _)) if rest.is_empty() || (!top_level && rest.starts_with('}')) => break,
+            Ok((rest, _)) => rest,
+            Err(_) => input,
+          };
+          record(error);
+          input = skip_invalid(start, match (top_level, start.starts_with('@')) {
+            (true, true) => &['{', ';'],
+            (true, false) => &['{'],
+            (false, true) => &['{', ';', '}'],
+            (false, false) => &['{', '}'],
+          });
+        }
+        Err(nom::Err::Failure(error)) => return Err(nom::Err::Failure(error)),
+        Err(_) => break,
+      }
+    }
+
+    let (rest, comments) = trivia(input)?;
+    match comments.is_empty() {
+      true => Ok((input, (rules, comments))),
+      false => Ok((rest, (rules, comments))),
+    }
+  }
+
+  pub fn from_string(input: &str) -> Result<Rule, ParseError> {
+    let (rest, rule) = with_source(input, || Self::parse(input))
+      .and_then(|(rest, rule)| Ok((trivia(rest)?.0, rule)))
+      .map_err(|error| ParseError::from_nom(input, error))?;
+
+    if !rest.is_empty() {
+      return Err(ParseError::new(input, rest, ParseErrorKind::UnexpectedToken, "end of input"));
+    }
+
+    Ok(rule)
+  }
+
+  pub fn as_style_rule(&self) -> Option<&CSSRule> {
+    match self {
+      Rule::Style(rule) => Some(rule),
+      _ => None,
+    }
+  }
+
+  pub fn as_style_rule_mut(&mut self) -> Option<&mut CSSRule> {
+    match self {
+      Rule::Style(rule) => Some(rule),
+      _ => None,
+    }
+  }
+
+  /// Returns the nested rules of a style rule or of a conditional group rule
+  /// (`@media`, `@supports`, `@container`, `@layer`, `@scope`, `@starting-style`).
+  pub fn child_rules(&self) -> Option<&Vec<Rule>> {
+    match self {
+      Rule::Style(rule) => Some(&rule.rules),
+      Rule::Media(rule) => Some(&rule.rules),
+      Rule::Supports(rule) => Some(&rule.rules),
+      Rule::Container(rule) => Some(&rule.rules),
+      Rule::Layer(rule) => rule.rules.as_ref(),
+      Rule::Scope(rule) => Some(&rule.rules),
+      Rule::StartingStyle(rule) => Some(&rule.rules),
+      _ => None,
+    }
+  }
+
+  pub fn child_rules_mut(&mut self) -> Option<&mut Vec<Rule>> {
+    match self {
+      Rule::Style(rule) => Some(&mut rule.rules),
+      Rule::Media(rule) => Some(&mut rule.rules),
+      Rule::Supports(rule) => Some(&mut rule.rules),
+      Rule::Container(rule) => Some(&mut rule.rules),
+      Rule::Layer(rule) => rule.rules.as_mut(),
+      Rule::Scope(rule) => Some(&mut rule.rules),
+      Rule::StartingStyle(rule) => Some(&mut rule.rules),
+      _ => None,
+    }
+  }
+
+  /// The source text the rule was parsed from, without the comments before
+  /// it. `None` for rules built with `new` and for nested declarations,
+  /// whose declarations have their own spans.
+  pub fn span(&self) -> Option<Span> {
+    match self {
+      Rule::Style(rule) => *rule.span,
+      Rule::Media(rule) => *rule.span,
+      Rule::Supports(rule) => *rule.span,
+      Rule::Container(rule) => *rule.span,
+      Rule::Layer(rule) => *rule.span,
+      Rule::Scope(rule) => *rule.span,
+      Rule::StartingStyle(rule) => *rule.span,
+      Rule::Import(rule) => *rule.span,
+      Rule::Namespace(rule) => *rule.span,
+      Rule::Charset(rule) => *rule.span,
+      Rule::FontFace(rule) => *rule.span,
+      Rule::Page(rule) => *rule.span,
+      Rule::CounterStyle(rule) => *rule.span,
+      Rule::Property(rule) => *rule.span,
+      Rule::FontPaletteValues(rule) => *rule.span,
+      Rule::ViewTransition(rule) => *rule.span,
+      Rule::Keyframes(rule) => *rule.span,
+      Rule::Unknown(rule) => *rule.span,
+      Rule::NestedDeclarations(_) => None,
+    }
+  }
+
+  fn span_mut(&mut self) -> Option<&mut NodeSpan> {
+    match self {
+      Rule::Style(rule) => Some(&mut rule.span),
+      Rule::Media(rule) => Some(&mut rule.span),
+      Rule::Supports(rule) => Some(&mut rule.span),
+      Rule::Container(rule) => Some(&mut rule.span),
+      Rule::Layer(rule) => Some(&mut rule.span),
+      Rule::Scope(rule) => Some(&mut rule.span),
+      Rule::StartingStyle(rule) => Some(&mut rule.span),
+      Rule::Import(rule) => Some(&mut rule.span),
+      Rule::Namespace(rule) => Some(&mut rule.span),
+      Rule::Charset(rule) => Some(&mut rule.span),
+      Rule::FontFace(rule) => Some(&mut rule.span),
+      Rule::Page(rule) => Some(&mut rule.span),
+      Rule::CounterStyle(rule) => Some(&mut rule.span),
+      Rule::Property(rule) => Some(&mut rule.span),
+      Rule::FontPaletteValues(rule) => Some(&mut rule.span),
+      Rule::ViewTransition(rule) => Some(&mut rule.span),
+      Rule::Keyframes(rule) => Some(&mut rule.span),
+      Rule::Unknown(rule) => Some(&mut rule.span),
+      Rule::NestedDeclarations(_) => None,
+    }
+  }
+
+  pub fn comments(&self) -> &Vec<CSSComment> {
+    match self {
+      Rule::Style(rule) => &rule.comments,
+      Rule::Media(rule) => &rule.comments,
+      Rule::Supports(rule) => &rule.comments,
+      Rule::Container(rule) => &rule.comments,
+      Rule::Layer(rule) => &rule.comments,
+      Rule::Scope(rule) => &rule.comments,
+      Rule::StartingStyle(rule) => &rule.comments,
+      Rule::Import(rule) => &rule.comments,
+      Rule::Namespace(rule) => &rule.comments,
+      Rule::Charset(rule) => &rule.comments,
+      Rule::FontFace(rule) => &rule.comments,
+      Rule::Page(rule) => &rule.comments,
+      Rule::CounterStyle(rule) => &rule.comments,
+      Rule::Property(rule) => &rule.comments,
+      Rule::FontPaletteValues(rule) => &rule.comments,
+      Rule::ViewTransition(rule) => &rule.comments,
+      Rule::Keyframes(rule) => &rule.comments,
+      Rule::Unknown(rule) => &rule.comments,
+      Rule::NestedDeclarations(rule) => &rule.comments,
+    }
+  }
+
+  pub fn comments_mut(&mut self) -> &mut Vec<CSSComment> {
+    match self {
+      Rule::Style(rule) => &mut rule.comments,
+      Rule::Media(rule) => &mut rule.comments,
+      Rule::Supports(rule) => &mut rule.comments,
+      Rule::Container(rule) => &mut rule.comments,
+      Rule::Layer(rule) => &mut rule.comments,
+      Rule::Scope(rule) => &mut rule.comments,
+      Rule::StartingStyle(rule) => &mut rule.comments,
+      Rule::Import(rule) => &mut rule.comments,
+      Rule::Namespace(rule) => &mut rule.comments,
+      Rule::Charset(rule) => &mut rule.comments,
+      Rule::FontFace(rule) => &mut rule.comments,
+      Rule::Page(rule) => &mut rule.comments,
+      Rule::CounterStyle(rule) => &mut rule.comments,
+      Rule::Property(rule) => &mut rule.comments,
+      Rule::FontPaletteValues(rule) => &mut rule.comments,
+      Rule::ViewTransition(rule) => &mut rule.comments,
+      Rule::Keyframes(rule) => &mut rule.comments,
+      Rule::Unknown(rule) => &mut rule.comments,
+      Rule::NestedDeclarations(rule) => &mut rule.comments,
+    }
+  }
+}
+
+/// Calls `f` with every declaration list in `rules` and their nested rules:
+/// those of style rules, nested declarations, keyframes and descriptor blocks
+/// such as `@font-face`.
+pub(crate) fn for_each_declaration_list(rules: &[Rule], f: &mut impl FnMut(&CSSDeclarationList)) {
+  for rule in rules {
+    match rule {
+      Rule::Style(rule) => f(&rule.declarations),
+      Rule::NestedDeclarations(rule) => f(&rule.declarations),
+      Rule::FontFace(rule) => f(&rule.declarations),
+      Rule::Page(rule) => f(&rule.declarations),
+      Rule::CounterStyle(rule) => f(&rule.declarations),
+      Rule::Property(rule) => f(&rule.declarations),
+      Rule::FontPaletteValues(rule) => f(&rule.declarations),
+      Rule::ViewTransition(rule) => f(&rule.declarations),
+      Rule::Keyframes(rule) => rule.keyframes.iter().for_each(|keyframe| f(&keyframe.declarations)),
+      _ => {}
+    }
+    if let Some(children) = rule.child_rules() {
+      for_each_declaration_list(children, f);
+    }
+  }
+}
+
+/// Same as `for_each_declaration_list`, with mutable access.
+pub(crate) fn for_each_declaration_list_mut(rules: &mut [Rule], f: &mut impl FnMut(&mut CSSDeclarationList)) {
+  for rule in rules {
+    match rule {
+      Rule::Style(rule) => f(&mut rule.declarations),
+      Rule::NestedDeclarations(rule) => f(&mut rule.declarations),
+      Rule::FontFace(rule) => f(&mut rule.declarations),
+      Rule::Page(rule) => f(&mut rule.declarations),
+      Rule::CounterStyle(rule) => f(&mut rule.declarations),
+      Rule::Property(rule) => f(&mut rule.declarations),
+      Rule::FontPaletteValues(rule) => f(&mut rule.declarations),
+      Rule::ViewTransition(rule) => f(&mut rule.declarations),
+      Rule::Keyframes(rule) => rule.keyframes.iter_mut().for_each(|keyframe| f(&mut keyframe.declarations)),
+      _ => {}
+    }
+    if let Some(children) = rule.child_rules_mut() {
+      for_each_declaration_list_mut(children, f);
+    }
+  }
+}
+
+/// Parses the rules of a conditional group rule up to and including the
+/// closing `}`, and the comments after the last rule.
+pub(crate) fn parse_rule_block(input: &str) -> ParseResult<'_, (Vec<Rule>, Vec<CSSComment>)> {
+  let (input, rules) = Rule::parse_list(input)?;
+  let (input, _) = trivia(input)?;
+
+  match input.strip_prefix('}') {
+    Some(rest) => Ok((rest, rules)),
+    None if input.is_empty() => close_at_end(input, rules),
+    None => Err(nom::Err::Failure(Rule::diagnose(input))),
+  }
+}
+
+/// Parses the declarations of a descriptor block up to and including the closing `}`.
+pub(crate) fn parse_declaration_block(input: &str) -> ParseResult<'_, CSSDeclarationList> {
+  CSSRule::parse_declarations_block(input)
+}
+
+impl From<CSSRule> for Rule {
+  fn from(rule: CSSRule) -> Self {
+    Rule::Style(rule)
+  }
+}
+
+impl fmt::Display for Rule {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Rule::Style(rule) => fmt::Display::fmt(rule, f),
+      Rule::Media(rule) => fmt::Display::fmt(rule, f),
+      Rule::Supports(rule) => fmt::Display::fmt(rule, f),
+      Rule::Container(rule) => fmt::Display::fmt(rule, f),
+      Rule::Layer(rule) => fmt::Display::fmt(rule, f),
+      Rule::Scope(rule) => fmt::Display::fmt(rule, f),
+      Rule::StartingStyle(rule) => fmt::Display::fmt(rule, f),
+      Rule::Import(rule) => fmt::Display::fmt(rule, f),
+      Rule::Namespace(rule) => fmt::Display::fmt(rule, f),
+      Rule::Charset(rule) => fmt::Display::fmt(rule, f),
+      Rule::FontFace(rule) => fmt::Display::fmt(rule, f),
+      Rule::Page(rule) => fmt::Display::fmt(rule, f),
+      Rule::CounterStyle(rule) => fmt::Display::fmt(rule, f),
+      Rule::Property(rule) => fmt::Display::fmt(rule, f),
+      Rule::FontPaletteValues(rule) => fmt::Display::fmt(rule, f),
+      Rule::ViewTransition(rule) => fmt::Display::fmt(rule, f),
+      Rule::Keyframes(rule) => fmt::Display::fmt(rule, f),
+      Rule::Unknown(rule) => fmt::Display::fmt(rule, f),
+      Rule::NestedDeclarations(rule) => fmt::Display::fmt(rule, f),
+    }
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::parse_error::with_recovery;
+
+  #[test]
+  fn test_parse_style_rule() {
+    let (remaining, rule) = Rule::parse("h1 { color: red; } p {}").unwrap();
+    assert_eq!(remaining, " p {}");
+    assert_eq!(rule.as_style_rule().unwrap().selector, "h1");
+  }
+
+  #[test]
+  fn test_parse_media_rule() {
+    let rule = Rule::from_string("@media (min-width: 600px) { .a { color: red } .b { color: blue } }").unwrap();
+    let Rule::Media(media) = &rule else { panic!("expected @media") };
+    assert_eq!(media.media, "(min-width: 600px)");
+    assert_eq!(media.rules.len(), 2);
+    assert_eq!(media.rules[0].as_style_rule().unwrap().selector, ".a");
+    assert_eq!(media.rules[1].as_style_rule().unwrap().selector, ".b");
+  }
+
+  #[test]
+  fn test_parse_at_keyword_case_insensitive() {
+    let rule = Rule::from_string("@MEDIA print { body { margin: 0 } }").unwrap();
+    assert!(matches!(rule, Rule::Media(_)));
+  }
+
+  #[test]
+  fn test_parse_nested_conditional_rules() {
+    let input = "@supports (display: grid) { @media screen { .grid { display: grid } } }";
+    let rule = Rule::from_string(input).unwrap();
+    let Rule::Supports(supports) = &rule else { panic!("expected @supports") };
+    assert_eq!(supports.condition, "(display: grid)");
+    let Rule::Media(media) = &supports.rules[0] else { panic!("expected @media") };
+    assert_eq!(media.media, "screen");
+    assert_eq!(media.rules[0].as_style_rule().unwrap().selector, ".grid");
+  }
+
+  #[test]
+  fn test_parse_container_and_scope() {
+    let rule = Rule::from_string("@container sidebar (min-width: 400px) { .card { display: grid } }").unwrap();
+    let Rule::Container(container) = &rule else { panic!("expected @container") };
+    assert_eq!(container.condition, "sidebar (min-width: 400px)");
+
+    let rule = Rule::from_string("@scope (.card) to (.content) { img { border: 0 } }").unwrap();
+    let Rule::Scope(scope) = &rule else { panic!("expected @scope") };
+    assert_eq!(scope.prelude, "(.card) to (.content)");
+    assert_eq!(scope.rules.len(), 1);
+  }
+
+  #[test]
+  fn test_parse_layer_rules() {
+    let rule = Rule::from_string("@layer reset, base.typography;").unwrap();
+    let Rule::Layer(layer) = &rule else { panic!("expected @layer") };
+    assert_eq!(layer.names, vec!["reset", "base.typography"]);
+    assert!(layer.rules.is_none());
+
+    let rule = Rule::from_string("@layer base { h1 { margin: 0 } }").unwrap();
+    let Rule::Layer(layer) = &rule else { panic!("expected @layer") };
+    assert_eq!(layer.names, vec!["base"]);
+    assert_eq!(layer.rules.as_ref().unwrap().len(), 1);
+
+    let rule = Rule::from_string("@layer { h1 { margin: 0 } }").unwrap();
+    let Rule::Layer(layer) = &rule else { panic!("expected @layer") };
+    assert!(layer.names.is_empty());
+
+    assert!(Rule::from_string("@layer a, b { h1 { margin: 0 } }").is_err());
+    assert!(Rule::from_string("@layer;").is_err());
+  }
+
+  #[test]
+  fn test_parse_import_rule() {
+    let rule = Rule::from_string("@import url(\"theme.css\") layer(base) supports(display: grid) screen and (min-width: 600px);").unwrap();
+    let Rule::Import(import) = &rule else { panic!("expected @import") };
+    assert_eq!(import.url, "theme.css");
+    assert_eq!(import.layer.as_deref(), Some("base"));
+    assert_eq!(import.supports.as_deref(), Some("display: grid"));
+    assert_eq!(import.media, "screen and (min-width: 600px)");
+
+    let rule = Rule::from_string("@import 'print.css' print;").unwrap();
+    let Rule::Import(import) = &rule else { panic!("expected @import") };
+    assert_eq!(import.url, "print.css");
+    assert_eq!(import.layer, None);
+    assert_eq!(import.media, "print");
+
+    assert!(Rule::from_string("@import;").is_err());
+  }
+
+  #[test]
+  fn test_parse_namespace_and_charset() {
+    let rule = Rule::from_string("@namespace svg url(http://www.w3.org/2000/svg);").unwrap();
+    let Rule::Namespace(namespace) = &rule else { panic!("expected @namespace") };
+    assert_eq!(namespace.prefix.as_deref(), Some("svg"));
+    assert_eq!(namespace.url, "http://www.w3.org/2000/svg");
+
+    let rule = Rule::from_string("@charset \"UTF-8\";").unwrap();
+    let Rule::Charset(charset) = &rule else { panic!("expected @charset") };
+    assert_eq!(charset.encoding, "UTF-8");
+  }
+
+  #[test]
+  fn test_parse_descriptor_rules() {
+    let rule = Rule::from_string("@font-face { font-family: Inter; src: url(inter.woff2); }").unwrap();
+    let Rule::FontFace(font_face) = &rule else { panic!("expected @font-face") };
+    assert_eq!(font_face.declarations.declarations.len(), 2);
+    assert_eq!(font_face.declarations.declarations[0], CSSDeclaration::new("font-family", "Inter", None));
+
+    let rule = Rule::from_string("@page :first { margin: 1in; }").unwrap();
+    let Rule::Page(page) = &rule else { panic!("expected @page") };
+    assert_eq!(page.selector, ":first");
+
+    let rule = Rule::from_string("@property --angle { syntax: '<angle>'; inherits: false; initial-value: 0deg; }").unwrap();
+    let Rule::Property(property) = &rule else { panic!("expected @property") };
+    assert_eq!(property.name, "--angle");
+    assert_eq!(property.declarations.declarations.len(), 3);
+
+    let rule = Rule::from_string("@counter-style thumbs { system: cyclic; symbols: a b; }").unwrap();
+    let Rule::CounterStyle(counter_style) = &rule else { panic!("expected @counter-style") };
+    assert_eq!(counter_style.name, "thumbs");
+
+    let rule = Rule::from_string("@view-transition { navigation: auto; }").unwrap();
+    assert!(matches!(rule, Rule::ViewTransition(_)));
+  }
+
+  #[test]
+  fn test_parse_keyframes_rule() {
+    let rule = Rule::from_string("@keyframes spin { from { transform: rotate(0deg) } 50%, 75% { opacity: 0.5 } to { transform: rotate(360deg) } }").unwrap();
+    let Rule::Keyframes(keyframes) = &rule else { panic!("expected @keyframes") };
+    assert_eq!(keyframes.name, "spin");
+    assert_eq!(keyframes.vendor_prefix, None);
+    assert_eq!(keyframes.keyframes.len(), 3);
+    assert_eq!(keyframes.keyframes[1].selector, "50%, 75%");
+
+    let rule = Rule::from_string("@-webkit-keyframes pulse { to { opacity: 0 } }").unwrap();
+    let Rule::Keyframes(keyframes) = &rule else { panic!("expected @keyframes") };
+    assert_eq!(keyframes.vendor_prefix.as_deref(), Some("-webkit-"));
+    assert_eq!(rule.to_string(), "@-webkit-keyframes pulse { to { opacity: 0; } }");
+  }
+
+  #[test]
+  fn test_parse_unknown_at_rule() {
+    let rule = Rule::from_string("@font-feature-values Font One { @styleset { nice-style: 12; } }").unwrap();
+    let Rule::Unknown(unknown) = &rule else { panic!("expected unknown at-rule") };
+    assert_eq!(unknown.name, "font-feature-values");
+    assert_eq!(unknown.prelude, "Font One");
+    assert_eq!(unknown.block.as_deref(), Some(" @styleset { nice-style: 12; } "));
+
+    let rule = Rule::from_string("@custom-selector :--heading h1, h2;").unwrap();
+    let Rule::Unknown(unknown) = &rule else { panic!("expected unknown at-rule") };
+    assert_eq!(unknown.block, None);
+    assert_eq!(rule.to_string(), "@custom-selector :--heading h1, h2;");
+  }
+
+  #[test]
+  fn test_parse_at_rule_comments() {
+    let rule = Rule::from_string("/* lead */ @media /* q */ print { /* inner */ a { color: red } /* tail */ }").unwrap();
+    assert_eq!(rule.comments(), &vec![CSSComment::new(" lead "), CSSComment::new(" q ")]);
+    let child = &rule.child_rules().unwrap()[0];
+    assert_eq!(child.comments(), &vec![CSSComment::new(" inner ")]);
+    let Rule::Media(media) = &rule else { panic!("expected @media") };
+    assert_eq!(media.trailing_comments, vec![CSSComment::new(" tail ")]);
+    assert_eq!(format!("{:#}", rule), "/* lead */ /* q */ @media print { /* inner */ a { color: red; } /* tail */ }");
+  }
+
+  #[test]
+  fn test_comments_of_empty_blocks() {
+    let inputs = [
+      "@media screen { /* only */ }",
+      "@layer base { /* only */ }",
+      "@keyframes spin { /* only */ }",
+      "@font-face { /* only */ }",
+      "a { /* only */ }",
+      "a { & b { top: 0; } /* after */ }",
+      "a { @media print { /* nested */ } }",
+    ];
+
+    for input in inputs {
+      let rule = Rule::from_string(input).expect(input);
+      assert_eq!(format!("{:#}", rule), input);
+    }
+  }
+
+  #[test]
+  fn test_parse_list() {
+    let (remaining, (rules, comments)) = Rule::parse_list("@import 'a.css'; h1 { color: red } @media print { h1 { color: black } } /* end */").unwrap();
+    assert_eq!(remaining, "");
+    assert_eq!(rules.len(), 3);
+    assert!(matches!(rules[0], Rule::Import(_)));
+    assert!(matches!(rules[1], Rule::Style(_)));
+    assert!(matches!(rules[2], Rule::Media(_)));
+    assert!(rules[2].comments().is_empty());
+    assert_eq!(comments, vec![CSSComment::new(" end ")]);
+  }
+
+  #[test]
+  fn test_display_round_trip() {
+    let inputs = [
+      "h1 { color: red; }",
+      "@media screen and (min-width: 600px) { .a { color: red; } .b { color: blue; } }",
+      "@supports (display: grid) { @media print { .grid { display: block; } } }",
+      "@container sidebar (min-width: 400px) { .card { display: grid; } }",
+      "@layer reset, base;",
+      "@layer base { h1 { margin: 0; } }",
+      "@layer { h1 { margin: 0; } }",
+      "@scope (.card) { img { border: 0; } }",
+      "@starting-style { .fade { opacity: 0; } }",
+      "@import url(\"theme.css\") layer(base) supports(display: grid) screen;",
+      "@import url(\"reset.css\") layer;",
+      "@namespace svg url(\"http://www.w3.org/2000/svg\");",
+      "@namespace url(\"http://www.w3.org/1999/xhtml\");",
+      "@charset \"UTF-8\";",
+      "@font-face { font-family: Inter; src: url(inter.woff2); }",
+      "@page :first { margin: 1in; }",
+      "@page { margin: 1in; }",
+      "@counter-style thumbs { system: cyclic; }",
+      "@property --angle { syntax: '<angle>'; inherits: false; }",
+      "@font-palette-values --identifier { font-family: Bixa; }",
+      "@view-transition { navigation: auto; }",
+      "@keyframes spin { from { transform: rotate(0deg); } to { transform: rotate(360deg); } }",
+      "@font-feature-values Font One { @styleset { nice-style: 12; } }",
+    ];
+
+    for input in inputs {
+      let rule = Rule::from_string(input).expect(input);
+      assert_eq!(rule.to_string(), input);
+      assert_eq!(Rule::from_string(&rule.to_string()).unwrap(), rule);
+    }
+  }
+
+  #[test]
+  fn test_parse_list_stops_at_closing_brace() {
+    let (remaining, (rules, _)) = Rule::parse_list(".a { color: red } } .b { color: blue }").unwrap();
+    assert_eq!(rules.len(), 1);
+    assert_eq!(remaining, " } .b { color: blue }");
+  }
+
+  #[test]
+  fn test_recovering_inside_blocks() {
+    let input = "@keyframes fade { from { opacity: 0 } { opacity: 1 } to { opacity 1; top: 0 } } @unknown { a";
+    let (rules, errors) = with_recovery(input, || Rule::parse_rules(input, true).unwrap().1.0);
+    assert_eq!(rules.len(), 2);
+    assert_eq!(rules[0].to_string(), "@keyframes fade { from { opacity: 0; } to { top: 0; } }");
+    assert_eq!(rules[1].to_string(), "@unknown { a}");
+    assert_eq!(errors.len(), 3);
+    assert_eq!(errors[0].kind, ParseErrorKind::InvalidSelector);
+    assert_eq!(errors[1].kind, ParseErrorKind::InvalidDeclaration);
+    assert_eq!(errors[2].kind, ParseErrorKind::UnexpectedEndOfInput);
+  }
+
+  #[test]
+  fn test_from_string_errors() {
+    let error = Rule::from_string("@media print { a { color red } }").unwrap_err();
+    assert_eq!(error.kind, ParseErrorKind::InvalidDeclaration);
+    assert_eq!(error.offset, 25);
+    assert_eq!(error.expected, "`:`");
+
+    let error = Rule::from_string("@media print { a { color: red }").unwrap_err();
+    assert_eq!(error.kind, ParseErrorKind::UnexpectedEndOfInput);
+    assert_eq!(error.expected, "`}`");
+
+    let error = Rule::from_string("@import 42;").unwrap_err();
+    assert_eq!(error.kind, ParseErrorKind::InvalidAtRule);
+    assert_eq!(error.offset, 8);
+    assert_eq!(error.expected, "a URL or string");
+
+    let error = Rule::from_string("@media print").unwrap_err();
+    assert_eq!(error.kind, ParseErrorKind::InvalidAtRule);
+    assert_eq!(error.expected, "`{` or `;`");
+
+    let error = Rule::from_string("@ media print {}").unwrap_err();
+    assert_eq!(error.kind, ParseErrorKind::InvalidAtRule);
+    assert_eq!(error.expected, "an at-rule name");
+
+    let error = Rule::from_string("@unknown { a { }").unwrap_err();
+    assert_eq!(error.kind, ParseErrorKind::UnexpectedEndOfInput);
+  }
+
+  #[test]
+  fn test_style_rule_rejects_at_keyword() {
+    assert!(CSSRule::from_string("@media print { a { color: red } }").is_err());
+  }
+}
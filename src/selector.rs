@@ -0,0 +1,1107 @@
+//! CSS Selectors
+//!
+//! This module provides a parsed model of selector text (Selectors Level 4).
+//! A `SelectorList` is a comma-separated list of complex `Selector`s, each a
+//! chain of `CompoundSelector`s joined by combinators (descendant, `>`, `+`
+//! and `~`). A compound selector is a sequence of `SimpleSelector`s: type and
+//! universal selectors with an optional namespace, ids, classes, attribute
+//! selectors, pseudo-classes (including `:not()`, `:is()`, `:where()`,
+//! `:has()` and `:nth-child(An+B of S)`), pseudo-elements and the nesting
+//! selector `&`.
+//!
+//! Escapes in identifiers are decoded when parsing and added back where needed
+//! when serializing. Serialization follows CSSOM: whitespace is normalized,
+//! attribute values are quoted, `An+B` expressions are written in canonical
+//! form (`odd` becomes `2n+1`), and the legacy `:before` / `:after` /
+//! `:first-line` / `:first-letter` are written as pseudo-elements.
+//!
+//! ## Main API
+//!
+//! - `SelectorList::from_string()` - Parse a selector list
+//! - `SelectorList::from_string_relative()` - Parse a list of relative selectors, such as `> a, + b`
+//! - `CSSRule::selector_list()` - Parse the selector of a style rule
+//! - `Selector::specificity()` / `CSSRule::specificities()` - Selector specificity
+//! - `SelectorList::matches()` - Match against an `Element` (see the `matching` module)
+//! - `Display` trait implementation for converting back to CSS string
+//!
+//! ## Examples
+//!
+//! ```rust
+//! use css_structs::CSSRule;
+//! use css_structs::selector::{Combinator, SimpleSelector, PseudoClass, Specificity};
+//!
+//! let rule = CSSRule::from_string("nav > a.active:not([href^='#']), li::marker { color: red }").unwrap();
+//! let list = rule.selector_list().unwrap();
+//! assert_eq!(list.selectors.len(), 2);
+//!
+//! let selector = &list.selectors[0];
+//! assert_eq!(selector.rest[0].0, Combinator::Child);
+//! let link = &selector.rest[0].1;
+//! assert_eq!(link.components[1], SimpleSelector::Class("active".to_string()));
+//! assert!(matches!(link.components[2], SimpleSelector::PseudoClass(PseudoClass::Not(_))));
+//!
+//! assert_eq!(list.to_string(), "nav > a.active:not([href^=\"#\"]), li::marker");
+//!
+//! // Specificity of each selector, as (ids, classes, types)
+//! let specificities = rule.specificities().unwrap();
+//! assert_eq!(specificities[0], Specificity::new(0, 2, 2));
+//! assert_eq!(specificities[1].to_string(), "(0, 0, 2)");
+//! ```
+
+
+use std::fmt;
+use crate::helpers::{parse_ident, parse_string, scan_until, serialize_identifier, serialize_string, trivia};
+use crate::matching::{matches_selector, matches_selector_list, Element};
+use crate::parse_error::{ParseError, ParseErrorKind, ParseResult, SyntaxError};
+
+
+/// A comma-separated list of selectors, such as `h1, h2.title`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SelectorList {
+  pub selectors: Vec<Selector>,
+}
+
+/// A complex selector: compound selectors joined by combinators, such as
+/// `nav > a:hover`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Selector {
+  /// The combinator in front of a relative selector, such as the `>` of
+  /// `:has(> img)` or of a nested rule's `> a`.
+  pub leading_combinator: Option<Combinator>,
+  pub first: CompoundSelector,
+  /// The following compound selectors, each with the combinator joining it
+  /// to the one before.
+  pub rest: Vec<(Combinator, CompoundSelector)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Combinator {
+  /// Whitespace: `a b`.
+  Descendant,
+  /// `a > b`.
+  Child,
+  /// `a + b`.
+  NextSibling,
+  /// `a ~ b`.
+  SubsequentSibling,
+}
+
+/// A sequence of simple selectors not separated by a combinator, such as
+/// `a.active:hover`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompoundSelector {
+  pub components: Vec<SimpleSelector>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SimpleSelector {
+  /// An element name, such as `div` or `svg|rect`.
+  Type { namespace: Option<NamespacePrefix>, name: String },
+  /// `*`, or `ns|*`.
+  Universal { namespace: Option<NamespacePrefix> },
+  /// `#id`.
+  Id(String),
+  /// `.class`.
+  Class(String),
+  /// `[name]`, `[name=value]`, ...
+  Attribute(AttributeSelector),
+  /// `:hover`, `:not(...)`, ...
+  PseudoClass(PseudoClass),
+  /// `::before`, `::part(...)`, ...
+  PseudoElement(PseudoElement),
+  /// The nesting selector `&`.
+  Nesting,
+}
+
+/// The namespace prefix of a type, universal or attribute selector.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NamespacePrefix {
+  /// `*|`: any namespace, including none.
+  Any,
+  /// `|`: no namespace.
+  None,
+  /// `prefix|`: the namespace declared for `prefix` with `@namespace`.
+  Named(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AttributeSelector {
+  pub namespace: Option<NamespacePrefix>,
+  pub name: String,
+  /// The operator and value, or `None` for a presence test like `[href]`.
+  pub operation: Option<(AttributeOperator, String)>,
+  /// The `i` or `s` flag after the value.
+  pub case_sensitivity: Option<CaseSensitivity>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttributeOperator {
+  /// `=`
+  Equals,
+  /// `~=`: one of the whitespace-separated words.
+  Includes,
+  /// `|=`: the value, or the value followed by `-`.
+  DashMatch,
+  /// `^=`
+  Prefix,
+  /// `$=`
+  Suffix,
+  /// `*=`
+  Substring,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaseSensitivity {
+  /// `i`
+  AsciiCaseInsensitive,
+  /// `s`
+  CaseSensitive,
+}
+
+/// A pseudo-class. Names are ASCII lowercased. Functional pseudo-classes other
+/// than the ones below keep their arguments as text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PseudoClass {
+  /// A pseudo-class without arguments, such as `hover` or `first-child`.
+  Simple(String),
+  Not(SelectorList),
+  Is(SelectorList),
+  Where(SelectorList),
+  /// `:has()`, whose selectors are relative (`:has(> img)`).
+  Has(SelectorList),
+  NthChild(Nth, Option<SelectorList>),
+  NthLastChild(Nth, Option<SelectorList>),
+  NthOfType(Nth),
+  NthLastOfType(Nth),
+  /// Any other functional pseudo-class, such as `:lang(en)` or `:dir(rtl)`.
+  Function { name: String, arguments: String },
+}
+
+/// An `An+B` expression, matching the elements at positions `A*n + B` for
+/// any `n >= 0`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Nth {
+  pub a: i32,
+  pub b: i32,
+}
+
+/// A pseudo-element, such as `::before` or `::part(label)`. The name is ASCII
+/// lowercased and arguments are kept as text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PseudoElement {
+  pub name: String,
+  pub arguments: Option<String>,
+}
+
+/// The specificity of a selector: the number of id selectors (`a`), of class,
+/// attribute and pseudo-class selectors (`b`) and of type and pseudo-element
+/// selectors (`c`). Specificities compare in that order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Specificity {
+  pub a: u32,
+  pub b: u32,
+  pub c: u32,
+}
+
+
+/// Pseudo-elements that may be written with a single colon.
+const LEGACY_PSEUDO_ELEMENTS: [&str; 4] = ["before", "after", "first-line", "first-letter"];
+
+fn invalid<'a, T>(input: &'a str, expected: &str) -> ParseResult<'a, T> {
+  SyntaxError::failure(input, ParseErrorKind::InvalidSelector, expected)
+}
+
+/// Turns a recoverable error into a failure, once the parser is committed.
+fn commit<'a, T>(result: ParseResult<'a, T>) -> ParseResult<'a, T> {
+  result.map_err(|error| match error {
+    nom::Err::Error(error) => nom::Err::Failure(error),
+    error => error,
+  })
+}
+
+/// Parses the arguments of a functional pseudo-class, after the `(`, up to
+/// and including the closing `)`.
+fn parse_arguments<'a, T>(input: &'a str, parser: impl Fn(&'a str) -> ParseResult<'a, T>) -> ParseResult<'a, T> {
+  let (input, _) = trivia(input)?;
+  let (input, value) = commit(parser(input))?;
+  let (input, _) = trivia(input)?;
+
+  match input.strip_prefix(')') {
+    Some(rest) => Ok((rest, value)),
+    None if input.is_empty() => SyntaxError::failure(input, ParseErrorKind::UnexpectedEndOfInput, "`)`"),
+    None => invalid(input, "`)`"),
+  }
+}
+
+fn map_arguments<'a, T>(
+  input: &'a str,
+  parser: impl Fn(&'a str) -> ParseResult<'a, T>,
+  pseudo_class: impl Fn(T) -> PseudoClass,
+) -> ParseResult<'a, PseudoClass> {
+  let (rest, value) = parse_arguments(input, parser)?;
+  Ok((rest, pseudo_class(value)))
+}
+
+impl SelectorList {
+  pub(crate) fn parse(input: &str, relative: bool) -> ParseResult<'_, SelectorList> {
+    let (mut input, first) = Selector::parse(input, relative)?;
+    let mut selectors = vec![first];
+
+    loop {
+      let (rest, _) = trivia(input)?;
+      let Some(rest) = rest.strip_prefix(',') else {
+        break;
+      };
+      let (rest, selector) = commit(Selector::parse(rest, relative))?;
+      selectors.push(selector);
+      input = rest;
+    }
+
+    Ok((input, SelectorList { selectors }))
+  }
+
+  fn parse_complete(input: &str, relative: bool) -> Result<Self, ParseError> {
+    let (rest, list) = Self::parse(input, relative)
+      .and_then(|(rest, list)| Ok((trivia(rest)?.0, list)))
+      .map_err(|error| ParseError::from_nom(input, error))?;
+
+    if !rest.is_empty() {
+      return Err(ParseError::new(input, rest, ParseErrorKind::InvalidSelector, "`,`"));
+    }
+
+    Ok(list)
+  }
+
+  pub fn from_string(input: &str) -> Result<Self, ParseError> {
+    Self::parse_complete(input, false)
+  }
+
+  /// Parses a list of relative selectors, which may start with a combinator
+  /// (`> a, + b`), as found in `:has()` and in nested style rules.
+  pub fn from_string_relative(input: &str) -> Result<Self, ParseError> {
+    Self::parse_complete(input, true)
+  }
+}
+
+impl Selector {
+  pub(crate) fn parse(input: &str, relative: bool) -> ParseResult<'_, Selector> {
+    let (input, _) = trivia(input)?;
+    let (input, leading_combinator) = match Combinator::parse_symbol(input) {
+      Some((rest, combinator)) if relative => (trivia(rest)?.0, Some(combinator)),
+      _ => (input, None),
+    };
+    let (mut input, first) = match leading_combinator {
+      Some(_) => commit(CompoundSelector::parse(input))?,
+      None => CompoundSelector::parse(input)?,
+    };
+    let mut rest = Vec::new();
+
+    loop {
+      let (after_trivia, _) = trivia(input)?;
+      if let Some((after, combinator)) = Combinator::parse_symbol(after_trivia) {
+        let (after, _) = trivia(after)?;
+        let (after, compound) = commit(CompoundSelector::parse(after))?;
+        rest.push((combinator, compound));
+        input = after;
+      } else if after_trivia.len() < input.len() {
+        match CompoundSelector::parse(after_trivia) {
+          Ok((after, compound)) => {
+            rest.push((Combinator::Descendant, compound));
+            input = after;
+          }
+          Err(nom::Err::Error(_)) => break,
+          Err(error) => return Err(error),
+        }
+      } else {
+        break;
+      }
+    }
+
+    Ok((input, Selector { leading_combinator, first, rest }))
+  }
+}
+
+impl Combinator {
+  fn parse_symbol(input: &str) -> Option<(&str, Combinator)> {
+    let combinator = match input.chars().next()? {
+      '>' => Combinator::Child,
+      '+' => Combinator::NextSibling,
+      '~' => Combinator::SubsequentSibling,
+      _ => return None,
+    };
+
+    Some((&input[1..], combinator))
+  }
+}
+
+impl CompoundSelector {
+  pub(crate) fn parse(input: &str) -> ParseResult<'_, CompoundSelector> {
+    let mut components = Vec::new();
+    let mut input = input;
+
+    if let Ok((rest, type_selector)) = SimpleSelector::parse_type(input) {
+      components.push(type_selector);
+      input = rest;
+    }
+
+    loop {
+      let (rest, component) = match input.chars().next() {
+        Some('#') => match parse_ident(&input[1..]) {
+          Ok((rest, id)) => (rest, SimpleSelector::Id(id)),
+          Err(_) => return invalid(&input[1..], "an id"),
+        },
+        Some('.') => match parse_ident(&input[1..]) {
+          Ok((rest, class)) => (rest, SimpleSelector::Class(class)),
+          Err(_) => return invalid(&input[1..], "a class name"),
+        },
+        Some('[') => {
+          let (rest, attribute) = AttributeSelector::parse(input)?;
+          (rest, SimpleSelector::Attribute(attribute))
+        }
+        Some(':') => SimpleSelector::parse_pseudo(input)?,
+        Some('&') => (&input[1..], SimpleSelector::Nesting),
+        _ => break,
+      };
+      components.push(component);
+      input = rest;
+    }
+
+    if components.is_empty() {
+      return SyntaxError::error(input, ParseErrorKind::InvalidSelector, "a selector");
+    }
+
+    Ok((input, CompoundSelector { components }))
+  }
+}
+
+impl NamespacePrefix {
+  /// Parses `ns|`, `*|` or `|`, taking care not to mistake the `|=` operator
+  /// of `[lang|=en]` for a namespace separator.
+  fn parse(input: &str) -> ParseResult<'_, NamespacePrefix> {
+    let (rest, prefix) = if input.starts_with('|') {
+      (input, NamespacePrefix::None)
+    } else if let Some(rest) = input.strip_prefix('*') {
+      (rest, NamespacePrefix::Any)
+    } else {
+      let (rest, name) = parse_ident(input)?;
+      (rest, NamespacePrefix::Named(name))
+    };
+
+    match rest.strip_prefix('|') {
+      Some(after) if !after.starts_with('=') => Ok((after, prefix)),
+      _ => SyntaxError::error(input, ParseErrorKind::InvalidSelector, "a namespace prefix"),
+    }
+  }
+}
+
+impl SimpleSelector {
+  /// Parses a type or universal selector, with its namespace prefix.
+  fn parse_type(input: &str) -> ParseResult<'_, SimpleSelector> {
+    let (rest, namespace) = match NamespacePrefix::parse(input) {
+      Ok((rest, namespace)) => (rest, Some(namespace)),
+      Err(_) => (input, None),
+    };
+
+    if let Some(rest) = rest.strip_prefix('*') {
+      return Ok((rest, SimpleSelector::Universal { namespace }));
+    }
+    match parse_ident(rest) {
+      Ok((rest, name)) => Ok((rest, SimpleSelector::Type { namespace, name })),
+      Err(_) if namespace.is_some() => invalid(rest, "an element name"),
+      Err(error) => Err(error),
+    }
+  }
+
+  /// Parses a pseudo-class or a pseudo-element, starting at the first `:`.
+  fn parse_pseudo(input: &str) -> ParseResult<'_, SimpleSelector> {
+    let (after_colons, is_element) = match input.strip_prefix("::") {
+      Some(rest) => (rest, true),
+      None => (&input[1..], false),
+    };
+    let Ok((rest, name)) = parse_ident(after_colons) else {
+      return invalid(after_colons, "a pseudo-class or pseudo-element name");
+    };
+    let name = name.to_ascii_lowercase();
+
+    let Some(after_paren) = rest.strip_prefix('(') else {
+      let selector = if is_element || LEGACY_PSEUDO_ELEMENTS.contains(&name.as_str()) {
+        SimpleSelector::PseudoElement(PseudoElement { name, arguments: None })
+      } else {
+        SimpleSelector::PseudoClass(PseudoClass::Simple(name))
+      };
+      return Ok((rest, selector));
+    };
+
+    if is_element {
+      let (rest, arguments) = Self::parse_raw_arguments(after_paren)?;
+      return Ok((rest, SimpleSelector::PseudoElement(PseudoElement { name, arguments: Some(arguments) })));
+    }
+
+    let selector_list = |relative| move |input| SelectorList::parse(input, relative);
+    let (rest, pseudo_class) = match name.as_str() {
+      "not" => map_arguments(after_paren, selector_list(false), PseudoClass::Not)?,
+      "is" => map_arguments(after_paren, selector_list(false), PseudoClass::Is)?,
+      "where" => map_arguments(after_paren, selector_list(false), PseudoClass::Where)?,
+      "has" => map_arguments(after_paren, selector_list(true), PseudoClass::Has)?,
+      "nth-child" => map_arguments(after_paren, Nth::parse_with_selector, |(nth, of)| PseudoClass::NthChild(nth, of))?,
+      "nth-last-child" => map_arguments(after_paren, Nth::parse_with_selector, |(nth, of)| PseudoClass::NthLastChild(nth, of))?,
+      "nth-of-type" => map_arguments(after_paren, Nth::parse, PseudoClass::NthOfType)?,
+      "nth-last-of-type" => map_arguments(after_paren, Nth::parse, PseudoClass::NthLastOfType)?,
+      _ => {
+        let (rest, arguments) = Self::parse_raw_arguments(after_paren)?;
+        (rest, PseudoClass::Function { name, arguments })
+      }
+    };
+
+    Ok((rest, SimpleSelector::PseudoClass(pseudo_class)))
+  }
+
+  /// Takes the arguments of a pseudo-class or pseudo-element that are not
+  /// parsed further, up to and including the closing `)`.
+  fn parse_raw_arguments(input: &str) -> ParseResult<'_, String> {
+    let arguments = &input[..scan_until(input, &[')'])];
+    match input[arguments.len()..].strip_prefix(')') {
+      Some(rest) => Ok((rest, arguments.trim().to_string())),
+      None => SyntaxError::failure(&input[arguments.len()..], ParseErrorKind::UnexpectedEndOfInput, "`)`"),
+    }
+  }
+}
+
+impl AttributeSelector {
+  fn parse(input: &str) -> ParseResult<'_, AttributeSelector> {
+    let (input, _) = trivia(&input[1..])?;
+    let (input, namespace) = match NamespacePrefix::parse(input) {
+      Ok((rest, namespace)) => (rest, Some(namespace)),
+      Err(_) => (input, None),
+    };
+    let Ok((input, name)) = parse_ident(input) else {
+      return invalid(input, "an attribute name");
+    };
+    let (input, _) = trivia(input)?;
+
+    let operators = [
+      ("=", AttributeOperator::Equals),
+      ("~=", AttributeOperator::Includes),
+      ("|=", AttributeOperator::DashMatch),
+      ("^=", AttributeOperator::Prefix),
+      ("$=", AttributeOperator::Suffix),
+      ("*=", AttributeOperator::Substring),
+    ];
+    let operator = operators.iter().find(|(symbol, _)| input.starts_with(symbol));
+
+    let (input, operation, case_sensitivity) = match operator {
+      None => (input, None, None),
+      Some((symbol, operator)) => {
+        let (input, _) = trivia(&input[symbol.len()..])?;
+        let Ok((input, value)) = parse_string(input).or_else(|_| parse_ident(input)) else {
+          return invalid(input, "an attribute value");
+        };
+        let (input, _) = trivia(input)?;
+        let (input, case_sensitivity) = match parse_ident(input) {
+          Ok((rest, flag)) if flag.eq_ignore_ascii_case("i") => (rest, Some(CaseSensitivity::AsciiCaseInsensitive)),
+          Ok((rest, flag)) if flag.eq_ignore_ascii_case("s") => (rest, Some(CaseSensitivity::CaseSensitive)),
+          Ok(_) => return invalid(input, "`]`"),
+          Err(_) => (input, None),
+        };
+        (input, Some((*operator, value)), case_sensitivity)
+      }
+    };
+
+    let (input, _) = trivia(input)?;
+    let Some(input) = input.strip_prefix(']') else {
+      return invalid(input, "`]`");
+    };
+
+    Ok((input, AttributeSelector { namespace, name, operation, case_sensitivity }))
+  }
+}
+
+impl Nth {
+  /// Parses an `An+B` expression, or the keywords `odd` and `even`.
+  fn parse(input: &str) -> ParseResult<'_, Nth> {
+    let word_len = input
+      .find(|c: char| !(c.is_ascii_alphanumeric() || c == '+' || c == '-'))
+      .unwrap_or(input.len());
+    let word = input[..word_len].to_ascii_lowercase();
+    let rest = &input[word_len..];
+
+    let parse_integer = |digits: &str| digits.parse::<i32>().ok().filter(|_| !digits.starts_with(['+', '-']));
+    let (sign, body) = match word.strip_prefix('-') {
+      Some(body) => (-1, body),
+      None => (1, word.strip_prefix('+').unwrap_or(&word)),
+    };
+
+    let nth = match (word.as_str(), body.find('n')) {
+      ("odd", _) => Nth { a: 2, b: 1 },
+      ("even", _) => Nth { a: 2, b: 0 },
+      (_, None) => match parse_integer(body) {
+        Some(b) => Nth { a: 0, b: sign * b },
+        None => return invalid(input, "an `An+B` expression"),
+      },
+      (_, Some(n)) => {
+        let a = match &body[..n] {
+          "" => 1,
+          digits => match parse_integer(digits) {
+            Some(a) => a,
+            None => return invalid(input, "an `An+B` expression"),
+          },
+        };
+        let (b_sign, b_digits, rest) = match &body[n + 1..] {
+          "" => {
+            // The sign of B may be separated by whitespace: `2n + 1`
+            let (after, _) = trivia(rest)?;
+            match after.chars().next() {
+              Some('+') => (1, "", &after[1..]),
+              Some('-') => (-1, "", &after[1..]),
+              _ => return Ok((rest, Nth { a: sign * a, b: 0 })),
+            }
+          }
+          after_n => match after_n.chars().next() {
+            Some('+') => (1, &after_n[1..], rest),
+            Some('-') => (-1, &after_n[1..], rest),
+            _ => return invalid(input, "an `An+B` expression"),
+          },
+        };
+        if !b_digits.is_empty() {
+          match parse_integer(b_digits) {
+            Some(b) => Nth { a: sign * a, b: b_sign * b },
+            None => return invalid(input, "an `An+B` expression"),
+          }
+        } else {
+          // The digits of B follow the sign after whitespace: `2n- 1`, `2n + 1`
+          let (after, _) = trivia(rest)?;
+          let digits_len = after.find(|c: char| !c.is_ascii_digit()).unwrap_or(after.len());
+          return match parse_integer(&after[..digits_len]) {
+            Some(b) => Ok((&after[digits_len..], Nth { a: sign * a, b: b_sign * b })),
+            None => invalid(after, "an integer"),
+          };
+        }
+      }
+    };
+
+    Ok((rest, nth))
+  }
+
+  /// Parses the argument of `:nth-child()`: `An+B`, optionally followed by
+  /// `of <selector list>`.
+  fn parse_with_selector(input: &str) -> ParseResult<'_, (Nth, Option<SelectorList>)> {
+    let (input, nth) = Nth::parse(input)?;
+    let (after, _) = trivia(input)?;
+
+    match parse_ident(after) {
+      Ok((rest, keyword)) if keyword.eq_ignore_ascii_case("of") && after.len() < input.len() => {
+        let (rest, selectors) = commit(SelectorList::parse(rest, false))?;
+        Ok((rest, (nth, Some(selectors))))
+      }
+      _ => Ok((input, (nth, None))),
+    }
+  }
+}
+
+
+impl Specificity {
+  pub fn new(a: u32, b: u32, c: u32) -> Self {
+    Specificity { a, b, c }
+  }
+}
+
+impl std::ops::Add for Specificity {
+  type Output = Specificity;
+
+  fn add(self, other: Specificity) -> Specificity {
+    Specificity::new(self.a + other.a, self.b + other.b, self.c + other.c)
+  }
+}
+
+impl SelectorList {
+  /// Returns whether any selector of the list matches `element`.
+  pub fn matches<E: Element>(&self, element: &E) -> bool {
+    matches_selector_list(self, element)
+  }
+
+  /// The specificity of the most specific selector in the list, which is
+  /// what `:is()`, `:not()` and `:has()` contribute.
+  pub fn specificity(&self) -> Specificity {
+    self.selectors.iter().map(Selector::specificity).max().unwrap_or_default()
+  }
+}
+
+impl Selector {
+  /// Returns whether this selector matches `element`. See the `matching`
+  /// module for how each kind of selector is evaluated.
+  pub fn matches<E: Element>(&self, element: &E) -> bool {
+    matches_selector(self, element)
+  }
+
+  /// The specificity of this selector, following Selectors Level 4. The
+  /// nesting selector `&` counts as zero, since it depends on the parent
+  /// rule; flatten the stylesheet first to account for it.
+  pub fn specificity(&self) -> Specificity {
+    std::iter::once(&self.first)
+      .chain(self.rest.iter().map(|(_, compound)| compound))
+      .flat_map(|compound| &compound.components)
+      .map(SimpleSelector::specificity)
+      .fold(Specificity::default(), |total, specificity| total + specificity)
+  }
+}
+
+impl SimpleSelector {
+  fn specificity(&self) -> Specificity {
+    match self {
+      SimpleSelector::Id(_) => Specificity::new(1, 0, 0),
+      SimpleSelector::Class(_) | SimpleSelector::Attribute(_) => Specificity::new(0, 1, 0),
+      SimpleSelector::Type { .. } | SimpleSelector::PseudoElement(_) => Specificity::new(0, 0, 1),
+      SimpleSelector::Universal { .. } | SimpleSelector::Nesting => Specificity::default(),
+      SimpleSelector::PseudoClass(pseudo_class) => match pseudo_class {
+        PseudoClass::Is(selectors) | PseudoClass::Not(selectors) | PseudoClass::Has(selectors) => selectors.specificity(),
+        PseudoClass::Where(_) => Specificity::default(),
+        PseudoClass::NthChild(_, Some(selectors)) | PseudoClass::NthLastChild(_, Some(selectors)) => {
+          Specificity::new(0, 1, 0) + selectors.specificity()
+        }
+        _ => Specificity::new(0, 1, 0),
+      },
+    }
+  }
+}
+
+
+impl fmt::Display for Specificity {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "({}, {}, {})", self.a, self.b, self.c)
+  }
+}
+
+impl fmt::Display for SelectorList {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    for (i, selector) in self.selectors.iter().enumerate() {
+      if i > 0 {
+        write!(f, ", ")?;
+      }
+      write!(f, "{}", selector)?;
+    }
+
+    Ok(())
+  }
+}
+
+impl fmt::Display for Selector {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    if let Some(combinator) = self.leading_combinator {
+      write!(f, "{} ", combinator)?;
+    }
+    write!(f, "{}", self.first)?;
+    for (combinator, compound) in &self.rest {
+      match combinator {
+        Combinator::Descendant => write!(f, " {}", compound)?,
+        combinator => write!(f, " {} {}", combinator, compound)?,
+      }
+    }
+
+    Ok(())
+  }
+}
+
+impl fmt::Display for Combinator {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let symbol = match self {
+      Combinator::Descendant => " ",
+      Combinator::Child => ">",
+      Combinator::NextSibling => "+",
+      Combinator::SubsequentSibling => "~",
+    };
+
+    write!(f, "{}", symbol)
+  }
+}
+
+impl fmt::Display for CompoundSelector {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    for component in &self.components {
+      write!(f, "{}", component)?;
+    }
+
+    Ok(())
+  }
+}
+
+impl fmt::Display for NamespacePrefix {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      NamespacePrefix::Any => write!(f, "*|"),
+      NamespacePrefix::None => write!(f, "|"),
+      NamespacePrefix::Named(prefix) => write!(f, "{}|", serialize_identifier(prefix)),
+    }
+  }
+}
+
+impl fmt::Display for SimpleSelector {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      SimpleSelector::Type { namespace, name } => {
+        if let Some(namespace) = namespace {
+          write!(f, "{}", namespace)?;
+        }
+        write!(f, "{}", serialize_identifier(name))
+      }
+      SimpleSelector::Universal { namespace } => {
+        if let Some(namespace) = namespace {
+          write!(f, "{}", namespace)?;
+        }
+        write!(f, "*")
+      }
+      SimpleSelector::Id(id) => write!(f, "#{}", serialize_identifier(id)),
+      SimpleSelector::Class(class) => write!(f, ".{}", serialize_identifier(class)),
+      SimpleSelector::Attribute(attribute) => write!(f, "{}", attribute),
+      SimpleSelector::PseudoClass(pseudo_class) => write!(f, "{}", pseudo_class),
+      SimpleSelector::PseudoElement(pseudo_element) => write!(f, "{}", pseudo_element),
+      SimpleSelector::Nesting => write!(f, "&"),
+    }
+  }
+}
+
+impl fmt::Display for AttributeSelector {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "[")?;
+    if let Some(namespace) = &self.namespace {
+      write!(f, "{}", namespace)?;
+    }
+    write!(f, "{}", serialize_identifier(&self.name))?;
+
+    if let Some((operator, value)) = &self.operation {
+      write!(f, "{}{}", operator, serialize_string(value))?;
+    }
+    match self.case_sensitivity {
+      Some(CaseSensitivity::AsciiCaseInsensitive) => write!(f, " i")?,
+      Some(CaseSensitivity::CaseSensitive) => write!(f, " s")?,
+      None => {}
+    }
+
+    write!(f, "]")
+  }
+}
+
+impl fmt::Display for AttributeOperator {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let symbol = match self {
+      AttributeOperator::Equals => "=",
+      AttributeOperator::Includes => "~=",
+      AttributeOperator::DashMatch => "|=",
+      AttributeOperator::Prefix => "^=",
+      AttributeOperator::Suffix => "$=",
+      AttributeOperator::Substring => "*=",
+    };
+
+    write!(f, "{}", symbol)
+  }
+}
+
+impl fmt::Display for PseudoClass {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let nth_with_selector = |f: &mut fmt::Formatter<'_>, name: &str, nth: &Nth, of: &Option<SelectorList>| {
+      match of {
+        Some(selectors) => write!(f, ":{}({} of {})", name, nth, selectors),
+        None => write!(f, ":{}({})", name, nth),
+      }
+    };
+
+    match self {
+      PseudoClass::Simple(name) => write!(f, ":{}", serialize_identifier(name)),
+      PseudoClass::Not(selectors) => write!(f, ":not({})", selectors),
+      PseudoClass::Is(selectors) => write!(f, ":is({})", selectors),
+      PseudoClass::Where(selectors) => write!(f, ":where({})", selectors),
+      PseudoClass::Has(selectors) => write!(f, ":has({})", selectors),
+      PseudoClass::NthChild(nth, of) => nth_with_selector(f, "nth-child", nth, of),
+      PseudoClass::NthLastChild(nth, of) => nth_with_selector(f, "nth-last-child", nth, of),
+      PseudoClass::NthOfType(nth) => write!(f, ":nth-of-type({})", nth),
+      PseudoClass::NthLastOfType(nth) => write!(f, ":nth-last-of-type({})", nth),
+      PseudoClass::Function { name, arguments } => write!(f, ":{}({})", serialize_identifier(name), arguments),
+    }
+  }
+}
+
+impl fmt::Display for Nth {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self.a {
+      0 => return write!(f, "{}", self.b),
+      1 => write!(f, "n")?,
+      -1 => write!(f, "-n")?,
+      a => write!(f, "{}n", a)?,
+    }
+    match self.b {
+      0 => Ok(()),
+      b if b > 0 => write!(f, "+{}", b),
+      b => write!(f, "{}", b),
+    }
+  }
+}
+
+impl fmt::Display for PseudoElement {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "::{}", serialize_identifier(&self.name))?;
+    if let Some(arguments) = &self.arguments {
+      write!(f, "({})", arguments)?;
+    }
+
+    Ok(())
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::css_rule::CSSRule;
+
+  fn compound(components: Vec<SimpleSelector>) -> CompoundSelector {
+    CompoundSelector { components }
+  }
+
+  fn class(name: &str) -> SimpleSelector {
+    SimpleSelector::Class(name.to_string())
+  }
+
+  fn element(name: &str) -> SimpleSelector {
+    SimpleSelector::Type { namespace: None, name: name.to_string() }
+  }
+
+  fn roundtrip(input: &str) -> String {
+    SelectorList::from_string(input).unwrap().to_string()
+  }
+
+  #[test]
+  fn test_parse_compound_selector() {
+    let list = SelectorList::from_string("div#main.a.b").unwrap();
+    assert_eq!(list.selectors[0].first, compound(vec![
+      element("div"),
+      SimpleSelector::Id("main".to_string()),
+      class("a"),
+      class("b"),
+    ]));
+    assert!(list.selectors[0].rest.is_empty());
+  }
+
+  #[test]
+  fn test_parse_combinators() {
+    let list = SelectorList::from_string("a b>c + d  ~e").unwrap();
+    let selector = &list.selectors[0];
+    assert_eq!(selector.first, compound(vec![element("a")]));
+    let combinators: Vec<Combinator> = selector.rest.iter().map(|(combinator, _)| *combinator).collect();
+    assert_eq!(combinators, vec![
+      Combinator::Descendant,
+      Combinator::Child,
+      Combinator::NextSibling,
+      Combinator::SubsequentSibling,
+    ]);
+    assert_eq!(list.to_string(), "a b > c + d ~ e");
+  }
+
+  #[test]
+  fn test_parse_selector_list() {
+    let list = SelectorList::from_string(" h1 ,h2.title,\n*").unwrap();
+    assert_eq!(list.selectors.len(), 3);
+    assert_eq!(list.selectors[2].first, compound(vec![SimpleSelector::Universal { namespace: None }]));
+    assert_eq!(list.to_string(), "h1, h2.title, *");
+  }
+
+  #[test]
+  fn test_parse_namespaces() {
+    let list = SelectorList::from_string("svg|rect, *|a, |b, ns|*").unwrap();
+    assert_eq!(list.selectors[0].first.components[0], SimpleSelector::Type {
+      namespace: Some(NamespacePrefix::Named("svg".to_string())),
+      name: "rect".to_string(),
+    });
+    assert_eq!(list.selectors[1].first.components[0], SimpleSelector::Type {
+      namespace: Some(NamespacePrefix::Any),
+      name: "a".to_string(),
+    });
+    assert_eq!(list.selectors[2].first.components[0], SimpleSelector::Type {
+      namespace: Some(NamespacePrefix::None),
+      name: "b".to_string(),
+    });
+    assert_eq!(list.to_string(), "svg|rect, *|a, |b, ns|*");
+  }
+
+  #[test]
+  fn test_parse_attribute_selectors() {
+    let list = SelectorList::from_string("[href], [ lang |= en ], a[title='x y' i], [xlink|href$=\".svg\" s]").unwrap();
+    let attribute = |i: usize| match &list.selectors[i].first.components.last() {
+      Some(SimpleSelector::Attribute(attribute)) => attribute.clone(),
+      other => panic!("expected an attribute selector, got {:?}", other),
+    };
+
+    assert_eq!(attribute(0).operation, None);
+    assert_eq!(attribute(1).operation, Some((AttributeOperator::DashMatch, "en".to_string())));
+    assert_eq!(attribute(2).case_sensitivity, Some(CaseSensitivity::AsciiCaseInsensitive));
+    assert_eq!(attribute(3).namespace, Some(NamespacePrefix::Named("xlink".to_string())));
+    assert_eq!(list.to_string(), "[href], [lang|=\"en\"], a[title=\"x y\" i], [xlink|href$=\".svg\" s]");
+  }
+
+  #[test]
+  fn test_parse_pseudo_classes() {
+    let list = SelectorList::from_string("a:HOVER:not(.x, .y):is(b):where(c):lang(en)").unwrap();
+    let components = &list.selectors[0].first.components;
+    assert_eq!(components[1], SimpleSelector::PseudoClass(PseudoClass::Simple("hover".to_string())));
+    match &components[2] {
+      SimpleSelector::PseudoClass(PseudoClass::Not(list)) => assert_eq!(list.selectors.len(), 2),
+      other => panic!("expected :not(), got {:?}", other),
+    }
+    assert_eq!(components[5], SimpleSelector::PseudoClass(PseudoClass::Function {
+      name: "lang".to_string(),
+      arguments: "en".to_string(),
+    }));
+    assert_eq!(list.to_string(), "a:hover:not(.x, .y):is(b):where(c):lang(en)");
+  }
+
+  #[test]
+  fn test_parse_has_with_relative_selectors() {
+    let list = SelectorList::from_string("a:has(> img, + p, span)").unwrap();
+    let SimpleSelector::PseudoClass(PseudoClass::Has(relative)) = &list.selectors[0].first.components[1] else {
+      panic!("expected :has()");
+    };
+    assert_eq!(relative.selectors[0].leading_combinator, Some(Combinator::Child));
+    assert_eq!(relative.selectors[1].leading_combinator, Some(Combinator::NextSibling));
+    assert_eq!(relative.selectors[2].leading_combinator, None);
+    assert_eq!(list.to_string(), "a:has(> img, + p, span)");
+  }
+
+  #[test]
+  fn test_parse_nth() {
+    let nth = |text: &str| Nth::parse(text).unwrap().1;
+    assert_eq!(nth("odd"), Nth { a: 2, b: 1 });
+    assert_eq!(nth("EVEN"), Nth { a: 2, b: 0 });
+    assert_eq!(nth("3"), Nth { a: 0, b: 3 });
+    assert_eq!(nth("-n+3"), Nth { a: -1, b: 3 });
+    assert_eq!(nth("2n + 1"), Nth { a: 2, b: 1 });
+    assert_eq!(nth("2n- 1"), Nth { a: 2, b: -1 });
+    assert_eq!(nth("+n"), Nth { a: 1, b: 0 });
+    assert_eq!(nth("-5"), Nth { a: 0, b: -5 });
+    assert!(Nth::parse("2x").is_err());
+  }
+
+  #[test]
+  fn test_parse_nth_child_of_selector() {
+    let list = SelectorList::from_string("li:nth-child(odd of .item):nth-last-of-type(2n)").unwrap();
+    let components = &list.selectors[0].first.components;
+    let SimpleSelector::PseudoClass(PseudoClass::NthChild(nth, Some(of))) = &components[1] else {
+      panic!("expected :nth-child() with a selector");
+    };
+    assert_eq!(*nth, Nth { a: 2, b: 1 });
+    assert_eq!(of.to_string(), ".item");
+    assert_eq!(list.to_string(), "li:nth-child(2n+1 of .item):nth-last-of-type(2n)");
+    assert_eq!(roundtrip("li:nth-child( -n + 3 )"), "li:nth-child(-n+3)");
+  }
+
+  #[test]
+  fn test_parse_pseudo_elements() {
+    let list = SelectorList::from_string("p::first-line, a:before, ::part(label)").unwrap();
+    assert_eq!(list.selectors[1].first.components[1], SimpleSelector::PseudoElement(PseudoElement {
+      name: "before".to_string(),
+      arguments: None,
+    }));
+    assert_eq!(list.to_string(), "p::first-line, a::before, ::part(label)");
+  }
+
+  #[test]
+  fn test_parse_escaped_identifiers() {
+    let list = SelectorList::from_string(".md\\:flex, #\\31 23").unwrap();
+    assert_eq!(list.selectors[0].first.components[0], class("md:flex"));
+    assert_eq!(list.selectors[1].first.components[0], SimpleSelector::Id("123".to_string()));
+    assert_eq!(list.to_string(), ".md\\:flex, #\\31 23");
+  }
+
+  #[test]
+  fn test_parse_nesting_and_relative_selectors() {
+    assert!(SelectorList::from_string("> a").is_err());
+    let list = SelectorList::from_string_relative("> a, &:hover, .x &").unwrap();
+    assert_eq!(list.selectors[0].leading_combinator, Some(Combinator::Child));
+    assert_eq!(list.selectors[1].first.components[0], SimpleSelector::Nesting);
+    assert_eq!(list.to_string(), "> a, &:hover, .x &");
+  }
+
+  #[test]
+  fn test_parse_with_comments() {
+    assert_eq!(roundtrip("a /* x */ > /* y */ b"), "a > b");
+  }
+
+  #[test]
+  fn test_parse_invalid_selectors() {
+    let error = SelectorList::from_string("a > ").unwrap_err();
+    assert_eq!(error.kind, ParseErrorKind::InvalidSelector);
+    assert_eq!(error.offset, 4);
+
+    let error = SelectorList::from_string("a, ").unwrap_err();
+    assert_eq!(error.expected, "a selector");
+
+    let error = SelectorList::from_string("a[href").unwrap_err();
+    assert_eq!(error.expected, "`]`");
+
+    let error = SelectorList::from_string("a:not(.b").unwrap_err();
+    assert_eq!(error.kind, ParseErrorKind::UnexpectedEndOfInput);
+
+    let error = SelectorList::from_string("a:not(.b $)").unwrap_err();
+    assert_eq!((error.offset, error.expected.as_str()), (9, "`)`"));
+
+    let error = SelectorList::from_string("a $").unwrap_err();
+    assert_eq!(error.offset, 2);
+    assert!(SelectorList::from_string("").is_err());
+    assert!(SelectorList::from_string(".1a").is_err());
+    assert!(SelectorList::from_string("a:nth-child(2x)").is_err());
+  }
+
+  fn specificity(selector: &str) -> Specificity {
+    SelectorList::from_string(selector).unwrap().selectors[0].specificity()
+  }
+
+  #[test]
+  fn test_specificity_of_simple_selectors() {
+    assert_eq!(specificity("*"), Specificity::new(0, 0, 0));
+    assert_eq!(specificity("li"), Specificity::new(0, 0, 1));
+    assert_eq!(specificity("ul li"), Specificity::new(0, 0, 2));
+    assert_eq!(specificity("ul ol+li"), Specificity::new(0, 0, 3));
+    assert_eq!(specificity("h1 + *[rel=up]"), Specificity::new(0, 1, 1));
+    assert_eq!(specificity("ul ol li.red"), Specificity::new(0, 1, 3));
+    assert_eq!(specificity("li.red.level"), Specificity::new(0, 2, 1));
+    assert_eq!(specificity("#x34y"), Specificity::new(1, 0, 0));
+    assert_eq!(specificity("a:hover::before"), Specificity::new(0, 1, 2));
+    assert_eq!(specificity("a:before"), Specificity::new(0, 0, 2));
+    assert_eq!(specificity("*|*"), Specificity::new(0, 0, 0));
+  }
+
+  #[test]
+  fn test_specificity_of_logical_pseudo_classes() {
+    assert_eq!(specificity("#s12:not(FOO)"), Specificity::new(1, 0, 1));
+    assert_eq!(specificity(".foo :is(.bar, #baz)"), Specificity::new(1, 1, 0));
+    assert_eq!(specificity("a:not(.b, #c div)"), Specificity::new(1, 0, 2));
+    assert_eq!(specificity(":where(#a, .b) p"), Specificity::new(0, 0, 1));
+    assert_eq!(specificity("div:has(> img.hero)"), Specificity::new(0, 1, 2));
+    assert_eq!(specificity("li:nth-child(2n+1)"), Specificity::new(0, 1, 1));
+    assert_eq!(specificity("li:nth-child(2n+1 of #a, .b)"), Specificity::new(1, 1, 1));
+    assert_eq!(specificity("li:nth-of-type(2)"), Specificity::new(0, 1, 1));
+  }
+
+  #[test]
+  fn test_specificity_ordering() {
+    assert!(Specificity::new(1, 0, 0) > Specificity::new(0, 10, 10));
+    assert!(Specificity::new(0, 2, 0) > Specificity::new(0, 1, 5));
+    assert!(Specificity::new(0, 1, 2) > Specificity::new(0, 1, 1));
+    assert_eq!(SelectorList::from_string("a, #b, .c").unwrap().specificity(), Specificity::new(1, 0, 0));
+  }
+
+  #[test]
+  fn test_specificities_from_rule() {
+    let rule = CSSRule::from_string("#nav a, .menu > li:hover { color: red }").unwrap();
+    assert_eq!(rule.specificities().unwrap(), vec![Specificity::new(1, 0, 1), Specificity::new(0, 2, 1)]);
+  }
+
+  #[test]
+  fn test_selector_list_from_rule() {
+    let rule = CSSRule::from_string("ul > li.item:first-child { color: red }").unwrap();
+    let list = rule.selector_list().unwrap();
+    assert_eq!(list.selectors[0].rest[0], (Combinator::Child, compound(vec![
+      element("li"),
+      class("item"),
+      SimpleSelector::PseudoClass(PseudoClass::Simple("first-child".to_string())),
+    ])));
+    assert_eq!(list.to_string(), rule.selector);
+  }
+}
@@ -0,0 +1,1315 @@
+//! CSS Shorthand Expansion and Collapsing
+//!
+//! This module expands shorthand declarations into their longhands, the way
+//! a browser does when it parses them: `margin: 0 auto` sets `margin-top` and
+//! `margin-bottom` to `0` and `margin-right` and `margin-left` to `auto`. A
+//! shorthand sets every one of its longhands (see `properties`), so the
+//! longhands it does not mention get their initial value. A CSS-wide keyword
+//! such as `inherit` applies to every longhand.
+//!
+//! Supported shorthands:
+//!
+//! - Box sides: `margin`, `padding`, `inset`, `scroll-margin`, `scroll-padding`,
+//!   `border-width`, `border-style`, `border-color` and their `-block` /
+//!   `-inline` forms, `border-radius`
+//! - Borders and lines: `border`, `border-top` ... `border-left`,
+//!   `border-block`, `border-inline` and their `-start` / `-end` forms,
+//!   `border-image`, `outline`, `column-rule`
+//! - `font`, `background` (with several layers), `background-position`,
+//!   `list-style`, `text-decoration`
+//! - Flexbox and grid: `flex`, `flex-flow`, `gap`, `place-content`,
+//!   `place-items`, `place-self`, `grid`, `grid-template`, `grid-area`,
+//!   `grid-row`, `grid-column`
+//! - `transition` and `animation` (with several layers)
+//! - `columns`, `container`, `marker`, `overflow`, `overscroll-behavior` and
+//!   the legacy `page-break-*` properties
+//!
+//! Values with `var()` cannot be expanded before substitution, and neither
+//! can system fonts such as `font: menu`.
+//!
+//! `collapse()` goes the other way, combining longhand values into the
+//! shortest equivalent shorthand value for `margin`, `padding`, `inset`,
+//! `border` and its parts, `border-radius`, `font`, `background`, `flex`,
+//! `gap` and `place-*`.
+//!
+//! ## Main API
+//!
+//! - `expand()` - The longhands of a shorthand value
+//! - `CSSDeclaration::expand_shorthand()` - The longhand declarations of a shorthand declaration
+//! - `CSSDeclarationList::expand_shorthands()` - Replace every shorthand of a list with its longhands
+//! - `collapse()` - The shortest shorthand value for longhand values
+//! - `CSSDeclarationList::collapse_shorthands()` - Combine the longhands of a list into shorthands
+//!
+//! ## Examples
+//!
+//! ```rust
+//! use css_structs::{CSSDeclaration, CSSDeclarationList};
+//! use css_structs::shorthand::expand;
+//!
+//! let mut list = CSSDeclarationList::from_string("margin: 0 auto; color: red").unwrap();
+//! list.expand_shorthands();
+//! list.remove_declaration("margin-top");
+//! assert_eq!(list.to_string(), "margin-right: auto; margin-bottom: 0; margin-left: auto; color: red;");
+//!
+//! let border = CSSDeclaration::from_string("border-top: 2px dashed red !important").unwrap();
+//! let longhands: Vec<String> = border.expand_shorthand().unwrap().iter().map(ToString::to_string).collect();
+//! assert_eq!(longhands, [
+//!   "border-top-width: 2px !important;",
+//!   "border-top-style: dashed !important;",
+//!   "border-top-color: red !important;",
+//! ]);
+//!
+//! let flex = expand("flex", "1").unwrap();
+//! assert_eq!(flex, [("flex-grow", "1".to_string()), ("flex-shrink", "1".to_string()), ("flex-basis", "0%".to_string())]);
+//!
+//! let background = expand("background", "url(a.png) no-repeat, center / cover red").unwrap();
+//! assert_eq!(background[0], ("background-image", "url(\"a.png\"), none".to_string()));
+//! assert_eq!(background[1], ("background-position-x", "0%, center".to_string()));
+//! assert_eq!(background[8], ("background-color", "red".to_string()));
+//!
+//! let mut list = CSSDeclarationList::from_string("
+//!   margin-top: 0; margin-right: 1px; margin-bottom: 0; margin-left: 1px;
+//!   border-top-left-radius: 4px; border-top-right-radius: 4px;
+//!   border-bottom-right-radius: 4px; border-bottom-left-radius: 4px !important;
+//! ").unwrap();
+//! list.collapse_shorthands();
+//! assert_eq!(list.to_string(), "margin: 0 1px; border-top-left-radius: 4px; border-top-right-radius: 4px; \
+//!   border-bottom-right-radius: 4px; border-bottom-left-radius: 4px !important;");
+//! ```
+
+
+use crate::calc::CalcNode;
+use crate::color::Color;
+use crate::dimension::{Dimension, UnitKind};
+use crate::properties::{property, PropertyInfo};
+use crate::value::{ComponentValue, Value};
+
+
+type Token<'a> = &'a ComponentValue;
+
+const CSS_WIDE_KEYWORDS: [&str; 5] = ["inherit", "initial", "unset", "revert", "revert-layer"];
+const LINE_STYLES: [&str; 10] = ["none", "hidden", "dotted", "dashed", "solid", "double", "groove", "ridge", "inset", "outset"];
+const LINE_WIDTHS: [&str; 3] = ["thin", "medium", "thick"];
+const FONT_SIZES: [&str; 11] = ["xx-small", "x-small", "small", "medium", "large", "x-large", "xx-large", "xxx-large", "larger", "smaller", "math"];
+const FONT_STRETCHES: [&str; 8] = [
+  "ultra-condensed", "extra-condensed", "condensed", "semi-condensed",
+  "semi-expanded", "expanded", "extra-expanded", "ultra-expanded",
+];
+const TIMING_FUNCTIONS: [&str; 7] = ["ease", "linear", "ease-in", "ease-out", "ease-in-out", "step-start", "step-end"];
+const BOXES: [&str; 3] = ["border-box", "padding-box", "content-box"];
+
+fn keyword(token: Token) -> Option<String> {
+  token.as_ident().map(str::to_ascii_lowercase)
+}
+
+fn is_keyword_in(token: Token, keywords: &[&str]) -> bool {
+  keyword(token).is_some_and(|keyword| keywords.contains(&keyword.as_str()))
+}
+
+fn is_math_function(token: Token) -> bool {
+  matches!(token, ComponentValue::Function { name, .. } if CalcNode::is_math_function(name))
+}
+
+/// A length or percentage, including a unitless zero and math functions.
+fn is_length_percentage(token: Token) -> bool {
+  match token {
+    ComponentValue::Number(number) => *number == 0.0,
+    ComponentValue::Percentage(_) => true,
+    ComponentValue::Dimension { .. } => Dimension::from_component(token).is_some_and(|dimension| dimension.unit.kind() == UnitKind::Length),
+    token => is_math_function(token),
+  }
+}
+
+/// A length, including a unitless zero and math functions.
+fn is_length(token: Token) -> bool {
+  match token {
+    ComponentValue::Percentage(_) => false,
+    token => is_length_percentage(token),
+  }
+}
+
+fn is_negative(token: Token) -> bool {
+  match token {
+    ComponentValue::Number(value) | ComponentValue::Percentage(value) | ComponentValue::Dimension { value, .. } => *value < 0.0,
+    _ => false,
+  }
+}
+
+fn is_time(token: Token) -> bool {
+  match token {
+    ComponentValue::Dimension { .. } => Dimension::from_component(token).is_some_and(|dimension| dimension.unit.kind() == UnitKind::Time),
+    token => is_math_function(token),
+  }
+}
+
+fn is_number(token: Token) -> bool {
+  matches!(token, ComponentValue::Number(_)) || is_math_function(token)
+}
+
+fn is_color(token: Token) -> bool {
+  Color::from_component(token).is_some()
+}
+
+fn is_image(token: Token) -> bool {
+  match token {
+    ComponentValue::Url(_) => true,
+    ComponentValue::Function { name, .. } => {
+      let name = name.to_ascii_lowercase();
+      name.ends_with("gradient") || ["image", "image-set", "cross-fade", "element", "paint"].contains(&name.as_str())
+    }
+    _ => false,
+  }
+}
+
+fn is_position(token: Token) -> bool {
+  is_keyword_in(token, &["left", "right", "top", "bottom", "center"]) || is_length_percentage(token)
+}
+
+/// Joins tokens with spaces, without a space before commas.
+fn join(tokens: &[Token]) -> String {
+  let mut output = String::new();
+  for (index, token) in tokens.iter().enumerate() {
+    if index > 0 && **token != ComponentValue::Comma {
+      output.push(' ');
+    }
+    output.push_str(&token.to_string());
+  }
+  output
+}
+
+/// Splits tokens at top-level commas or slashes.
+fn split<'a>(tokens: &[Token<'a>], separator: &ComponentValue) -> Vec<Vec<Token<'a>>> {
+  tokens.split(|token| *token == separator).map(<[Token]>::to_vec).collect()
+}
+
+/// Whether a token is valid for one of the sides of a box shorthand such as
+/// `margin`, or one of the two values of a shorthand such as `gap`.
+fn is_side_value(shorthand: &str, token: Token) -> bool {
+  let non_negative = |token: Token| is_length_percentage(token) && !is_negative(token);
+  match shorthand {
+    "margin" | "margin-block" | "margin-inline" | "inset" | "inset-block" | "inset-inline" => {
+      is_length_percentage(token) || token.is_ident("auto")
+    }
+    "padding" | "padding-block" | "padding-inline" => non_negative(token),
+    "scroll-margin" => is_length(token),
+    "scroll-padding" => non_negative(token) || token.is_ident("auto"),
+    "overflow" => is_keyword_in(token, &["visible", "hidden", "clip", "scroll", "auto"]),
+    "overscroll-behavior" => is_keyword_in(token, &["contain", "none", "auto"]),
+    "gap" => non_negative(token) || token.is_ident("normal"),
+    name if name.ends_with("-width") => is_length(token) && !is_negative(token) || is_keyword_in(token, &LINE_WIDTHS),
+    name if name.ends_with("-style") => is_keyword_in(token, &LINE_STYLES),
+    name if name.ends_with("-color") => is_color(token),
+    _ => false,
+  }
+}
+
+/// The values of the four sides from one to four values: top, right,
+/// bottom and left. Returns `None` when `accept` rejects one of them.
+fn sides(tokens: &[Token], accept: &dyn Fn(Token) -> bool) -> Option<[String; 4]> {
+  if !tokens.iter().all(|token| accept(token)) {
+    return None;
+  }
+  let values: Vec<String> = tokens.iter().map(ToString::to_string).collect();
+  match values.as_slice() {
+    [all] => Some([all.clone(), all.clone(), all.clone(), all.clone()]),
+    [vertical, horizontal] => Some([vertical.clone(), horizontal.clone(), vertical.clone(), horizontal.clone()]),
+    [top, horizontal, bottom] => Some([top.clone(), horizontal.clone(), bottom.clone(), horizontal.clone()]),
+    [top, right, bottom, left] => Some([top.clone(), right.clone(), bottom.clone(), left.clone()]),
+    _ => None,
+  }
+}
+
+/// Groups `first` / `last` with `baseline` and `safe` / `unsafe` with the
+/// alignment they modify.
+fn alignment_groups(tokens: &[Token]) -> Vec<String> {
+  let mut groups: Vec<String> = Vec::new();
+  let mut modifier = false;
+  for token in tokens {
+    match modifier {
+      true => groups.last_mut().unwrap().push_str(&format!(" {}", token)),
+      false => groups.push(token.to_string()),
+    }
+    modifier = is_keyword_in(token, &["first", "last", "safe", "unsafe"]);
+  }
+  groups
+}
+
+/// One or two values, the second defaulting to the first.
+fn pair(values: Vec<String>) -> Option<Vec<String>> {
+  match values.as_slice() {
+    [both] => Some(vec![both.clone(), both.clone()]),
+    [_, _] => Some(values),
+    _ => None,
+  }
+}
+
+/// The width, style and color of a border or outline, in any order. An
+/// outline takes `auto` as its style first, then as its color, and has no
+/// `hidden` style.
+fn line(tokens: &[Token], outline: bool) -> Option<[Option<String>; 3]> {
+  let mut parts: [Option<String>; 3] = [None, None, None];
+  for token in tokens {
+    let index = if is_length_percentage(token) || is_keyword_in(token, &LINE_WIDTHS) {
+      0
+    } else if is_keyword_in(token, &LINE_STYLES) && !(outline && token.is_ident("hidden")) || outline && token.is_ident("auto") && parts[1].is_none() {
+      1
+    } else if is_color(token) || outline && token.is_ident("auto") {
+      2
+    } else {
+      return None;
+    };
+    if parts[index].replace(token.to_string()).is_some() {
+      return None;
+    }
+  }
+  Some(parts)
+}
+
+/// Splits a `<bg-position>` into its horizontal and vertical parts.
+fn position(tokens: &[Token]) -> Option<(String, String)> {
+  let is_vertical = |token: Token| is_keyword_in(token, &["top", "bottom"]);
+  let is_horizontal = |token: Token| is_keyword_in(token, &["left", "right"]);
+  match tokens {
+    [only] if is_vertical(only) => Some(("center".to_string(), only.to_string())),
+    [only] => Some((only.to_string(), "center".to_string())),
+    [first, second] if is_vertical(first) || is_horizontal(second) => Some((second.to_string(), first.to_string())),
+    [first, second] => Some((first.to_string(), second.to_string())),
+    _ => {
+      // Three or four values: keywords, each followed by an optional offset
+      let mut groups: Vec<Vec<Token>> = Vec::new();
+      for token in tokens {
+        match (token.as_ident(), groups.last_mut()) {
+          (None, Some(group)) if group.len() == 1 => group.push(token),
+          (Some(_), _) => groups.push(vec![token]),
+          _ => return None,
+        }
+      }
+      let [first, second] = groups.as_slice() else { return None };
+      match (is_vertical(first[0]) || is_horizontal(second[0]), first[0].is_ident("center") && second[0].is_ident("center")) {
+        (_, true) => None,
+        (true, false) => Some((join(second), join(first))),
+        (false, false) => Some((join(first), join(second))),
+      }
+    }
+  }
+}
+
+/// Assigns each of `values` to the longhand of `info` whose name ends with
+/// its suffix, such as `-width`.
+fn by_suffix(info: &PropertyInfo, values: &[(&str, Option<String>)]) -> Vec<(&'static str, Option<String>)> {
+  info.longhands
+    .iter()
+    .map(|longhand| {
+      // `border` only resets `border-image`
+      let value = match longhand.starts_with("border-image-") {
+        true => None,
+        false => values.iter().find(|(suffix, _)| longhand.ends_with(suffix)).and_then(|(_, value)| value.clone()),
+      };
+      (*longhand, value)
+    })
+    .collect()
+}
+
+fn font(tokens: &[Token]) -> Option<Vec<Option<String>>> {
+  let (mut style, mut caps, mut weight, mut stretch) = (None, None, None, None);
+  let mut index = 0;
+  while let Some(token) = tokens.get(index) {
+    let slot = match keyword(token).as_deref() {
+      Some("normal") => None,
+      Some("italic" | "oblique") => Some(&mut style),
+      Some("small-caps") => Some(&mut caps),
+      Some("bold" | "bolder" | "lighter") => Some(&mut weight),
+      Some(stretch_keyword) if FONT_STRETCHES.contains(&stretch_keyword) => Some(&mut stretch),
+      None if matches!(token, ComponentValue::Number(number) if (1.0..=1000.0).contains(number)) => Some(&mut weight),
+      _ => break,
+    };
+    if let Some(slot) = slot && slot.replace(token.to_string()).is_some() {
+      return None;
+    }
+    index += 1;
+  }
+  if index > 4 {
+    return None;
+  }
+
+  let size = tokens.get(index).filter(|token| is_length_percentage(token) || is_keyword_in(token, &FONT_SIZES))?;
+  index += 1;
+  let mut line_height = None;
+  if tokens.get(index) == Some(&&ComponentValue::Slash) {
+    line_height = Some(tokens.get(index + 1)?.to_string());
+    index += 2;
+  }
+  let family = &tokens[index..];
+  if family.is_empty() {
+    return None;
+  }
+
+  Some(vec![style, caps, weight, stretch, Some(size.to_string()), line_height, Some(join(family))])
+}
+
+#[derive(Default)]
+struct BackgroundLayer {
+  image: Option<String>,
+  position: Option<(String, String)>,
+  size: Option<String>,
+  repeat: Option<String>,
+  attachment: Option<String>,
+  origin: Option<String>,
+  clip: Option<String>,
+  color: Option<String>,
+}
+
+fn background_layer(tokens: &[Token], last: bool) -> Option<BackgroundLayer> {
+  let mut layer = BackgroundLayer::default();
+  let mut index = 0;
+  while let Some(token) = tokens.get(index) {
+    if (is_image(token) || token.is_ident("none")) && layer.image.is_none() {
+      layer.image = Some(token.to_string());
+    } else if is_position(token) && layer.position.is_none() {
+      let end = tokens[index..].iter().position(|token| !is_position(token)).map_or(tokens.len(), |end| index + end);
+      layer.position = Some(position(&tokens[index..end.min(index + 4)])?);
+      index = end.min(index + 4);
+      if tokens.get(index) == Some(&&ComponentValue::Slash) {
+        let is_size = |token: &&Token| is_length_percentage(token) || token.is_ident("auto");
+        let size: Vec<Token> = match tokens.get(index + 1) {
+          Some(token) if is_keyword_in(token, &["cover", "contain"]) => vec![token],
+          _ => tokens[index + 1..].iter().take_while(is_size).take(2).copied().collect(),
+        };
+        if size.is_empty() {
+          return None;
+        }
+        layer.size = Some(join(&size));
+        index += 1 + size.len();
+      }
+      continue;
+    } else if is_keyword_in(token, &["repeat-x", "repeat-y"]) && layer.repeat.is_none() {
+      layer.repeat = Some(token.to_string());
+    } else if is_keyword_in(token, &["repeat", "space", "round", "no-repeat"]) && layer.repeat.is_none() {
+      let is_repeat = |token: &&Token| is_keyword_in(token, &["repeat", "space", "round", "no-repeat"]);
+      let repeat: Vec<Token> = tokens[index..].iter().take_while(is_repeat).take(2).copied().collect();
+      layer.repeat = Some(join(&repeat));
+      index += repeat.len();
+      continue;
+    } else if is_keyword_in(token, &["scroll", "fixed", "local"]) && layer.attachment.is_none() {
+      layer.attachment = Some(token.to_string());
+    } else if is_keyword_in(token, &BOXES) && layer.origin.is_none() {
+      layer.origin = Some(token.to_string());
+    } else if (is_keyword_in(token, &BOXES) || token.is_ident("text")) && layer.clip.is_none() {
+      layer.clip = Some(token.to_string());
+    } else if last && is_color(token) && layer.color.is_none() {
+      layer.color = Some(token.to_string());
+    } else {
+      return None;
+    }
+    index += 1;
+  }
+
+  // A single box sets both the origin and the clip
+  if layer.clip.is_none() {
+    layer.clip = layer.origin.clone();
+  }
+  Some(layer)
+}
+
+fn background(tokens: &[Token]) -> Option<Vec<Option<String>>> {
+  let layers = split(tokens, &ComponentValue::Comma);
+  let layers: Vec<BackgroundLayer> = layers
+    .iter()
+    .enumerate()
+    .map(|(index, layer)| background_layer(layer, index == layers.len() - 1))
+    .collect::<Option<_>>()?;
+
+  let column = |value: &dyn Fn(&BackgroundLayer) -> Option<String>, initial: &str| {
+    Some(layers.iter().map(|layer| value(layer).unwrap_or_else(|| initial.to_string())).collect::<Vec<_>>().join(", "))
+  };
+  Some(vec![
+    column(&|layer| layer.image.clone(), "none"),
+    column(&|layer| layer.position.as_ref().map(|position| position.0.clone()), "0%"),
+    column(&|layer| layer.position.as_ref().map(|position| position.1.clone()), "0%"),
+    column(&|layer| layer.size.clone(), "auto"),
+    column(&|layer| layer.repeat.clone(), "repeat"),
+    column(&|layer| layer.attachment.clone(), "scroll"),
+    column(&|layer| layer.origin.clone(), "padding-box"),
+    column(&|layer| layer.clip.clone(), "border-box"),
+    layers.last().and_then(|layer| layer.color.clone()),
+  ])
+}
+
+fn border_radius(tokens: &[Token]) -> Option<Vec<Option<String>>> {
+  let parts = split(tokens, &ComponentValue::Slash);
+  let radius = |token: Token| is_length_percentage(token) && !is_negative(token);
+  let (horizontal, vertical) = match parts.as_slice() {
+    [horizontal] => (sides(horizontal, &radius)?, sides(horizontal, &radius)?),
+    [horizontal, vertical] => (sides(horizontal, &radius)?, sides(vertical, &radius)?),
+    _ => return None,
+  };
+  let corners = horizontal.into_iter().zip(vertical).map(|(horizontal, vertical)| match horizontal == vertical {
+    true => Some(horizontal),
+    false => Some(format!("{} {}", horizontal, vertical)),
+  });
+  Some(corners.collect())
+}
+
+fn border_image(tokens: &[Token]) -> Option<Vec<Option<String>>> {
+  let (mut source, mut slice, mut width, mut outset, mut repeat) = (None, None, None, None, None);
+  let mut index = 0;
+  let run = |start: usize, accept: &dyn Fn(Token) -> bool| {
+    tokens[start..].iter().position(|token| !accept(token)).map_or(tokens.len(), |end| start + end)
+  };
+  while let Some(token) = tokens.get(index) {
+    if (is_image(token) || token.is_ident("none")) && source.is_none() {
+      source = Some(token.to_string());
+      index += 1;
+    } else if is_keyword_in(token, &["stretch", "repeat", "round", "space"]) && repeat.is_none() {
+      let end = run(index, &|token| is_keyword_in(token, &["stretch", "repeat", "round", "space"])).min(index + 2);
+      repeat = Some(join(&tokens[index..end]));
+      index = end;
+    } else if (is_number(token) || matches!(token, ComponentValue::Percentage(_)) || token.is_ident("fill")) && slice.is_none() {
+      let is_slice = |token: Token| is_number(token) || matches!(token, ComponentValue::Percentage(_)) || token.is_ident("fill");
+      let end = run(index, &is_slice);
+      slice = Some(join(&tokens[index..end]));
+      index = end;
+      // `/ width` and `/ / outset` or `/ width / outset`
+      let is_width = |token: Token| is_length_percentage(token) || is_number(token) || token.is_ident("auto");
+      if tokens.get(index) == Some(&&ComponentValue::Slash) {
+        let end = run(index + 1, &is_width);
+        width = Some(join(&tokens[index + 1..end])).filter(|width| !width.is_empty());
+        index = end;
+        if tokens.get(index) == Some(&&ComponentValue::Slash) {
+          let end = run(index + 1, &|token| is_length_percentage(token) || is_number(token));
+          outset = Some(join(&tokens[index + 1..end])).filter(|outset| !outset.is_empty());
+          outset.as_ref()?;
+          index = end;
+        } else {
+          width.as_ref()?;
+        }
+      }
+    } else {
+      return None;
+    }
+  }
+  Some(vec![source, slice, width, outset, repeat])
+}
+
+fn flex(tokens: &[Token]) -> Option<Vec<Option<String>>> {
+  let value = |value: &str| Some(value.to_string());
+  if let [only] = tokens {
+    match keyword(only).as_deref() {
+      Some("none") => return Some(vec![value("0"), value("0"), value("auto")]),
+      Some("auto") => return Some(vec![value("1"), value("1"), value("auto")]),
+      _ => {}
+    }
+  }
+
+  // The grow and shrink factors are adjacent, before or after the basis
+  let (mut grow, mut shrink, mut basis) = (None, None, None);
+  let mut after_grow = false;
+  for token in tokens {
+    let number = matches!(token, ComponentValue::Number(_));
+    if number && grow.is_none() {
+      grow = Some(token.to_string());
+    } else if number && after_grow && shrink.is_none() {
+      shrink = Some(token.to_string());
+    } else if (is_length_percentage(token) || token.as_ident().is_some()) && basis.is_none() {
+      basis = Some(token.to_string());
+    } else {
+      return None;
+    }
+    after_grow = number && shrink.is_none();
+  }
+  // An omitted grow is 1, an omitted shrink 1 and an omitted basis 0%
+  Some(vec![grow.or(value("1")), shrink.or(value("1")), basis.or(value("0%"))])
+}
+
+/// Assigns each token to the first of `slots` that accepts it and is free.
+fn any_order(tokens: &[Token], slots: &[&dyn Fn(Token) -> bool]) -> Option<Vec<Option<String>>> {
+  let mut values = vec![None; slots.len()];
+  for token in tokens {
+    let slot = (0..slots.len()).find(|index| values[*index].is_none() && slots[*index](token))?;
+    values[slot] = Some(token.to_string());
+  }
+  Some(values)
+}
+
+fn is_timing_function(token: Token) -> bool {
+  is_keyword_in(token, &TIMING_FUNCTIONS)
+    || matches!(token, ComponentValue::Function { name, .. } if ["cubic-bezier", "steps", "linear"].iter().any(|function| name.eq_ignore_ascii_case(function)))
+}
+
+/// Expands a list of layers with `expand_layer`, joining each longhand's
+/// values with commas.
+fn layers(tokens: &[Token], initials: &[&str], expand_layer: impl Fn(&[Token]) -> Option<Vec<Option<String>>>) -> Option<Vec<Option<String>>> {
+  let layers = split(tokens, &ComponentValue::Comma).iter().map(|layer| expand_layer(layer)).collect::<Option<Vec<_>>>()?;
+  let column = |index: usize| {
+    let values: Vec<String> = layers.iter().map(|layer| layer[index].clone().unwrap_or_else(|| initials[index].to_string())).collect();
+    Some(values.join(", "))
+  };
+  Some((0..initials.len()).map(column).collect())
+}
+
+fn transition(tokens: &[Token]) -> Option<Vec<Option<String>>> {
+  // property, duration, timing function, delay, behavior
+  layers(tokens, &["all", "0s", "ease", "0s", "normal"], |layer| {
+    let mut values = vec![None; 5];
+    for token in layer {
+      let slot = if is_time(token) {
+        if values[1].is_none() { 1 } else { 3 }
+      } else if is_timing_function(token) {
+        2
+      } else if is_keyword_in(token, &["normal", "allow-discrete"]) {
+        4
+      } else if token.as_ident().is_some() {
+        0
+      } else {
+        return None;
+      };
+      if values[slot].replace(token.to_string()).is_some() {
+        return None;
+      }
+    }
+    Some(values)
+  })
+}
+
+fn animation(tokens: &[Token]) -> Option<Vec<Option<String>>> {
+  // duration, timing function, delay, iteration count, direction, fill mode, play state, name
+  layers(tokens, &["0s", "ease", "0s", "1", "normal", "none", "running", "none"], |layer| {
+    let mut values = vec![None; 8];
+    for token in layer {
+      let free = |slot: usize, values: &Vec<Option<String>>| values[slot].is_none();
+      let slot = if is_time(token) {
+        if free(0, &values) { 0 } else { 2 }
+      } else if is_timing_function(token) && free(1, &values) {
+        1
+      } else if (is_number(token) || token.is_ident("infinite")) && free(3, &values) {
+        3
+      } else if is_keyword_in(token, &["normal", "reverse", "alternate", "alternate-reverse"]) && free(4, &values) {
+        4
+      } else if token.is_ident("none") && free(7, &values) {
+        7
+      } else if is_keyword_in(token, &["none", "forwards", "backwards", "both"]) && free(5, &values) {
+        5
+      } else if is_keyword_in(token, &["running", "paused"]) && free(6, &values) {
+        6
+      } else if (token.as_ident().is_some() || matches!(token, ComponentValue::String(_))) && free(7, &values) {
+        7
+      } else {
+        return None;
+      };
+      values[slot] = Some(token.to_string());
+    }
+    Some(values)
+  })
+}
+
+fn list_style(tokens: &[Token]) -> Option<Vec<Option<String>>> {
+  let (mut position, mut image, mut kind) = (None, None, None);
+  let mut nones = 0;
+  for token in tokens {
+    if is_keyword_in(token, &["inside", "outside"]) && position.is_none() {
+      position = Some(token.to_string());
+    } else if token.is_ident("none") {
+      nones += 1;
+    } else if is_image(token) && image.is_none() {
+      image = Some(token.to_string());
+    } else if (token.as_ident().is_some() || matches!(token, ComponentValue::String(_) | ComponentValue::Function { .. })) && kind.is_none() {
+      kind = Some(token.to_string());
+    } else {
+      return None;
+    }
+  }
+  // `none` sets whichever of the image and the type is not otherwise set,
+  // both when neither is
+  if nones == 1 && image.is_none() && kind.is_none() {
+    return Some(vec![position, Some("none".to_string()), Some("none".to_string())]);
+  }
+  for _ in 0..nones {
+    let slot = if image.is_none() { &mut image } else if kind.is_none() { &mut kind } else { return None };
+    *slot = Some("none".to_string());
+  }
+  Some(vec![position, image, kind])
+}
+
+fn text_decoration(tokens: &[Token]) -> Option<Vec<Option<String>>> {
+  let (mut lines, mut style, mut color, mut thickness) = (Vec::new(), None, None, None);
+  for token in tokens {
+    let slot = if is_keyword_in(token, &["none", "underline", "overline", "line-through", "blink"]) {
+      lines.push(token.to_string());
+      continue;
+    } else if is_keyword_in(token, &["solid", "double", "dotted", "dashed", "wavy"]) {
+      &mut style
+    } else if is_length_percentage(token) || is_keyword_in(token, &["auto", "from-font"]) {
+      &mut thickness
+    } else if is_color(token) {
+      &mut color
+    } else {
+      return None;
+    };
+    if slot.replace(token.to_string()).is_some() {
+      return None;
+    }
+  }
+  let lines = (!lines.is_empty()).then(|| lines.join(" "));
+  Some(vec![lines, style, color, thickness])
+}
+
+/// A grid line is repeated for the omitted end when it is a custom
+/// identifier, and `auto` otherwise.
+fn grid_lines(tokens: &[Token], count: usize) -> Option<Vec<Option<String>>> {
+  let parts = split(tokens, &ComponentValue::Slash);
+  if parts.len() > count || parts.iter().any(Vec::is_empty) {
+    return None;
+  }
+  let is_custom_ident = |part: &Vec<Token>| matches!(part.as_slice(), [token] if token.as_ident().is_some_and(|name| !name.eq_ignore_ascii_case("auto") && !name.eq_ignore_ascii_case("span")));
+  // Row-start, column-start, row-end and column-end for `grid-area`, where
+  // the column-start and row-end default to the row-start and the
+  // column-end to the column-start
+  let mut values: Vec<(String, bool)> = Vec::new();
+  for index in 0..count {
+    let value = match parts.get(index) {
+      Some(part) => (join(part), is_custom_ident(part)),
+      None => {
+        let start = &values[if index == 3 { 1 } else { 0 }];
+        if start.1 { start.clone() } else { ("auto".to_string(), false) }
+      }
+    };
+    values.push(value);
+  }
+  Some(values.into_iter().map(|(value, _)| Some(value)).collect())
+}
+
+/// `grid-template`: rows, columns and areas.
+fn grid_template(tokens: &[Token]) -> Option<Vec<Option<String>>> {
+  if let [only] = tokens && only.is_ident("none") {
+    return Some(vec![Some("none".to_string()); 3]);
+  }
+  let parts = split(tokens, &ComponentValue::Slash);
+  let (rows, columns) = match parts.as_slice() {
+    [rows, columns] if !rows.is_empty() && !columns.is_empty() => (rows, Some(columns)),
+    [rows] if rows.iter().any(|token| matches!(token, ComponentValue::String(_))) => (rows, None),
+    _ => return None,
+  };
+  if !rows.iter().any(|token| matches!(token, ComponentValue::String(_))) {
+    return Some(vec![Some(join(rows)), Some(join(columns?)), Some("none".to_string())]);
+  }
+
+  // `[names]? "area" size? [names]?`, each row's size defaulting to `auto`
+  let mut row_sizes: Vec<String> = Vec::new();
+  let mut areas: Vec<String> = Vec::new();
+  let mut pending_size = false;
+  for token in rows {
+    match token {
+      ComponentValue::String(_) => {
+        if pending_size {
+          row_sizes.push("auto".to_string());
+        }
+        areas.push(token.to_string());
+        pending_size = true;
+      }
+      ComponentValue::Block { bracket: '[', .. } => {
+        // Line names right after an area end a row without a size
+        if pending_size {
+          row_sizes.push("auto".to_string());
+          pending_size = false;
+        }
+        row_sizes.push(token.to_string());
+      }
+      _ if pending_size => {
+        row_sizes.push(token.to_string());
+        pending_size = false;
+      }
+      _ => return None,
+    }
+  }
+  if pending_size {
+    row_sizes.push("auto".to_string());
+  }
+  let columns = columns.map_or("none".to_string(), |columns| join(columns));
+  Some(vec![Some(row_sizes.join(" ")), Some(columns), Some(areas.join(" "))])
+}
+
+/// `grid`: a `grid-template`, or auto-placed rows or columns.
+fn grid(tokens: &[Token]) -> Option<Vec<Option<String>>> {
+  let none = || Some("none".to_string());
+  let auto = || Some("auto".to_string());
+  let is_flow = |token: &&Token| is_keyword_in(token, &["auto-flow", "dense"]);
+  let flow = |part: &[Token], direction: &str| {
+    let dense = part.iter().any(|token| token.is_ident("dense"));
+    let sizes: Vec<Token> = part.iter().filter(|token| !is_flow(token)).copied().collect();
+    let flow = if dense { format!("{} dense", direction) } else { direction.to_string() };
+    (Some(flow), if sizes.is_empty() { auto() } else { Some(join(&sizes)) })
+  };
+
+  let parts = split(tokens, &ComponentValue::Slash);
+  match parts.as_slice() {
+    [rows, columns] if rows.iter().any(|token| token.is_ident("auto-flow")) => {
+      let (flow, auto_rows) = flow(rows, "row");
+      Some(vec![none(), Some(join(columns)), none(), auto_rows, auto(), flow])
+    }
+    [rows, columns] if columns.iter().any(|token| token.is_ident("auto-flow")) => {
+      let (flow, auto_columns) = flow(columns, "column");
+      Some(vec![Some(join(rows)), none(), none(), auto(), auto_columns, flow])
+    }
+    _ => {
+      let mut values = grid_template(tokens)?;
+      values.extend([auto(), auto(), Some("row".to_string())]);
+      Some(values)
+    }
+  }
+}
+
+/// Expands a shorthand value into the values of its longhands, in the order
+/// of `PropertyInfo::longhands`. Returns `None` when `name` is not a
+/// supported shorthand or the value cannot be expanded.
+pub fn expand(name: &str, value: &str) -> Option<Vec<(&'static str, String)>> {
+  let info = property(name).filter(|info| info.shorthand && !info.longhands.is_empty())?;
+  let trimmed = value.trim();
+  if CSS_WIDE_KEYWORDS.iter().any(|keyword| trimmed.eq_ignore_ascii_case(keyword)) {
+    return Some(info.longhands.iter().map(|longhand| (*longhand, trimmed.to_ascii_lowercase())).collect());
+  }
+  if trimmed.to_ascii_lowercase().contains("var(") {
+    return None;
+  }
+
+  let parsed = Value::from_string(trimmed).ok()?;
+  let tokens: Vec<Token> = parsed.components.iter().filter(|token| **token != ComponentValue::Whitespace).collect();
+  // A CSS-wide keyword can only be the whole value
+  if tokens.is_empty() || tokens.len() > 1 && tokens.iter().any(|token| is_keyword_in(token, &CSS_WIDE_KEYWORDS)) {
+    return None;
+  }
+  let strings = |values: Vec<String>| values.into_iter().map(Some).collect::<Vec<_>>();
+  let single = || match tokens.as_slice() {
+    [only] => Some(only.to_string()),
+    _ => None,
+  };
+
+  let values: Vec<Option<String>> = match info.name {
+    "margin" | "padding" | "inset" | "scroll-margin" | "scroll-padding" | "border-width" | "border-style" | "border-color" => {
+      strings(sides(&tokens, &|token| is_side_value(info.name, token))?.to_vec())
+    }
+    "margin-block" | "margin-inline" | "padding-block" | "padding-inline" | "inset-block" | "inset-inline"
+    | "border-block-width" | "border-block-style" | "border-block-color" | "border-inline-width" | "border-inline-style"
+    | "border-inline-color" | "overflow" | "overscroll-behavior" | "gap" => {
+      if !tokens.iter().all(|token| is_side_value(info.name, token)) {
+        return None;
+      }
+      strings(pair(tokens.iter().map(ToString::to_string).collect())?)
+    }
+    "place-content" | "place-items" | "place-self" => strings(pair(alignment_groups(&tokens))?),
+    "border-radius" => border_radius(&tokens)?,
+    "background-position" => {
+      let (x, y) = position(&tokens)?;
+      vec![Some(x), Some(y)]
+    }
+    "border" | "border-top" | "border-right" | "border-bottom" | "border-left" | "border-block" | "border-inline"
+    | "border-block-start" | "border-block-end" | "border-inline-start" | "border-inline-end" | "outline" | "column-rule" => {
+      let [width, style, color] = line(&tokens, info.name == "outline")?;
+      let values = by_suffix(info, &[("-width", width), ("-style", style), ("-color", color)]);
+      values.into_iter().map(|(_, value)| value).collect()
+    }
+    "border-image" => border_image(&tokens)?,
+    "font" => font(&tokens)?,
+    "background" => background(&tokens)?,
+    "list-style" => list_style(&tokens)?,
+    "text-decoration" => text_decoration(&tokens)?,
+    "flex" => flex(&tokens)?,
+    "flex-flow" => any_order(&tokens, &[
+      &|token| is_keyword_in(token, &["row", "row-reverse", "column", "column-reverse"]),
+      &|token| is_keyword_in(token, &["nowrap", "wrap", "wrap-reverse"]),
+    ])?,
+    "columns" => {
+      let values = any_order(&tokens, &[&|token| is_length_percentage(token) || token.is_ident("auto"), &|token| is_number(token) || token.is_ident("auto")])?;
+      values.into_iter().map(|value| value.or(Some("auto".to_string()))).collect()
+    }
+    "grid-row" | "grid-column" => grid_lines(&tokens, 2)?,
+    "grid-area" => grid_lines(&tokens, 4)?,
+    "grid-template" => grid_template(&tokens)?,
+    "grid" => grid(&tokens)?,
+    "transition" => transition(&tokens)?,
+    "animation" => animation(&tokens)?,
+    "container" => {
+      let parts = split(&tokens, &ComponentValue::Slash);
+      match parts.as_slice() {
+        [name] => vec![Some(join(name)), None],
+        [name, kind] if !kind.is_empty() => vec![Some(join(name)), Some(join(kind))],
+        _ => return None,
+      }
+    }
+    "marker" => vec![Some(single()?); 3],
+    "page-break-after" | "page-break-before" | "page-break-inside" => {
+      let value = single()?;
+      vec![Some(if value.eq_ignore_ascii_case("always") { "page".to_string() } else { value })]
+    }
+    _ => return None,
+  };
+
+  // The longhands after the given values, such as those `font` only
+  // resets, take their initial value
+  let mut values = values;
+  values.resize(info.longhands.len(), None);
+  let longhands = info.longhands.iter().zip(values).map(|(longhand, value)| {
+    let initial = || property(longhand).map_or(String::new(), |longhand| longhand.initial.to_string());
+    (*longhand, value.unwrap_or_else(initial))
+  });
+  Some(longhands.collect())
+}
+
+
+/// Normalizes the spacing of a value the way expanded values are written,
+/// so that values can be compared.
+fn normalize(value: &str) -> String {
+  match Value::from_string(value.trim()) {
+    Ok(parsed) => join(&parsed.components.iter().filter(|token| **token != ComponentValue::Whitespace).collect::<Vec<_>>()),
+    Err(_) => value.trim().to_string(),
+  }
+}
+
+/// Every combination of `parts`, keeping their order, with the parts for
+/// which `required` is true always present.
+fn combinations(parts: &[String], required: &[bool]) -> Vec<String> {
+  (0..1u32 << parts.len())
+    .filter(|mask| (0..parts.len()).all(|index| !required[index] || mask & (1 << index) != 0))
+    .map(|mask| {
+      let chosen: Vec<&str> = (0..parts.len()).filter(|index| mask & (1 << index) != 0).map(|index| parts[index].as_str()).collect();
+      chosen.join(" ")
+    })
+    .collect()
+}
+
+/// The side values from the shortest to the longest form.
+fn side_candidates(values: &[String]) -> Vec<String> {
+  (1..=values.len()).map(|count| values[..count].join(" ")).collect()
+}
+
+/// The horizontal and vertical radii of a corner.
+fn radii(value: &str) -> Option<(String, String)> {
+  let parsed = Value::from_string(value).ok()?;
+  let tokens: Vec<Token> = parsed.components.iter().filter(|token| **token != ComponentValue::Whitespace).collect();
+  match tokens.as_slice() {
+    [both] => Some((both.to_string(), both.to_string())),
+    [horizontal, vertical] => Some((horizontal.to_string(), vertical.to_string())),
+    _ => None,
+  }
+}
+
+/// The shortest background layer that sets `values`, one per longhand in
+/// the order of `expand`, without the color.
+fn background_layer_value(values: &[&str]) -> String {
+  let [image, x, y, size, repeat, attachment, origin, clip] = values else { return String::new() };
+  let mut parts: Vec<String> = Vec::new();
+  if *image != "none" {
+    parts.push(image.to_string());
+  }
+  if (*x, *y) != ("0%", "0%") || *size != "auto" {
+    parts.push(match (*x, *y) {
+      (x, "center") if !x.contains(' ') => x.to_string(),
+      ("center", y @ ("top" | "bottom")) => y.to_string(),
+      (x, y) => format!("{} {}", x, y),
+    });
+  }
+  if *size != "auto" {
+    parts.push(format!("/ {}", size));
+  }
+  for (value, initial) in [(repeat, "repeat"), (attachment, "scroll")] {
+    if *value != initial {
+      parts.push(value.to_string());
+    }
+  }
+  match (*origin, *clip) {
+    ("padding-box", "border-box") => {}
+    (origin, clip) if origin == clip => parts.push(origin.to_string()),
+    (origin, clip) => parts.push(format!("{} {}", origin, clip)),
+  }
+  parts.join(" ")
+}
+
+/// Splits comma-separated layers at top-level commas.
+fn layer_values(value: &str) -> Vec<String> {
+  let Ok(parsed) = Value::from_string(value) else { return vec![value.to_string()] };
+  let tokens: Vec<Token> = parsed.components.iter().filter(|token| **token != ComponentValue::Whitespace).collect();
+  split(&tokens, &ComponentValue::Comma).iter().map(|layer| join(layer)).collect()
+}
+
+/// Candidate shorthand values for the longhand values, not necessarily
+/// equivalent: `collapse` keeps the shortest one that expands back to them.
+fn candidates(name: &str, values: &[String]) -> Vec<String> {
+  let is_initial = |index: usize, longhand: &str| property(longhand).is_some_and(|property| property.initial == values[index]);
+  match name {
+    "margin" | "padding" | "inset" | "border-width" | "border-style" | "border-color" | "gap" | "place-content" | "place-items" | "place-self" => {
+      side_candidates(values)
+    }
+    "border" => {
+      let parts = [values[0].clone(), values[4].clone(), values[8].clone()];
+      let mut candidates = combinations(&parts, &[false; 3]);
+      candidates[0] = "none".to_string();
+      candidates
+    }
+    "border-radius" => {
+      let Some(corners) = values.iter().map(|value| radii(value)).collect::<Option<Vec<_>>>() else { return Vec::new() };
+      let horizontal: Vec<String> = corners.iter().map(|corner| corner.0.clone()).collect();
+      let vertical: Vec<String> = corners.iter().map(|corner| corner.1.clone()).collect();
+      let mut candidates = side_candidates(&horizontal);
+      for horizontal in side_candidates(&horizontal) {
+        candidates.extend(side_candidates(&vertical).iter().map(|vertical| format!("{} / {}", horizontal, vertical)));
+      }
+      candidates
+    }
+    "font" => {
+      // style, variant-caps, weight, stretch, size, line-height, family
+      let size = match values[5].as_str() {
+        "normal" => values[4].clone(),
+        line_height => format!("{}/{}", values[4], line_height),
+      };
+      let parts = [values[0].clone(), values[1].clone(), values[2].clone(), values[3].clone(), size, values[6].clone()];
+      let mut candidates = combinations(&parts, &[false, false, false, false, true, true]);
+      candidates.sort_by_key(String::len);
+      candidates
+    }
+    "background" => {
+      let layers: Vec<Vec<String>> = values[..8].iter().map(|value| layer_values(value)).collect();
+      let count = layers[0].len();
+      if layers.iter().any(|layer| layer.len() != count) {
+        return Vec::new();
+      }
+      let mut output: Vec<String> = (0..count)
+        .map(|index| {
+          let layer: Vec<&str> = layers.iter().map(|values| values[index].as_str()).collect();
+          background_layer_value(&layer)
+        })
+        .collect();
+      let last = output.last_mut().unwrap();
+      if !is_initial(8, "background-color") {
+        *last = format!("{} {}", last, values[8]).trim_start().to_string();
+      }
+      let layers: Vec<String> = output.into_iter().map(|layer| if layer.is_empty() { "none".to_string() } else { layer }).collect();
+      vec![layers.join(", ")]
+    }
+    "flex" => {
+      let [grow, shrink, basis] = values else { return Vec::new() };
+      vec![
+        "none".to_string(),
+        "auto".to_string(),
+        grow.clone(),
+        basis.clone(),
+        format!("{} {}", grow, shrink),
+        format!("{} {}", grow, basis),
+        format!("{} {} {}", grow, shrink, basis),
+      ]
+    }
+    _ => Vec::new(),
+  }
+}
+
+/// Combines longhand values, given in the order of `PropertyInfo::longhands`,
+/// into the shortest equivalent value of the shorthand `name`, such as
+/// `0 auto` for `margin`. Every candidate is checked by expanding it again,
+/// so the result always expands back to `values`.
+///
+/// Supports `margin`, `padding`, `inset`, `border`, `border-width`,
+/// `border-style`, `border-color`, `border-radius`, `font`, `background`,
+/// `flex`, `gap` and `place-*`. Returns `None` for other shorthands, for
+/// values with `var()` or mixed CSS-wide keywords, and when no shorthand
+/// value is equivalent, such as a `border` whose sides differ.
+pub fn collapse(name: &str, values: &[&str]) -> Option<String> {
+  let info = property(name).filter(|info| info.shorthand && info.longhands.len() == values.len())?;
+  if values.iter().any(|value| value.to_ascii_lowercase().contains("var(")) {
+    return None;
+  }
+  let keywords: Vec<bool> = values.iter().map(|value| CSS_WIDE_KEYWORDS.iter().any(|keyword| value.trim().eq_ignore_ascii_case(keyword))).collect();
+  if keywords.iter().any(|keyword| *keyword) {
+    let first = values[0].trim().to_ascii_lowercase();
+    return values.iter().all(|value| value.trim().eq_ignore_ascii_case(&first)).then_some(first);
+  }
+
+  let values: Vec<String> = values.iter().map(|value| normalize(value)).collect();
+  let mut candidates = candidates(info.name, &values);
+  candidates.sort_by_key(String::len);
+  candidates.into_iter().find(|candidate| {
+    expand(info.name, candidate).is_some_and(|longhands| longhands.iter().zip(&values).all(|((_, value), expected)| normalize(value) == *expected))
+  })
+}
+
+/// The shorthands `CSSDeclarationList::collapse_shorthands` tries, `border`
+/// before its parts.
+pub(crate) const COLLAPSIBLE: [&str; 15] = [
+  "border", "border-width", "border-style", "border-color", "border-radius", "margin", "padding", "inset",
+  "font", "background", "flex", "gap", "place-content", "place-items", "place-self",
+];
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// The expanded values, in the registry order of the longhands.
+  fn values(name: &str, value: &str) -> Vec<String> {
+    expand(name, value).unwrap_or_else(|| panic!("{}: {}", name, value)).into_iter().map(|(_, value)| value).collect()
+  }
+
+  #[test]
+  fn test_box_sides() {
+    assert_eq!(values("margin", "0 auto"), ["0", "auto", "0", "auto"]);
+    assert_eq!(values("padding", "1px 2px 3px"), ["1px", "2px", "3px", "2px"]);
+    assert_eq!(values("inset", "1px 2px 3px 4px"), ["1px", "2px", "3px", "4px"]);
+    assert_eq!(values("border-color", "red"), ["red", "red", "red", "red"]);
+    assert_eq!(values("margin-inline", "1px 2px"), ["1px", "2px"]);
+    assert_eq!(values("margin", "calc(1px + 2%)"), ["calc(1px + 2%)"; 4]);
+    assert!(expand("margin", "1px 2px 3px 4px 5px").is_none());
+    assert!(expand("margin", "1px, 2px").is_none());
+    assert!(expand("margin", "red").is_none());
+    assert!(expand("padding", "auto").is_none());
+    assert!(expand("padding", "-1px").is_none());
+    assert!(expand("scroll-margin", "10%").is_none());
+    assert!(expand("border-width", "1px solid").is_none());
+    assert!(expand("border-style", "solid red").is_none());
+    assert!(expand("gap", "1px auto").is_none());
+    assert!(expand("border-radius", "1px / auto").is_none());
+    assert_eq!(expand("margin", "0 auto").unwrap()[1], ("margin-right", "auto".to_string()));
+  }
+
+  #[test]
+  fn test_css_wide_keywords_and_var() {
+    assert_eq!(values("border", "INHERIT"), ["inherit"; 17]);
+    assert!(expand("margin", "var(--x) 0").is_none());
+    assert!(expand("margin", "inherit 0").is_none());
+    assert!(expand("border", "1px solid initial").is_none());
+    assert!(expand("margin-top", "0").is_none());
+    assert!(expand("all", "unset").is_none());
+    assert_eq!(values("-webkit-flex", "2"), ["2", "1", "0%"]);
+  }
+
+  #[test]
+  fn test_border_radius() {
+    assert_eq!(values("border-radius", "4px"), ["4px"; 4]);
+    assert_eq!(values("border-radius", "1px 2px / 3px"), ["1px 3px", "2px 3px", "1px 3px", "2px 3px"]);
+  }
+
+  #[test]
+  fn test_lines() {
+    assert_eq!(
+      values("border", "red 1px"),
+      [
+        "1px", "1px", "1px", "1px", "none", "none", "none", "none", "red", "red", "red", "red",
+        "none", "100%", "1", "0", "stretch",
+      ],
+    );
+    assert_eq!(values("border-left", "dotted"), ["medium", "dotted", "currentcolor"]);
+    assert_eq!(values("border-block-start", "thin solid #000"), ["thin", "solid", "#000"]);
+    assert_eq!(values("outline", "2px solid blue"), ["blue", "solid", "2px"]);
+    assert_eq!(values("outline", "auto 1px"), ["auto", "auto", "1px"]);
+    assert_eq!(values("outline", "red auto"), ["red", "auto", "medium"]);
+    assert_eq!(values("outline", "solid auto"), ["auto", "solid", "medium"]);
+    assert!(expand("outline", "hidden").is_none());
+    assert!(expand("border", "1px solid auto").is_none());
+    assert!(expand("border-top", "auto").is_none());
+    assert!(expand("column-rule", "auto solid").is_none());
+    assert_eq!(values("column-rule", "1px solid"), ["1px", "solid", "currentcolor"]);
+    assert!(expand("border", "1px 2px").is_none());
+    assert!(expand("border", "1px solid nonsense").is_none());
+  }
+
+  #[test]
+  fn test_border_image() {
+    assert_eq!(values("border-image", "url(b.png) 30 round"), ["url(\"b.png\")", "30", "1", "0", "round"]);
+    assert_eq!(values("border-image", "linear-gradient(red, blue) 27 fill / 1px / 2px stretch repeat"), [
+      "linear-gradient(red, blue)", "27 fill", "1px", "2px", "stretch repeat",
+    ]);
+  }
+
+  #[test]
+  fn test_font() {
+    assert_eq!(values("font", "12px serif")[..7], ["normal", "normal", "normal", "normal", "12px", "normal", "serif"]);
+    assert_eq!(
+      values("font", "italic small-caps bold condensed 1.2em/1.5 \"Helvetica Neue\", Arial, sans-serif")[..7],
+      ["italic", "small-caps", "bold", "condensed", "1.2em", "1.5", "\"Helvetica Neue\", Arial, sans-serif"],
+    );
+    assert_eq!(values("font", "normal 700 large/normal mono")[..7], ["normal", "normal", "700", "normal", "large", "normal", "mono"]);
+    // The other font properties are reset
+    assert_eq!(
+      values("font", "bold 12px serif")[7..],
+      ["normal", "normal", "normal", "normal", "normal", "normal", "none", "auto", "normal", "auto", "normal", "normal"],
+    );
+    assert!(expand("font", "menu").is_none());
+    assert!(expand("font", "bold serif").is_none());
+    assert!(expand("font", "12px").is_none());
+  }
+
+  #[test]
+  fn test_background() {
+    assert_eq!(values("background", "red"), [
+      "none", "0%", "0%", "auto", "repeat", "scroll", "padding-box", "border-box", "red",
+    ]);
+    assert_eq!(values("background", "url(a.png) right 10px top / 50% auto no-repeat fixed content-box #fff"), [
+      "url(\"a.png\")", "right 10px", "top", "50% auto", "no-repeat", "fixed", "content-box", "content-box", "#fff",
+    ]);
+    assert_eq!(values("background", "linear-gradient(red, blue) top, url(b.png) 10px 20px repeat-x padding-box text"), [
+      "linear-gradient(red, blue), url(\"b.png\")",
+      "center, 10px",
+      "top, 20px",
+      "auto, auto",
+      "repeat, repeat-x",
+      "scroll, scroll",
+      "padding-box, padding-box",
+      "border-box, text",
+      "transparent",
+    ]);
+    assert!(expand("background", "red, url(a.png)").is_none());
+  }
+
+  #[test]
+  fn test_flex() {
+    assert_eq!(values("flex", "none"), ["0", "0", "auto"]);
+    assert_eq!(values("flex", "auto"), ["1", "1", "auto"]);
+    assert_eq!(values("flex", "2 3"), ["2", "3", "0%"]);
+    assert_eq!(values("flex", "10px"), ["1", "1", "10px"]);
+    assert_eq!(values("flex", "2 30%"), ["2", "1", "30%"]);
+    assert_eq!(values("flex", "1 0 0"), ["1", "0", "0"]);
+    assert_eq!(values("flex", "content 2 2"), ["2", "2", "content"]);
+    assert_eq!(values("flex-flow", "wrap column"), ["column", "wrap"]);
+    assert!(expand("flex", "1 2 3").is_none());
+  }
+
+  #[test]
+  fn test_alignment_and_gap() {
+    assert_eq!(values("gap", "1px"), ["1px", "1px"]);
+    assert_eq!(values("gap", "1px 2em"), ["1px", "2em"]);
+    assert_eq!(values("place-items", "center start"), ["center", "start"]);
+    assert_eq!(values("place-self", "last baseline"), ["last baseline", "last baseline"]);
+    assert_eq!(values("place-content", "safe center space-between"), ["safe center", "space-between"]);
+  }
+
+  #[test]
+  fn test_grid() {
+    assert_eq!(values("grid-row", "1 / 3"), ["1", "3"]);
+    assert_eq!(values("grid-column", "main"), ["main", "main"]);
+    assert_eq!(values("grid-column", "span 2"), ["span 2", "auto"]);
+    assert_eq!(values("grid-area", "a"), ["a", "a", "a", "a"]);
+    assert_eq!(values("grid-area", "1 / b"), ["1", "b", "auto", "b"]);
+    assert_eq!(values("grid-template", "none"), ["none", "none", "none"]);
+    assert_eq!(values("grid-template", "100px 1fr / repeat(2, 50px)"), ["100px 1fr", "repeat(2, 50px)", "none"]);
+    assert_eq!(
+      values("grid-template", "[top] \"a a\" 40px [middle] \"b c\" / 1fr 2fr"),
+      ["[top] 40px [middle] auto", "1fr 2fr", "\"a a\" \"b c\""],
+    );
+    assert_eq!(values("grid", "auto-flow dense 40px / 1fr 1fr"), ["none", "1fr 1fr", "none", "40px", "auto", "row dense"]);
+    assert_eq!(values("grid", "100px / auto-flow"), ["100px", "none", "none", "auto", "auto", "column"]);
+    assert_eq!(values("grid", "\"a\" / 1fr"), ["auto", "1fr", "\"a\"", "auto", "auto", "row"]);
+  }
+
+  #[test]
+  fn test_transition_and_animation() {
+    assert_eq!(values("transition", "opacity 1s"), ["opacity", "1s", "ease", "0s", "normal"]);
+    assert_eq!(values("transition", "color 200ms ease-in 50ms, transform 1s steps(4) allow-discrete"), [
+      "color, transform", "200ms, 1s", "ease-in, steps(4)", "50ms, 0s", "normal, allow-discrete",
+    ]);
+    assert_eq!(values("animation", "spin 2s linear infinite"), [
+      "2s", "linear", "0s", "infinite", "normal", "none", "running", "spin",
+    ]);
+    assert_eq!(values("animation", "1s 2s 3 reverse both paused slide, none"), [
+      "1s, 0s", "ease, ease", "2s, 0s", "3, 1", "reverse, normal", "both, none", "paused, running", "slide, none",
+    ]);
+  }
+
+  #[test]
+  fn test_other_shorthands() {
+    assert_eq!(values("list-style", "square inside"), ["inside", "none", "square"]);
+    assert_eq!(values("list-style", "none"), ["outside", "none", "none"]);
+    assert_eq!(values("list-style", "inside none"), ["inside", "none", "none"]);
+    assert_eq!(values("list-style", "none square"), ["outside", "none", "square"]);
+    assert_eq!(values("list-style", "none url(a.png)"), ["outside", "url(\"a.png\")", "none"]);
+    assert_eq!(values("list-style", "none none"), ["outside", "none", "none"]);
+    assert_eq!(values("list-style", "url(a.png) \"-\""), ["outside", "url(\"a.png\")", "\"-\""]);
+    assert_eq!(values("text-decoration", "underline overline wavy red 2px"), ["underline overline", "wavy", "red", "2px"]);
+    assert_eq!(values("text-decoration", "none"), ["none", "solid", "currentcolor", "auto"]);
+    assert_eq!(values("background-position", "bottom 10px right"), ["right", "bottom 10px"]);
+    assert_eq!(values("columns", "3"), ["auto", "3"]);
+    assert_eq!(values("container", "sidebar / inline-size"), ["sidebar", "inline-size"]);
+    assert_eq!(values("page-break-before", "always"), ["page"]);
+    assert!(expand("font-variant", "small-caps").is_none());
+  }
+
+  /// The shorthand of `values`, the longhands after them taking their
+  /// initial value, like those `border` and `font` only reset.
+  fn collapsed(name: &str, values: &[&str]) -> Option<String> {
+    let longhands = property(name).unwrap().longhands;
+    let initial = longhands[values.len()..].iter().map(|longhand| property(longhand).unwrap().initial);
+    collapse(name, &values.iter().copied().chain(initial).collect::<Vec<_>>())
+  }
+
+  #[test]
+  fn test_collapse_sides() {
+    assert_eq!(collapsed("margin", &["0", "auto", "0", "auto"]).as_deref(), Some("0 auto"));
+    assert_eq!(collapsed("padding", &["1px", "1px", "1px", "1px"]).as_deref(), Some("1px"));
+    assert_eq!(collapsed("inset", &["1px", "2px", "3px", "2px"]).as_deref(), Some("1px 2px 3px"));
+    assert_eq!(collapsed("margin", &["1px", "2px", "3px", "4px"]).as_deref(), Some("1px 2px 3px 4px"));
+    assert_eq!(collapsed("gap", &["1px", "2px"]).as_deref(), Some("1px 2px"));
+    assert_eq!(collapsed("place-self", &["end", "end"]).as_deref(), Some("end"));
+    assert_eq!(collapsed("margin", &["inherit", "INHERIT", "inherit", "inherit"]).as_deref(), Some("inherit"));
+    assert_eq!(collapsed("margin", &["inherit", "0", "0", "0"]), None);
+    assert_eq!(collapsed("margin", &["var(--x)", "0", "0", "0"]), None);
+    assert_eq!(collapse("margin", &["0", "0", "0"]), None);
+  }
+
+  #[test]
+  fn test_collapse_border_and_radius() {
+    let border = |width: &str, style: &str, color: &str| {
+      let mut values = vec![width; 4];
+      values.extend([style; 4]);
+      values.extend([color; 4]);
+      collapsed("border", &values)
+    };
+    assert_eq!(border("medium", "none", "currentcolor").as_deref(), Some("none"));
+    assert_eq!(border("1px", "solid", "currentcolor").as_deref(), Some("1px solid"));
+    assert_eq!(border("medium", "dashed", "red").as_deref(), Some("dashed red"));
+    let mut values = vec!["1px"; 12];
+    values[11] = "red";
+    assert_eq!(collapsed("border", &values), None);
+    let mut values = vec!["1px"; 4];
+    values.extend(["solid"; 4]);
+    values.extend(["red"; 4]);
+    assert_eq!(collapse("border", &values), None);
+    values.extend(["url(a.png)", "100%", "1", "0", "stretch"]);
+    assert_eq!(collapse("border", &values), None);
+    values[12] = "none";
+    assert_eq!(collapse("border", &values).as_deref(), Some("1px solid red"));
+
+    assert_eq!(collapsed("border-radius", &["4px", "4px", "4px", "4px"]).as_deref(), Some("4px"));
+    assert_eq!(collapsed("border-radius", &["1px 3px", "2px 3px", "1px 3px", "2px 3px"]).as_deref(), Some("1px 2px / 3px"));
+    assert_eq!(collapsed("border-radius", &["1px", "2px", "3px", "4px 5px"]).as_deref(), Some("1px 2px 3px 4px / 1px 2px 3px 5px"));
+  }
+
+  #[test]
+  fn test_collapse_font_flex_background() {
+    assert_eq!(
+      collapsed("font", &["normal", "normal", "bold", "normal", "12px", "1.5", "Arial,  serif"]).as_deref(),
+      Some("bold 12px/1.5 Arial, serif"),
+    );
+    assert_eq!(collapsed("font", &["normal", "normal", "normal", "normal", "12px", "normal", "serif"]).as_deref(), Some("12px serif"));
+    assert_eq!(collapsed("font", &["normal", "normal", "normal", "125%", "12px", "normal", "serif"]), None);
+    let mut font = vec!["normal", "normal", "normal", "normal", "12px", "normal", "serif"];
+    font.extend(["normal", "normal", "normal", "normal", "normal", "normal", "none", "none", "normal", "auto", "normal", "normal"]);
+    assert_eq!(collapse("font", &font), None);
+    font[14] = "auto";
+    assert_eq!(collapse("font", &font).as_deref(), Some("12px serif"));
+
+    assert_eq!(collapsed("flex", &["0", "0", "auto"]).as_deref(), Some("none"));
+    assert_eq!(collapsed("flex", &["1", "1", "0%"]).as_deref(), Some("1"));
+    assert_eq!(collapsed("flex", &["1", "1", "10px"]).as_deref(), Some("10px"));
+    assert_eq!(collapsed("flex", &["2", "3", "10px"]).as_deref(), Some("2 3 10px"));
+
+    let initial = ["none", "0%", "0%", "auto", "repeat", "scroll", "padding-box", "border-box", "transparent"];
+    assert_eq!(collapsed("background", &initial).as_deref(), Some("none"));
+    let mut values = initial;
+    values[8] = "red";
+    assert_eq!(collapsed("background", &values).as_deref(), Some("red"));
+    let values = [
+      "url(a.png), none", "right, 0%", "0%, 0%", "auto, contain", "no-repeat, repeat",
+      "scroll, fixed", "padding-box, content-box", "border-box, content-box", "#fff",
+    ];
+    assert_eq!(
+      collapsed("background", &values).as_deref(),
+      Some("url(\"a.png\") right 0% no-repeat, 0% 0% / contain fixed content-box #fff"),
+    );
+    assert_eq!(collapsed("background", &["none, none", "0%", "0%", "auto", "repeat", "scroll", "padding-box", "border-box", "red"]), None);
+  }
+}
@@ -0,0 +1,220 @@
+//! CSS Source Spans
+//!
+//! This module provides the `Span` type recorded on parsed nodes, pointing back
+//! at the text they were parsed from: a `Stylesheet`, a `CSSRule` and its
+//! selector, every at-rule, and a `CSSDeclaration` with its name, value and
+//! `!important` flag.
+//!
+//! Spans are filled in by `from_string` and are `None` on nodes created with
+//! `new`. Nodes hold them in a `NodeSpan`, which is equal to every other
+//! `NodeSpan`: spans are ignored when comparing nodes, so a parsed node is
+//! equal to the same node built programmatically.
+//!
+//! ## Main API
+//!
+//! - `Span::start` / `Span::end` - Byte offset, line and column of each end
+//! - `Span::text()` - The spanned text in the original source
+//! - `NodeSpan` - The optional span of a node, dereferencing to `Option<Span>`
+//!
+//! ## Examples
+//!
+//! ```rust
+//! use css_structs::CSSRule;
+//!
+//! let source = "h1 {\n  color: red !important;\n}";
+//! let rule = CSSRule::from_string(source).unwrap();
+//!
+//! let value = rule.declarations.declarations[0].value_span.unwrap();
+//! assert_eq!(value.text(source), "red");
+//! assert_eq!((value.start.line, value.start.column), (2, 10));
+//! assert_eq!(value.start.offset, 14);
+//!
+//! let selector = rule.selector_span.unwrap();
+//! assert_eq!(selector.text(source), "h1");
+//! ```
+
+
+use std::cell::RefCell;
+use std::ops::{Deref, DerefMut};
+
+
+/// A position in the source. `offset` is in bytes, while `line` and `column`
+/// are 1-based, with the column counted in characters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Position {
+  pub offset: usize,
+  pub line: usize,
+  pub column: usize,
+}
+
+/// The range of source text a node was parsed from; `end` is exclusive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Span {
+  pub start: Position,
+  pub end: Position,
+}
+
+impl Span {
+  /// Returns the spanned text, or an empty string when `source` is not the
+  /// text the span was recorded in.
+  pub fn text<'a>(&self, source: &'a str) -> &'a str {
+    source.get(self.start.offset..self.end.offset).unwrap_or("")
+  }
+}
+
+
+/// The span a node was parsed from, if any. Always equal to any other
+/// `NodeSpan`, so that nodes can derive `PartialEq` without comparing spans.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NodeSpan(pub Option<Span>);
+
+impl PartialEq for NodeSpan {
+  fn eq(&self, _other: &Self) -> bool {
+    true
+  }
+}
+
+impl Eq for NodeSpan {}
+
+impl Deref for NodeSpan {
+  type Target = Option<Span>;
+
+  fn deref(&self) -> &Option<Span> {
+    &self.0
+  }
+}
+
+impl DerefMut for NodeSpan {
+  fn deref_mut(&mut self) -> &mut Option<Span> {
+    &mut self.0
+  }
+}
+
+
+/// The source being parsed, recorded by address so that the parsers can turn
+/// any slice of it into a span.
+struct Source {
+  address: usize,
+  text: String,
+  line_starts: Vec<usize>,
+}
+
+impl Source {
+  fn new(text: &str) -> Self {
+    let line_starts = std::iter::once(0)
+      .chain(text.match_indices('\n').map(|(i, _)| i + 1))
+      .collect();
+
+    Source {
+      address: text.as_ptr() as usize,
+      text: text.to_string(),
+      line_starts,
+    }
+  }
+
+  fn position(&self, offset: usize) -> Position {
+    let line = self.line_starts.partition_point(|&start| start <= offset);
+    let line_start = self.line_starts[line - 1];
+
+    Position {
+      offset,
+      line,
+      column: self.text[line_start..offset].chars().count() + 1,
+    }
+  }
+}
+
+thread_local! {
+  /// The source of the `from_string` call in progress, if any.
+  static SOURCE: RefCell<Option<Source>> = const { RefCell::new(None) };
+}
+
+/// Runs `parse` with `source` recorded, so that nodes parsed from slices of
+/// it get spans.
+pub(crate) fn with_source<T>(source: &str, parse: impl FnOnce() -> T) -> T {
+  let outer = SOURCE.with(|recorded| recorded.replace(Some(Source::new(source))));
+  let result = parse();
+  SOURCE.with(|recorded| recorded.replace(outer));
+
+  result
+}
+
+/// Returns the span of `text`, which must be a slice of the recorded source.
+/// Returns `None` outside of `with_source` or for text from elsewhere.
+pub(crate) fn span_of(text: &str) -> Option<Span> {
+  SOURCE.with(|recorded| {
+    let recorded = recorded.borrow();
+    let source = recorded.as_ref()?;
+    let start = (text.as_ptr() as usize).checked_sub(source.address)?;
+    let end = start + text.len();
+    if end > source.text.len() || !source.text.is_char_boundary(start) || !source.text.is_char_boundary(end) {
+      return None;
+    }
+
+    Some(Span { start: source.position(start), end: source.position(end) })
+  })
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_span_of_slice() {
+    let source = "a {\n  b: c;\n}";
+    let span = with_source(source, || span_of(&source[6..10])).unwrap();
+    assert_eq!(span.start, Position { offset: 6, line: 2, column: 3 });
+    assert_eq!(span.end, Position { offset: 10, line: 2, column: 7 });
+    assert_eq!(span.text(source), "b: c");
+  }
+
+  #[test]
+  fn test_span_of_counts_columns_in_characters() {
+    let source = "é { ü: x }";
+    let span = with_source(source, || span_of(&source[5..7])).unwrap();
+    assert_eq!(span.text(source), "ü");
+    assert_eq!((span.start.column, span.end.column), (5, 6));
+  }
+
+  #[test]
+  fn test_span_of_end_of_input() {
+    let source = "a\n";
+    let span = with_source(source, || span_of(&source[2..])).unwrap();
+    assert_eq!(span.start, Position { offset: 2, line: 2, column: 1 });
+  }
+
+  #[test]
+  fn test_span_of_outside_source() {
+    let source = "a { b: c }";
+    let other = String::from("b: c");
+    assert_eq!(span_of(&source[4..8]), None);
+    assert_eq!(with_source(source, || span_of(&other)), None);
+  }
+
+  #[test]
+  fn test_with_source_restores_outer_source() {
+    let outer = "outer";
+    let inner = "inner";
+    let span = with_source(outer, || {
+      with_source(inner, || ());
+      span_of(&outer[1..3])
+    });
+    assert_eq!(span.unwrap().text(outer), "ut");
+  }
+
+  #[test]
+  fn test_node_spans_are_always_equal() {
+    let source = "abc";
+    let span = NodeSpan(with_source(source, || span_of(source)));
+    assert_eq!(span, NodeSpan(None));
+    assert_eq!(span.unwrap().text(source), "abc");
+  }
+
+  #[test]
+  fn test_text_with_other_source() {
+    let source = "abcdef";
+    let span = with_source(source, || span_of(source)).unwrap();
+    assert_eq!(span.text("abc"), "");
+  }
+}
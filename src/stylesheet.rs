@@ -2,11 +2,22 @@
 //!
 //! This module provides parsing and representation for complete CSS stylesheets
 //! containing multiple CSS rules. A stylesheet represents the top-level structure
-//! that holds all CSS rules like `body { margin: 0; } .title { color: red; }`.
+//! that holds all CSS rules like `body { margin: 0; } .title { color: red; }`,
+//! including at-rules such as `@media print { ... }` (see `Rule`).
 //!
 //! ## Main API
 //! 
-//! - `Stylesheet::from_string()` - Parse a complete stylesheet from a CSS string
+//! - `Stylesheet::from_string()` - Parse a complete stylesheet from a CSS string, failing on the first error
+//! - `Stylesheet::from_string_recovering()` - Parse a stylesheet, skipping invalid rules and declarations
+//! - `Stylesheet::from_string_lossless()` - Parse a stylesheet and keep its source text, to write it back as written
+//! - `Stylesheet::to_lossless_string()` - Write the stylesheet, reprinting only the nodes edited since it was parsed (see the `lossless` module)
+//! - `Stylesheet::text_edits()` - The byte ranges of the source to replace to apply the edits made since it was parsed
+//! - `Stylesheet::flatten_nesting()` - Lower nested rules to flat rules for browsers without CSS Nesting
+//! - `Stylesheet::normalize_colors()` - Rewrite every color in one format (see the `color` module)
+//! - `Stylesheet::format()` - Pretty-print the stylesheet with `FormatOptions` (see the `format` module)
+//! - `Stylesheet::to_minified_string()` - Serialize the stylesheet in as few bytes as possible (see the `minify` module)
+//! - `Stylesheet::optimize()` - Remove overridden declarations and empty rules, and merge adjacent rules
+//! - `Stylesheet::variable_usage()` - Custom properties used without being declared, or declared without being used (see the `variables` module)
 //! - `Stylesheet::new()` - Create a new stylesheet programmatically with optional rules
 //! - `Display` trait implementation for converting back to CSS string format
 //!
@@ -31,45 +42,161 @@
 
 
 use std::fmt;
-use crate::css_rule::CSSRule;
-use nom::{
-  IResult,
-  multi::many0,
-  Parser,
-};
+use std::sync::Arc;
+use crate::color::{normalize_value, ColorFormat};
+use crate::css_comment::CSSComment;
+use crate::format::{format_rules, FormatOptions};
+use crate::helpers::trivia;
+use crate::lossless::{write_lossless, Original, TextEdit};
+use crate::minify::{optimize_rules, write_rules};
+use crate::nesting::{flatten_rules, NestingMode};
+use crate::parse_error::{with_recovery, ParseError, ParseResult};
+use crate::rule::{for_each_declaration_list_mut, Rule};
+use crate::span::{span_of, with_source, NodeSpan};
+use crate::variables::{variable_usage, VariableUsage};
 
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone)]
 pub struct Stylesheet {
-  pub rules: Vec<CSSRule>,
+  pub rules: Vec<Rule>,
+  /// Comments after the last rule, or in an empty stylesheet.
+  pub trailing_comments: Vec<CSSComment>,
+  /// The whole source the stylesheet was parsed from.
+  pub span: NodeSpan,
+  /// The source and the rules as parsed by `from_string_lossless`.
+  pub(crate) original: Option<Arc<Original>>,
 }
 
 impl Stylesheet {  
-  fn parse(input: &str) -> IResult<&str, Vec<CSSRule>> {
-    many0(CSSRule::parse).parse(input)
+  fn parse(input: &str) -> ParseResult<'_, (Vec<Rule>, Vec<CSSComment>)> {
+    Rule::parse_rules(input, true)
   }
 
-  pub fn from_string(input: &str) -> Result<Self, String> {
-    let (_, rules) = Self::parse(input)
-      .map_err(|_| "Failed to parse CSS".to_string())?;
+  pub fn from_string(input: &str) -> Result<Self, ParseError> {
+    with_source(input, || {
+      let (rest, (rules, trailing_comments)) = Self::parse(input)
+        .and_then(|(rest, rules)| Ok((trivia(rest)?.0, rules)))
+        .map_err(|error| ParseError::from_nom(input, error))?;
 
-    Ok(Self { rules })
+      if !rest.is_empty() {
+        // Report why the next rule could not be parsed
+        return Err(ParseError::from_nom(input, nom::Err::Error(Rule::diagnose(rest))));
+      }
+
+      Ok(Self { rules, trailing_comments, span: NodeSpan(span_of(input)), original: None })
+    })
+  }
+
+  /// Parses a stylesheet following the CSS Syntax error recovery rules: an
+  /// invalid rule is skipped up to the end of its block, an invalid declaration
+  /// up to the next `;`, and blocks left open at the end of the input are
+  /// closed. Returns the valid rules together with the errors that were found.
+  pub fn from_string_recovering(input: &str) -> (Self, Vec<ParseError>) {
+    with_source(input, || with_recovery(input, || {
+      let (rules, trailing_comments) = Self::parse(input).map_or_else(|_| (Vec::new(), Vec::new()), |(_, parsed)| parsed);
+      Self { rules, trailing_comments, span: NodeSpan(span_of(input)), original: None }
+    }))
+  }
+
+  /// Parses a stylesheet like `from_string`, and keeps its source text so
+  /// that `to_lossless_string` can write it back as it was written.
+  pub fn from_string_lossless(input: &str) -> Result<Self, ParseError> {
+    let mut stylesheet = Self::from_string(input)?;
+    stylesheet.original = Some(Arc::new(Original { text: input.to_string(), rules: stylesheet.rules.clone() }));
+    Ok(stylesheet)
+  }
+
+  /// Writes the stylesheet with the source text of every node that is
+  /// unchanged since `from_string_lossless`, so that only edited nodes are
+  /// written anew. See the `lossless` module. A stylesheet that was not
+  /// parsed with `from_string_lossless` is written with `Display`.
+  pub fn to_lossless_string(&self) -> String {
+    match &self.original {
+      Some(original) => write_lossless(&self.rules, original).0,
+      None => self.to_string(),
+    }
+  }
+
+  /// Returns the edits that turn the source given to `from_string_lossless`
+  /// into `to_lossless_string()`, sorted by position: one per changed region,
+  /// such as a rewritten value or a removed declaration with its separator.
+  /// Returns `None` for a stylesheet not parsed with `from_string_lossless`.
+  pub fn text_edits(&self) -> Option<Vec<TextEdit>> {
+    self.original.as_ref().map(|original| write_lossless(&self.rules, original).1)
+  }
+
+  /// Returns a copy of the stylesheet with nested rules lowered to flat rules.
+  /// See the `nesting` module for how selectors and group rules are rewritten.
+  pub fn flatten_nesting(&self, mode: NestingMode) -> Self {
+    Self { rules: flatten_rules(&self.rules, None, mode), trailing_comments: self.trailing_comments.clone(), span: self.span, original: None }
+  }
+
+  /// Rewrites every color in the stylesheet's declarations in `format`.
+  /// Color keywords such as `red` are only rewritten in properties that take
+  /// colors, and the rest of each value is kept as written.
+  pub fn normalize_colors(&mut self, format: ColorFormat) {
+    for_each_declaration_list_mut(&mut self.rules, &mut |declarations| {
+      for declaration in &mut declarations.declarations {
+        if let Some(value) = normalize_value(&declaration.name, &declaration.value, format) {
+          declaration.value = value;
+        }
+      }
+    });
+  }
+
+  /// Pretty-prints the stylesheet, one rule after the other, laid out as
+  /// `options` asks. Formatting the output again gives the same text.
+  pub fn format(&self, options: &FormatOptions) -> String {
+    format_rules(&self.rules, &self.trailing_comments, options)
+  }
+
+  /// Removes what cannot change how the stylesheet applies: overridden
+  /// declarations and empty rules. Longhands are combined into shorthands
+  /// when no fallback declaration is lost, and adjacent style rules are
+  /// merged when they have the same selector or the same declarations.
+  /// See the `minify` module for when this is done.
+  pub fn optimize(&mut self) {
+    optimize_rules(&mut self.rules);
+  }
+
+  /// Serializes the stylesheet without comments or unneeded whitespace, and
+  /// with shortened colors, numbers and lengths.
+  pub fn to_minified_string(&self) -> String {
+    let mut output = String::new();
+    write_rules(&self.rules, &mut output);
+    output
+  }
+
+  /// Reports the custom properties referenced with `var()` but never
+  /// declared, and those declared but never referenced, across every rule.
+  pub fn variable_usage(&self) -> VariableUsage {
+    variable_usage(&self.rules)
   }
 
-  pub fn new(rules: Option<Vec<CSSRule>>) -> Self {
+  pub fn new(rules: Option<Vec<Rule>>) -> Self {
     if let Some(rules) = rules {
-      Self { rules }
+      Self { rules, trailing_comments: Vec::new(), span: NodeSpan(None), original: None }
     } else {
-      Self { rules: Vec::new() }
+      Self { rules: Vec::new(), trailing_comments: Vec::new(), span: NodeSpan(None), original: None }
     }
   }
 }
 
+/// The source kept by `from_string_lossless` is ignored, so a stylesheet parsed
+/// losslessly equals the same stylesheet parsed with `from_string`.
+impl PartialEq for Stylesheet {
+  fn eq(&self, other: &Self) -> bool {
+    self.rules == other.rules && self.trailing_comments == other.trailing_comments
+  }
+}
+
 impl fmt::Display for Stylesheet {
   fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let trailing_comments = self.trailing_comments.iter().filter(|_| f.alternate()).map(|comment| comment.to_string());
     let stylesheet = self.rules
       .iter()
-      .map(|decl| decl.to_string())
+      .map(|rule| if f.alternate() { format!("{:#}", rule) } else { rule.to_string() })
+      .chain(trailing_comments)
       .collect::<Vec<_>>()
       .join(" ");
 
@@ -81,7 +208,9 @@ impl fmt::Display for Stylesheet {
 #[cfg(test)]
 mod tests {
   use super::*;
+  use crate::css_comment::CSSComment;
   use crate::css_declaration::CSSDeclaration;
+  use crate::parse_error::ParseErrorKind;
 
   #[test]
   fn test_empty_stylesheet() {
@@ -95,7 +224,7 @@ mod tests {
     let input = "body { margin: 0; padding: 0; }";
     let result = Stylesheet::from_string(input).unwrap();
     assert_eq!(result.rules.len(), 1);
-    let rule = &result.rules[0];
+    let rule = result.rules[0].as_style_rule().unwrap();
     assert_eq!(rule.selector, "body");
     assert_eq!(rule.declarations.declarations.len(), 2);
     assert_eq!(rule.declarations.declarations[0], CSSDeclaration::new("margin", "0", None));
@@ -113,15 +242,15 @@ mod tests {
     let result = Stylesheet::from_string(input).unwrap();
     assert_eq!(result.rules.len(), 3);
 
-    let rule1 = &result.rules[0];
+    let rule1 = result.rules[0].as_style_rule().unwrap();
     assert_eq!(rule1.selector, "h1");
     assert_eq!(rule1.declarations.declarations[0], CSSDeclaration::new("color", "red", None));
 
-    let rule2 = &result.rules[1];
+    let rule2 = result.rules[1].as_style_rule().unwrap();
     assert_eq!(rule2.selector, "p");
     assert_eq!(rule2.declarations.declarations[0], CSSDeclaration::new("font-size", "16px", None));
 
-    let rule3 = &result.rules[2];
+    let rule3 = result.rules[2].as_style_rule().unwrap();
     assert_eq!(rule3.selector, ".box");
     assert_eq!(rule3.declarations.declarations.len(), 2);
     assert_eq!(rule3.declarations.declarations[0], CSSDeclaration::new("border", "1px solid black", None));
@@ -145,17 +274,210 @@ mod tests {
     let result = Stylesheet::from_string(input).unwrap();
     assert_eq!(result.rules.len(), 2);
 
-    let title_rule = &result.rules[0];
+    let title_rule = result.rules[0].as_style_rule().unwrap();
     assert_eq!(title_rule.selector, ".title");
     assert_eq!(title_rule.declarations.declarations[0], CSSDeclaration::new("font-weight", "bold", None));
     assert_eq!(title_rule.declarations.declarations[1], CSSDeclaration::new("font-size", "24px", None));
 
-    let subtitle_rule = &result.rules[1];
+    let subtitle_rule = result.rules[1].as_style_rule().unwrap();
     assert_eq!(subtitle_rule.selector, ".subtitle");
     assert_eq!(subtitle_rule.declarations.declarations[0], CSSDeclaration::new("font-weight", "normal", None));
     assert_eq!(subtitle_rule.declarations.declarations[1], CSSDeclaration::new("font-size", "18px", None));
   }
 
+  #[test]
+  fn test_trailing_comments_keep_their_place() {
+    let stylesheet = Stylesheet::from_string("a{b:c}/* end */").unwrap();
+    assert_eq!(format!("{:#}", stylesheet), "a { b: c; } /* end */");
+
+    let stylesheet = Stylesheet::from_string("a{b:c;/* tail */}").unwrap();
+    assert_eq!(format!("{:#}", stylesheet), "a { b: c; /* tail */ }");
+
+    let stylesheet = Stylesheet::from_string("@media screen{/* only */}").unwrap();
+    assert_eq!(format!("{:#}", stylesheet), "@media screen { /* only */ }");
+    assert_eq!(Stylesheet::from_string(&format!("{:#}", stylesheet)).unwrap(), stylesheet);
+  }
+
+  #[test]
+  fn test_comments_between_rules() {
+    let input = r#"
+            /* Layout */
+            body { margin: 0; /* reset */ }
+
+            /* Typography */
+            h1 /* main title */ { font-size: 2em /* large */; }
+            /* end of file */
+        "#;
+
+    let result = Stylesheet::from_string(input).unwrap();
+    assert_eq!(result.rules.len(), 2);
+    let body = result.rules[0].as_style_rule().unwrap();
+    let h1 = result.rules[1].as_style_rule().unwrap();
+    assert_eq!(body.selector, "body");
+    assert_eq!(h1.selector, "h1");
+    assert_eq!(h1.declarations.declarations[0], CSSDeclaration {
+      comments: vec![CSSComment::new(" large ")],
+      ..CSSDeclaration::new("font-size", "2em", None)
+    });
+    assert_eq!(h1.comments.len(), 2);
+    assert_eq!(result.trailing_comments, vec![CSSComment::new(" end of file ")]);
+    assert_eq!(result.to_string(), "body { margin: 0; } h1 { font-size: 2em; }");
+    assert_eq!(
+      format!("{:#}", result),
+      "/* Layout */ body { margin: 0; /* reset */ } /* Typography */ /* main title */ h1 { /* large */ font-size: 2em; } /* end of file */"
+    );
+  }
+
+  #[test]
+  fn test_at_rules() {
+    let input = r#"
+            @charset "UTF-8";
+            @import url("reset.css") screen;
+            @media (min-width: 600px) { .a { color: red } }
+            .b { color: blue }
+            @font-face { font-family: Inter; src: url(inter.woff2); }
+            @keyframes fade { from { opacity: 0 } to { opacity: 1 } }
+        "#;
+
+    let result = Stylesheet::from_string(input).unwrap();
+    assert_eq!(result.rules.len(), 6);
+    assert!(matches!(result.rules[0], Rule::Charset(_)));
+    assert!(matches!(result.rules[1], Rule::Import(_)));
+    let Rule::Media(media) = &result.rules[2] else { panic!("expected @media") };
+    assert_eq!(media.media, "(min-width: 600px)");
+    assert_eq!(media.rules[0].as_style_rule().unwrap().selector, ".a");
+    assert_eq!(result.rules[3].as_style_rule().unwrap().selector, ".b");
+    assert!(matches!(result.rules[4], Rule::FontFace(_)));
+    assert!(matches!(result.rules[5], Rule::Keyframes(_)));
+    assert_eq!(
+      result.to_string(),
+      "@charset \"UTF-8\"; @import url(\"reset.css\") screen; @media (min-width: 600px) { .a { color: red; } } .b { color: blue; } @font-face { font-family: Inter; src: url(inter.woff2); } @keyframes fade { from { opacity: 0; } to { opacity: 1; } }"
+    );
+    assert_eq!(Stylesheet::from_string(&result.to_string()).unwrap(), result);
+  }
+
+  #[test]
+  fn test_spans() {
+    let source = "a { color: red }\n@media print {\n  b { top: 0 }\n}";
+    let stylesheet = Stylesheet::from_string(source).unwrap();
+    assert_eq!(stylesheet.span.unwrap().text(source), source);
+
+    let Rule::Media(media) = &stylesheet.rules[1] else { panic!("expected @media") };
+    let nested = media.rules[0].as_style_rule().unwrap();
+    let span = nested.span.unwrap();
+    assert_eq!(span.text(source), "b { top: 0 }");
+    assert_eq!((span.start.line, span.start.column, span.start.offset), (3, 3, 34));
+    assert_eq!(nested.declarations.declarations[0].value_span.unwrap().text(source), "0");
+
+    let (stylesheet, _) = Stylesheet::from_string_recovering("a { x } b { top: 0 }");
+    let b = stylesheet.rules[1].as_style_rule().unwrap();
+    assert_eq!(b.span.unwrap().start.offset, 8);
+  }
+
+  #[test]
+  fn test_recovering_inside_nested_rules() {
+    let input = ".a { color: red; & .b { top 0; left: 0 } right: 0; {} bottom: 0 } .c { }";
+    let (stylesheet, errors) = Stylesheet::from_string_recovering(input);
+    assert_eq!(stylesheet.to_string(), ".a { color: red; & .b { left: 0; } right: 0; bottom: 0; } .c {  }");
+    assert_eq!(errors.len(), 2);
+    assert_eq!(errors[0].kind, ParseErrorKind::InvalidDeclaration);
+    assert_eq!(errors[1].kind, ParseErrorKind::InvalidSelector);
+  }
+
+  #[test]
+  fn test_error_location() {
+    let input = "h1 { color: red; }\n\np {\n  margin: 0;\n  padding 1em;\n}\n\n.c { color: blue; }";
+    let error = Stylesheet::from_string(input).unwrap_err();
+    assert_eq!(error.kind, ParseErrorKind::InvalidDeclaration);
+    assert_eq!((error.line, error.column), (5, 11));
+    assert_eq!(error.expected, "`:`");
+    assert_eq!(error.found, "`1em`");
+  }
+
+  #[test]
+  fn test_error_missing_closing_brace() {
+    let error = Stylesheet::from_string("div { color: blue; padding: 10px ").unwrap_err();
+    assert_eq!(error.kind, ParseErrorKind::UnexpectedEndOfInput);
+    assert_eq!(error.expected, "`}`");
+    assert_eq!(error.offset, 33);
+  }
+
+  #[test]
+  fn test_error_stray_closing_brace() {
+    let error = Stylesheet::from_string("a { color: red } } b { color: blue }").unwrap_err();
+    assert_eq!(error.offset, 17);
+    assert_eq!(error.expected, "`{`");
+    assert_eq!(error.found, "`}`");
+  }
+
+  #[test]
+  fn test_recovering_skips_invalid_rule() {
+    let input = "a { color: red } { color: blue } b { color: green }";
+    let (stylesheet, errors) = Stylesheet::from_string_recovering(input);
+    assert_eq!(stylesheet.to_string(), "a { color: red; } b { color: green; }");
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].kind, ParseErrorKind::InvalidSelector);
+
+    let input = "a { color: red } b } c { color: blue } d { color: green }";
+    let (stylesheet, errors) = Stylesheet::from_string_recovering(input);
+    assert_eq!(stylesheet.to_string(), "a { color: red; } d { color: green; }");
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].expected, "`{`");
+    assert_eq!(errors[0].offset, 19);
+  }
+
+  #[test]
+  fn test_recovering_skips_invalid_declarations() {
+    let input = "a {\n  color red;\n  margin: 0;\n  : 1px;\n  padding: 2px\n}";
+    let (stylesheet, errors) = Stylesheet::from_string_recovering(input);
+    assert_eq!(stylesheet.to_string(), "a { margin: 0; padding: 2px; }");
+    assert_eq!(errors.len(), 2);
+    assert_eq!(errors[0].kind, ParseErrorKind::InvalidDeclaration);
+    assert_eq!((errors[0].line, errors[0].column), (2, 9));
+    assert_eq!((errors[1].line, errors[1].column), (4, 3));
+  }
+
+  #[test]
+  fn test_recovering_invalid_at_rules() {
+    let input = "@import 42; @media print { a { color: red } ) } b { color: blue }";
+    let (stylesheet, errors) = Stylesheet::from_string_recovering(input);
+    assert_eq!(stylesheet.to_string(), "@media print { a { color: red; } } b { color: blue; }");
+    assert_eq!(errors.len(), 2);
+    assert_eq!(errors[0].kind, ParseErrorKind::InvalidAtRule);
+    assert_eq!(errors[0].offset, 8);
+    assert_eq!(errors[1].offset, 46);
+    assert_eq!(errors[1].expected, "`{`");
+  }
+
+  #[test]
+  fn test_recovering_closes_blocks_at_end_of_input() {
+    let (stylesheet, errors) = Stylesheet::from_string_recovering("@media print { div { color: blue; padding: 10px ");
+    assert_eq!(stylesheet.to_string(), "@media print { div { color: blue; padding: 10px; } }");
+    assert_eq!(errors.len(), 2);
+    assert!(errors.iter().all(|error| error.kind == ParseErrorKind::UnexpectedEndOfInput));
+
+    let (stylesheet, errors) = Stylesheet::from_string_recovering("a { color: red } /* open");
+    assert_eq!(stylesheet.rules.len(), 1);
+    assert_eq!(errors[0].kind, ParseErrorKind::UnterminatedComment);
+  }
+
+  #[test]
+  fn test_recovering_valid_input() {
+    let input = "/* a */ a { color: red; } @media print { b { color: blue } }";
+    let (stylesheet, errors) = Stylesheet::from_string_recovering(input);
+    assert!(errors.is_empty());
+    assert_eq!(stylesheet, Stylesheet::from_string(input).unwrap());
+  }
+
+  #[test]
+  fn test_strict_fails_where_recovering_succeeds() {
+    let input = "a { color red } b { color: blue }";
+    assert!(Stylesheet::from_string(input).is_err());
+    let (stylesheet, errors) = Stylesheet::from_string_recovering(input);
+    assert_eq!(stylesheet.rules.len(), 2);
+    assert_eq!(errors.len(), 1);
+  }
+
   #[test]
   #[should_panic]
   fn test_malformed_css_returns_error() {
@@ -0,0 +1,528 @@
+//! CSS Component Values
+//!
+//! This module provides a parsed model of declaration values (CSS Syntax
+//! Level 3, "component values"). A `Value` is a list of `ComponentValue`s:
+//! identifiers, numbers, percentages, dimensions, strings, URLs, hash tokens,
+//! functions with their arguments, `()` / `[]` / `{}` blocks, commas, slashes,
+//! whitespace and any other delimiter character.
+//!
+//! Escapes are decoded, comments and runs of whitespace become a single
+//! `Whitespace`, and whitespace at the start and end of a list is dropped.
+//! `CSSDeclaration.value` keeps the text as written, so nothing is lost by
+//! parsing it.
+//!
+//! ## Main API
+//!
+//! - `Value::from_string()` - Parse a value from a string
+//! - `CSSDeclaration::parsed_value()` - Parse the value of a declaration
+//! - `Value::new()` - Create a value programmatically
+//! - `Display` trait implementation for converting back to CSS string
+//!
+//! ## Examples
+//!
+//! ```rust
+//! use css_structs::{CSSDeclaration, ComponentValue};
+//!
+//! let decl = CSSDeclaration::from_string("background: url(bg.png) no-repeat, rgb(0 0 0 / 50%)").unwrap();
+//! let value = decl.parsed_value().unwrap();
+//!
+//! assert_eq!(value.components[0], ComponentValue::Url("bg.png".to_string()));
+//! assert_eq!(value.components[2], ComponentValue::Ident("no-repeat".to_string()));
+//! assert_eq!(value.components[3], ComponentValue::Comma);
+//!
+//! let ComponentValue::Function { name, arguments } = &value.components[5] else { panic!() };
+//! assert_eq!(name, "rgb");
+//! assert_eq!(arguments[0], ComponentValue::Number(0.0));
+//! assert_eq!(arguments[6], ComponentValue::Slash);
+//! assert_eq!(arguments[8], ComponentValue::Percentage(50.0));
+//!
+//! assert_eq!(value.to_string(), "url(\"bg.png\") no-repeat, rgb(0 0 0 / 50%)");
+//! assert_eq!(decl.value, "url(bg.png) no-repeat, rgb(0 0 0 / 50%)");
+//! ```
+
+
+use std::fmt;
+use crate::helpers::{is_css_whitespace, parse_ident, parse_name, parse_string, serialize_identifier, serialize_string, trivia};
+use crate::parse_error::{ParseError, ParseErrorKind, ParseResult, SyntaxError};
+
+
+/// A parsed declaration value.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Value {
+  pub components: Vec<ComponentValue>,
+}
+
+/// A single component of a value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ComponentValue {
+  Ident(String),
+  Number(f64),
+  /// A percentage, such as `50%`, holding the number before the `%`.
+  Percentage(f64),
+  /// A number with a unit, such as `1.5rem`. The unit is kept as written.
+  Dimension { value: f64, unit: String },
+  String(String),
+  /// A URL, from either `url(a.png)` or `url("a.png")`.
+  Url(String),
+  /// A `#` followed by a name, such as `#fff`, holding the name.
+  Hash(String),
+  Function { name: String, arguments: Vec<ComponentValue> },
+  /// A `()`, `[]` or `{}` block, such as the `[full-width]` of a grid line name.
+  Block { bracket: char, contents: Vec<ComponentValue> },
+  Comma,
+  Slash,
+  Whitespace,
+  /// Any other character, such as `+`, `*` or `!`.
+  Delim(char),
+}
+
+/// Formats a number the way CSSOM serializes it: rounded to six decimals,
+/// without an exponent, and without a fractional part for integers.
+pub(crate) fn format_number(value: f64) -> String {
+  let rounded = (value * 1e6).round() / 1e6;
+  if rounded == 0.0 {
+    return "0".to_string();
+  }
+  rounded.to_string()
+}
+
+/// Returns the number at the start of `input` and its byte length, following
+/// the CSS Syntax number grammar: `[+-]? (\d+ (\.\d+)? | \.\d+) ([eE][+-]?\d+)?`.
+fn scan_number(input: &str) -> Option<(f64, usize)> {
+  let bytes = input.as_bytes();
+  let digits = |from: usize| bytes[from.min(bytes.len())..].iter().take_while(|b| b.is_ascii_digit()).count();
+
+  let mut len = usize::from(matches!(bytes.first(), Some(b'+' | b'-')));
+  let integer = digits(len);
+  len += integer;
+
+  let fraction = match bytes.get(len) {
+    Some(b'.') => digits(len + 1),
+    _ => 0,
+  };
+  if fraction > 0 {
+    len += 1 + fraction;
+  }
+  if integer == 0 && fraction == 0 {
+    return None;
+  }
+
+  if let Some(b'e' | b'E') = bytes.get(len) {
+    let sign = usize::from(matches!(bytes.get(len + 1), Some(b'+' | b'-')));
+    let exponent = digits(len + 1 + sign);
+    if exponent > 0 {
+      len += 1 + sign + exponent;
+    }
+  }
+
+  input[..len].parse().ok().map(|value| (value, len))
+}
+
+/// Parses the component values up to `closer` or the end of the input.
+/// A closing bracket that does not belong to an open block is an error.
+fn parse_list(input: &str, closer: Option<char>) -> ParseResult<'_, Vec<ComponentValue>> {
+  let mut components = Vec::new();
+  let mut input = input;
+
+  loop {
+    let (rest, _) = trivia(input)?;
+    if rest.len() < input.len() && !components.is_empty() {
+      components.push(ComponentValue::Whitespace);
+    }
+    input = rest;
+
+    match input.chars().next() {
+      None => break,
+      Some(c) if Some(c) == closer => break,
+      Some(')' | ']' | '}') => return SyntaxError::failure(input, ParseErrorKind::UnexpectedToken, "a value"),
+      _ => {}
+    }
+
+    let (rest, component) = ComponentValue::parse(input)?;
+    components.push(component);
+    input = rest;
+  }
+
+  if components.last() == Some(&ComponentValue::Whitespace) {
+    components.pop();
+  }
+
+  Ok((input, components))
+}
+
+/// Parses the contents of a block or function, whose opening bracket has
+/// already been consumed, and the closing bracket. A block that is never
+/// closed runs to the end of the input.
+fn parse_block(input: &str, closer: char) -> ParseResult<'_, Vec<ComponentValue>> {
+  let (rest, contents) = parse_list(input, Some(closer))?;
+  Ok((rest.strip_prefix(closer).unwrap_or(rest), contents))
+}
+
+/// Parses the rest of an unquoted `url(`, up to and including the `)`.
+fn parse_unquoted_url(input: &str) -> ParseResult<'_, String> {
+  let (input, _) = trivia(input)?;
+  let mut url = String::new();
+  let mut rest = input;
+
+  loop {
+    match rest.chars().next() {
+      None => return Ok((rest, url)),
+      Some(')') => return Ok((&rest[1..], url)),
+      Some(c) if is_css_whitespace(c) => {
+        let after = rest.trim_start_matches(is_css_whitespace);
+        return match after.strip_prefix(')') {
+          Some(after) => Ok((after, url)),
+          None if after.is_empty() => Ok((after, url)),
+          None => SyntaxError::failure(after, ParseErrorKind::UnexpectedToken, "`)`"),
+        };
+      }
+      Some('\\') => {
+        let (after, name) = parse_name(rest)
+          .map_err(|_| nom::Err::Failure(SyntaxError::new(rest, ParseErrorKind::UnexpectedToken, "a URL")))?;
+        url.push_str(&name);
+        rest = after;
+      }
+      Some('"' | '\'' | '(') => return SyntaxError::failure(rest, ParseErrorKind::UnexpectedToken, "a URL"),
+      Some(c) => {
+        url.push(c);
+        rest = &rest[c.len_utf8()..];
+      }
+    }
+  }
+}
+
+impl ComponentValue {
+  pub(crate) fn parse(input: &str) -> ParseResult<'_, ComponentValue> {
+    if let Some((value, len)) = scan_number(input) {
+      let rest = &input[len..];
+      if let Some(rest) = rest.strip_prefix('%') {
+        return Ok((rest, ComponentValue::Percentage(value)));
+      }
+      return match parse_ident(rest) {
+        Ok((rest, unit)) => Ok((rest, ComponentValue::Dimension { value, unit })),
+        Err(_) => Ok((rest, ComponentValue::Number(value))),
+      };
+    }
+
+    if let Ok((rest, name)) = parse_ident(input) {
+      let Some(arguments) = rest.strip_prefix('(') else {
+        return Ok((rest, ComponentValue::Ident(name)));
+      };
+      if name.eq_ignore_ascii_case("url") && !trivia(arguments)?.0.starts_with(['"', '\'']) {
+        let (rest, url) = parse_unquoted_url(arguments)?;
+        return Ok((rest, ComponentValue::Url(url)));
+      }
+
+      let (rest, arguments) = parse_block(arguments, ')')?;
+      return match (name.eq_ignore_ascii_case("url"), arguments.as_slice()) {
+        (true, [ComponentValue::String(url)]) => Ok((rest, ComponentValue::Url(url.clone()))),
+        _ => Ok((rest, ComponentValue::Function { name, arguments })),
+      };
+    }
+
+    let Some(c) = input.chars().next() else {
+      return SyntaxError::error(input, ParseErrorKind::UnexpectedEndOfInput, "a value");
+    };
+    let rest = &input[c.len_utf8()..];
+
+    match c {
+      '"' | '\'' => {
+        let (rest, value) = parse_string(input)
+          .map_err(|_| nom::Err::Failure(SyntaxError::new(input, ParseErrorKind::UnexpectedToken, "a closing quote")))?;
+        Ok((rest, ComponentValue::String(value)))
+      }
+      '#' => match parse_name(rest) {
+        Ok((rest, name)) => Ok((rest, ComponentValue::Hash(name))),
+        Err(_) => Ok((rest, ComponentValue::Delim('#'))),
+      },
+      '(' => map_block(parse_block(rest, ')'), c),
+      '[' => map_block(parse_block(rest, ']'), c),
+      '{' => map_block(parse_block(rest, '}'), c),
+      ',' => Ok((rest, ComponentValue::Comma)),
+      '/' => Ok((rest, ComponentValue::Slash)),
+      c => Ok((rest, ComponentValue::Delim(c))),
+    }
+  }
+
+  /// Returns the identifier, if this is one.
+  pub fn as_ident(&self) -> Option<&str> {
+    match self {
+      ComponentValue::Ident(name) => Some(name),
+      _ => None,
+    }
+  }
+
+  /// Whether this is the identifier `keyword`, ignoring ASCII case.
+  pub fn is_ident(&self, keyword: &str) -> bool {
+    self.as_ident().is_some_and(|name| name.eq_ignore_ascii_case(keyword))
+  }
+}
+
+/// Copies `input`, a list of component values up to `closer`, to `output`,
+/// with the text `replace` returns for a component in place of its own text.
+/// Returns the rest of the input, or `None` when it cannot be parsed.
+fn replace_list<'a>(
+  input: &'a str,
+  closer: Option<char>,
+  replace: &mut dyn FnMut(&ComponentValue, &str) -> Option<String>,
+  output: &mut String,
+) -> Option<&'a str> {
+  let mut input = input;
+  loop {
+    let (rest, _) = trivia(input).ok()?;
+    output.push_str(&input[..input.len() - rest.len()]);
+    input = rest;
+    match input.chars().next() {
+      None => return Some(input),
+      Some(c) if Some(c) == closer => return Some(input),
+      Some(')' | ']' | '}') => return None,
+      _ => {}
+    }
+
+    let (rest, component) = ComponentValue::parse(input).ok()?;
+    let text = &input[..input.len() - rest.len()];
+    let (contents, closer) = match (replace(&component, text), &component) {
+      (Some(replacement), _) => {
+        output.push_str(&replacement);
+        (None, None)
+      }
+      (None, ComponentValue::Function { .. }) => (parse_ident(text).ok().map(|(arguments, _)| &arguments[1..]), Some(')')),
+      (None, ComponentValue::Block { bracket, .. }) => {
+        let closer = match bracket {
+          '(' => ')',
+          '[' => ']',
+          _ => '}',
+        };
+        (Some(&text[1..]), Some(closer))
+      }
+      (None, _) => {
+        output.push_str(text);
+        (None, None)
+      }
+    };
+    if let Some(contents) = contents {
+      output.push_str(&text[..text.len() - contents.len()]);
+      let end = replace_list(contents, closer, replace, output)?;
+      output.push_str(end);
+    }
+    input = rest;
+  }
+}
+
+/// Copies `input`, a value, with the text `replace` returns for a component
+/// in place of its own text. Everything else is kept as written, including
+/// whitespace and comments, and the functions and blocks `replace` leaves
+/// alone are searched too. Returns `None` when `input` is not a valid value.
+pub(crate) fn replace_components(input: &str, replace: &mut dyn FnMut(&ComponentValue, &str) -> Option<String>) -> Option<String> {
+  let mut output = String::with_capacity(input.len());
+  replace_list(input, None, replace, &mut output)?;
+  Some(output)
+}
+
+fn map_block(result: ParseResult<'_, Vec<ComponentValue>>, bracket: char) -> ParseResult<'_, ComponentValue> {
+  result.map(|(rest, contents)| (rest, ComponentValue::Block { bracket, contents }))
+}
+
+impl Value {
+  pub(crate) fn parse(input: &str) -> ParseResult<'_, Value> {
+    let (rest, components) = parse_list(input, None)?;
+    Ok((rest, Value { components }))
+  }
+
+  pub fn from_string(input: &str) -> Result<Value, ParseError> {
+    Self::parse(input)
+      .map(|(_, value)| value)
+      .map_err(|error| ParseError::from_nom(input, error))
+  }
+
+  pub fn new(components: Vec<ComponentValue>) -> Self {
+    Value { components }
+  }
+}
+
+fn write_list(f: &mut fmt::Formatter<'_>, components: &[ComponentValue]) -> fmt::Result {
+  components.iter().try_for_each(|component| write!(f, "{}", component))
+}
+
+impl fmt::Display for ComponentValue {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      ComponentValue::Ident(name) => write!(f, "{}", serialize_identifier(name)),
+      ComponentValue::Number(value) => write!(f, "{}", format_number(*value)),
+      ComponentValue::Percentage(value) => write!(f, "{}%", format_number(*value)),
+      ComponentValue::Dimension { value, unit } => write!(f, "{}{}", format_number(*value), serialize_identifier(unit)),
+      ComponentValue::String(value) => write!(f, "{}", serialize_string(value)),
+      ComponentValue::Url(url) => write!(f, "url({})", serialize_string(url)),
+      ComponentValue::Hash(name) => write!(f, "#{}", name),
+      ComponentValue::Function { name, arguments } => {
+        write!(f, "{}(", serialize_identifier(name))?;
+        write_list(f, arguments)?;
+        write!(f, ")")
+      }
+      ComponentValue::Block { bracket, contents } => {
+        let closer = match bracket {
+          '[' => ']',
+          '{' => '}',
+          _ => ')',
+        };
+        write!(f, "{}", bracket)?;
+        write_list(f, contents)?;
+        write!(f, "{}", closer)
+      }
+      ComponentValue::Comma => write!(f, ","),
+      ComponentValue::Slash => write!(f, "/"),
+      ComponentValue::Whitespace => write!(f, " "),
+      ComponentValue::Delim(c) => write!(f, "{}", c),
+    }
+  }
+}
+
+impl fmt::Display for Value {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write_list(f, &self.components)
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::css_declaration::CSSDeclaration;
+
+  fn ident(name: &str) -> ComponentValue {
+    ComponentValue::Ident(name.to_string())
+  }
+
+  fn dimension(value: f64, unit: &str) -> ComponentValue {
+    ComponentValue::Dimension { value, unit: unit.to_string() }
+  }
+
+  #[test]
+  fn test_numbers_and_dimensions() {
+    let value = Value::from_string("1.5rem -2 +.5 50% 1e3 2E-1px 1em 10e-x").unwrap();
+    assert_eq!(value.components, vec![
+      dimension(1.5, "rem"), ComponentValue::Whitespace,
+      ComponentValue::Number(-2.0), ComponentValue::Whitespace,
+      ComponentValue::Number(0.5), ComponentValue::Whitespace,
+      ComponentValue::Percentage(50.0), ComponentValue::Whitespace,
+      ComponentValue::Number(1000.0), ComponentValue::Whitespace,
+      dimension(0.2, "px"), ComponentValue::Whitespace,
+      dimension(1.0, "em"), ComponentValue::Whitespace,
+      dimension(10.0, "e-x"),
+    ]);
+  }
+
+  #[test]
+  fn test_identifiers_and_delimiters() {
+    let value = Value::from_string("-webkit-box  a\\:b + -- * !").unwrap();
+    assert_eq!(value.components, vec![
+      ident("-webkit-box"), ComponentValue::Whitespace,
+      ident("a:b"), ComponentValue::Whitespace,
+      ComponentValue::Delim('+'), ComponentValue::Whitespace,
+      ident("--"), ComponentValue::Whitespace,
+      ComponentValue::Delim('*'), ComponentValue::Whitespace,
+      ComponentValue::Delim('!'),
+    ]);
+    assert!(value.components[0].is_ident("-WEBKIT-BOX"));
+    assert_eq!(value.components[1].as_ident(), None);
+  }
+
+  #[test]
+  fn test_strings_urls_and_hashes() {
+    let value = Value::from_string("'a\\'b' url( a.png ) URL(\"b c.png\") #fff #1a2b3c # x").unwrap();
+    assert_eq!(value.components, vec![
+      ComponentValue::String("a'b".to_string()), ComponentValue::Whitespace,
+      ComponentValue::Url("a.png".to_string()), ComponentValue::Whitespace,
+      ComponentValue::Url("b c.png".to_string()), ComponentValue::Whitespace,
+      ComponentValue::Hash("fff".to_string()), ComponentValue::Whitespace,
+      ComponentValue::Hash("1a2b3c".to_string()), ComponentValue::Whitespace,
+      ComponentValue::Delim('#'), ComponentValue::Whitespace,
+      ident("x"),
+    ]);
+  }
+
+  #[test]
+  fn test_functions_and_blocks() {
+    let value = Value::from_string("calc( 100% - (2 * 10px) ) [a b]").unwrap();
+    let ComponentValue::Function { name, arguments } = &value.components[0] else { panic!() };
+    assert_eq!(name, "calc");
+    assert_eq!(arguments.len(), 5);
+    assert_eq!(arguments[2], ComponentValue::Delim('-'));
+    assert_eq!(arguments[4], ComponentValue::Block {
+      bracket: '(',
+      contents: vec![
+        ComponentValue::Number(2.0), ComponentValue::Whitespace,
+        ComponentValue::Delim('*'), ComponentValue::Whitespace,
+        dimension(10.0, "px"),
+      ],
+    });
+    assert_eq!(value.components[2], ComponentValue::Block {
+      bracket: '[',
+      contents: vec![ident("a"), ComponentValue::Whitespace, ident("b")],
+    });
+  }
+
+  #[test]
+  fn test_commas_slashes_and_comments() {
+    let value = Value::from_string("a,b /* c */ / d").unwrap();
+    assert_eq!(value.components, vec![
+      ident("a"), ComponentValue::Comma, ident("b"), ComponentValue::Whitespace,
+      ComponentValue::Slash, ComponentValue::Whitespace, ident("d"),
+    ]);
+  }
+
+  #[test]
+  fn test_unclosed_blocks_close_at_end() {
+    let value = Value::from_string("var(--x, (a").unwrap();
+    assert_eq!(value.to_string(), "var(--x, (a))");
+  }
+
+  #[test]
+  fn test_errors() {
+    let error = Value::from_string("a ) b").unwrap_err();
+    assert_eq!(error.kind, ParseErrorKind::UnexpectedToken);
+    assert_eq!(error.offset, 2);
+
+    let error = Value::from_string("url(a b)").unwrap_err();
+    assert_eq!(error.offset, 6);
+
+    assert!(Value::from_string("\"abc").is_err());
+    assert!(Value::from_string("a /* b").is_err());
+  }
+
+  #[test]
+  fn test_display() {
+    let value = Value::from_string("0.50em  -0 1.0 #ABC 'x\"y' a\\31 b rgb(1,2 ,3)").unwrap();
+    assert_eq!(value.to_string(), "0.5em 0 1 #ABC \"x\\\"y\" a1b rgb(1,2 ,3)");
+    assert_eq!(format_number(1.0 / 3.0), "0.333333");
+    assert_eq!(format_number(1e-9), "0");
+    assert_eq!(format_number(-2.5), "-2.5");
+  }
+
+  #[test]
+  fn test_new() {
+    let value = Value::new(vec![dimension(10.0, "px"), ComponentValue::Whitespace, ident("auto")]);
+    assert_eq!(value.to_string(), "10px auto");
+    assert_eq!(Value::from_string("").unwrap(), Value::default());
+  }
+
+  #[test]
+  fn test_replace_components() {
+    let mut upper = |component: &ComponentValue, _text: &str| component.as_ident().map(str::to_uppercase);
+    assert_eq!(
+      replace_components("a  /* b */ f( c,[d] ) url( e.png ) 'f' (g", &mut upper).as_deref(),
+      Some("A  /* b */ f( C,[D] ) url( e.png ) 'f' (G"),
+    );
+    assert_eq!(replace_components("a)", &mut upper), None);
+  }
+
+  #[test]
+  fn test_declaration_parsed_value() {
+    let decl = CSSDeclaration::from_string("margin: 0 /* top */ auto !important").unwrap();
+    let value = decl.parsed_value().unwrap();
+    assert_eq!(value.components, vec![ComponentValue::Number(0.0), ComponentValue::Whitespace, ident("auto")]);
+
+    let decl = CSSDeclaration::from_string("--x: { a: b }").unwrap();
+    assert!(matches!(decl.parsed_value().unwrap().components[0], ComponentValue::Block { bracket: '{', .. }));
+  }
+}
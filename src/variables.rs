@@ -0,0 +1,336 @@
+//! CSS Custom Properties
+//!
+//! This module substitutes `var()` references (CSS Custom Properties Level 1).
+//! `var(--name)` is replaced with the value of the custom property `--name`,
+//! and `var(--name, fallback)` with the fallback when `--name` has no value.
+//! A value with a reference that can be neither substituted nor replaced by a
+//! fallback is invalid at computed-value time.
+//!
+//! Custom properties may reference each other. Those whose references form a
+//! cycle, such as `--a: var(--b); --b: var(--a)`, are all invalid at
+//! computed-value time and have no value, so references to them take their
+//! fallback. `Cascade::computed_style()` resolves custom properties this way
+//! and substitutes them into the other properties, which act as `unset` when
+//! they are invalid.
+//!
+//! ## Main API
+//!
+//! - `resolve_custom_properties()` - Resolve the references between custom properties
+//! - `substitute_variables()` - Substitute `var()` references in a value
+//! - `Stylesheet::variable_usage()` - Custom properties that are used but never declared, or declared but never used
+//!
+//! ## Examples
+//!
+//! ```rust
+//! use std::collections::BTreeMap;
+//! use css_structs::Stylesheet;
+//! use css_structs::variables::{resolve_custom_properties, substitute_variables};
+//!
+//! let declared = BTreeMap::from([
+//!   ("--gap".to_string(), "4px".to_string()),
+//!   ("--padding".to_string(), "var(--gap) calc(var(--gap) * 2)".to_string()),
+//!   ("--a".to_string(), "var(--b)".to_string()),
+//!   ("--b".to_string(), "var(--a, red)".to_string()),
+//! ]);
+//! let variables = resolve_custom_properties(&declared);
+//! assert_eq!(variables["--padding"], "4px calc(4px * 2)");
+//! assert!(!variables.contains_key("--a")); // cycle
+//!
+//! assert_eq!(substitute_variables("var(--a, blue)", &variables).as_deref(), Some("blue"));
+//! assert_eq!(substitute_variables("var(--missing)", &variables), None);
+//!
+//! let stylesheet = Stylesheet::from_string(":root { --main: red; --old: blue } a { color: var(--main) var(--accent) }").unwrap();
+//! let usage = stylesheet.variable_usage();
+//! assert_eq!(usage.undefined, ["--accent"]);
+//! assert_eq!(usage.unused, ["--old"]);
+//! ```
+
+
+use std::collections::{BTreeMap, BTreeSet};
+use crate::css_declaration_list::CSSDeclarationList;
+use crate::rule::{for_each_declaration_list, Rule};
+use crate::value::{ComponentValue, Value};
+
+
+/// The custom properties that are referenced but never declared, and those
+/// declared but never referenced, from `Stylesheet::variable_usage()`.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct VariableUsage {
+  /// Referenced with `var()` but neither declared nor registered with
+  /// `@property`, even if every reference has a fallback. Sorted.
+  pub undefined: Vec<String>,
+  /// Declared but never referenced. Sorted.
+  pub unused: Vec<String>,
+}
+
+fn is_whitespace(value: &ComponentValue) -> bool {
+  *value == ComponentValue::Whitespace
+}
+
+/// Splits the arguments of `var()` into the custom property name and the
+/// fallback, if there is a comma. Returns `None` for an invalid `var()`.
+fn var_arguments(arguments: &[ComponentValue]) -> Option<(&str, Option<&[ComponentValue]>)> {
+  let (name, fallback) = match arguments.iter().position(|value| *value == ComponentValue::Comma) {
+    Some(comma) => (&arguments[..comma], Some(&arguments[comma + 1..])),
+    None => (arguments, None),
+  };
+  let mut name = name.iter().filter(|value| !is_whitespace(value));
+  match (name.next(), name.next()) {
+    (Some(ComponentValue::Ident(name)), None) if name.starts_with("--") => {
+      let fallback = fallback.map(|fallback| {
+        let start = fallback.iter().position(|value| !is_whitespace(value)).unwrap_or(fallback.len());
+        let end = fallback.iter().rposition(|value| !is_whitespace(value)).map_or(start, |end| end + 1);
+        &fallback[start..end]
+      });
+      Some((name, fallback))
+    }
+    _ => None,
+  }
+}
+
+fn is_var(name: &str) -> bool {
+  name.eq_ignore_ascii_case("var")
+}
+
+/// Collects the custom property names referenced in `components`, including
+/// in fallbacks.
+fn collect_references(components: &[ComponentValue], references: &mut Vec<String>) {
+  for component in components {
+    match component {
+      ComponentValue::Function { name, arguments } => {
+        if let Some((variable, _)) = is_var(name).then(|| var_arguments(arguments)).flatten() {
+          references.push(variable.to_string());
+        }
+        collect_references(arguments, references);
+      }
+      ComponentValue::Block { contents, .. } => collect_references(contents, references),
+      _ => {}
+    }
+  }
+}
+
+/// The custom property names referenced in `value`.
+fn references(value: &str) -> Vec<String> {
+  let mut references = Vec::new();
+  if let Ok(value) = Value::from_string(value) {
+    collect_references(&value.components, &mut references);
+  }
+  references
+}
+
+/// Collapses the whitespace left around values substituted with nothing.
+fn collapse_whitespace(components: &mut Vec<ComponentValue>) {
+  components.dedup_by(|a, b| is_whitespace(a) && is_whitespace(b));
+  if components.last().is_some_and(is_whitespace) {
+    components.pop();
+  }
+  if components.first().is_some_and(is_whitespace) {
+    components.remove(0);
+  }
+}
+
+fn substitute(components: &[ComponentValue], variables: &BTreeMap<String, String>, output: &mut Vec<ComponentValue>) -> Option<()> {
+  for component in components {
+    match component {
+      ComponentValue::Function { name, arguments } if is_var(name) => {
+        let (variable, fallback) = var_arguments(arguments)?;
+        match (variables.get(variable), fallback) {
+          (Some(value), _) => output.extend(Value::from_string(value).ok()?.components),
+          (None, Some(fallback)) => substitute(fallback, variables, output)?,
+          (None, None) => return None,
+        }
+      }
+      ComponentValue::Function { name, arguments } => {
+        let mut substituted = Vec::new();
+        substitute(arguments, variables, &mut substituted)?;
+        collapse_whitespace(&mut substituted);
+        output.push(ComponentValue::Function { name: name.clone(), arguments: substituted });
+      }
+      ComponentValue::Block { bracket, contents } => {
+        let mut substituted = Vec::new();
+        substitute(contents, variables, &mut substituted)?;
+        collapse_whitespace(&mut substituted);
+        output.push(ComponentValue::Block { bracket: *bracket, contents: substituted });
+      }
+      component => output.push(component.clone()),
+    }
+  }
+  Some(())
+}
+
+/// Substitutes the `var()` references in `value` with the values in
+/// `variables`, keyed by custom property name. Returns `None` when the value
+/// is invalid at computed-value time: a reference to a custom property that
+/// is not in `variables` and has no fallback, or a malformed `var()`.
+///
+/// Values without `var()` are returned as written; the others are
+/// reserialized (see `Value`).
+pub fn substitute_variables(value: &str, variables: &BTreeMap<String, String>) -> Option<String> {
+  if !value.to_ascii_lowercase().contains("var(") {
+    return Some(value.to_string());
+  }
+  let parsed = Value::from_string(value).ok()?;
+  let mut substituted = Vec::new();
+  substitute(&parsed.components, variables, &mut substituted)?;
+  collapse_whitespace(&mut substituted);
+  Some(Value::new(substituted).to_string())
+}
+
+/// Resolves the `var()` references between custom properties, given their
+/// declared values keyed by name. Custom properties in a reference cycle,
+/// and those that are otherwise invalid at computed-value time, are left out
+/// of the result.
+pub fn resolve_custom_properties(declared: &BTreeMap<String, String>) -> BTreeMap<String, String> {
+  let graph: BTreeMap<&str, Vec<String>> = declared.iter().map(|(name, value)| (name.as_str(), references(value))).collect();
+
+  // A custom property is in a cycle when it can reach itself
+  let in_cycle = |start: &str| {
+    let mut visited = BTreeSet::new();
+    let mut pending: Vec<&str> = graph[start].iter().map(String::as_str).collect();
+    while let Some(name) = pending.pop() {
+      if name == start {
+        return true;
+      }
+      if visited.insert(name) {
+        pending.extend(graph.get(name).into_iter().flatten().map(String::as_str));
+      }
+    }
+    false
+  };
+  let cyclic: BTreeSet<&str> = graph.keys().copied().filter(|name| in_cycle(name)).collect();
+
+  let mut resolved: BTreeMap<String, Option<String>> = BTreeMap::new();
+  for name in declared.keys() {
+    resolve(name, declared, &graph, &cyclic, &mut resolved);
+  }
+  resolved.into_iter().filter_map(|(name, value)| Some((name, value?))).collect()
+}
+
+/// Resolves the custom property `name`, after the custom properties it
+/// references. There is no cycle left outside `cyclic`, so this terminates.
+fn resolve(
+  name: &str,
+  declared: &BTreeMap<String, String>,
+  graph: &BTreeMap<&str, Vec<String>>,
+  cyclic: &BTreeSet<&str>,
+  resolved: &mut BTreeMap<String, Option<String>>,
+) -> Option<String> {
+  if let Some(value) = resolved.get(name) {
+    return value.clone();
+  }
+  let value = match cyclic.contains(name) {
+    true => None,
+    false => {
+      let mut variables = BTreeMap::new();
+      for reference in &graph[name] {
+        let value = declared.contains_key(reference).then(|| resolve(reference, declared, graph, cyclic, resolved)).flatten();
+        if let Some(value) = value {
+          variables.insert(reference.clone(), value);
+        }
+      }
+      substitute_variables(&declared[name], &variables)
+    }
+  };
+  resolved.insert(name.to_string(), value.clone());
+  value
+}
+
+/// Collects the names registered with `@property` in `rules` and their nested rules.
+fn collect_registered(rules: &[Rule], names: &mut BTreeSet<String>) {
+  for rule in rules {
+    if let Rule::Property(rule) = rule {
+      names.insert(rule.name.clone());
+    }
+    if let Some(children) = rule.child_rules() {
+      collect_registered(children, names);
+    }
+  }
+}
+
+pub(crate) fn variable_usage(rules: &[Rule]) -> VariableUsage {
+  let mut declared = BTreeSet::new();
+  let mut referenced = BTreeSet::new();
+  collect_registered(rules, &mut declared);
+  for_each_declaration_list(rules, &mut |declarations: &CSSDeclarationList| {
+    for declaration in &declarations.declarations {
+      if declaration.name.starts_with("--") {
+        declared.insert(declaration.name.clone());
+      }
+      referenced.extend(references(&declaration.value));
+    }
+  });
+
+  VariableUsage {
+    undefined: referenced.difference(&declared).cloned().collect(),
+    unused: declared.difference(&referenced).cloned().collect(),
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::stylesheet::Stylesheet;
+
+  fn variables(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+    pairs.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect()
+  }
+
+  #[test]
+  fn test_substitute() {
+    let variables = variables(&[("--x", "10px"), ("--color", "rgb(0 0 0)"), ("--empty", "")]);
+    let substitute = |value: &str| substitute_variables(value, &variables);
+    assert_eq!(substitute("var(--x)").as_deref(), Some("10px"));
+    assert_eq!(substitute("calc(var(--x) * 2) VAR( --x )").as_deref(), Some("calc(10px * 2) 10px"));
+    assert_eq!(substitute("1px solid var(--color)").as_deref(), Some("1px solid rgb(0 0 0)"));
+    assert_eq!(substitute("a var(--empty) b").as_deref(), Some("a b"));
+    assert_eq!(substitute("var(--y, 1px 2px)").as_deref(), Some("1px 2px"));
+    assert_eq!(substitute("var(--y,)").as_deref(), Some(""));
+    assert_eq!(substitute("var(--y, var(--z, var(--x)))").as_deref(), Some("10px"));
+    assert_eq!(substitute("var(--y, var(--z))"), None);
+    assert_eq!(substitute("var(x)"), None);
+    // Values without references are kept as written
+    assert_eq!(substitute("url(a.png)  red").as_deref(), Some("url(a.png)  red"));
+  }
+
+  #[test]
+  fn test_resolve_chains() {
+    let resolved = resolve_custom_properties(&variables(&[
+      ("--a", "var(--b) var(--c)"),
+      ("--b", "var(--c)"),
+      ("--c", "1px"),
+      ("--d", "var(--missing)"),
+      ("--e", "var(--d, 2px)"),
+    ]));
+    assert_eq!(resolved["--a"], "1px 1px");
+    assert_eq!(resolved["--b"], "1px");
+    assert!(!resolved.contains_key("--d"));
+    assert_eq!(resolved["--e"], "2px");
+  }
+
+  #[test]
+  fn test_cycles_are_invalid() {
+    let resolved = resolve_custom_properties(&variables(&[
+      ("--self", "var(--self)"),
+      ("--a", "var(--b)"),
+      ("--b", "var(--c, 1px)"),
+      ("--c", "var(--a)"),
+      ("--outside", "var(--a, 3px)"),
+      ("--fallback-cycle", "var(--x, var(--fallback-cycle))"),
+    ]));
+    assert_eq!(resolved, variables(&[("--outside", "3px")]));
+  }
+
+  #[test]
+  fn test_variable_usage() {
+    let stylesheet = Stylesheet::from_string("
+      @property --registered { syntax: '*'; inherits: false }
+      :root { --a: 1px; --b: var(--a); --unused: 0 }
+      @media print { p { margin: var(--b) var(--registered) var(--nowhere, 0) } }
+      @keyframes k { from { width: var(--in-keyframe) } }
+    ").unwrap();
+    let usage = stylesheet.variable_usage();
+    assert_eq!(usage.undefined, ["--in-keyframe", "--nowhere"]);
+    assert_eq!(usage.unused, ["--unused"]);
+  }
+}